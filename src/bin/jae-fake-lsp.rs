@@ -0,0 +1,200 @@
+//! Minimal language server used by the LSP tests.
+//!
+//! It understands just enough of the protocol to exercise the client:
+//! - diagnostics: a warning for every `TODO` and an error for every `FIXME`
+//! - hover: the word under the cursor
+//! - definition: the first `fn <word>` (or first occurrence) in the document
+//! - references: every occurrence of the word under the cursor
+//! - completion: words in the document starting with the prefix before the cursor
+
+use jae::lsp::protocol::{char_to_lsp_col, lsp_col_to_char, PositionEncoding};
+use jae::lsp::transport::{read_message, write_message};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufReader};
+
+const ENCODING: PositionEncoding = PositionEncoding::Utf16;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// (line, char start, char end) of every whole-word occurrence of `word`
+fn occurrences(text: &str, word: &str) -> Vec<(usize, usize, usize)> {
+    let mut found = Vec::new();
+    for (row, line) in text.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let target: Vec<char> = word.chars().collect();
+        let mut col = 0;
+        while col + target.len() <= chars.len() {
+            let bounded = (col == 0 || !is_word_char(chars[col - 1]))
+                && chars.get(col + target.len()).is_none_or(|c| !is_word_char(*c));
+            match chars[col..col + target.len()] == target[..] && bounded {
+                true => {
+                    found.push((row, col, col + target.len()));
+                    col += target.len();
+                }
+                false => col += 1,
+            }
+        }
+    }
+    found
+}
+
+/// The word at (or just before) a char position, with its start column
+fn word_at(line: &str, col: usize) -> Option<(usize, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut start = col.min(chars.len());
+    while start > 0 && is_word_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = col.min(chars.len());
+    while end < chars.len() && is_word_char(chars[end]) {
+        end += 1;
+    }
+    match start < end {
+        true => Some((start, chars[start..end].iter().collect())),
+        false => None,
+    }
+}
+
+fn range(line_text: &str, row: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": {"line": row, "character": char_to_lsp_col(line_text, start, ENCODING)},
+        "end": {"line": row, "character": char_to_lsp_col(line_text, end, ENCODING)}
+    })
+}
+
+fn diagnostics(uri: &str, text: &str) -> Value {
+    let mut items = Vec::new();
+    for (marker, severity) in [("TODO", 2), ("FIXME", 1)] {
+        for (row, start, end) in occurrences(text, marker) {
+            let line = text.lines().nth(row).unwrap_or("");
+            items.push(json!({
+                "range": range(line, row, start, end),
+                "severity": severity,
+                "message": format!("{} found", marker)
+            }));
+        }
+    }
+    json!({"jsonrpc": "2.0", "method": "textDocument/publishDiagnostics",
+           "params": {"uri": uri, "diagnostics": items}})
+}
+
+/// Resolve the textDocument/position params to (uri, text, row, char col)
+fn position<'a>(params: &Value, docs: &'a HashMap<String, String>) -> Option<(String, &'a str, usize, usize)> {
+    let uri = params.pointer("/textDocument/uri")?.as_str()?.to_string();
+    let text = docs.get(&uri)?;
+    let row = params.pointer("/position/line")?.as_u64()? as usize;
+    let character = params.pointer("/position/character")?.as_u64()? as usize;
+    let line = text.lines().nth(row).unwrap_or("");
+    Some((uri, text.as_str(), row, lsp_col_to_char(line, character, ENCODING)))
+}
+
+fn handle_request(method: &str, params: &Value, docs: &HashMap<String, String>) -> Value {
+    let at = position(params, docs);
+    let word = at
+        .as_ref()
+        .and_then(|(_, text, row, col)| word_at(text.lines().nth(*row).unwrap_or(""), *col));
+
+    match (method, at, word) {
+        ("initialize", _, _) => json!({
+            "capabilities": {
+                "positionEncoding": "utf-16",
+                "textDocumentSync": 1,
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "completionProvider": {}
+            },
+            "serverInfo": {"name": "jae-fake-lsp"}
+        }),
+        ("textDocument/hover", Some(_), Some((_, word))) => {
+            json!({"contents": {"kind": "markdown", "value": format!("word `{}`", word)}})
+        }
+        ("textDocument/definition", Some((uri, text, _, _)), Some((_, word))) => {
+            let all = occurrences(text, &word);
+            let definition = all
+                .iter()
+                .find(|(row, start, _)| {
+                    let line = text.lines().nth(*row).unwrap_or("");
+                    line.chars().take(*start).collect::<String>().ends_with("fn ")
+                })
+                .or(all.first());
+            match definition {
+                Some((row, start, end)) => {
+                    let line = text.lines().nth(*row).unwrap_or("");
+                    json!({"uri": uri, "range": range(line, *row, *start, *end)})
+                }
+                None => Value::Null,
+            }
+        }
+        ("textDocument/references", Some((uri, text, _, _)), Some((_, word))) => occurrences(text, &word)
+            .into_iter()
+            .map(|(row, start, end)| {
+                let line = text.lines().nth(row).unwrap_or("");
+                json!({"uri": uri, "range": range(line, row, start, end)})
+            })
+            .collect(),
+        ("textDocument/completion", Some((_, text, row, col)), Some((start, _))) => {
+            let line = text.lines().nth(row).unwrap_or("");
+            let prefix: String = line.chars().skip(start).take(col - start).collect();
+            let words: BTreeSet<String> = text
+                .split(|c: char| !is_word_char(c))
+                .filter(|w| w.starts_with(&prefix) && *w != prefix)
+                .map(str::to_string)
+                .collect();
+            let items: Vec<Value> = words
+                .into_iter()
+                .map(|w| json!({"label": w, "detail": "word"}))
+                .collect();
+            json!({"isIncomplete": false, "items": items})
+        }
+        _ => Value::Null,
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut reader = BufReader::new(io::stdin());
+    let mut stdout = io::stdout();
+    let mut docs: HashMap<String, String> = HashMap::new();
+
+    while let Some(message) = read_message(&mut reader)? {
+        let method = message.get("method").and_then(|m| m.as_str()).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match message.get("id") {
+            Some(id) => {
+                let result = handle_request(method, &params, &docs);
+                write_message(&mut stdout, &json!({"jsonrpc": "2.0", "id": id, "result": result}))?;
+            }
+            None => match method {
+                "textDocument/didOpen" | "textDocument/didChange" => {
+                    let uri = params.pointer("/textDocument/uri").and_then(|u| u.as_str());
+                    let text = params
+                        .pointer("/textDocument/text")
+                        .or_else(|| params.pointer("/contentChanges/0/text"))
+                        .and_then(|t| t.as_str());
+                    match (uri, text) {
+                        (Some(uri), Some(text)) => {
+                            docs.insert(uri.to_string(), text.to_string());
+                            write_message(&mut stdout, &diagnostics(uri, text))?;
+                        }
+                        _ => {}
+                    }
+                }
+                "textDocument/didClose" => {
+                    match params.pointer("/textDocument/uri").and_then(|u| u.as_str()) {
+                        Some(uri) => {
+                            docs.remove(uri);
+                        }
+                        None => {}
+                    }
+                }
+                "exit" => break,
+                _ => {}
+            },
+        }
+    }
+    Ok(())
+}
@@ -1,44 +1,221 @@
 //! System clipboard integration for JAE editor.
 //!
-//! Provides simple cut/copy/paste using the OS clipboard via arboard.
+//! The backend is picked once at startup, the first that works of:
+//! - the OS clipboard via arboard
+//! - a clipboard command on PATH (wl-copy, xclip, xsel or pbcopy)
+//! - OSC 52 escape sequences, which ask the terminal to set its clipboard
+//!   (this also works over SSH and inside tmux)
+//! - an internal register
+//!
+//! Every copy is kept in the internal register too. The OSC 52 and internal
+//! backends paste from it, since terminals rarely let programs read the
+//! clipboard. Failures are returned as messages for the echo area.
 
 use arboard::Clipboard;
+use base64::{engine::general_purpose, Engine as _};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A clipboard tool run as a child process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipboardCommand {
+    /// Program and arguments that read the new clipboard text from stdin
+    copy: &'static [&'static str],
+    /// Program and arguments that print the clipboard text
+    paste: &'static [&'static str],
+    /// Environment variable the tool needs to reach the display server
+    needs_env: Option<&'static str>,
+}
+
+/// Clipboard commands, in order of preference
+const CLIPBOARD_COMMANDS: &[ClipboardCommand] = &[
+    ClipboardCommand {
+        copy: &["wl-copy"],
+        paste: &["wl-paste", "--no-newline"],
+        needs_env: Some("WAYLAND_DISPLAY"),
+    },
+    ClipboardCommand {
+        copy: &["xclip", "-selection", "clipboard"],
+        paste: &["xclip", "-selection", "clipboard", "-o"],
+        needs_env: Some("DISPLAY"),
+    },
+    ClipboardCommand {
+        copy: &["xsel", "--clipboard", "--input"],
+        paste: &["xsel", "--clipboard", "--output"],
+        needs_env: Some("DISPLAY"),
+    },
+    ClipboardCommand {
+        copy: &["pbcopy"],
+        paste: &["pbpaste"],
+        needs_env: None,
+    },
+];
+
+impl ClipboardCommand {
+    fn name(&self) -> &'static str {
+        self.copy[0]
+    }
+
+    /// Whether the tool is installed and its display server is reachable
+    fn available(&self) -> bool {
+        let env_ok = match self.needs_env {
+            Some(var) => std::env::var_os(var).is_some_and(|v| !v.is_empty()),
+            None => true,
+        };
+        env_ok && on_path(self.copy[0]) && on_path(self.paste[0])
+    }
+
+    fn copy(&self, text: &str) -> Result<(), String> {
+        let fail = |e: String| format!("Clipboard copy with {} failed: {}", self.name(), e);
+        // Clipboard tools may stay in the background to serve the text,
+        // so their output must not reach the terminal
+        let mut child = Command::new(self.copy[0])
+            .args(&self.copy[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| fail(e.to_string()))?;
+        match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()).map_err(|e| fail(e.to_string()))?,
+            None => {}
+        }
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(fail(status.to_string())),
+            Err(e) => Err(fail(e.to_string())),
+        }
+    }
+
+    fn paste(&self) -> Result<Option<String>, String> {
+        let fail = |e: String| format!("Clipboard paste with {} failed: {}", self.name(), e);
+        let output = Command::new(self.paste[0])
+            .args(&self.paste[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| fail(e.to_string()))?;
+        // An empty clipboard makes some tools exit with an error
+        match (output.status.success(), output.stdout.is_empty()) {
+            (_, true) => Ok(None),
+            (true, false) => Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned())),
+            (false, false) => Err(fail(output.status.to_string())),
+        }
+    }
+}
+
+/// Whether an executable named `program` is in a PATH directory
+fn on_path(program: &str) -> bool {
+    match std::env::var_os("PATH") {
+        Some(paths) => std::env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file()),
+        None => false,
+    }
+}
+
+/// OSC 52 sequence that sets the terminal's clipboard to `text`.
+/// Inside tmux it is wrapped so tmux passes it on to the outer terminal.
+pub fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let osc = format!("\x1b]52;c;{}\x07", general_purpose::STANDARD.encode(text.as_bytes()));
+    match tmux {
+        true => format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b")),
+        false => osc,
+    }
+}
+
+/// Where copied text goes
+pub enum ClipboardBackend {
+    System(Clipboard),
+    Command(ClipboardCommand),
+    Osc52,
+    Internal,
+}
+
+impl ClipboardBackend {
+    /// First backend of the fallback chain that is available
+    fn detect() -> Self {
+        match Clipboard::new() {
+            Ok(cb) => return Self::System(cb),
+            Err(e) => log::info!("No system clipboard: {}", e),
+        }
+        match CLIPBOARD_COMMANDS.iter().find(|command| command.available()) {
+            Some(command) => return Self::Command(*command),
+            None => {}
+        }
+        match io::stdout().is_terminal() {
+            true => Self::Osc52,
+            false => Self::Internal,
+        }
+    }
+
+    /// Short name for the settings menu
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::System(_) => "system",
+            Self::Command(command) => command.name(),
+            Self::Osc52 => "OSC 52",
+            Self::Internal => "internal",
+        }
+    }
+}
 
 pub struct ClipboardManager {
-    clipboard: Option<Clipboard>,
+    backend: ClipboardBackend,
+    /// The last copied text
+    register: Option<String>,
 }
 
 impl ClipboardManager {
     pub fn new() -> Self {
+        let backend = ClipboardBackend::detect();
+        log::info!("Clipboard backend: {}", backend.name());
+        Self::with_backend(backend)
+    }
+
+    pub fn with_backend(backend: ClipboardBackend) -> Self {
         Self {
-            clipboard: match Clipboard::new() {
-                Ok(cb) => Some(cb),
-                Err(_) => None,
-            },
+            backend,
+            register: None,
         }
     }
 
-    /// Copy text to system clipboard
-    pub fn copy(&mut self, text: &str) {
+    /// The backend in use
+    pub fn backend(&self) -> &ClipboardBackend {
+        &self.backend
+    }
+
+    /// Copy text to the clipboard
+    pub fn copy(&mut self, text: &str) -> Result<(), String> {
         match text.is_empty() {
-            true => {}
-            false => match &mut self.clipboard {
-                Some(cb) => {
-                    let _ = cb.set_text(text);
-                }
-                None => {}
-            },
+            true => return Ok(()),
+            false => {}
+        }
+        self.register = Some(text.to_string());
+        match &mut self.backend {
+            ClipboardBackend::System(cb) => cb.set_text(text).map_err(|e| format!("Clipboard copy failed: {}", e)),
+            ClipboardBackend::Command(command) => command.copy(text),
+            ClipboardBackend::Osc52 => {
+                let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+                let mut stdout = io::stdout();
+                stdout
+                    .write_all(sequence.as_bytes())
+                    .and_then(|_| stdout.flush())
+                    .map_err(|e| format!("Clipboard copy failed: {}", e))
+            }
+            ClipboardBackend::Internal => Ok(()),
         }
     }
 
-    /// Paste text from system clipboard
-    pub fn paste(&mut self) -> Option<String> {
-        match &mut self.clipboard {
-            Some(cb) => match cb.get_text() {
-                Ok(text) => Some(text),
-                Err(_) => None,
+    /// Paste text from the clipboard (None when it holds no text)
+    pub fn paste(&mut self) -> Result<Option<String>, String> {
+        match &mut self.backend {
+            ClipboardBackend::System(cb) => match cb.get_text() {
+                Ok(text) => Ok(Some(text)),
+                Err(arboard::Error::ContentNotAvailable) => Ok(None),
+                Err(e) => Err(format!("Clipboard paste failed: {}", e)),
             },
-            None => None,
+            ClipboardBackend::Command(command) => command.paste(),
+            ClipboardBackend::Osc52 | ClipboardBackend::Internal => Ok(self.register.clone()),
         }
     }
 }
@@ -48,3 +225,24 @@ impl Default for ClipboardManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52_sequence("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+
+    #[test]
+    fn test_internal_register() {
+        let mut clipboard = ClipboardManager::with_backend(ClipboardBackend::Internal);
+        assert_eq!(clipboard.paste(), Ok(None));
+        clipboard.copy("one").unwrap();
+        clipboard.copy("").unwrap();
+        assert_eq!(clipboard.paste(), Ok(Some("one".to_string())));
+        assert_eq!(clipboard.backend().name(), "internal");
+    }
+}
@@ -5,6 +5,7 @@
 //! - A flexible prefix system for multi-key sequences (C-x, C-c, etc.)
 //! - Command categories for organization in which-key display
 
+use crate::editor::{TextObject, TextObjectAction};
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use std::collections::HashMap;
 
@@ -17,11 +18,12 @@ pub enum Category {
     Selection,
     System,
     Input,
+    Text,
 }
 
 
 /// A single key with modifiers
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct KeyCombo {
     pub key: KeyCode,
     pub modifiers: KeyModifiers,
@@ -46,6 +48,13 @@ impl KeyCombo {
         }
     }
 
+    pub fn ctrl_alt(c: char) -> Self {
+        Self {
+            key: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+        }
+    }
+
     #[allow(dead_code)]
     pub fn ctrl_shift(c: char) -> Self {
         Self {
@@ -68,6 +77,71 @@ impl KeyCombo {
         }
     }
 
+    /// Build a combo from a key event, normalized for keymap lookup.
+    /// Shift is dropped for characters since it is already part of the char
+    /// (terminals report M-% as ALT|SHIFT + '%').
+    pub fn from_event(key: &KeyEvent) -> Self {
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers.difference(KeyModifiers::SHIFT),
+            _ => key.modifiers,
+        };
+        Self {
+            key: key.code,
+            modifiers: modifiers.intersection(
+                KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT,
+            ),
+        }
+    }
+
+    /// Parse Emacs-style key notation: "C-x", "M-%", "C-M-f", "C-SPC", "RET", "M-DEL", "F5"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        while rest.len() > 2 {
+            match rest.get(..2) {
+                Some("C-") => modifiers |= KeyModifiers::CONTROL,
+                Some("M-") => modifiers |= KeyModifiers::ALT,
+                Some("S-") => modifiers |= KeyModifiers::SHIFT,
+                _ => break,
+            }
+            rest = &rest[2..];
+        }
+
+        let key = match rest {
+            "SPC" => KeyCode::Char(' '),
+            "RET" | "Enter" => KeyCode::Enter,
+            "TAB" | "Tab" => KeyCode::Tab,
+            "ESC" => KeyCode::Esc,
+            "DEL" | "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PgUp" => KeyCode::PageUp,
+            "PgDn" => KeyCode::PageDown,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match rest.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                        _ => return Err(format!("invalid key '{}'", s)),
+                    },
+                }
+            }
+        };
+
+        // Shift is implied by the character itself (see from_event)
+        let modifiers = match key {
+            KeyCode::Char(_) => modifiers.difference(KeyModifiers::SHIFT),
+            _ => modifiers,
+        };
+        Ok(Self { key, modifiers })
+    }
+
     /// Format for display (e.g., "C-x", "M-f", "k")
     pub fn display(&self) -> String {
         let mut parts = Vec::new();
@@ -83,6 +157,7 @@ impl KeyCombo {
         }
 
         let key_str = match self.key {
+            KeyCode::Char(' ') => "SPC".to_string(),
             KeyCode::Char(c) => c.to_string(),
             KeyCode::Enter => "Enter".to_string(),
             KeyCode::Backspace => "Backspace".to_string(),
@@ -97,6 +172,7 @@ impl KeyCombo {
             KeyCode::End => "End".to_string(),
             KeyCode::PageUp => "PgUp".to_string(),
             KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
             _ => "?".to_string(),
         };
 
@@ -112,6 +188,19 @@ pub enum Keybinding {
 }
 
 impl Keybinding {
+    /// Parse a space-separated key sequence, e.g. "C-x C-f"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut keys = s
+            .split_whitespace()
+            .map(KeyCombo::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        match keys.len() {
+            0 => Err("empty key sequence".to_string()),
+            1 => Ok(Keybinding::Single(keys.remove(0))),
+            _ => Ok(Keybinding::Sequence(keys)),
+        }
+    }
+
     pub fn display(&self) -> String {
         match self {
             Keybinding::Single(k) => k.display(),
@@ -146,17 +235,23 @@ pub trait KeyPrefix: Send + Sync {
     fn trigger(&self) -> KeyCombo;
 
     /// Display name shown in which-key (e.g., "C-x")
-    fn display_name(&self) -> &'static str;
-
-    /// Look up which command to execute for a given follow-up key
-    /// Returns None if the key is not recognized (cancels prefix)
-    fn get_command(&self, key: &KeyEvent) -> Option<&'static str>;
+    fn display_name(&self) -> &str;
 
     /// Returns all bindings for which-key display
     fn bindings(&self) -> Vec<PrefixBinding>;
+
+    /// Look up which command to execute for a given follow-up key
+    /// Returns None if the key is not recognized (cancels prefix)
+    fn get_command(&self, key: &KeyEvent) -> Option<&'static str> {
+        let combo = KeyCombo::from_event(key);
+        self.bindings()
+            .into_iter()
+            .find(|b| b.key == combo)
+            .map(|b| b.command)
+    }
 }
 
-/// C-x prefix implementation
+/// C-x prefix implementation (default bindings; the active table lives in the keymap)
 pub struct CtrlXPrefix;
 
 impl KeyPrefix for CtrlXPrefix {
@@ -164,23 +259,10 @@ impl KeyPrefix for CtrlXPrefix {
         KeyCombo::ctrl('x')
     }
 
-    fn display_name(&self) -> &'static str {
+    fn display_name(&self) -> &str {
         "C-x"
     }
 
-    fn get_command(&self, key: &KeyEvent) -> Option<&'static str> {
-        match (key.code, key.modifiers) {
-            (KeyCode::Char('f'), KeyModifiers::CONTROL) => Some("open-file"),
-            (KeyCode::Char('s'), KeyModifiers::CONTROL) => Some("save-file"),
-            (KeyCode::Char('w'), KeyModifiers::CONTROL) => Some("save-file-as"),
-            (KeyCode::Char('x'), KeyModifiers::CONTROL) => Some("swap-cursor-mark"),
-            (KeyCode::Char('q'), KeyModifiers::CONTROL) => Some("force-quit"),
-            (KeyCode::Char('k'), KeyModifiers::NONE) => Some("delete-file"),
-            (KeyCode::Char('w'), KeyModifiers::NONE) => Some("toggle-soft-wrap"),
-            _ => None,
-        }
-    }
-
     fn bindings(&self) -> Vec<PrefixBinding> {
         vec![
             PrefixBinding { key: KeyCombo::ctrl('f'), command: "open-file" },
@@ -190,10 +272,131 @@ impl KeyPrefix for CtrlXPrefix {
             PrefixBinding { key: KeyCombo::ctrl('q'), command: "force-quit" },
             PrefixBinding { key: KeyCombo::plain('k'), command: "delete-file" },
             PrefixBinding { key: KeyCombo::plain('w'), command: "toggle-soft-wrap" },
+            PrefixBinding { key: KeyCombo::plain('b'), command: "switch-to-buffer" },
+            PrefixBinding { key: KeyCombo::ctrl('b'), command: "list-buffers" },
+            PrefixBinding { key: KeyCombo::ctrl('k'), command: "kill-buffer" },
+            PrefixBinding { key: KeyCombo::plain('u'), command: "undo-tree" },
+        ]
+    }
+}
+
+/// C-c prefix implementation: user and mode-specific commands
+pub struct CtrlCPrefix;
+
+impl KeyPrefix for CtrlCPrefix {
+    fn trigger(&self) -> KeyCombo {
+        KeyCombo::ctrl('c')
+    }
+
+    fn display_name(&self) -> &str {
+        "C-c"
+    }
+
+    fn bindings(&self) -> Vec<PrefixBinding> {
+        vec![
+            PrefixBinding { key: KeyCombo::plain('s'), command: "save-settings" },
+            PrefixBinding { key: KeyCombo::plain('m'), command: "view-messages" },
+            PrefixBinding { key: KeyCombo::plain('w'), command: "toggle-soft-wrap" },
+            PrefixBinding { key: KeyCombo::plain('%'), command: "query-replace-regexp" },
+            PrefixBinding { key: KeyCombo::plain('h'), command: "lsp-hover" },
+            PrefixBinding { key: KeyCombo::plain('r'), command: "lsp-find-references" },
+            PrefixBinding { key: KeyCombo::plain('!'), command: "lsp-diagnostics" },
+            PrefixBinding { key: KeyCombo::plain(']'), command: "next-hunk" },
+            PrefixBinding { key: KeyCombo::plain('['), command: "previous-hunk" },
+            PrefixBinding { key: KeyCombo::plain('|'), command: "shell-command-replace-region" },
+            PrefixBinding { key: KeyCombo::plain('n'), command: "add-cursor-below" },
+            PrefixBinding { key: KeyCombo::plain('p'), command: "add-cursor-above" },
+            PrefixBinding { key: KeyCombo::plain('d'), command: "add-cursor-next-match" },
+            PrefixBinding { key: KeyCombo::plain('a'), command: "add-cursors-all-matches" },
+            PrefixBinding { key: KeyCombo::plain('f'), command: "toggle-fold" },
+            PrefixBinding { key: KeyCombo::plain('-'), command: "fold" },
+            PrefixBinding { key: KeyCombo::plain('+'), command: "unfold" },
+            PrefixBinding { key: KeyCombo::plain('F'), command: "fold-all-at-level" },
+            PrefixBinding { key: KeyCombo::plain('U'), command: "unfold-all" },
+            PrefixBinding { key: KeyCombo::plain('o'), command: "outline" },
         ]
     }
 }
 
+/// C-t prefix implementation: text transforms (the M-q menu actions)
+pub struct CtrlTPrefix;
+
+impl KeyPrefix for CtrlTPrefix {
+    fn trigger(&self) -> KeyCombo {
+        KeyCombo::ctrl('t')
+    }
+
+    fn display_name(&self) -> &str {
+        "C-t"
+    }
+
+    fn bindings(&self) -> Vec<PrefixBinding> {
+        vec![
+            PrefixBinding { key: KeyCombo::plain('u'), command: "upcase-region" },
+            PrefixBinding { key: KeyCombo::plain('l'), command: "downcase-region" },
+            PrefixBinding { key: KeyCombo::plain('c'), command: "capitalize-region" },
+            PrefixBinding { key: KeyCombo::plain('r'), command: "reverse-region" },
+            PrefixBinding { key: KeyCombo::plain('b'), command: "base64-encode-region" },
+            PrefixBinding { key: KeyCombo::plain('B'), command: "base64-decode-region" },
+            PrefixBinding { key: KeyCombo::plain('e'), command: "url-encode-region" },
+            PrefixBinding { key: KeyCombo::plain('E'), command: "url-decode-region" },
+            PrefixBinding { key: KeyCombo::plain('d'), command: "insert-date" },
+            PrefixBinding { key: KeyCombo::plain('t'), command: "insert-time" },
+            PrefixBinding { key: KeyCombo::plain('T'), command: "insert-date-time" },
+            PrefixBinding { key: KeyCombo::plain('w'), command: "count-words" },
+            PrefixBinding { key: KeyCombo::plain('n'), command: "count-chars" },
+            PrefixBinding { key: KeyCombo::plain('L'), command: "count-lines" },
+        ]
+    }
+}
+
+/// Follow-up keys of a text object prefix, running `action` on the object
+fn text_object_bindings(action: TextObjectAction) -> Vec<PrefixBinding> {
+    TextObject::KEYS
+        .iter()
+        .flat_map(|(object, keys)| {
+            keys.iter().map(move |key| PrefixBinding {
+                key: KeyCombo::plain(*key),
+                command: action.command(*object),
+            })
+        })
+        .collect()
+}
+
+/// M-m prefix implementation: select inside a text object (pair, tag or word)
+pub struct SelectInsidePrefix;
+
+impl KeyPrefix for SelectInsidePrefix {
+    fn trigger(&self) -> KeyCombo {
+        KeyCombo::alt('m')
+    }
+
+    fn display_name(&self) -> &str {
+        "M-m (inside)"
+    }
+
+    fn bindings(&self) -> Vec<PrefixBinding> {
+        text_object_bindings(TextObjectAction::SelectInside)
+    }
+}
+
+/// M-a prefix implementation: select a text object with its delimiters
+pub struct SelectAroundPrefix;
+
+impl KeyPrefix for SelectAroundPrefix {
+    fn trigger(&self) -> KeyCombo {
+        KeyCombo::alt('a')
+    }
+
+    fn display_name(&self) -> &str {
+        "M-a (around)"
+    }
+
+    fn bindings(&self) -> Vec<PrefixBinding> {
+        text_object_bindings(TextObjectAction::SelectAround)
+    }
+}
+
 /// Command registry holding all available commands
 pub struct CommandRegistry {
     commands: HashMap<&'static str, Command>,
@@ -276,6 +479,62 @@ impl CommandRegistry {
             category: Category::Movement,
             keybinding: Some(Keybinding::Single(KeyCombo::ctrl('l'))),
         });
+        self.register(Command {
+            name: "isearch-forward",
+            description: "Incremental search forward",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl('s'))),
+        });
+        self.register(Command {
+            name: "isearch-backward",
+            description: "Incremental search backward",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl('r'))),
+        });
+
+        // Structural movement (syntax tree)
+        self.register(Command {
+            name: "forward-sibling-node",
+            description: "Move to the next sibling syntax node",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('f'))),
+        });
+        self.register(Command {
+            name: "backward-sibling-node",
+            description: "Move to the start of the syntax node or its previous sibling",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('b'))),
+        });
+        self.register(Command {
+            name: "up-node",
+            description: "Move to the start of the enclosing syntax node",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('u'))),
+        });
+        self.register(Command {
+            name: "beginning-of-defun",
+            description: "Move to the beginning of the enclosing function or class",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('a'))),
+        });
+        self.register(Command {
+            name: "end-of-defun",
+            description: "Move to the end of the enclosing function or class",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('e'))),
+        });
+        self.register(Command {
+            name: "next-function",
+            description: "Move to the next function definition",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('n'))),
+        });
+        self.register(Command {
+            name: "previous-function",
+            description: "Move to the previous function definition",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl_alt('p'))),
+        });
 
         // File commands
         self.register(Command {
@@ -302,6 +561,24 @@ impl CommandRegistry {
             category: Category::File,
             keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('x'), KeyCombo::plain('k')])),
         });
+        self.register(Command {
+            name: "switch-to-buffer",
+            description: "Switch to another open buffer",
+            category: Category::File,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('x'), KeyCombo::plain('b')])),
+        });
+        self.register(Command {
+            name: "list-buffers",
+            description: "List all open buffers",
+            category: Category::File,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('x'), KeyCombo::ctrl('b')])),
+        });
+        self.register(Command {
+            name: "kill-buffer",
+            description: "Close a buffer",
+            category: Category::File,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('x'), KeyCombo::ctrl('k')])),
+        });
 
         // Edit commands
         self.register(Command {
@@ -334,6 +611,33 @@ impl CommandRegistry {
             category: Category::Edit,
             keybinding: Some(Keybinding::Single(KeyCombo::ctrl('y'))),
         });
+        self.register(Command {
+            name: "yank-pop",
+            description: "Replace the yanked text with an older kill",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('y'))),
+        });
+        self.register(Command {
+            name: "browse-kill-ring",
+            description: "Pick killed text to yank from a list",
+            category: Category::Edit,
+            keybinding: None,
+        });
+        self.register(Command {
+            name: "query-replace",
+            description: "Replace string, asking about each match",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('%'))),
+        });
+        self.register(Command {
+            name: "query-replace-regexp",
+            description: "Replace regexp (\\1 or $1 for groups), asking about each match",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Single(KeyCombo::new(
+                KeyCode::Char('%'),
+                KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+            ))),
+        });
         self.register(Command {
             name: "undo",
             description: "Undo last edit",
@@ -346,6 +650,12 @@ impl CommandRegistry {
             category: Category::Edit,
             keybinding: Some(Keybinding::Single(KeyCombo::alt('z'))),
         });
+        self.register(Command {
+            name: "undo-tree",
+            description: "Show the undo history as a tree",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('x'), KeyCombo::plain('u')])),
+        });
         self.register(Command {
             name: "insert-newline",
             description: "Insert a newline",
@@ -356,13 +666,13 @@ impl CommandRegistry {
             name: "delete-char",
             description: "Delete character at cursor",
             category: Category::Edit,
-            keybinding: Some(Keybinding::Single(KeyCombo::special(KeyCode::Delete))),
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl('d'))),
         });
         self.register(Command {
             name: "delete-char-backward",
             description: "Delete character before cursor",
             category: Category::Edit,
-            keybinding: Some(Keybinding::Single(KeyCombo::special(KeyCode::Backspace))),
+            keybinding: Some(Keybinding::Single(KeyCombo::ctrl('h'))),
         });
         self.register(Command {
             name: "insert-char",
@@ -408,6 +718,54 @@ impl CommandRegistry {
             category: Category::Selection,
             keybinding: None, // Via C-g
         });
+        self.register(Command {
+            name: "expand-selection",
+            description: "Expand selection to the enclosing syntax node",
+            category: Category::Selection,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('o'))),
+        });
+        self.register(Command {
+            name: "shrink-selection",
+            description: "Shrink selection to the previous syntax node",
+            category: Category::Selection,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('i'))),
+        });
+        self.register(Command {
+            name: "add-cursor-below",
+            description: "Add a cursor on the next line",
+            category: Category::Selection,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('n')])),
+        });
+        self.register(Command {
+            name: "add-cursor-above",
+            description: "Add a cursor on the previous line",
+            category: Category::Selection,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('p')])),
+        });
+        self.register(Command {
+            name: "add-cursor-next-match",
+            description: "Add a cursor at the next occurrence of the selection",
+            category: Category::Selection,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('d')])),
+        });
+        self.register(Command {
+            name: "add-cursors-all-matches",
+            description: "Add a cursor at every match of a jump pattern",
+            category: Category::Selection,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('a')])),
+        });
+        self.register(Command {
+            name: "outline",
+            description: "List the symbols of the buffer and jump to one",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('o')])),
+        });
+        self.register(Command {
+            name: "jump-to-char",
+            description: "Jump to a visible match (avy-style)",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('j'))),
+        });
 
         // Display commands
         self.register(Command {
@@ -416,6 +774,36 @@ impl CommandRegistry {
             category: Category::Edit,
             keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('x'), KeyCombo::plain('w')])),
         });
+        self.register(Command {
+            name: "toggle-fold",
+            description: "Fold or unfold the definition at the cursor",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('f')])),
+        });
+        self.register(Command {
+            name: "fold",
+            description: "Fold the definition at the cursor",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('-')])),
+        });
+        self.register(Command {
+            name: "unfold",
+            description: "Unfold the fold at the cursor",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('+')])),
+        });
+        self.register(Command {
+            name: "fold-all-at-level",
+            description: "Fold every definition at a nesting level",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('F')])),
+        });
+        self.register(Command {
+            name: "unfold-all",
+            description: "Unfold every fold",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('U')])),
+        });
 
         // System commands
         self.register(Command {
@@ -454,12 +842,167 @@ impl CommandRegistry {
             category: Category::System,
             keybinding: Some(Keybinding::Single(KeyCombo::alt('x'))),
         });
+        self.register(Command {
+            name: "view-messages",
+            description: "Show the *Messages* history",
+            category: Category::System,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('m')])),
+        });
+        self.register(Command {
+            name: "save-settings",
+            description: "Save current settings to the config file",
+            category: Category::System,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('s')])),
+        });
+
+        // Language server commands
+        self.register(Command {
+            name: "lsp-hover",
+            description: "Show documentation for the symbol at point",
+            category: Category::System,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('h')])),
+        });
+        self.register(Command {
+            name: "lsp-goto-definition",
+            description: "Jump to the definition of the symbol at point",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('.'))),
+        });
+        self.register(Command {
+            name: "lsp-find-references",
+            description: "List references to the symbol at point",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('r')])),
+        });
+        self.register(Command {
+            name: "lsp-complete",
+            description: "Complete the word at point using the language server",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('/'))),
+        });
+        self.register(Command {
+            name: "lsp-diagnostics",
+            description: "List language server diagnostics for this buffer",
+            category: Category::System,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('!')])),
+        });
+        self.register(Command {
+            name: "lsp-restart",
+            description: "Restart the language server for this buffer",
+            category: Category::System,
+            keybinding: None,
+        });
+
+        // Git gutter commands
+        self.register(Command {
+            name: "next-hunk",
+            description: "Move to the next change against HEAD",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain(']')])),
+        });
+        self.register(Command {
+            name: "previous-hunk",
+            description: "Move to the previous change against HEAD",
+            category: Category::Movement,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('[')])),
+        });
+
+        // Shell commands
+        self.register(Command {
+            name: "shell-command",
+            description: "Run a shell command and insert its output",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('!'))),
+        });
+        self.register(Command {
+            name: "shell-command-on-region",
+            description: "Pipe the region (or buffer) through a command and show the output",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Single(KeyCombo::alt('|'))),
+        });
+        self.register(Command {
+            name: "shell-command-replace-region",
+            description: "Replace the region (or buffer) with a command's output",
+            category: Category::Edit,
+            keybinding: Some(Keybinding::Sequence(vec![KeyCombo::ctrl('c'), KeyCombo::plain('|')])),
+        });
+
+        // File format commands
+        let format_commands: &[(&'static str, &'static str)] = &[
+            ("set-line-ending-lf", "Save the file with LF line endings"),
+            ("set-line-ending-crlf", "Save the file with CRLF line endings"),
+            ("toggle-final-newline", "Toggle the newline at the end of the file"),
+            ("toggle-bom", "Toggle the byte order mark"),
+            ("set-encoding", "Save the file in another encoding"),
+            ("recover-this-file", "Recover auto-saved changes to the file"),
+            ("revert-buffer", "Reload the file from disk, discarding changes"),
+            ("purge-undo-history", "Delete saved undo histories of changed or deleted files"),
+        ];
+        for (name, description) in format_commands {
+            self.register(Command {
+                name,
+                description,
+                category: Category::File,
+                keybinding: None,
+            });
+        }
+
+        // Text commands (the M-q menu actions, also under C-t)
+        let text_commands: &[(&'static str, &'static str, Option<char>)] = &[
+            ("upcase-region", "Convert the selection to UPPERCASE", Some('u')),
+            ("downcase-region", "Convert the selection to lowercase", Some('l')),
+            ("capitalize-region", "Capitalize each word in the selection", Some('c')),
+            ("reverse-region", "Reverse the selected text", Some('r')),
+            ("base64-encode-region", "Base64-encode the selection", Some('b')),
+            ("base64-decode-region", "Base64-decode the selection", Some('B')),
+            ("url-encode-region", "URL-encode the selection", Some('e')),
+            ("url-decode-region", "URL-decode the selection", Some('E')),
+            ("insert-date", "Insert the current date", Some('d')),
+            ("insert-time", "Insert the current time", Some('t')),
+            ("insert-date-time", "Insert the current date and time", Some('T')),
+            ("insert-lorem", "Insert lorem ipsum text", None),
+            ("insert-bullets", "Insert a bullet list", None),
+            ("insert-numbers", "Insert a numbered list", None),
+            ("insert-todo", "Insert a todo list", None),
+            ("count-words", "Insert the word count of the selection", Some('w')),
+            ("count-chars", "Insert the character count of the selection", Some('n')),
+            ("count-lines", "Insert the line count of the selection", Some('L')),
+        ];
+        for (name, description, key) in text_commands {
+            self.register(Command {
+                name,
+                description,
+                category: Category::Text,
+                keybinding: key.map(|c| Keybinding::Sequence(vec![KeyCombo::ctrl('t'), KeyCombo::plain(c)])),
+            });
+        }
+
+        // Text object commands (select inside under M-m, around under M-a)
+        for (action, object, name) in TextObjectAction::COMMANDS {
+            let prefix = match action {
+                TextObjectAction::SelectInside => Some(KeyCombo::alt('m')),
+                TextObjectAction::SelectAround => Some(KeyCombo::alt('a')),
+                _ => None,
+            };
+            let key = TextObject::KEYS.iter().find(|(o, _)| o == object).map(|(_, keys)| keys[0]);
+            self.register(Command {
+                name,
+                description: action.description(),
+                category: Category::Selection,
+                keybinding: prefix.zip(key).map(|(prefix, key)| Keybinding::Sequence(vec![prefix, KeyCombo::plain(key)])),
+            });
+        }
     }
 
     fn register(&mut self, command: Command) {
         self.commands.insert(command.name, command);
     }
 
+    /// Look up a command by name
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
     /// Get all commands
     pub fn all_commands(&self) -> impl Iterator<Item = &Command> {
         self.commands.values()
@@ -483,6 +1026,34 @@ mod tests {
         assert_eq!(KeyCombo::plain('k').display(), "k");
     }
 
+    #[test]
+    fn test_keycombo_parse() {
+        assert_eq!(KeyCombo::parse("C-x"), Ok(KeyCombo::ctrl('x')));
+        assert_eq!(KeyCombo::parse("M-%"), Ok(KeyCombo::alt('%')));
+        assert_eq!(
+            KeyCombo::parse("C-M-f"),
+            Ok(KeyCombo::new(KeyCode::Char('f'), KeyModifiers::CONTROL | KeyModifiers::ALT))
+        );
+        assert_eq!(
+            KeyCombo::parse("C-SPC"),
+            Ok(KeyCombo::new(KeyCode::Char(' '), KeyModifiers::CONTROL))
+        );
+        assert_eq!(KeyCombo::parse("M-DEL"), Ok(KeyCombo::new(KeyCode::Backspace, KeyModifiers::ALT)));
+        assert_eq!(KeyCombo::parse("F5"), Ok(KeyCombo::special(KeyCode::F(5))));
+        assert!(KeyCombo::parse("C-foo").is_err());
+
+        // Round-trips through display
+        for s in ["C-x", "M-f", "C-SPC", "M-Backspace", "k"] {
+            assert_eq!(KeyCombo::parse(s).unwrap().display(), s);
+        }
+    }
+
+    #[test]
+    fn test_keycombo_from_event_drops_shift_for_chars() {
+        let event = KeyEvent::new(KeyCode::Char('%'), KeyModifiers::ALT | KeyModifiers::SHIFT);
+        assert_eq!(KeyCombo::from_event(&event), KeyCombo::alt('%'));
+    }
+
     #[test]
     fn test_registry_all_commands() {
         let registry = CommandRegistry::new();
@@ -500,5 +1071,32 @@ mod tests {
 
         let bindings = prefix.bindings();
         assert!(!bindings.is_empty());
+
+        let key = KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL);
+        assert_eq!(prefix.get_command(&key), Some("open-file"));
+    }
+
+    #[test]
+    fn test_prefix_bindings_are_registered() {
+        let registry = CommandRegistry::new();
+        let prefixes: [&dyn KeyPrefix; 5] =
+            [&CtrlXPrefix, &CtrlCPrefix, &CtrlTPrefix, &SelectInsidePrefix, &SelectAroundPrefix];
+        for prefix in prefixes {
+            for binding in prefix.bindings() {
+                assert!(registry.get(binding.command).is_some(), "unregistered: {}", binding.command);
+            }
+        }
+
+        for (_, command) in crate::editor::MenuAction::COMMANDS {
+            assert!(registry.get(command).is_some(), "unregistered: {}", command);
+        }
+
+        // Quotes are typed with SHIFT on most layouts
+        let key = KeyEvent::new(KeyCode::Char('"'), KeyModifiers::SHIFT);
+        assert_eq!(SelectInsidePrefix.get_command(&key), Some("select-inside-double-quotes"));
+
+        // Shifted letters arrive with SHIFT set
+        let key = KeyEvent::new(KeyCode::Char('B'), KeyModifiers::SHIFT);
+        assert_eq!(CtrlTPrefix.get_command(&key), Some("base64-decode-region"));
     }
 }
@@ -0,0 +1,392 @@
+//! User configuration file (`~/.config/jae/config.toml`).
+//!
+//! Settings are read at startup and applied on top of `Settings::default()`.
+//! Each entry is validated on its own: a bad value or unknown key produces a
+//! warning and keeps the default, it never aborts loading.
+//!
+//! ```toml
+//! soft_wrap = true
+//! wrap_indicator = "↪ "
+//! line_numbers = "hybrid"   # off, absolute, relative or hybrid
+//! backup = "versioned"      # off, simple (file~) or versioned
+//! backup_dir = "~/.cache/jae/backups"
+//! autosave_interval_secs = 30  # 0 turns auto-save off
+//! undo_history_max_mb = 50     # 0 discards undo history on exit
+//! cursor_color = "red"
+//! selection_color = "#5f00af"
+//! floating_window_width = 60
+//! parse_timeout_ms = 100
+//! jump_timeout_ms = 500
+//! ```
+
+use crate::editor::settings::WINDOW_SIZE_RANGE;
+use crate::editor::{BackupMode, LineNumbers, Settings};
+use ratatui::style::Color;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::{Table, Value};
+
+/// Keys written by `save_settings` (everything else in the file is preserved)
+const SETTINGS_KEYS: &[&str] = &[
+    "show_metadata",
+    "show_preview",
+    "floating_window_width",
+    "floating_window_height",
+    "cursor_color",
+    "selection_color",
+    "soft_wrap",
+    "wrap_indicator",
+    "wrap_indicator_color",
+    "line_numbers",
+    "backup",
+    "backup_dir",
+    "autosave_interval_secs",
+    "autosave_dir",
+    "undo_history_max_mb",
+    "undo_history_dir",
+    "parse_timeout_ms",
+    "jump_timeout_ms",
+];
+
+/// Path of the config file (`<config dir>/jae/config.toml`)
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("jae").join("config.toml"))
+}
+
+/// Read and parse the config file.
+/// A missing file is not an error and yields an empty table.
+pub fn read_config_table(path: &Path) -> Result<Table, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .parse::<Table>()
+            .map_err(|e| format!("{}: {}", path.display(), e.message())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Load settings from the config file, returning any warnings
+pub fn load_settings(path: &Path) -> (Settings, Vec<String>) {
+    let mut settings = Settings::default();
+    let warnings = match read_config_table(path) {
+        Ok(table) => apply_settings(&mut settings, &table),
+        Err(e) => vec![e],
+    };
+    (settings, warnings)
+}
+
+/// Apply the settings entries of a config table. Returns a warning for every
+/// invalid entry (which is skipped).
+pub fn apply_settings(settings: &mut Settings, table: &Table) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for (key, value) in table {
+        let result = match key.as_str() {
+            "show_metadata" => as_bool(value).map(|v| settings.show_metadata = v),
+            "show_preview" => as_bool(value).map(|v| settings.show_preview = v),
+            "soft_wrap" => as_bool(value).map(|v| settings.soft_wrap = v),
+            "floating_window_width" => {
+                as_window_size(value).map(|v| settings.floating_window_width = v)
+            }
+            "floating_window_height" => {
+                as_window_size(value).map(|v| settings.floating_window_height = v)
+            }
+            "cursor_color" => as_color(value).map(|v| settings.cursor_color = v),
+            "selection_color" => as_color(value).map(|v| settings.selection_color = v),
+            "wrap_indicator_color" => as_color(value).map(|v| settings.wrap_indicator_color = v),
+            "wrap_indicator" => match value {
+                Value::String(s) => {
+                    settings.wrap_indicator = s.clone();
+                    Ok(())
+                }
+                _ => Err("expected a string".to_string()),
+            },
+            "line_numbers" => match value {
+                Value::String(s) => LineNumbers::from_name(s)
+                    .map(|v| settings.line_numbers = v)
+                    .ok_or_else(|| format!("unknown mode '{}' (off, absolute, relative or hybrid)", s)),
+                _ => Err("expected a string".to_string()),
+            },
+            "backup" => match value {
+                Value::String(s) => BackupMode::from_name(s)
+                    .map(|v| settings.backup = v)
+                    .ok_or_else(|| format!("unknown mode '{}' (off, simple or versioned)", s)),
+                _ => Err("expected a string".to_string()),
+            },
+            "backup_dir" => match value {
+                Value::String(s) => {
+                    settings.backup_dir = s.clone();
+                    Ok(())
+                }
+                _ => Err("expected a string".to_string()),
+            },
+            "autosave_interval_secs" => as_quantity(value, "seconds").map(|v| settings.autosave_interval_secs = v),
+            "autosave_dir" => match value {
+                Value::String(s) => {
+                    settings.autosave_dir = s.clone();
+                    Ok(())
+                }
+                _ => Err("expected a string".to_string()),
+            },
+            "undo_history_max_mb" => as_quantity(value, "megabytes").map(|v| settings.undo_history_max_mb = v),
+            "undo_history_dir" => match value {
+                Value::String(s) => {
+                    settings.undo_history_dir = s.clone();
+                    Ok(())
+                }
+                _ => Err("expected a string".to_string()),
+            },
+            "parse_timeout_ms" => as_quantity(value, "milliseconds").map(|v| settings.parse_timeout_ms = v),
+            "jump_timeout_ms" => as_quantity(value, "milliseconds").map(|v| settings.jump_timeout_ms = v),
+            // Tables are sections owned by other features (e.g. key bindings)
+            _ => match value {
+                Value::Table(_) => Ok(()),
+                _ => Err("unknown setting".to_string()),
+            },
+        };
+
+        match result {
+            Ok(()) => {}
+            Err(e) => warnings.push(format!("config: {}: {}", key, e)),
+        }
+    }
+
+    warnings
+}
+
+fn as_bool(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Boolean(b) => Ok(*b),
+        _ => Err("expected true or false".to_string()),
+    }
+}
+
+fn as_window_size(value: &Value) -> Result<u16, String> {
+    match value {
+        Value::Integer(n) => u16::try_from(*n)
+            .ok()
+            .filter(|n| WINDOW_SIZE_RANGE.contains(n))
+            .ok_or_else(|| format!("must be between {} and {}", WINDOW_SIZE_RANGE.start(), WINDOW_SIZE_RANGE.end())),
+        _ => Err("expected a number".to_string()),
+    }
+}
+
+/// A non-negative number of `unit`s
+fn as_quantity(value: &Value, unit: &str) -> Result<u64, String> {
+    match value {
+        Value::Integer(n) if *n >= 0 => Ok(*n as u64),
+        Value::Integer(_) => Err("must not be negative".to_string()),
+        _ => Err(format!("expected a number of {}", unit)),
+    }
+}
+
+fn as_color(value: &Value) -> Result<Color, String> {
+    match value {
+        Value::String(s) => Color::from_str(s).map_err(|_| format!("unknown color '{}'", s)),
+        _ => Err("expected a color name or \"#rrggbb\"".to_string()),
+    }
+}
+
+/// Convert settings into config table entries
+pub fn settings_to_table(settings: &Settings) -> Table {
+    let mut table = Table::new();
+    table.insert("show_metadata".into(), Value::Boolean(settings.show_metadata));
+    table.insert("show_preview".into(), Value::Boolean(settings.show_preview));
+    table.insert(
+        "floating_window_width".into(),
+        Value::Integer(settings.floating_window_width as i64),
+    );
+    table.insert(
+        "floating_window_height".into(),
+        Value::Integer(settings.floating_window_height as i64),
+    );
+    table.insert("cursor_color".into(), Value::String(settings.cursor_color.to_string()));
+    table.insert(
+        "selection_color".into(),
+        Value::String(settings.selection_color.to_string()),
+    );
+    table.insert("soft_wrap".into(), Value::Boolean(settings.soft_wrap));
+    table.insert("wrap_indicator".into(), Value::String(settings.wrap_indicator.clone()));
+    table.insert(
+        "wrap_indicator_color".into(),
+        Value::String(settings.wrap_indicator_color.to_string()),
+    );
+    table.insert(
+        "line_numbers".into(),
+        Value::String(settings.line_numbers.name().to_string()),
+    );
+    table.insert("backup".into(), Value::String(settings.backup.name().to_string()));
+    table.insert("backup_dir".into(), Value::String(settings.backup_dir.clone()));
+    table.insert(
+        "autosave_interval_secs".into(),
+        Value::Integer(settings.autosave_interval_secs.min(i64::MAX as u64) as i64),
+    );
+    table.insert("autosave_dir".into(), Value::String(settings.autosave_dir.clone()));
+    table.insert(
+        "undo_history_max_mb".into(),
+        Value::Integer(settings.undo_history_max_mb.min(i64::MAX as u64) as i64),
+    );
+    table.insert(
+        "undo_history_dir".into(),
+        Value::String(settings.undo_history_dir.clone()),
+    );
+    table.insert(
+        "parse_timeout_ms".into(),
+        Value::Integer(settings.parse_timeout_ms.min(i64::MAX as u64) as i64),
+    );
+    table.insert(
+        "jump_timeout_ms".into(),
+        Value::Integer(settings.jump_timeout_ms.min(i64::MAX as u64) as i64),
+    );
+    table
+}
+
+/// Write the settings to the config file. Only the keys in `SETTINGS_KEYS`
+/// change; comments, layout and every other entry are kept as written.
+pub fn save_settings(settings: &Settings, path: &Path) -> Result<(), String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let mut document = contents
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| format!("{}: {}", path.display(), e.message()))?;
+
+    let table = settings_to_table(settings);
+    for key in SETTINGS_KEYS {
+        let value = match table.get(*key) {
+            Some(value) => value,
+            None => continue,
+        };
+        let value = value
+            .to_string()
+            .parse::<toml_edit::Value>()
+            .map_err(|e| format!("{}: {}", key, e))?;
+        match document.get_mut(key).and_then(|item| item.as_value_mut()) {
+            // Keep the comment after the value
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = value;
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                document.insert(key, toml_edit::Item::Value(value));
+            }
+        }
+    }
+
+    match path.parent() {
+        Some(dir) => fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?,
+        None => {}
+    }
+    crate::fileio::write_atomic(path, document.to_string().as_bytes())
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_apply_valid_settings() {
+        let table: Table = r##"
+            soft_wrap = true
+            cursor_color = "green"
+            selection_color = "#102030"
+            floating_window_width = 80
+            jump_timeout_ms = 250
+            line_numbers = "relative"
+            backup = "simple"
+            autosave_interval_secs = 0
+            undo_history_max_mb = 10
+        "##
+        .parse()
+        .unwrap();
+
+        let mut settings = Settings::default();
+        let warnings = apply_settings(&mut settings, &table);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(settings.soft_wrap);
+        assert_eq!(settings.cursor_color, Color::Green);
+        assert_eq!(settings.selection_color, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(settings.floating_window_width, 80);
+        assert_eq!(settings.jump_timeout_ms, 250);
+        assert_eq!(settings.line_numbers, LineNumbers::Relative);
+        assert_eq!(settings.backup, BackupMode::Simple);
+        assert_eq!(settings.autosave_interval_secs, 0);
+        assert_eq!(settings.undo_history_max_mb, 10);
+    }
+
+    #[test]
+    fn test_invalid_entries_warn_and_keep_defaults() {
+        let table: Table = r#"
+            soft_wrap = "yes"
+            cursor_color = "chartreuse"
+            floating_window_height = 5
+            no_such_setting = 1
+            show_preview = false
+        "#
+        .parse()
+        .unwrap();
+
+        let mut settings = Settings::default();
+        let warnings = apply_settings(&mut settings, &table);
+        assert_eq!(warnings.len(), 4);
+        assert!(!settings.soft_wrap);
+        assert_eq!(settings.cursor_color, Settings::default().cursor_color);
+        assert_eq!(settings.floating_window_height, Settings::default().floating_window_height);
+        // Valid entries are still applied
+        assert!(!settings.show_preview);
+    }
+
+    #[test]
+    fn test_save_roundtrip_preserves_other_sections() {
+        let dir = TempDir::new("config-roundtrip");
+        let path = dir.join("config.toml");
+        fs::write(&path, "soft_wrap = false\n[keys]\n\"C-o\" = \"undo\"\n").unwrap();
+
+        let settings = Settings {
+            soft_wrap: true,
+            selection_color: Color::Cyan,
+            ..Settings::default()
+        };
+        save_settings(&settings, &path).unwrap();
+
+        let (loaded, warnings) = load_settings(&path);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(loaded.soft_wrap);
+        assert_eq!(loaded.selection_color, Color::Cyan);
+        let table = read_config_table(&path).unwrap();
+        assert!(table.get("keys").and_then(|k| k.as_table()).is_some());
+    }
+
+    #[test]
+    fn test_save_keeps_comments_and_layout() {
+        let dir = TempDir::new("config-comments");
+        let path = dir.join("config.toml");
+        let original = "# My editor\nsoft_wrap = false  # off on small screens\n\n[keys]\n# undo\n\"C-o\" = \"undo\"\n";
+        fs::write(&path, original).unwrap();
+
+        let settings = Settings {
+            soft_wrap: true,
+            ..Settings::default()
+        };
+        save_settings(&settings, &path).unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.starts_with("# My editor\nsoft_wrap = true  # off on small screens\n"), "{}", saved);
+        assert!(saved.ends_with("\n[keys]\n# undo\n\"C-o\" = \"undo\"\n"), "{}", saved);
+        assert!(saved.contains("jump_timeout_ms = "));
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let (settings, warnings) = load_settings(Path::new("/nonexistent/jae/config.toml"));
+        assert!(warnings.is_empty());
+        assert_eq!(settings.floating_window_width, Settings::default().floating_window_width);
+    }
+}
@@ -0,0 +1,284 @@
+//! Auto-save and crash recovery.
+//!
+//! `autosave_tick` runs from the main loop. Every `autosave_interval_secs`
+//! it copies each file buffer edited since the last auto-save to a recovery
+//! file (see `crate::fileio`), always as UTF-8 with LF line endings so the
+//! copy can be written whatever the file's own format is.
+//!
+//! When a file is opened and its recovery file is newer than the file, a
+//! `RecoverFileConfirmation` offers to recover the auto-saved text, show how
+//! it differs from the file, or discard it.
+
+use super::dialogs::RecoverFileConfirmation;
+use super::diff_view::unified_diff;
+use super::{Editor, FileFormat};
+use crate::fileio;
+use ropey::Rope;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Contents written to a recovery file
+fn recovery_bytes(text: &Rope) -> Vec<u8> {
+    // UTF-8 can encode any buffer, so this never fails
+    FileFormat::default().encode(text).unwrap_or_default()
+}
+
+/// Read and decode a file into buffer text
+fn read_text(path: &Path) -> io::Result<String> {
+    Ok(FileFormat::decode(&fs::read(path)?).0)
+}
+
+/// Read and decode a file into buffer lines
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_text(path)?.split('\n').map(str::to_string).collect())
+}
+
+impl Editor {
+    // ==================== Auto-save ====================
+
+    /// Directory recovery files are written to
+    pub fn autosave_dir(&self) -> Option<PathBuf> {
+        match self.settings.autosave_dir.is_empty() {
+            true => fileio::default_autosave_dir(),
+            false => Some(Self::expand_path(&self.settings.autosave_dir)),
+        }
+    }
+
+    /// Auto-save modified buffers once the interval has passed
+    pub fn autosave_tick(&mut self) {
+        let interval = Duration::from_secs(self.settings.autosave_interval_secs);
+        match interval.is_zero() || self.last_autosave.elapsed() < interval {
+            true => {}
+            false => {
+                self.autosave_now();
+            }
+        }
+    }
+
+    /// Write a recovery file for every file buffer edited since the last
+    /// auto-save. Returns how many were written.
+    pub fn autosave_now(&mut self) -> usize {
+        self.last_autosave = Instant::now();
+        let dir = match self.autosave_dir() {
+            Some(dir) => dir,
+            None => return 0,
+        };
+
+        let mut written = 0;
+        let mut failed: Option<String> = None;
+        let mut autosave = |path: Option<&Path>, text: &Rope, pending: &mut bool| match path {
+            Some(path) if *pending => match fileio::write_recovery_file(&dir, path, &recovery_bytes(text)) {
+                Ok(_) => {
+                    *pending = false;
+                    written += 1;
+                }
+                Err(e) => {
+                    failed.get_or_insert_with(|| format!("Auto-save of {} failed: {}", path.display(), e));
+                }
+            },
+            _ => {}
+        };
+
+        autosave(self.current_file.as_deref(), self.textarea.rope(), &mut self.autosave_pending);
+        for buffer in &mut self.buffers {
+            autosave(buffer.current_file.as_deref(), buffer.textarea.rope(), &mut buffer.autosave_pending);
+        }
+
+        // A failure is reported once, not at every interval, until an
+        // auto-save works again
+        match failed {
+            Some(text) if self.autosave_failure.as_ref() != Some(&text) => {
+                self.warn(text.clone());
+                self.autosave_failure = Some(text);
+            }
+            Some(_) => {}
+            None if written > 0 => self.autosave_failure = None,
+            None => {}
+        }
+        written
+    }
+
+    /// Delete the recovery file of a file that was saved or whose buffer was killed
+    pub fn remove_recovery_file(&mut self, path: &Path) {
+        match self.autosave_dir() {
+            Some(dir) => match fileio::remove_recovery_file(&dir, path) {
+                Ok(()) => {}
+                Err(e) => self.warn(format!("Could not remove recovery file of {}: {}", path.display(), e)),
+            },
+            None => {}
+        }
+    }
+
+    /// After opening a file: offer to recover it when a newer recovery file exists.
+    /// A recovery file with the same text as the file is simply deleted.
+    pub fn check_recovery_file(&mut self) {
+        let path = match &self.current_file {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let recovery = match self.autosave_dir().and_then(|dir| fileio::newer_recovery_file(&dir, &path)) {
+            Some(recovery) => recovery,
+            None => return,
+        };
+        match read_text(&recovery) {
+            Ok(text) if *self.textarea.rope() == text => self.remove_recovery_file(&path),
+            _ => self.offer_recovery(path, recovery),
+        }
+    }
+
+    /// Offer to recover the current file from its recovery file (M-x recover-this-file)
+    pub fn recover_this_file(&mut self) {
+        let path = match &self.current_file {
+            Some(path) => path.clone(),
+            None => {
+                self.message("Buffer is not visiting a file");
+                return;
+            }
+        };
+        let recovery = self.autosave_dir().map(|dir| fileio::recovery_file_path(&dir, &path));
+        match recovery {
+            Some(recovery) if recovery.is_file() => self.offer_recovery(path, recovery),
+            _ => self.message(format!("No auto-save data for {}", path.display())),
+        }
+    }
+
+    /// Ask whether to recover, diff or discard a recovery file
+    pub fn offer_recovery(&mut self, path: PathBuf, recovery: PathBuf) {
+        self.start_confirmation(Box::new(RecoverFileConfirmation::new(path, recovery)));
+    }
+
+    /// Replace the current buffer's text with the contents of a recovery file.
+    /// The buffer stays modified (and the recovery file is kept) until saved.
+    pub fn recover_file(&mut self, recovery: &Path) {
+        let text = match read_text(recovery) {
+            Ok(text) => text,
+            Err(e) => {
+                self.error(format!("Cannot read {}: {}", recovery.display(), e));
+                return;
+            }
+        };
+
+        self.save_undo_state();
+        self.textarea.set_text(&text);
+        self.cancel_mark();
+        self.mark_modified();
+        self.message("Recovered auto-saved changes; save to keep them");
+    }
+
+    /// Delete a recovery file the user chose not to recover
+    pub fn discard_recovery(&mut self, path: &Path) {
+        self.remove_recovery_file(path);
+        self.message("Auto-saved changes discarded");
+    }
+
+    /// Show the differences between a file and its recovery file.
+    /// Closing the pane asks about the recovery file again.
+    pub fn show_recovery_diff(&mut self, path: PathBuf, recovery: PathBuf) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let lines = match (read_lines(&path), read_lines(&recovery)) {
+            (Ok(saved), Ok(recovered)) => unified_diff(&saved, &recovered, &name, &format!("{} (auto-saved)", name)),
+            (Err(e), _) | (_, Err(e)) => {
+                self.error(format!("Cannot compare with the recovery file: {}", e));
+                return;
+            }
+        };
+        let title = format!("Auto-saved changes to {}", path.display());
+        self.show_diff(title, lines, Box::new(RecoverFileConfirmation::new(path, recovery)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_in;
+    use crate::editor::{ConfirmationDialog, FloatingMode, ResponseResult};
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_autosave_writes_edited_file_buffers() {
+        let dir = TempDir::new("autosave-write");
+        let path = dir.join("a.txt");
+        fs::write(&path, "one\n").unwrap();
+
+        let mut editor = editor_in(&dir);
+        editor.open_file(&path).unwrap();
+        assert_eq!(editor.autosave_now(), 0);
+
+        editor.textarea.insert_str("new ");
+        editor.mark_modified();
+        assert_eq!(editor.autosave_now(), 1);
+        // Nothing changed since the last auto-save
+        assert_eq!(editor.autosave_now(), 0);
+
+        let recovery = fileio::recovery_file_path(&editor.autosave_dir().unwrap(), &path);
+        assert_eq!(fs::read_to_string(&recovery).unwrap(), "new one\n");
+
+        // Saving removes the recovery file
+        editor.save_file().unwrap();
+        assert!(!recovery.exists());
+    }
+
+    #[test]
+    fn test_autosave_failure_is_reported_once() {
+        let dir = TempDir::new("autosave-failure");
+        let path = dir.join("a.txt");
+        fs::write(&path, "one\n").unwrap();
+
+        let mut editor = editor_in(&dir);
+        // A file where the directory should be
+        fs::write(dir.join("autosave"), "").unwrap();
+        editor.open_file(&path).unwrap();
+        let reported = editor.messages.history().len();
+
+        for _ in 0..2 {
+            editor.textarea.insert_str("x");
+            editor.mark_modified();
+            assert_eq!(editor.autosave_now(), 0);
+        }
+        assert_eq!(editor.messages.history().len(), reported + 1);
+        assert!(editor.echo_text().is_some_and(|text| text.starts_with("Auto-save of")));
+    }
+
+    #[test]
+    fn test_recovery_on_open() {
+        let dir = TempDir::new("autosave-recover");
+        let path = dir.join("b.txt");
+        fs::write(&path, "saved\n").unwrap();
+        let autosave = dir.join("autosave");
+        fileio::write_recovery_file(&autosave, &path, b"unsaved\n").unwrap();
+        let old = std::time::SystemTime::now() - Duration::from_secs(60);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+
+        let mut editor = editor_in(&dir);
+        editor.open_file(&path).unwrap();
+        let mut dialog = match editor.floating_window.take().map(|fw| fw.mode) {
+            Some(FloatingMode::Confirm { dialog, .. }) => dialog,
+            _ => panic!("expected the recovery dialog"),
+        };
+
+        // Diff first, then recover
+        assert!(matches!(dialog.handle_response(0, "d", &mut editor), ResponseResult::Finish));
+        dialog.on_complete(&mut editor).unwrap();
+        match &editor.floating_window.as_ref().unwrap().mode {
+            FloatingMode::Diff { lines, .. } => {
+                assert!(lines.contains(&"-saved".to_string()));
+                assert!(lines.contains(&"+unsaved".to_string()));
+            }
+            _ => panic!("expected the diff pane"),
+        }
+
+        let mut dialog = RecoverFileConfirmation::new(path.clone(), fileio::recovery_file_path(&autosave, &path));
+        assert!(matches!(dialog.handle_response(0, "r", &mut editor), ResponseResult::Finish));
+        assert_eq!(editor.textarea.text(), "unsaved");
+        assert!(editor.modified);
+
+        // Undo brings back the file's text
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "saved");
+
+        assert!(matches!(dialog.handle_response(0, "x", &mut editor), ResponseResult::Finish));
+        assert_eq!(fileio::newer_recovery_file(&autosave, &path), None);
+    }
+}
@@ -0,0 +1,445 @@
+//! Buffer abstraction for the JAE editor.
+//!
+//! A `Buffer` bundles everything that belongs to one piece of text being
+//! edited: the text itself, its file path and format, modification flag,
+//! mark, undo history, syntax state, git diff and scroll position.
+//!
+//! The active buffer lives directly on `Editor` (so all editing code can keep
+//! using `editor.textarea`, `editor.mark`, ...). Background buffers are stored
+//! in `Editor::buffers` and swapped in and out when switching.
+
+use super::syntax::{HighlightSpan, Language, SyntaxState};
+use super::{
+    DiskWatch, Editor, FileFormat, FloatingMode, FloatingWindow, GitGutter, MarkState, MinibufferCallback, TextArea,
+    UndoManager,
+};
+use std::path::{Path, PathBuf};
+
+/// Name used for buffers that are not visiting a file
+pub const SCRATCH_BUFFER_NAME: &str = "*scratch*";
+
+/// A single editing buffer
+pub struct Buffer {
+    pub textarea: TextArea,
+    pub current_file: Option<PathBuf>,
+    pub file_format: FileFormat,
+    pub modified: bool,
+    pub autosave_pending: bool,
+    pub disk_watch: DiskWatch,
+    pub mark: MarkState,
+    pub undo_manager: UndoManager,
+    pub language: Language,
+    pub syntax_state: Option<SyntaxState>,
+    pub cached_highlights: Vec<HighlightSpan>,
+    pub syntax_error: Option<String>,
+    pub scroll_offset: usize,
+    pub selection_history: Vec<(usize, usize)>,
+    pub git_gutter: GitGutter,
+}
+
+impl Buffer {
+    /// Create an empty buffer that is not visiting any file
+    pub fn new() -> Self {
+        Self {
+            textarea: TextArea::default(),
+            current_file: None,
+            file_format: FileFormat::default(),
+            modified: false,
+            autosave_pending: false,
+            disk_watch: DiskWatch::default(),
+            mark: MarkState::None,
+            undo_manager: UndoManager::new(),
+            language: Language::PlainText,
+            syntax_state: None,
+            cached_highlights: Vec::new(),
+            syntax_error: None,
+            scroll_offset: 0,
+            selection_history: Vec::new(),
+            git_gutter: GitGutter::default(),
+        }
+    }
+
+    /// Whether this is an untouched scratch buffer that can be reused
+    pub fn is_pristine(&self) -> bool {
+        self.current_file.is_none()
+            && !self.modified
+            && self.textarea.is_empty()
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One row of the buffer list (C-x C-b)
+#[derive(Clone)]
+pub struct BufferListEntry {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub modified: bool,
+    pub is_current: bool,
+}
+
+/// Compute the display name for a buffer.
+///
+/// Uses the file name, or `*scratch*` when there is no file. When another open
+/// buffer visits a file with the same name, the parent directory is appended
+/// (`main.rs<src>`) so names stay unique.
+pub fn buffer_display_name(path: Option<&Path>, all_paths: &[Option<&Path>]) -> String {
+    let path = match path {
+        Some(p) => p,
+        None => return SCRATCH_BUFFER_NAME.to_string(),
+    };
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+
+    let clashes = all_paths
+        .iter()
+        .flatten()
+        .filter(|other| **other != path && other.file_name() == path.file_name())
+        .count();
+
+    match clashes {
+        0 => file_name,
+        _ => {
+            let parent = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            format!("{}<{}>", file_name, parent)
+        }
+    }
+}
+
+impl Editor {
+    // ==================== Buffer Management ====================
+
+    /// Move the active buffer's state out of the editor, leaving a fresh scratch buffer
+    pub(crate) fn take_current_buffer(&mut self) -> Buffer {
+        Buffer {
+            textarea: std::mem::take(&mut self.textarea),
+            current_file: self.current_file.take(),
+            file_format: std::mem::take(&mut self.file_format),
+            modified: std::mem::take(&mut self.modified),
+            autosave_pending: std::mem::take(&mut self.autosave_pending),
+            disk_watch: std::mem::take(&mut self.disk_watch),
+            mark: std::mem::take(&mut self.mark),
+            undo_manager: std::mem::take(&mut self.undo_manager),
+            language: std::mem::take(&mut self.language),
+            syntax_state: self.syntax_state.take(),
+            cached_highlights: std::mem::take(&mut self.cached_highlights),
+            syntax_error: self.syntax_error.take(),
+            scroll_offset: std::mem::take(&mut self.scroll_offset),
+            selection_history: std::mem::take(&mut self.selection_history),
+            git_gutter: std::mem::take(&mut self.git_gutter),
+        }
+    }
+
+    /// Make the given buffer the active one (the previous active state is discarded)
+    pub(crate) fn load_buffer(&mut self, buffer: Buffer) {
+        self.textarea = buffer.textarea;
+        self.current_file = buffer.current_file;
+        self.file_format = buffer.file_format;
+        self.modified = buffer.modified;
+        self.autosave_pending = buffer.autosave_pending;
+        self.disk_watch = buffer.disk_watch;
+        self.mark = buffer.mark;
+        self.undo_manager = buffer.undo_manager;
+        self.language = buffer.language;
+        self.syntax_state = buffer.syntax_state;
+        self.cached_highlights = buffer.cached_highlights;
+        self.syntax_error = buffer.syntax_error;
+        self.scroll_offset = buffer.scroll_offset;
+        self.selection_history = buffer.selection_history;
+        self.git_gutter = buffer.git_gutter;
+
+        self.jump_mode = None;
+        self.isearch = None;
+    }
+
+    /// Push the active buffer to the front of the background list and start a new one.
+    /// A pristine scratch buffer is simply replaced instead of being kept around.
+    pub(crate) fn push_current_buffer(&mut self) {
+        let current = self.take_current_buffer();
+        match current.is_pristine() {
+            true => {}
+            false => self.buffers.insert(0, current),
+        }
+        self.load_buffer(Buffer::new());
+    }
+
+    /// Paths of all open buffers, active buffer first
+    fn all_buffer_paths(&self) -> Vec<Option<&Path>> {
+        std::iter::once(self.current_file.as_deref())
+            .chain(self.buffers.iter().map(|b| b.current_file.as_deref()))
+            .collect()
+    }
+
+    /// Display name of the active buffer
+    pub fn current_buffer_name(&self) -> String {
+        buffer_display_name(self.current_file.as_deref(), &self.all_buffer_paths())
+    }
+
+    /// Names of all open buffers, active buffer first, then most recently used
+    pub fn buffer_names(&self) -> Vec<String> {
+        let paths = self.all_buffer_paths();
+        paths
+            .iter()
+            .map(|p| buffer_display_name(*p, &paths))
+            .collect()
+    }
+
+    /// Names of all buffers with unsaved changes, active buffer first
+    pub fn modified_buffer_names(&self) -> Vec<String> {
+        let names = self.buffer_names();
+        let modified = std::iter::once(self.modified).chain(self.buffers.iter().map(|b| b.modified));
+        names
+            .into_iter()
+            .zip(modified)
+            .filter(|(_, m)| *m)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Whether any open buffer has unsaved changes
+    pub fn has_modified_buffers(&self) -> bool {
+        self.modified || self.buffers.iter().any(|b| b.modified)
+    }
+
+    /// Entries for the buffer list display, active buffer first
+    pub fn buffer_list_entries(&self) -> Vec<BufferListEntry> {
+        let names = self.buffer_names();
+        let current = std::iter::once((self.current_file.clone(), self.modified, true));
+        let others = self
+            .buffers
+            .iter()
+            .map(|b| (b.current_file.clone(), b.modified, false));
+        names
+            .into_iter()
+            .zip(current.chain(others))
+            .map(|(name, (path, modified, is_current))| BufferListEntry {
+                name,
+                path,
+                modified,
+                is_current,
+            })
+            .collect()
+    }
+
+    /// Find the background buffer index visiting the given path (as
+    /// normalized by `fileio::normalize_path`)
+    pub(crate) fn find_buffer_by_path(&self, path: &Path) -> Option<usize> {
+        self.buffers
+            .iter()
+            .position(|b| b.current_file.as_deref() == Some(path))
+    }
+
+    /// Switch to the buffer with the given display name.
+    /// Returns false if no such buffer exists.
+    pub fn switch_to_buffer(&mut self, name: &str) -> bool {
+        let names = self.buffer_names();
+        match names.iter().position(|n| n == name) {
+            Some(0) => true,
+            Some(idx) => {
+                self.switch_to_buffer_index(idx - 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switch to the background buffer at `index` in `self.buffers`
+    pub(crate) fn switch_to_buffer_index(&mut self, index: usize) {
+        match index < self.buffers.len() {
+            true => {
+                let target = self.buffers.remove(index);
+                let current = self.take_current_buffer();
+                self.buffers.insert(0, current);
+                self.load_buffer(target);
+            }
+            false => {}
+        }
+    }
+
+    /// Kill the buffer with the given name without asking.
+    /// Killing the last buffer leaves an empty scratch buffer.
+    pub fn kill_buffer(&mut self, name: &str) -> bool {
+        let names = self.buffer_names();
+        let position = names.iter().position(|n| n == name);
+
+        // The language server no longer needs to track the file, and its
+        // unsaved changes are gone for good
+        let path = match position {
+            Some(0) => self.current_file.clone(),
+            Some(idx) => self.buffers[idx - 1].current_file.clone(),
+            None => None,
+        };
+        match path {
+            Some(path) => {
+                self.lsp.close_document(&crate::lsp::absolute_path(&path));
+                self.remove_recovery_file(&path);
+            }
+            None => {}
+        }
+
+        match position {
+            Some(0) => {
+                let next = match self.buffers.is_empty() {
+                    true => Buffer::new(),
+                    false => self.buffers.remove(0),
+                };
+                self.load_buffer(next);
+                true
+            }
+            Some(idx) => {
+                self.buffers.remove(idx - 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Open minibuffer to switch buffers (C-x b)
+    pub fn switch_to_buffer_prompt(&mut self) {
+        // Default to the most recently used other buffer, like Emacs
+        let mut names = self.buffer_names();
+        let current = names.remove(0);
+        names.push(current);
+        self.open_buffer_name_prompt("Switch to buffer: ", names, MinibufferCallback::SwitchToBuffer);
+    }
+
+    /// Open minibuffer to kill a buffer (C-x C-k)
+    pub fn kill_buffer_prompt(&mut self) {
+        let names = self.buffer_names();
+        self.open_buffer_name_prompt("Kill buffer: ", names, MinibufferCallback::KillBuffer);
+    }
+
+    fn open_buffer_name_prompt(
+        &mut self,
+        prompt: &str,
+        names: Vec<String>,
+        callback: fn(Vec<String>) -> MinibufferCallback,
+    ) {
+        let initial = names.first().cloned().unwrap_or_default();
+        self.open_minibuffer(prompt, &initial, callback(names));
+    }
+
+    /// Kill a buffer, asking for confirmation first if it has unsaved changes
+    pub fn request_kill_buffer(&mut self, name: &str) {
+        let modified = self
+            .buffer_list_entries()
+            .into_iter()
+            .find(|e| e.name == name)
+            .map(|e| e.modified);
+
+        match modified {
+            Some(true) => {
+                let dialog = super::dialogs::KillBufferConfirmation {
+                    name: name.to_string(),
+                };
+                self.start_confirmation(Box::new(dialog));
+            }
+            Some(false) => {
+                self.kill_buffer(name);
+            }
+            None => {}
+        }
+    }
+
+    /// Show the buffer list (C-x C-b)
+    pub fn list_buffers(&mut self) {
+        let entries = self.buffer_list_entries();
+        let height = (entries.len() as u16 + 2).clamp(5, self.settings.floating_window_height.max(5));
+
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(40),
+            height,
+            mode: FloatingMode::BufferList {
+                entries,
+                selected: 0,
+            },
+        });
+        self.focus_floating = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_name_scratch() {
+        assert_eq!(buffer_display_name(None, &[]), SCRATCH_BUFFER_NAME);
+    }
+
+    #[test]
+    fn test_display_name_disambiguates_clashes() {
+        let a = Path::new("/src/a/main.rs");
+        let b = Path::new("/src/b/main.rs");
+        let all = vec![Some(a), Some(b)];
+        assert_eq!(buffer_display_name(Some(a), &all), "main.rs<a>");
+        assert_eq!(buffer_display_name(Some(b), &all), "main.rs<b>");
+
+        let single = vec![Some(a)];
+        assert_eq!(buffer_display_name(Some(a), &single), "main.rs");
+    }
+
+    #[test]
+    fn test_same_file_opens_once() {
+        let dir = crate::test_util::TempDir::new("buffer-same-file");
+        let path = dir.join("a.txt");
+        std::fs::write(&path, "a\n").unwrap();
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("b.txt"), "b\n").unwrap();
+
+        let mut editor = crate::editor::test_util::editor_in(&dir);
+        editor.open_file(&dir.join("./a.txt")).unwrap();
+        editor.open_file(&dir.join("b.txt")).unwrap();
+        editor.open_file(&dir.join("sub/../a.txt")).unwrap();
+        assert_eq!(editor.buffer_names(), vec!["a.txt", "b.txt"]);
+        assert_eq!(editor.current_file, Some(std::fs::canonicalize(&path).unwrap()));
+    }
+
+    #[test]
+    fn test_switch_and_kill_buffers() {
+        let mut editor = Editor::new();
+        editor.textarea.insert_str("first");
+        editor.modified = true;
+        editor.current_file = Some(PathBuf::from("/tmp/first.txt"));
+
+        editor.push_current_buffer();
+        editor.textarea.insert_str("second");
+        editor.current_file = Some(PathBuf::from("/tmp/second.txt"));
+
+        assert_eq!(editor.buffer_names(), vec!["second.txt", "first.txt"]);
+        assert_eq!(editor.modified_buffer_names(), vec!["first.txt"]);
+
+        assert!(editor.switch_to_buffer("first.txt"));
+        assert_eq!(editor.textarea.to_lines(), ["first"]);
+        assert!(editor.modified);
+        assert_eq!(editor.buffer_names(), vec!["first.txt", "second.txt"]);
+
+        assert!(editor.kill_buffer("first.txt"));
+        assert_eq!(editor.textarea.to_lines(), ["second"]);
+        assert_eq!(editor.buffer_names(), vec!["second.txt"]);
+
+        assert!(editor.kill_buffer("second.txt"));
+        assert_eq!(editor.buffer_names(), vec![SCRATCH_BUFFER_NAME]);
+    }
+
+    #[test]
+    fn test_pristine_scratch_is_not_kept() {
+        let mut editor = Editor::new();
+        editor.push_current_buffer();
+        assert!(editor.buffers.is_empty());
+    }
+}
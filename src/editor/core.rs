@@ -1,15 +1,21 @@
 //! Core Editor struct and initialization.
 
 use super::syntax::{HighlightResult, HighlightSpan, Language, SyntaxState};
-use super::{JumpMode, MarkState, Settings, StatusBarState, UndoManager};
+use super::{
+    Buffer, DiskWatch, FileFormat, Messages, GitGutter, ISearch, JumpMode, KillRing, LineNumbers, MarkState, Settings, StatusBarState, TextArea, UndoManager,
+};
 use crate::clipboard::ClipboardManager;
+use crate::keymap::Keymap;
+use crate::lsp::LspManager;
 use ratatui::crossterm::event::{KeyCode, KeyModifiers};
-use ratatui::style::{Color, Modifier, Style};
 use std::path::PathBuf;
-use tui_textarea::TextArea;
+use std::time::Instant;
 
 use super::FloatingWindow;
 
+/// Syntax error recorded while parsing takes longer than `parse_timeout_ms`
+const PARSE_TIMEOUT: &str = "Parse timeout";
+
 /// State for the C-l recenter cycling behavior
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum RecenterState {
@@ -26,16 +32,32 @@ pub enum RecenterState {
 
 /// The main editor state
 pub struct Editor {
-    pub textarea: TextArea<'static>,
+    pub textarea: TextArea,
     pub mark: MarkState,
     pub clipboard: ClipboardManager,
+    /// Killed text shared by all buffers
+    pub kill_ring: KillRing,
+    /// Which cursor (in document order) a multiple-cursor command is running at
+    pub cursor_visit: Option<usize>,
     pub floating_window: Option<FloatingWindow>,
     pub focus_floating: bool,
     pub settings: Settings,
     pub status_bar: StatusBarState,
     pub last_key: Option<(KeyCode, KeyModifiers)>,
     pub current_file: Option<PathBuf>,
+    /// Encoding, line endings and final newline to write the file with
+    pub file_format: FileFormat,
     pub modified: bool,
+    /// Edited since the last auto-save (or save)
+    pub autosave_pending: bool,
+    /// When modified buffers were last auto-saved
+    pub last_autosave: Instant,
+    /// The auto-save failure last reported, until an auto-save succeeds
+    pub autosave_failure: Option<String>,
+    /// What the file looked like on disk when last read or written
+    pub disk_watch: DiskWatch,
+    /// When the file was last checked for changes by other programs
+    pub last_disk_check: Instant,
     pub pending_quit: bool,
     pub undo_manager: UndoManager,
     /// Detected language for syntax operations
@@ -58,41 +80,46 @@ pub struct Editor {
     pub selection_history: Vec<(usize, usize)>,
     /// Avy-like jump mode state (None when not active)
     pub jump_mode: Option<JumpMode>,
+    /// Incremental search state (None when not searching)
+    pub isearch: Option<ISearch>,
+    /// Last completed search string (reused by C-s C-s)
+    pub last_search: String,
+    /// Match currently offered by query-replace, as (start, end) positions
+    pub replace_highlight: Option<((usize, usize), (usize, usize))>,
+    /// Echo-area message and the *Messages* history
+    pub messages: Messages,
+    /// Background buffers, most recently used first (the active buffer lives in the fields above)
+    pub buffers: Vec<Buffer>,
+    /// Active key bindings (defaults plus config overrides)
+    pub keymap: Keymap,
+    /// Language servers and their diagnostics
+    pub lsp: LspManager,
+    /// Changes against the file's HEAD version, shown in the gutter
+    pub git_gutter: GitGutter,
 }
 
 impl Editor {
     /// Create a new editor instance
     pub fn new() -> Self {
-        let mut textarea = TextArea::default();
-        let settings = Settings::default();
-
-        // Set cursor to be a visible block with red background
-        textarea.set_cursor_style(
-            Style::default()
-                .bg(settings.cursor_color)
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        );
-        // Remove underline from the current line
-        textarea.set_cursor_line_style(Style::default());
-        // Set selection style to be magenta/purple background (distinct from cursor)
-        textarea.set_selection_style(
-            Style::default()
-                .bg(settings.selection_color)
-                .fg(Color::White),
-        );
-
         Self {
-            textarea,
+            textarea: TextArea::default(),
             mark: MarkState::None,
             clipboard: ClipboardManager::new(),
+            kill_ring: KillRing::new(),
+            cursor_visit: None,
             floating_window: None,
             focus_floating: false,
-            settings,
+            settings: Settings::default(),
             status_bar: StatusBarState::new(),
             last_key: None,
             current_file: None,
+            file_format: FileFormat::default(),
             modified: false,
+            autosave_pending: false,
+            last_autosave: Instant::now(),
+            autosave_failure: None,
+            disk_watch: DiskWatch::default(),
+            last_disk_check: Instant::now(),
             pending_quit: false,
             undo_manager: UndoManager::new(),
             language: Language::PlainText,
@@ -105,6 +132,14 @@ impl Editor {
             scroll_offset: 0,
             selection_history: Vec::new(),
             jump_mode: None,
+            isearch: None,
+            last_search: String::new(),
+            replace_highlight: None,
+            messages: Messages::default(),
+            buffers: Vec::new(),
+            keymap: Keymap::new(),
+            lsp: LspManager::new(),
+            git_gutter: GitGutter::default(),
         }
     }
 
@@ -113,28 +148,37 @@ impl Editor {
         self.settings.soft_wrap = !self.settings.soft_wrap;
     }
 
-    /// Update textarea colors based on current settings
-    pub fn update_textarea_colors(&mut self) {
-        self.textarea.set_cursor_style(
-            Style::default()
-                .bg(self.settings.cursor_color)
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        );
-        self.textarea.set_selection_style(
-            Style::default()
-                .bg(self.settings.selection_color)
-                .fg(Color::White),
-        );
+    /// Digits reserved for line numbers (0 when they are off).
+    /// Grows with the line count, with room for at least 3 digits.
+    pub fn line_number_width(&self) -> usize {
+        match self.settings.line_numbers {
+            LineNumbers::Off => 0,
+            _ => self.textarea.len_lines().to_string().len().max(3),
+        }
+    }
+
+    /// Columns left of the text taken by the gutter:
+    /// line numbers plus a separating space, then the git marker
+    pub fn gutter_width(&self) -> u16 {
+        let numbers = match self.line_number_width() {
+            0 => 0,
+            width => width + 1,
+        };
+        let git = match self.git_gutter.is_tracked() {
+            true => 1,
+            false => 0,
+        };
+        (numbers + git).min(u16::MAX as usize) as u16
     }
 
     /// Ensure highlights are current before rendering.
     /// This performs lazy parsing only when needed.
     pub fn ensure_highlights_current(&mut self) {
+        let mut timed_out = false;
         match &mut self.syntax_state {
             Some(state) => match state.cache_valid {
                 false => {
-                    let source = self.textarea.lines().join("\n");
+                    let source = self.textarea.text();
                     let timeout = self.settings.parse_timeout_ms;
 
                     match state.parse_with_timeout(&source, timeout) {
@@ -158,7 +202,7 @@ impl Editor {
                         }
                         false => {
                             // Parse timed out - keep old cached highlights
-                            self.syntax_error = Some("Parse timeout".to_string());
+                            timed_out = true;
                         }
                     }
                 }
@@ -166,6 +210,18 @@ impl Editor {
             },
             None => {}
         }
+
+        // Report a timeout once, not on every frame it persists
+        match timed_out && self.syntax_error.as_deref() != Some(PARSE_TIMEOUT) {
+            true => {
+                self.syntax_error = Some(PARSE_TIMEOUT.to_string());
+                self.warn(format!(
+                    "Syntax parse timed out after {}ms; highlighting may be stale",
+                    self.settings.parse_timeout_ms
+                ));
+            }
+            false => {}
+        }
     }
 
     /// Update cached syntax highlights for the current buffer.
@@ -178,28 +234,6 @@ impl Editor {
             None => self.cached_highlights.clear(),
         }
     }
-
-    // ==================== String Utilities ====================
-
-    pub(crate) fn char_index_to_byte_index(s: &str, char_idx: usize) -> usize {
-        s.char_indices()
-            .nth(char_idx)
-            .map(|(byte_idx, _)| byte_idx)
-            .unwrap_or(s.len())
-    }
-
-    pub(crate) fn safe_string_slice(s: &str, start_char: usize, end_char: usize) -> String {
-        let start_byte = Self::char_index_to_byte_index(s, start_char);
-        let end_byte = match end_char >= s.chars().count() {
-            true => s.len(),
-            false => Self::char_index_to_byte_index(s, end_char),
-        };
-
-        match start_byte <= end_byte && end_byte <= s.len() {
-            true => s[start_byte..end_byte].to_string(),
-            false => String::new(),
-        }
-    }
 }
 
 impl Default for Editor {
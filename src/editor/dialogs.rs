@@ -5,10 +5,9 @@
 
 use std::fs;
 use std::path::PathBuf;
-use tui_textarea::TextArea;
 
 use super::types::{ConfirmationStep, ResponseResult, ResponseType};
-use super::Editor;
+use super::{Editor, TextArea};
 
 /// Trait for actions requiring user confirmation.
 /// Implement this to create new confirmable dialogs.
@@ -39,25 +38,37 @@ pub trait ConfirmationDialog {
     fn on_cancel(&self, _editor: &mut Editor) {}
 }
 
-/// Quit confirmation dialog - shown when quitting with unsaved changes
-pub struct QuitConfirmation;
+/// Quit confirmation dialog - shown when quitting with unsaved changes.
+///
+/// The first step asks whether to quit at all, then one step per modified
+/// buffer asks whether to save it.
+pub struct QuitConfirmation {
+    /// Names of the buffers with unsaved changes
+    pub buffers: Vec<String>,
+}
 
 impl ConfirmationDialog for QuitConfirmation {
     fn steps(&self) -> Vec<ConfirmationStep> {
-        vec![
-            ConfirmationStep {
-                prompt: "Buffer has unsaved changes. Quit anyway?".to_string(),
-                response_type: ResponseType::Binary,
-            },
-            ConfirmationStep {
-                prompt: "Save before quitting?".to_string(),
+        let summary = match self.buffers.len() {
+            1 => format!("Buffer '{}' has unsaved changes. Quit anyway?", self.buffers[0]),
+            n => format!("{} buffers have unsaved changes. Quit anyway?", n),
+        };
+
+        let mut steps = vec![ConfirmationStep {
+            prompt: summary,
+            response_type: ResponseType::Binary,
+        }];
+        for name in &self.buffers {
+            steps.push(ConfirmationStep {
+                prompt: format!("Save '{}' before quitting?", name),
                 response_type: ResponseType::Choice(vec![
-                    ('y', "save & quit".to_string()),
-                    ('n', "quit without saving".to_string()),
+                    ('y', "save".to_string()),
+                    ('n', "don't save".to_string()),
                     ('c', "cancel".to_string()),
                 ]),
-            },
-        ]
+            });
+        }
+        steps
     }
 
     fn handle_response(
@@ -70,38 +81,38 @@ impl ConfirmationDialog for QuitConfirmation {
             0 => {
                 // "Quit anyway?" step
                 match response {
-                    "y" => ResponseResult::Continue, // Go to save prompt
+                    "y" => ResponseResult::Continue, // Go to per-buffer save prompts
                     "n" => ResponseResult::Cancel,   // Don't quit
                     _ => ResponseResult::Stay,
                 }
             }
-            1 => {
-                // "Save before quitting?" step
+            i => {
+                // "Save <buffer> before quitting?" step
+                let name = match self.buffers.get(i - 1) {
+                    Some(name) => name.clone(),
+                    None => return ResponseResult::Stay,
+                };
                 match response {
                     "y" => {
-                        // Save and quit
-                        match editor.save_file() {
-                            Ok(_) => {
-                                // Save succeeded, mark for quit
-                                editor.pending_quit = true;
-                                ResponseResult::Finish
-                            }
-                            Err(_) => {
-                                // Save failed (likely no filename), don't quit yet
-                                ResponseResult::Cancel
-                            }
+                        editor.switch_to_buffer(&name);
+                        match editor.current_file.is_some() {
+                            true => match editor.save_file() {
+                                Ok(_) => ResponseResult::Continue,
+                                // Save failed, stay on the buffer and don't quit
+                                Err(e) => {
+                                    editor.error(format!("Failed to save {}: {}", name, e));
+                                    ResponseResult::Cancel
+                                }
+                            },
+                            // No file name yet - leave the user on that buffer to save it
+                            false => ResponseResult::Cancel,
                         }
                     }
-                    "n" => {
-                        // Quit without saving
-                        editor.pending_quit = true;
-                        ResponseResult::Finish
-                    }
+                    "n" => ResponseResult::Continue,
                     "c" => ResponseResult::Cancel,
                     _ => ResponseResult::Stay,
                 }
             }
-            _ => ResponseResult::Stay,
         }
     }
 
@@ -111,6 +122,40 @@ impl ConfirmationDialog for QuitConfirmation {
     }
 }
 
+/// Kill buffer confirmation dialog - shown when killing a modified buffer
+pub struct KillBufferConfirmation {
+    pub name: String,
+}
+
+impl ConfirmationDialog for KillBufferConfirmation {
+    fn steps(&self) -> Vec<ConfirmationStep> {
+        vec![ConfirmationStep {
+            prompt: format!("Buffer '{}' modified; kill anyway?", self.name),
+            response_type: ResponseType::Binary,
+        }]
+    }
+
+    fn handle_response(
+        &mut self,
+        _step_index: usize,
+        response: &str,
+        _editor: &mut Editor,
+    ) -> ResponseResult {
+        match response {
+            "y" => ResponseResult::Continue,
+            "n" => ResponseResult::Cancel,
+            _ => ResponseResult::Stay,
+        }
+    }
+
+    fn on_complete(&self, editor: &mut Editor) -> Result<(), String> {
+        match editor.kill_buffer(&self.name) {
+            true => Ok(()),
+            false => Err(format!("No buffer named '{}'", self.name)),
+        }
+    }
+}
+
 /// Delete file confirmation dialog
 pub struct DeleteFileConfirmation {
     pub path: PathBuf,
@@ -152,10 +197,186 @@ impl ConfirmationDialog for DeleteFileConfirmation {
             editor.current_file = None;
             editor.modified = false;
             editor.textarea = TextArea::default();
-            editor.update_textarea_colors();
-            editor.textarea.set_cursor_line_style(ratatui::style::Style::default());
+            editor.undo_manager.clear();
         }
 
         Ok(())
     }
 }
+
+/// Recovery dialog - shown when a file is opened and its auto-saved copy is
+/// newer than the file (e.g. after a crash)
+pub struct RecoverFileConfirmation {
+    pub path: PathBuf,
+    /// Recovery file holding the auto-saved text
+    pub recovery: PathBuf,
+    /// Set when the user asked to see the differences first
+    show_diff: bool,
+}
+
+impl RecoverFileConfirmation {
+    pub fn new(path: PathBuf, recovery: PathBuf) -> Self {
+        Self {
+            path,
+            recovery,
+            show_diff: false,
+        }
+    }
+}
+
+impl ConfirmationDialog for RecoverFileConfirmation {
+    fn steps(&self) -> Vec<ConfirmationStep> {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string());
+        vec![ConfirmationStep {
+            prompt: format!("'{}' has newer auto-saved changes.", name),
+            response_type: ResponseType::Choice(vec![
+                ('r', "recover".to_string()),
+                ('d', "show diff".to_string()),
+                ('x', "discard".to_string()),
+            ]),
+        }]
+    }
+
+    fn handle_response(
+        &mut self,
+        _step_index: usize,
+        response: &str,
+        editor: &mut Editor,
+    ) -> ResponseResult {
+        match response {
+            "r" => {
+                editor.recover_file(&self.recovery);
+                ResponseResult::Finish
+            }
+            // The diff pane replaces this dialog, so it is opened on completion
+            "d" => {
+                self.show_diff = true;
+                ResponseResult::Finish
+            }
+            "x" => {
+                editor.discard_recovery(&self.path);
+                ResponseResult::Finish
+            }
+            _ => ResponseResult::Stay,
+        }
+    }
+
+    fn on_complete(&self, editor: &mut Editor) -> Result<(), String> {
+        match self.show_diff {
+            true => editor.show_recovery_diff(self.path.clone(), self.recovery.clone()),
+            false => {}
+        }
+        Ok(())
+    }
+
+    fn on_cancel(&self, editor: &mut Editor) {
+        editor.message("Auto-saved changes kept; M-x recover-this-file to recover them");
+    }
+}
+
+/// Shown when another program changed the open file on disk
+pub struct ExternalChangeConfirmation {
+    pub path: PathBuf,
+    /// Set when the user asked to see the differences first
+    show_diff: bool,
+}
+
+impl ExternalChangeConfirmation {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, show_diff: false }
+    }
+}
+
+impl ConfirmationDialog for ExternalChangeConfirmation {
+    fn steps(&self) -> Vec<ConfirmationStep> {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string());
+        vec![ConfirmationStep {
+            prompt: format!("'{}' changed on disk.", name),
+            response_type: ResponseType::Choice(vec![
+                ('r', "reload".to_string()),
+                ('k', "keep mine".to_string()),
+                ('d', "show diff".to_string()),
+            ]),
+        }]
+    }
+
+    fn handle_response(
+        &mut self,
+        _step_index: usize,
+        response: &str,
+        editor: &mut Editor,
+    ) -> ResponseResult {
+        match response {
+            "r" => {
+                match editor.reload_file() {
+                    Ok(()) => {}
+                    Err(e) => editor.error(format!("Reload failed: {}", e)),
+                }
+                ResponseResult::Finish
+            }
+            "k" => {
+                editor.keep_buffer_version();
+                ResponseResult::Finish
+            }
+            // The diff pane replaces this dialog, so it is opened on completion
+            "d" => {
+                self.show_diff = true;
+                ResponseResult::Finish
+            }
+            _ => ResponseResult::Stay,
+        }
+    }
+
+    fn on_complete(&self, editor: &mut Editor) -> Result<(), String> {
+        match self.show_diff {
+            true => editor.show_external_diff(self.path.clone()),
+            false => {}
+        }
+        Ok(())
+    }
+
+    fn on_cancel(&self, editor: &mut Editor) {
+        editor.keep_buffer_version();
+    }
+}
+
+/// Shown when saving would overwrite changes another program made to the file
+pub struct OverwriteChangedFileConfirmation {
+    pub path: PathBuf,
+}
+
+impl ConfirmationDialog for OverwriteChangedFileConfirmation {
+    fn steps(&self) -> Vec<ConfirmationStep> {
+        vec![ConfirmationStep {
+            prompt: format!("'{}' changed on disk since it was read; save anyway?", self.path.display()),
+            response_type: ResponseType::Binary,
+        }]
+    }
+
+    fn handle_response(
+        &mut self,
+        _step_index: usize,
+        response: &str,
+        _editor: &mut Editor,
+    ) -> ResponseResult {
+        match response {
+            "y" => ResponseResult::Continue,
+            "n" => ResponseResult::Cancel,
+            _ => ResponseResult::Stay,
+        }
+    }
+
+    fn on_complete(&self, editor: &mut Editor) -> Result<(), String> {
+        editor
+            .force_save_file_to(&self.path)
+            .map_err(|e| format!("Failed to save file: {}", e))
+    }
+}
@@ -0,0 +1,68 @@
+//! Read-only diff pane shown while a dialog waits for an answer.
+//!
+//! Dialogs that offer to "show diff" (file recovery, files changed on disk)
+//! finish, open this pane, and are started again when it is closed, so the
+//! user can look at the differences and then make their choice.
+
+use super::{ConfirmationDialog, Editor, FloatingMode, FloatingWindow};
+use similar::TextDiff;
+
+/// Unified diff between two versions of a text, one entry per line
+/// (empty when they are the same)
+pub fn unified_diff(old: &[String], new: &[String], old_name: &str, new_name: &str) -> Vec<String> {
+    let old = old.join("\n") + "\n";
+    let new = new.join("\n") + "\n";
+    TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_name, new_name)
+        .to_string()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+impl Editor {
+    // ==================== Diff View ====================
+
+    /// Show a diff; closing it starts `dialog` again
+    pub fn show_diff(&mut self, title: String, lines: Vec<String>, dialog: Box<dyn ConfirmationDialog>) {
+        let height = (lines.len() as u16 + 2).clamp(5, self.settings.floating_window_height.max(5));
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(60),
+            height,
+            mode: FloatingMode::Diff {
+                title,
+                lines,
+                scroll: 0,
+                dialog,
+            },
+        });
+        self.focus_floating = true;
+    }
+
+    /// Close the diff pane and go back to the question it was opened from
+    pub fn close_diff(&mut self) {
+        match self.floating_window.take().map(|fw| fw.mode) {
+            Some(FloatingMode::Diff { dialog, .. }) => self.start_confirmation(dialog),
+            _ => self.focus_floating = false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = vec!["a".to_string(), "b".to_string()];
+        let new = vec!["a".to_string(), "c".to_string()];
+        let diff = unified_diff(&old, &new, "old", "new");
+        assert_eq!(diff, ["--- old", "+++ new", "@@ -1,2 +1,2 @@", " a", "-b", "+c"]);
+        assert!(unified_diff(&old, &old, "old", "new").is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Noticing when another program changes the open file.
+//!
+//! The file's `DiskStamp` (mtime, size, inode) is recorded whenever jae reads
+//! or writes it. `external_change_tick` compares it with the file on disk
+//! about once a second and asks whether to reload, keep the buffer, or see a
+//! diff. Saving over a file that changed on disk needs an explicit confirm.
+
+use super::diff_view::unified_diff;
+use super::dialogs::{ExternalChangeConfirmation, OverwriteChangedFileConfirmation};
+use super::{Editor, FileFormat};
+use crate::fileio::{disk_stamp, DiskStamp};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the file on disk is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Per-buffer record of the file on disk
+#[derive(Default)]
+pub struct DiskWatch {
+    /// Stamp when the file was last read or written by us
+    loaded: Option<DiskStamp>,
+    /// Changed version the user already chose to ignore (not asked about again)
+    answered: Option<DiskStamp>,
+}
+
+impl DiskWatch {
+    /// Start watching a file as it is now
+    pub fn new(path: &Path) -> Self {
+        Self {
+            loaded: disk_stamp(path),
+            answered: None,
+        }
+    }
+
+    /// Whether the file at `path` differs from what was read or written.
+    /// A deleted file has nothing to clobber and does not count.
+    pub fn changed(&self, path: &Path) -> bool {
+        match disk_stamp(path) {
+            Some(stamp) => Some(stamp) != self.loaded,
+            None => false,
+        }
+    }
+}
+
+/// Error returned when a save would overwrite changes made by another program
+fn changed_on_disk_error(path: &Path) -> io::Error {
+    io::Error::other(format!("{} changed on disk since it was read", path.display()))
+}
+
+impl Editor {
+    // ==================== External Changes ====================
+
+    /// Check the current file for outside changes at most once per `CHECK_INTERVAL`
+    pub fn external_change_tick(&mut self) {
+        match self.last_disk_check.elapsed() < CHECK_INTERVAL {
+            true => {}
+            false => {
+                self.last_disk_check = Instant::now();
+                // Don't interrupt a prompt or dialog that is already open
+                match self.floating_window.is_none() {
+                    true => self.check_external_change(),
+                    false => {}
+                }
+            }
+        }
+    }
+
+    /// Ask what to do when the current file changed on disk (once per change)
+    pub fn check_external_change(&mut self) {
+        let path = match &self.current_file {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let stamp = disk_stamp(&path);
+        match stamp.is_some() && stamp != self.disk_watch.loaded && stamp != self.disk_watch.answered {
+            true => self.start_confirmation(Box::new(ExternalChangeConfirmation::new(path))),
+            false => {}
+        }
+    }
+
+    /// Whether saving the current buffer to `path` would overwrite outside changes
+    pub fn file_changed_on_disk(&self, path: &Path) -> bool {
+        self.current_file.as_deref() == Some(path) && self.disk_watch.changed(path)
+    }
+
+    /// Save the current buffer, asking first when the file changed on disk (C-x C-s)
+    pub fn save_file_checked(&mut self, path: PathBuf) -> io::Result<()> {
+        match self.file_changed_on_disk(&path) {
+            true => {
+                let error = changed_on_disk_error(&path);
+                self.start_confirmation(Box::new(OverwriteChangedFileConfirmation { path }));
+                Err(error)
+            }
+            false => self.save_file_to(&path),
+        }
+    }
+
+    /// Refuse to save over outside changes (see `force_save_file_to`)
+    pub(crate) fn check_not_changed_on_disk(&self, path: &Path) -> io::Result<()> {
+        match self.file_changed_on_disk(path) {
+            true => Err(changed_on_disk_error(path)),
+            false => Ok(()),
+        }
+    }
+
+    /// Keep the buffer as it is and stop asking about this version of the file.
+    /// Saving still asks before overwriting it.
+    pub fn keep_buffer_version(&mut self) {
+        self.disk_watch.answered = self.current_file.as_deref().and_then(disk_stamp);
+    }
+
+    /// Replace the buffer with the file on disk (undoable)
+    pub fn reload_file(&mut self) -> io::Result<()> {
+        let path = match &self.current_file {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+        let watch = DiskWatch::new(&path);
+        let (text, format) = FileFormat::decode(&fs::read(&path)?);
+
+        self.save_undo_state();
+        self.textarea.set_text(&text);
+        self.cancel_mark();
+        self.file_format = format;
+        self.modified = false;
+        self.autosave_pending = false;
+        self.disk_watch = watch;
+        self.update_highlights();
+        self.git_reload();
+        // Unsaved edits that were thrown away need no recovery
+        self.remove_recovery_file(&path);
+        self.message(format!("Reverted buffer from {}", path.display()));
+        Ok(())
+    }
+
+    /// Show how the file on disk differs from the buffer.
+    /// Closing the pane asks about the change again.
+    pub fn show_external_diff(&mut self, path: PathBuf) {
+        let disk = match fs::read(&path) {
+            Ok(bytes) => FileFormat::decode(&bytes).0.split('\n').map(str::to_string).collect::<Vec<_>>(),
+            Err(e) => {
+                self.error(format!("Cannot read {}: {}", path.display(), e));
+                return;
+            }
+        };
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let lines = unified_diff(
+            &self.textarea.to_lines(),
+            &disk,
+            &format!("{} (buffer)", name),
+            &format!("{} (on disk)", name),
+        );
+        let title = format!("Changes to {} on disk", path.display());
+        self.show_diff(title, lines, Box::new(ExternalChangeConfirmation::new(path)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_in;
+    use crate::editor::{ConfirmationDialog, FloatingMode, ResponseResult};
+    use crate::test_util::TempDir;
+
+    /// A temp dir holding `file.txt`, and an editor that saves its state there
+    fn editor_with_file(name: &str, contents: &str) -> (TempDir, PathBuf, Editor) {
+        let dir = TempDir::new(&format!("external-{}", name));
+        let path = dir.join("file.txt");
+        fs::write(&path, contents).unwrap();
+        let editor = editor_in(&dir);
+        (dir, path, editor)
+    }
+
+    fn take_dialog(editor: &mut Editor) -> Box<dyn ConfirmationDialog> {
+        match editor.floating_window.take().map(|fw| fw.mode) {
+            Some(FloatingMode::Confirm { dialog, .. }) => dialog,
+            _ => panic!("expected a dialog"),
+        }
+    }
+
+    #[test]
+    fn test_detects_change_and_reloads() {
+        let (_dir, path, mut editor) = editor_with_file("reload", "one\n");
+        editor.open_file(&path).unwrap();
+        editor.check_external_change();
+        assert!(editor.floating_window.is_none());
+
+        fs::write(&path, "one\ntwo\n").unwrap();
+        editor.check_external_change();
+        let mut dialog = take_dialog(&mut editor);
+
+        assert!(matches!(dialog.handle_response(0, "d", &mut editor), ResponseResult::Finish));
+        dialog.on_complete(&mut editor).unwrap();
+        match &editor.floating_window.as_ref().unwrap().mode {
+            FloatingMode::Diff { lines, .. } => assert!(lines.contains(&"+two".to_string())),
+            _ => panic!("expected the diff pane"),
+        }
+        editor.close_diff();
+        let mut dialog = take_dialog(&mut editor);
+
+        assert!(matches!(dialog.handle_response(0, "r", &mut editor), ResponseResult::Finish));
+        assert_eq!(editor.textarea.to_lines(), ["one", "two"]);
+        assert!(!editor.modified);
+        editor.check_external_change();
+        assert!(editor.floating_window.is_none());
+    }
+
+    #[test]
+    fn test_keep_mine_and_save_needs_confirmation() {
+        let (_dir, path, mut editor) = editor_with_file("keep", "one\n");
+        editor.open_file(&path).unwrap();
+        editor.textarea.insert_str("my ");
+        editor.mark_modified();
+
+        fs::write(&path, "theirs\n").unwrap();
+        editor.check_external_change();
+        let mut dialog = take_dialog(&mut editor);
+        assert!(matches!(dialog.handle_response(0, "k", &mut editor), ResponseResult::Finish));
+        // Not asked again about the same change
+        editor.check_external_change();
+        assert!(editor.floating_window.is_none());
+
+        // Saving refuses and asks instead
+        assert!(editor.save_file().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theirs\n");
+        let mut dialog = take_dialog(&mut editor);
+        assert!(matches!(dialog.handle_response(0, "y", &mut editor), ResponseResult::Continue));
+        dialog.on_complete(&mut editor).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "my one\n");
+        assert!(!editor.file_changed_on_disk(&path));
+    }
+}
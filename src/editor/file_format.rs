@@ -0,0 +1,382 @@
+//! On-disk format of a file: encoding, BOM, line endings and final newline.
+//!
+//! Files are decoded into plain `\n`-separated text when opened and the
+//! detected format is kept with the buffer, so saving writes the bytes back
+//! the way they were read. Conversion commands change the format; the text
+//! itself is untouched.
+
+use super::{Editor, MinibufferCallback};
+use ropey::Rope;
+use std::fmt;
+
+/// Character encoding of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1: every byte is the code point of the same value
+    Latin1,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 4] = [Self::Utf8, Self::Utf16Le, Self::Utf16Be, Self::Latin1];
+
+    /// Name shown in the status bar and accepted by `set-encoding`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Latin1 => "latin-1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        match name.as_str() {
+            "utf8" => Some(Self::Utf8),
+            "latin1" | "iso-8859-1" => Some(Self::Latin1),
+            _ => Self::ALL.into_iter().find(|e| e.name() == name),
+        }
+    }
+
+    /// Byte order mark for this encoding (Latin-1 has none)
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Self::Utf16Le => &[0xFF, 0xFE],
+            Self::Utf16Be => &[0xFE, 0xFF],
+            Self::Latin1 => &[],
+        }
+    }
+}
+
+/// Line terminator used when writing the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+}
+
+/// Everything needed to write a buffer back byte-for-byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    /// Whether the last line is terminated
+    pub final_newline: bool,
+}
+
+impl Default for FileFormat {
+    /// Format for new files: UTF-8, LF, with a final newline
+    fn default() -> Self {
+        Self {
+            encoding: Encoding::Utf8,
+            bom: false,
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.encoding.name())?;
+        match self.bom && self.encoding != Encoding::Latin1 {
+            true => write!(f, "-bom")?,
+            false => {}
+        }
+        write!(f, " {}", self.line_ending.name())?;
+        match self.final_newline {
+            true => Ok(()),
+            false => write!(f, " noeol"),
+        }
+    }
+}
+
+/// Decode UTF-16 code units, replacing unpaired surrogates
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| match little_endian {
+        true => u16::from_le_bytes([pair[0], pair[1]]),
+        false => u16::from_be_bytes([pair[0], pair[1]]),
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Guess a BOM-less UTF-16 file from its NUL bytes: mostly-ASCII UTF-16
+/// text has zeros in every other byte and none in between
+fn guess_utf16(bytes: &[u8]) -> Option<Encoding> {
+    match bytes.len() >= 2 && bytes.len().is_multiple_of(2) {
+        true => {}
+        false => return None,
+    }
+    let pairs = bytes.len() / 2;
+    let even_zeros = bytes.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    match (even_zeros, odd_zeros) {
+        (even, 0) if even * 2 > pairs => Some(Encoding::Utf16Be),
+        (0, odd) if odd * 2 > pairs => Some(Encoding::Utf16Le),
+        _ => None,
+    }
+}
+
+impl FileFormat {
+    /// Decode file contents into `\n`-separated text and the detected format
+    pub fn decode(bytes: &[u8]) -> (String, FileFormat) {
+        let bom_encoding = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+            .into_iter()
+            .find(|e| bytes.starts_with(e.bom()));
+
+        let (encoding, bom, body) = match bom_encoding {
+            Some(encoding) => (encoding, true, &bytes[encoding.bom().len()..]),
+            // NULs are valid UTF-8, so UTF-16 has to be ruled out first
+            None => match (guess_utf16(bytes), std::str::from_utf8(bytes).is_ok()) {
+                (Some(encoding), _) => (encoding, false, bytes),
+                (None, true) => (Encoding::Utf8, false, bytes),
+                (None, false) => (Encoding::Latin1, false, bytes),
+            },
+        };
+        let text = match encoding {
+            Encoding::Utf8 => String::from_utf8_lossy(body).into_owned(),
+            Encoding::Utf16Le => decode_utf16(body, true),
+            Encoding::Utf16Be => decode_utf16(body, false),
+            Encoding::Latin1 => body.iter().map(|b| *b as char).collect(),
+        };
+
+        // The more common terminator wins. Only a CRLF file drops its CRs;
+        // in an LF file they stay in the text (with lone CRs), so the lines
+        // ending in CRLF are written back unchanged.
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        let (line_ending, mut text) = match crlf > lf {
+            true => (LineEnding::Crlf, text.replace("\r\n", "\n")),
+            false => (LineEnding::Lf, text),
+        };
+        let final_newline = match text.ends_with('\n') {
+            true => {
+                text.pop();
+                true
+            }
+            false => false,
+        };
+
+        let format = FileFormat {
+            encoding,
+            bom,
+            line_ending,
+            final_newline,
+        };
+        (text, format)
+    }
+
+    /// Encode buffer text for writing
+    pub fn encode(&self, text: &Rope) -> Result<Vec<u8>, String> {
+        let mut bytes = match self.bom {
+            true => self.encoding.bom().to_vec(),
+            false => Vec::with_capacity(text.len_bytes()),
+        };
+        let final_newline = match self.final_newline {
+            true => Some("\n"),
+            false => None,
+        };
+
+        // Chunk by chunk so large buffers are never copied into one string
+        for chunk in text.chunks().chain(final_newline) {
+            let chunk = match self.line_ending {
+                LineEnding::Lf => chunk.to_string(),
+                LineEnding::Crlf => chunk.replace('\n', "\r\n"),
+            };
+            match self.encoding {
+                Encoding::Utf8 => bytes.extend_from_slice(chunk.as_bytes()),
+                Encoding::Utf16Le => bytes.extend(chunk.encode_utf16().flat_map(u16::to_le_bytes)),
+                Encoding::Utf16Be => bytes.extend(chunk.encode_utf16().flat_map(u16::to_be_bytes)),
+                Encoding::Latin1 => {
+                    for c in chunk.chars() {
+                        match u8::try_from(c as u32) {
+                            Ok(b) => bytes.push(b),
+                            Err(_) => return Err(format!("'{}' cannot be encoded in latin-1", c)),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+impl Editor {
+    // ==================== File Format ====================
+
+    /// Change a format property; the file differs on disk until saved
+    fn change_file_format(&mut self, change: impl FnOnce(&mut FileFormat)) {
+        let before = self.file_format;
+        change(&mut self.file_format);
+        match self.file_format != before {
+            true => {
+                self.modified = true;
+                self.message(format!("File format: {}", self.file_format));
+            }
+            false => {}
+        }
+    }
+
+    /// Write the file with the given line terminator
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.change_file_format(|f| f.line_ending = line_ending);
+    }
+
+    /// Toggle whether the last line ends with a newline
+    pub fn toggle_final_newline(&mut self) {
+        self.change_file_format(|f| f.final_newline = !f.final_newline);
+    }
+
+    /// Toggle writing a byte order mark
+    pub fn toggle_bom(&mut self) {
+        self.change_file_format(|f| f.bom = !f.bom);
+    }
+
+    /// Ask for the encoding to save the file in
+    pub fn set_encoding_prompt(&mut self) {
+        self.open_minibuffer("Encoding: ", "", MinibufferCallback::SetEncoding);
+    }
+
+    /// Save the file in another encoding
+    pub fn set_encoding(&mut self, name: &str) {
+        match Encoding::from_name(name) {
+            Some(encoding) => self.change_file_format(|f| f.encoding = encoding),
+            None => self.error(format!("Unknown encoding '{}'", name.trim())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(bytes: &[u8]) -> (String, FileFormat) {
+        let (text, format) = FileFormat::decode(bytes);
+        assert_eq!(format.encode(&Rope::from_str(&text)).unwrap(), bytes, "{:?} {:?}", text, format);
+        (text, format)
+    }
+
+    #[test]
+    fn test_line_endings_and_final_newline() {
+        let (text, format) = roundtrip(b"a\r\nb\r\n");
+        assert_eq!(text, "a\nb");
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert!(format.final_newline);
+
+        let (text, format) = roundtrip(b"a\n\nb");
+        assert_eq!(text, "a\n\nb");
+        assert_eq!(format.line_ending, LineEnding::Lf);
+        assert!(!format.final_newline);
+
+        let (text, format) = roundtrip(b"");
+        assert_eq!(text, "");
+        assert!(!format.final_newline);
+        roundtrip(b"\n");
+    }
+
+    #[test]
+    fn test_mixed_line_endings_roundtrip() {
+        // LF wins: the minority CRs stay in the text
+        let (text, format) = roundtrip(b"a\r\nb\nc\n");
+        assert_eq!(text, "a\r\nb\nc");
+        assert_eq!(format.line_ending, LineEnding::Lf);
+
+        let (text, format) = roundtrip(b"a\nb\r\n");
+        assert_eq!(text, "a\nb\r");
+        assert_eq!(format.line_ending, LineEnding::Lf);
+        assert!(format.final_newline);
+    }
+
+    #[test]
+    fn test_encodings_and_bom() {
+        let (text, format) = roundtrip(b"\xEF\xBB\xBFcaf\xC3\xA9\n");
+        assert_eq!(text, "café");
+        assert_eq!((format.encoding, format.bom), (Encoding::Utf8, true));
+        assert_eq!(format.to_string(), "utf-8-bom LF");
+
+        let (text, format) = roundtrip(b"\xFF\xFEh\x00i\x00\r\x00\n\x00");
+        assert_eq!(text, "hi");
+        assert_eq!((format.encoding, format.bom), (Encoding::Utf16Le, true));
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+
+        let (text, format) = roundtrip(b"\x00h\x00i");
+        assert_eq!(text, "hi");
+        assert_eq!((format.encoding, format.bom), (Encoding::Utf16Be, false));
+
+        let (text, format) = roundtrip(b"caf\xE9");
+        assert_eq!(text, "café");
+        assert_eq!(format.encoding, Encoding::Latin1);
+        assert_eq!(format.to_string(), "latin-1 LF noeol");
+    }
+
+    #[test]
+    fn test_unencodable_text() {
+        let format = FileFormat {
+            encoding: Encoding::Latin1,
+            ..FileFormat::default()
+        };
+        assert!(format.encode(&Rope::from_str("λ")).is_err());
+    }
+
+    #[test]
+    fn test_open_and_save_preserve_format() {
+        let dir = std::env::temp_dir().join(format!("jae-format-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dos.txt");
+        std::fs::write(&path, b"\xEF\xBB\xBFone\r\ntwo").unwrap();
+
+        let mut editor = Editor::new();
+        editor.settings.undo_history_dir = dir.join("undo").display().to_string();
+        editor.open_file(&path).unwrap();
+        assert_eq!(editor.textarea.text(), "one\ntwo");
+        editor.save_file().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"\xEF\xBB\xBFone\r\ntwo");
+
+        editor.set_line_ending(LineEnding::Lf);
+        editor.toggle_bom();
+        editor.toggle_final_newline();
+        editor.save_file().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"one\ntwo\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_conversion_commands() {
+        let mut editor = Editor::new();
+        editor.set_line_ending(LineEnding::Crlf);
+        editor.toggle_final_newline();
+        editor.set_encoding("UTF-16LE");
+        assert!(editor.modified);
+        assert_eq!(editor.file_format.to_string(), "utf-16le CRLF noeol");
+
+        editor.set_encoding("ebcdic");
+        assert_eq!(editor.file_format.encoding, Encoding::Utf16Le);
+    }
+}
@@ -2,14 +2,15 @@
 
 use super::syntax::{Language, SyntaxState};
 use super::{
-    CommandInfo, ConfirmationDialog, DeleteFileConfirmation, Editor, FloatingMode, FloatingWindow,
-    MarkState, MinibufferCallback, QuitConfirmation,
+    CommandInfo, ConfirmationDialog, DeleteFileConfirmation, DiskWatch, Editor, FileFormat, FloatingMode,
+    FloatingWindow, MarkState, MinibufferCallback, QuitConfirmation, TextArea,
 };
-use ratatui::style::Style;
+use crate::commands::Command;
+use crate::fileio;
+use crate::keymap::Keymap;
 use std::fs;
-use std::io::{self, Read, Write};
+use std::io;
 use std::path::PathBuf;
-use tui_textarea::TextArea;
 
 impl Editor {
     // ==================== File Operations ====================
@@ -156,26 +157,41 @@ impl Editor {
         self.focus_floating = true;
     }
 
-    /// Open file from path, load into textarea
+    /// Open file from path in a buffer.
+    /// Switches to the existing buffer if the file is already open,
+    /// otherwise loads it into a new buffer.
     pub fn open_file(&mut self, path: &std::path::Path) -> io::Result<()> {
-        let mut file = fs::File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
+        let path = fileio::normalize_path(path);
+        let path = path.as_path();
+        match self.current_file.as_deref() == Some(path) {
+            true => return Ok(()),
+            false => {}
+        }
+        match self.find_buffer_by_path(path) {
+            Some(idx) => {
+                self.switch_to_buffer_index(idx);
+                return Ok(());
+            }
+            None => {}
+        }
 
-        let lines: Vec<&str> = contents.lines().collect();
-        self.textarea = if lines.is_empty() {
-            TextArea::default()
-        } else {
-            TextArea::new(lines.iter().map(|s| s.to_string()).collect())
-        };
+        // Stamped before reading so a change made meanwhile is still noticed
+        let disk_watch = DiskWatch::new(path);
+        let bytes = fs::read(path)?;
+        let (contents, format) = FileFormat::decode(&bytes);
 
-        self.update_textarea_colors();
-        self.textarea.set_cursor_line_style(Style::default());
+        // Keep the current buffer around in the background
+        self.push_current_buffer();
+
+        self.textarea = TextArea::from_text(&contents);
+        self.file_format = format;
+        self.disk_watch = disk_watch;
 
         self.current_file = Some(path.to_path_buf());
         self.modified = false;
         self.mark = MarkState::None;
         self.undo_manager.clear();
+        self.restore_undo_history(path);
 
         // Detect language and initialize unified syntax state
         self.language = Language::from_path(path);
@@ -189,6 +205,8 @@ impl Editor {
             None => {}
         }
         self.cached_highlights.clear();
+        self.git_reload();
+        self.check_recovery_file();
 
         Ok(())
     }
@@ -196,7 +214,7 @@ impl Editor {
     /// Save current buffer to current_file (or prompt if none)
     pub fn save_file(&mut self) -> io::Result<()> {
         match self.current_file.clone() {
-            Some(path) => self.save_file_to(&path),
+            Some(path) => self.save_file_checked(path),
             None => {
                 self.save_file_as_prompt();
                 Ok(())
@@ -234,14 +252,50 @@ impl Editor {
         self.focus_floating = true;
     }
 
-    /// Save to specific path
+    /// Save to specific path, refusing to overwrite the current file if
+    /// another program changed it since it was read
     pub fn save_file_to(&mut self, path: &std::path::Path) -> io::Result<()> {
-        let contents = self.textarea.lines().join("\n");
-        let mut file = fs::File::create(path)?;
-        file.write_all(contents.as_bytes())?;
+        self.check_not_changed_on_disk(path)?;
+        self.force_save_file_to(path)
+    }
+
+    /// Save to specific path even if the file changed on disk
+    pub fn force_save_file_to(&mut self, path: &std::path::Path) -> io::Result<()> {
+        let contents = self
+            .file_format
+            .encode(self.textarea.rope())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // A failed backup is reported but does not stop the save
+        let backup_dir = match self.settings.backup_dir.is_empty() {
+            true => None,
+            false => Some(Self::expand_path(&self.settings.backup_dir)),
+        };
+        match fileio::make_backup(path, self.settings.backup, backup_dir.as_deref()) {
+            Ok(_) => {}
+            Err(e) => {
+                self.warn(format!("Backup failed: {}", e));
+            }
+        }
+        fileio::write_atomic(path, &contents)?;
+        let path = fileio::normalize_path(path);
+        let path = path.as_path();
+
+        // The auto-saved copy is out of date now (also under the old name after save-as)
+        match self.current_file.clone() {
+            Some(old) if old != path => self.remove_recovery_file(&old),
+            _ => {}
+        }
+        self.remove_recovery_file(path);
+        self.save_undo_history(path);
 
         self.current_file = Some(path.to_path_buf());
         self.modified = false;
+        self.autosave_pending = false;
+        self.disk_watch = DiskWatch::new(path);
+        self.message(format!("Wrote {}", path.display()));
+        self.lsp.did_save(&crate::lsp::absolute_path(path));
+        self.git_reload();
 
         Ok(())
     }
@@ -278,7 +332,11 @@ impl Editor {
 
     /// Start the confirmation dialog for deleting a file
     pub fn start_delete_confirmation(&mut self, path: PathBuf) {
-        let dialog = DeleteFileConfirmation { path };
+        self.start_confirmation(Box::new(DeleteFileConfirmation { path }));
+    }
+
+    /// Show any confirmation dialog at the bottom of the screen
+    pub fn start_confirmation(&mut self, dialog: Box<dyn ConfirmationDialog>) {
         let steps = dialog.steps();
 
         self.floating_window = Some(FloatingWindow {
@@ -288,7 +346,7 @@ impl Editor {
             width: 80,
             height: 1,
             mode: FloatingMode::Confirm {
-                dialog: Box::new(dialog),
+                dialog,
                 steps,
                 current_index: 0,
             },
@@ -306,6 +364,7 @@ impl Editor {
                     ..
                 } => {
                     let path = Self::expand_path(input);
+                    let input_clone = input.clone();
                     let callback_clone = callback.clone();
                     let path_clone = path.clone();
 
@@ -316,18 +375,42 @@ impl Editor {
                         MinibufferCallback::OpenFile => {
                             match self.open_file(&path_clone) {
                                 Ok(_) => {}
-                                Err(e) => eprintln!("Failed to open file: {}", e),
+                                Err(e) => self.error(format!("Failed to open {}: {}", path_clone.display(), e)),
                             }
                         }
                         MinibufferCallback::SaveFileAs => {
                             match self.save_file_to(&path_clone) {
                                 Ok(_) => {}
-                                Err(e) => eprintln!("Failed to save file: {}", e),
+                                Err(e) => self.error(format!("Failed to save {}: {}", path_clone.display(), e)),
                             }
                         }
                         MinibufferCallback::DeleteFile => {
                             self.start_delete_confirmation(path_clone);
                         }
+                        MinibufferCallback::SwitchToBuffer(_) => {
+                            self.switch_to_buffer(input_clone.trim());
+                        }
+                        MinibufferCallback::KillBuffer(_) => {
+                            self.request_kill_buffer(input_clone.trim());
+                        }
+                        MinibufferCallback::QueryReplaceFrom { regex } => {
+                            self.query_replace_to_prompt(input_clone, regex);
+                        }
+                        MinibufferCallback::QueryReplaceTo { from, regex } => {
+                            self.start_query_replace(&from, &input_clone, regex);
+                        }
+                        MinibufferCallback::ShellCommand => {
+                            self.shell_command(&input_clone);
+                        }
+                        MinibufferCallback::ShellCommandOnRegion { replace } => {
+                            self.shell_command_on_region(&input_clone, replace);
+                        }
+                        MinibufferCallback::SetEncoding => {
+                            self.set_encoding(&input_clone);
+                        }
+                        MinibufferCallback::FoldLevel => {
+                            self.fold_all_at_level(&input_clone);
+                        }
                     }
                 }
                 _ => {}
@@ -339,28 +422,17 @@ impl Editor {
     /// Mark buffer as modified (called when text changes)
     pub fn mark_modified(&mut self) {
         self.modified = true;
+        self.autosave_pending = true;
         // Update syntax highlights when buffer changes
         self.update_highlights();
     }
 
-    /// Start the quit confirmation dialog (when buffer is modified)
+    /// Start the quit confirmation dialog (when any buffer is modified)
     pub fn start_quit_confirmation(&mut self) {
-        let dialog = QuitConfirmation;
-        let steps = dialog.steps();
-
-        self.floating_window = Some(FloatingWindow {
-            visible: true,
-            x: 0,
-            y: 0,
-            width: 80,
-            height: 1,
-            mode: FloatingMode::Confirm {
-                dialog: Box::new(dialog),
-                steps,
-                current_index: 0,
-            },
-        });
-        self.focus_floating = true;
+        let dialog = QuitConfirmation {
+            buffers: self.modified_buffer_names(),
+        };
+        self.start_confirmation(Box::new(dialog));
     }
 
     /// Open the M-x command palette
@@ -372,7 +444,7 @@ impl Editor {
             .map(|cmd| CommandInfo {
                 name: cmd.name,
                 description: cmd.description,
-                keybinding: cmd.keybinding.as_ref().map(|kb| kb.display()),
+                keybinding: self.command_binding_display(cmd),
             })
             .collect();
 
@@ -395,6 +467,19 @@ impl Editor {
         self.focus_floating = true;
     }
 
+    /// Key sequence shown for a command in the palette. Keymap bindings
+    /// reflect config overrides; hard-wired keys fall back to the registry.
+    fn command_binding_display(&self, cmd: &Command) -> Option<String> {
+        match Keymap::is_default_binding(cmd.name) {
+            true => self.keymap.binding_for(cmd.name).map(|kb| kb.display()),
+            false => self
+                .keymap
+                .binding_for(cmd.name)
+                .or_else(|| cmd.keybinding.clone())
+                .map(|kb| kb.display()),
+        }
+    }
+
     /// Filter commands for command palette based on search input
     pub fn filter_commands(&self, query: &str) -> Vec<CommandInfo> {
         let query_lower = query.to_lowercase();
@@ -409,7 +494,7 @@ impl Editor {
             .map(|cmd| CommandInfo {
                 name: cmd.name,
                 description: cmd.description,
-                keybinding: cmd.keybinding.as_ref().map(|kb| kb.display()),
+                keybinding: self.command_binding_display(cmd),
             })
             .collect();
 
@@ -0,0 +1,365 @@
+//! Code folding.
+//!
+//! A fold hides the lines of a syntax node below its first line, which
+//! stays on screen followed by a placeholder counting the hidden lines.
+//! The nodes that fold are the definitions of `SyntaxState::definitions`:
+//! functions, impl blocks, classes, Markdown sections and so on.
+//!
+//! Folds are kept in the text area as char ranges, so they follow edits
+//! the way extra cursors do; an edit across either end of a fold opens it.
+//! Moving up, down or across a line boundary steps over hidden lines,
+//! paging and scrolling count only the lines on screen, and jump mode
+//! leaves hidden lines out. Any other move into a fold opens it.
+
+use super::{CursorMove, Editor, MinibufferCallback};
+
+/// Rows hidden by folds, as runs of (first row, last hidden row) where the
+/// first row stays on screen and the rows after it up to the last are hidden
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FoldedRows {
+    runs: Vec<(usize, usize)>,
+}
+
+impl FoldedRows {
+    /// Merge folds given as (first row, last row); nested and overlapping
+    /// folds become one run
+    pub fn new(mut folds: Vec<(usize, usize)>) -> Self {
+        folds.retain(|(first, last)| first < last);
+        folds.sort();
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for (first, last) in folds {
+            match runs.last_mut() {
+                Some(run) if first <= run.1 => run.1 = run.1.max(last),
+                _ => runs.push((first, last)),
+            }
+        }
+        Self { runs }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Whether a fold hides `row`
+    pub fn is_hidden(&self, row: usize) -> bool {
+        self.runs.iter().any(|(first, last)| *first < row && row <= *last)
+    }
+
+    /// Number of rows hidden below `row` when it starts a fold
+    pub fn hidden_below(&self, row: usize) -> Option<usize> {
+        self.runs.iter().find(|(first, _)| *first == row).map(|(first, last)| last - first)
+    }
+
+    /// `row`, or the first row of the fold hiding it
+    pub fn shown_row(&self, row: usize) -> usize {
+        match self.runs.iter().find(|(first, last)| *first < row && row <= *last) {
+            Some((first, _)) => *first,
+            None => row,
+        }
+    }
+
+    /// Row shown after the one `row` is shown on (may be past the text)
+    pub fn next_shown(&self, row: usize) -> usize {
+        let row = self.shown_row(row);
+        row + 1 + self.hidden_below(row).unwrap_or(0)
+    }
+
+    /// Row shown before the one `row` is shown on
+    pub fn previous_shown(&self, row: usize) -> Option<usize> {
+        let row = self.shown_row(row).checked_sub(1)?;
+        Some(self.shown_row(row))
+    }
+
+    /// Row shown `count` screen lines above `row` (stopping at the top)
+    pub fn rows_up(&self, row: usize, count: usize) -> usize {
+        let mut row = self.shown_row(row);
+        for _ in 0..count {
+            match self.previous_shown(row) {
+                Some(previous) => row = previous,
+                None => break,
+            }
+        }
+        row
+    }
+
+    /// Row shown `count` screen lines below `row` (stopping at `last_row`)
+    pub fn rows_down(&self, row: usize, count: usize, last_row: usize) -> usize {
+        let mut row = self.shown_row(row);
+        for _ in 0..count {
+            match self.next_shown(row) {
+                next if next <= last_row => row = next,
+                _ => break,
+            }
+        }
+        row
+    }
+
+    /// Rows shown from `row` on (without end; callers stop at the text's end)
+    pub fn shown_rows(&self, row: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(self.shown_row(row)), |row| Some(self.next_shown(*row)))
+    }
+
+    /// Number of screen lines the rows `from..=to` take
+    pub fn count_shown(&self, from: usize, to: usize) -> usize {
+        self.shown_rows(from).take_while(|row| *row <= to).count()
+    }
+}
+
+impl Editor {
+    // ==================== Folding ====================
+
+    /// Char ranges of the definitions that span more than one line, in
+    /// document order (None without a grammar)
+    fn foldable_ranges(&mut self) -> Option<Vec<(usize, usize)>> {
+        // The tree is only reparsed before drawing, so it may be behind the text
+        self.ensure_highlights_current();
+        let syntax = self.syntax_state.as_ref()?;
+        let rope = self.textarea.rope();
+        let ranges = syntax
+            .definitions(&self.textarea.text(), None)
+            .into_iter()
+            .map(|(start, end)| (rope.byte_to_char(start), rope.byte_to_char(end)))
+            .filter(|(start, end)| {
+                let (first, last) = self.textarea.fold_rows(*start, *end);
+                first < last
+            })
+            .collect();
+        Some(ranges)
+    }
+
+    /// Fold the innermost definition around the cursor line that is not
+    /// folded yet (C-c -). Repeating folds the definitions around it.
+    pub fn fold_at_cursor(&mut self) -> bool {
+        let ranges = match self.foldable_ranges() {
+            Some(ranges) => ranges,
+            None => {
+                self.message("No syntax tree for this buffer");
+                return false;
+            }
+        };
+        let (row, _) = self.textarea.cursor();
+        let innermost = ranges.into_iter().rfind(|(start, end)| {
+            let (first, last) = self.textarea.fold_rows(*start, *end);
+            first <= row && row <= last && !self.textarea.is_folded(*start, *end)
+        });
+        match innermost {
+            Some((start, end)) => {
+                self.textarea.fold(start, end);
+                self.leave_folded_rows();
+                true
+            }
+            None => {
+                self.message("Nothing to fold here");
+                false
+            }
+        }
+    }
+
+    /// Open the folds starting on the cursor line (C-c +)
+    pub fn unfold_at_cursor(&mut self) -> bool {
+        let (row, _) = self.textarea.cursor();
+        match self.textarea.unfold_where(|(first, _)| first == row) {
+            0 => {
+                self.message("No fold here");
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Open the fold on the cursor line, or fold there (C-c f)
+    pub fn toggle_fold(&mut self) -> bool {
+        let (row, _) = self.textarea.cursor();
+        match self.textarea.folded_rows().hidden_below(row) {
+            Some(_) => self.unfold_at_cursor(),
+            None => self.fold_at_cursor(),
+        }
+    }
+
+    /// Ask for the nesting level to fold at (C-c F)
+    pub fn fold_all_at_level_prompt(&mut self) {
+        self.open_minibuffer("Fold level: ", "1", MinibufferCallback::FoldLevel);
+    }
+
+    /// Fold every definition at nesting level `input` (1 is the outermost
+    /// definitions, 2 the ones directly inside those, ...). Returns how
+    /// many definitions were folded.
+    pub fn fold_all_at_level(&mut self, input: &str) -> usize {
+        let level = match input.trim().parse::<usize>() {
+            Ok(level) if level > 0 => level,
+            _ => {
+                self.error(format!("Invalid fold level '{}'", input.trim()));
+                return 0;
+            }
+        };
+        let ranges = match self.foldable_ranges() {
+            Some(ranges) => ranges,
+            None => {
+                self.message("No syntax tree for this buffer");
+                return 0;
+            }
+        };
+
+        // Sorted by start, so the ends of the definitions around one are
+        // on the stack when it comes up
+        let mut enclosing: Vec<usize> = Vec::new();
+        let mut folded = 0;
+        for (start, end) in ranges {
+            while enclosing.last().is_some_and(|outer_end| *outer_end < end) {
+                enclosing.pop();
+            }
+            match enclosing.len() + 1 == level && self.textarea.fold(start, end) {
+                true => folded += 1,
+                false => {}
+            }
+            enclosing.push(end);
+        }
+        self.leave_folded_rows();
+        self.message(format!("Folded {} definitions at level {}", folded, level));
+        folded
+    }
+
+    /// Open every fold (C-c U)
+    pub fn unfold_all(&mut self) {
+        self.textarea.unfold_where(|_| true);
+    }
+
+    /// After folding: move the cursor out of the hidden rows to the line
+    /// the fold shows on
+    fn leave_folded_rows(&mut self) {
+        let (row, col) = self.textarea.cursor();
+        let shown = self.textarea.folded_rows().shown_row(row);
+        match shown == row {
+            true => self.ensure_cursor_visible(),
+            false => self.move_cursor(CursorMove::Jump(shown, col)),
+        }
+    }
+
+    /// After `movement` took the cursor from `before` into hidden rows:
+    /// step over them in the direction of the movement, or for a jump,
+    /// open the folds there
+    pub(super) fn skip_folded_rows(&mut self, movement: CursorMove, before: (usize, usize)) {
+        let (row, _) = self.textarea.cursor();
+        let folds = self.textarea.folded_rows();
+        match folds.is_hidden(row) {
+            true => {}
+            false => return,
+        }
+        let first = folds.shown_row(row);
+        let next = folds.next_shown(row);
+        let below = next < self.textarea.len_lines();
+        let target = match movement {
+            CursorMove::Up | CursorMove::Bottom => Some((first, before.1)),
+            CursorMove::Back | CursorMove::WordBack => Some((first, self.textarea.line_len(first))),
+            CursorMove::Down if below => Some((next, before.1)),
+            CursorMove::Forward | CursorMove::WordForward if below => Some((next, 0)),
+            CursorMove::Down | CursorMove::Forward | CursorMove::WordForward => Some(before),
+            CursorMove::Head | CursorMove::End | CursorMove::Top | CursorMove::Jump(..) => None,
+        };
+        match target {
+            Some((row, col)) => self.textarea.move_cursor(CursorMove::Jump(row, col)),
+            None => {
+                self.textarea.unfold_where(|(first, last)| first < row && row <= last);
+            }
+        }
+    }
+
+    /// Open the folds the cursor ended up in (by an edit, undo or search),
+    /// so it is never on a hidden line
+    pub fn reveal_cursor(&mut self) {
+        let (row, _) = self.textarea.cursor();
+        match self.textarea.unfold_where(|(first, last)| first < row && row <= last) {
+            0 => {}
+            _ => self.ensure_cursor_visible(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::syntax::{Language, SyntaxState};
+    use crate::editor::TextArea;
+
+    const SOURCE: &str = "struct S;\n\nimpl S {\n    fn one(&self) {\n        1;\n    }\n\n    fn two(&self) {\n        2;\n    }\n}\n\nfn three() {\n    3;\n}\n";
+
+    fn rust_editor() -> Editor {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::from_text(SOURCE);
+        editor.syntax_state = SyntaxState::new(Language::Rust);
+        editor.viewport_height = 20;
+        editor
+    }
+
+    #[test]
+    fn test_folded_rows() {
+        // Nested and touching folds merge into one run
+        let folds = FoldedRows::new(vec![(2, 10), (3, 5), (10, 12), (14, 14), (20, 22)]);
+        assert!(!folds.is_hidden(2));
+        assert!(folds.is_hidden(3) && folds.is_hidden(12));
+        assert!(!folds.is_hidden(14));
+        assert_eq!(folds.hidden_below(2), Some(10));
+        assert_eq!(folds.hidden_below(3), None);
+        assert_eq!(folds.shown_row(7), 2);
+        assert_eq!(folds.next_shown(1), 2);
+        assert_eq!(folds.next_shown(2), 13);
+        assert_eq!(folds.previous_shown(13), Some(2));
+        assert_eq!(folds.rows_up(14, 2), 2);
+        assert_eq!(folds.rows_down(0, 3, 30), 13);
+        assert_eq!(folds.rows_down(19, 5, 22), 20);
+        assert_eq!(folds.shown_rows(0).take(5).collect::<Vec<_>>(), vec![0, 1, 2, 13, 14]);
+        assert_eq!(folds.count_shown(0, 20), 11);
+    }
+
+    #[test]
+    fn test_fold_and_move_over_it() {
+        let mut editor = rust_editor();
+        editor.textarea.move_cursor(CursorMove::Jump(4, 8));
+        assert!(editor.fold_at_cursor());
+        // The cursor leaves the hidden lines for the line the fold shows on
+        assert_eq!(editor.textarea.cursor(), (3, 8));
+        assert_eq!(editor.textarea.folded_rows().hidden_below(3), Some(2));
+
+        // Moving down and back up steps over the hidden lines
+        editor.move_cursor(CursorMove::Down);
+        assert_eq!(editor.textarea.cursor(), (6, 0));
+        editor.move_cursor(CursorMove::Up);
+        assert_eq!(editor.textarea.cursor(), (3, 0));
+        editor.move_cursor(CursorMove::End);
+        editor.move_cursor(CursorMove::Forward);
+        assert_eq!(editor.textarea.cursor(), (6, 0));
+
+        // Folding again folds the impl around it; toggling opens only that
+        assert!(editor.fold_at_cursor());
+        assert_eq!(editor.textarea.cursor(), (2, 0));
+        assert!(editor.toggle_fold());
+        assert_eq!(editor.textarea.folded_rows().hidden_below(3), Some(2));
+
+        // Edits keep the fold on its lines; jumping into it opens it
+        editor.textarea.move_cursor(CursorMove::Jump(0, 0));
+        editor.textarea.insert_str("\n");
+        assert_eq!(editor.textarea.folded_rows().hidden_below(4), Some(2));
+        editor.move_cursor(CursorMove::Jump(5, 0));
+        assert!(editor.textarea.folded_rows().is_empty());
+    }
+
+    #[test]
+    fn test_fold_all_at_level() {
+        let mut editor = rust_editor();
+        assert_eq!(editor.fold_all_at_level("2"), 2);
+        assert_eq!(editor.textarea.folded_rows(), FoldedRows::new(vec![(3, 5), (7, 9)]));
+        editor.unfold_all();
+        // One-line definitions (struct S;) do not fold
+        assert_eq!(editor.fold_all_at_level("1"), 2);
+        assert_eq!(editor.textarea.folded_rows(), FoldedRows::new(vec![(2, 10), (12, 14)]));
+        assert_eq!(editor.fold_all_at_level("x"), 0);
+
+        // Paging and scrolling count the lines on screen
+        editor.viewport_height = 6;
+        editor.textarea.move_cursor(CursorMove::Jump(0, 0));
+        editor.page_down();
+        assert_eq!(editor.textarea.cursor(), (11, 0));
+        editor.page_up();
+        assert_eq!(editor.textarea.cursor(), (0, 0));
+    }
+}
@@ -0,0 +1,163 @@
+//! Git diff gutter for the editor.
+//!
+//! The HEAD version of the file is read when the file is opened or saved.
+//! Edits are re-diffed by `git_tick` once the buffer has been left alone for
+//! `DIFF_DEBOUNCE`, so typing never waits on the diff.
+
+use super::{CursorMove, Editor, TextArea};
+use crate::git::{diff_hunks, head_text, Hunk, LineChange};
+use std::time::{Duration, Instant};
+
+/// How long the buffer must be unchanged before it is re-diffed
+const DIFF_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Per-buffer diff state against HEAD
+#[derive(Default)]
+pub struct GitGutter {
+    /// HEAD version of the file (None when untracked)
+    base: Option<String>,
+    /// Changes as of the last diff
+    pub hunks: Vec<Hunk>,
+    /// `TextArea::version` of the text the hunks were computed from
+    diffed: u64,
+    /// When the buffer was first seen to differ from `diffed`
+    changed_at: Option<Instant>,
+}
+
+impl GitGutter {
+    /// Whether the file has a HEAD version to diff against
+    pub fn is_tracked(&self) -> bool {
+        self.base.is_some()
+    }
+
+    /// Whether an edit is waiting to be re-diffed
+    pub fn is_pending(&self) -> bool {
+        self.changed_at.is_some()
+    }
+
+    fn rediff(&mut self, textarea: &TextArea) {
+        self.hunks = match &self.base {
+            Some(base) => diff_hunks(base, &textarea.to_lines()),
+            None => Vec::new(),
+        };
+        self.diffed = textarea.version();
+        self.changed_at = None;
+    }
+}
+
+impl Editor {
+    // ==================== Git Gutter ====================
+
+    /// Re-read the HEAD version of the current file and re-diff immediately
+    pub fn git_reload(&mut self) {
+        self.git_gutter.base = self.current_file.as_deref().and_then(head_text);
+        self.git_gutter.rediff(&self.textarea);
+    }
+
+    /// Re-diff the buffer once edits have settled
+    pub fn git_tick(&mut self) {
+        match self.git_gutter.base.is_some() && self.git_gutter.diffed != self.textarea.version() {
+            true => match self.git_gutter.changed_at {
+                Some(at) if at.elapsed() >= DIFF_DEBOUNCE => self.git_gutter.rediff(&self.textarea),
+                Some(_) => {}
+                None => self.git_gutter.changed_at = Some(Instant::now()),
+            },
+            false => self.git_gutter.changed_at = None,
+        }
+    }
+
+    /// Gutter marker for a buffer row
+    pub fn git_marker(&self, row: usize) -> Option<LineChange> {
+        let line_count = self.textarea.len_lines();
+        self.git_gutter
+            .hunks
+            .iter()
+            .find(|h| match h.change {
+                LineChange::Removed => h.row(line_count) == row,
+                _ => (h.start..h.end).contains(&row),
+            })
+            .map(|h| h.change)
+    }
+
+    /// Move to the start of the next changed hunk
+    pub fn next_hunk(&mut self) {
+        let (row, _) = self.textarea.cursor();
+        let line_count = self.textarea.len_lines();
+        let target = self
+            .git_gutter
+            .hunks
+            .iter()
+            .map(|h| h.row(line_count))
+            .find(|start| *start > row);
+        self.move_to_hunk(target, "No next hunk");
+    }
+
+    /// Move to the start of the previous (or current) changed hunk
+    pub fn previous_hunk(&mut self) {
+        let (row, _) = self.textarea.cursor();
+        let line_count = self.textarea.len_lines();
+        let target = self
+            .git_gutter
+            .hunks
+            .iter()
+            .map(|h| h.row(line_count))
+            .rfind(|start| *start < row);
+        self.move_to_hunk(target, "No previous hunk");
+    }
+
+    fn move_to_hunk(&mut self, target: Option<usize>, not_found: &str) {
+        match target {
+            Some(row) => self.move_cursor(CursorMove::Jump(row, 0)),
+            None => self.message(not_found.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor_with_base(base: &str, text: &str) -> Editor {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::from_text(text);
+        editor.git_gutter.base = Some(base.to_string());
+        editor.git_gutter.rediff(&editor.textarea);
+        editor
+    }
+
+    #[test]
+    fn test_markers_and_hunk_navigation() {
+        let mut editor = editor_with_base("a\nb\nc\nd\ne\nf\n", "a\nB\nc\nd\nf\ng");
+        assert_eq!(editor.git_marker(0), None);
+        assert_eq!(editor.git_marker(1), Some(LineChange::Modified));
+        assert_eq!(editor.git_marker(4), Some(LineChange::Removed));
+        assert_eq!(editor.git_marker(5), Some(LineChange::Added));
+
+        editor.next_hunk();
+        assert_eq!(editor.textarea.cursor(), (1, 0));
+        editor.next_hunk();
+        assert_eq!(editor.textarea.cursor(), (4, 0));
+        editor.next_hunk();
+        assert_eq!(editor.textarea.cursor(), (5, 0));
+        editor.next_hunk();
+        assert_eq!(editor.echo_text(), Some("No next hunk"));
+
+        editor.previous_hunk();
+        assert_eq!(editor.textarea.cursor(), (4, 0));
+    }
+
+    #[test]
+    fn test_edits_are_rediffed_after_debounce() {
+        let mut editor = editor_with_base("a\nb\n", "a\nb");
+        editor.textarea.insert_str("x");
+
+        editor.git_tick();
+        assert!(editor.git_gutter.is_pending());
+        assert!(editor.git_gutter.hunks.is_empty());
+
+        editor.git_gutter.changed_at = Some(Instant::now() - DIFF_DEBOUNCE);
+        editor.git_tick();
+        assert!(!editor.git_gutter.is_pending());
+        assert_eq!(editor.git_marker(0), Some(LineChange::Modified));
+    }
+}
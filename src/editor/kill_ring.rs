@@ -0,0 +1,396 @@
+//! Kill ring (Emacs-style).
+//!
+//! Every kill (C-w, C-k, C-u, M-d, M-Backspace) and copy (M-w) pushes its
+//! text onto the ring, except that consecutive kills grow the newest entry:
+//! forward kills append to it, backward kills prepend. The newest entry is
+//! also put on the system clipboard.
+//!
+//! Yank (C-y) inserts the entry at the yank pointer, picking up text copied
+//! in other programs from the clipboard first. M-y right after a yank
+//! replaces the yanked text with the next older entry.
+//!
+//! A kill at several cursors keeps what each cursor killed, and the ring
+//! gets the texts joined by newlines. Yanking that entry at the same number
+//! of cursors gives each cursor its own text back.
+
+use super::{CursorMove, Editor, FloatingMode, FloatingWindow, MarkState};
+use std::collections::VecDeque;
+
+/// Entries kept in the kill ring
+const KILL_RING_MAX: usize = 60;
+
+/// Which side of the killed text the cursor was on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillDirection {
+    /// Text after the cursor (appended to the previous kill)
+    Forward,
+    /// Text before the cursor (prepended to the previous kill)
+    Backward,
+}
+
+/// What a command did with the kill ring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KillAction {
+    Kill,
+    /// Killed at several cursors (see `kill_at_cursor`)
+    CursorKill,
+    /// Yanked text occupies chars `start..end`
+    Yank { start: usize, end: usize },
+}
+
+/// Killed text, newest first
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    /// Entry the next yank inserts
+    yank_pointer: usize,
+    /// What the previous command did (kills and M-y depend on it)
+    previous: Option<KillAction>,
+    /// What the running command did
+    current: Option<KillAction>,
+    /// Text killed at each cursor by the last multiple-cursor kill, in document order
+    cursor_kills: Vec<String>,
+    /// Whether the running command killed at several cursors
+    cursor_kills_changed: bool,
+}
+
+impl KillRing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start handling a new key: what the running command did becomes
+    /// what the previous command did
+    pub fn next_command(&mut self) {
+        self.previous = self.current.take();
+    }
+
+    /// Add a new entry and point the next yank at it
+    fn push(&mut self, text: String) {
+        self.entries.push_front(text);
+        self.entries.truncate(KILL_RING_MAX);
+        self.yank_pointer = 0;
+    }
+
+    /// Record killed text, joining it to the newest entry if the previous
+    /// command was a kill too. Returns the (possibly joined) entry.
+    pub fn kill(&mut self, text: &str, direction: KillDirection) -> &str {
+        match (self.previous == Some(KillAction::Kill), self.entries.front_mut()) {
+            (true, Some(newest)) => match direction {
+                KillDirection::Forward => newest.push_str(text),
+                KillDirection::Backward => newest.insert_str(0, text),
+            },
+            _ => self.push(text.to_string()),
+        }
+        self.yank_pointer = 0;
+        self.current = Some(KillAction::Kill);
+        &self.entries[0]
+    }
+
+    /// Record copied text (never joined to a previous kill)
+    pub fn copy(&mut self, text: &str) {
+        self.push(text.to_string());
+    }
+
+    /// Record text killed (or, without a direction, copied) at cursor
+    /// `visit` of a multiple-cursor command. `finish_cursor_kills` adds
+    /// the result to the ring.
+    pub fn kill_at_cursor(&mut self, visit: usize, text: &str, direction: Option<KillDirection>) {
+        let join = direction.is_some() && self.previous == Some(KillAction::CursorKill);
+        match (self.cursor_kills_changed, join) {
+            (false, false) => self.cursor_kills.clear(),
+            _ => {}
+        }
+        self.cursor_kills_changed = true;
+        match self.cursor_kills.len() <= visit {
+            true => self.cursor_kills.resize(visit + 1, String::new()),
+            false => {}
+        }
+        match direction {
+            Some(KillDirection::Backward) => self.cursor_kills[visit].insert_str(0, text),
+            _ => self.cursor_kills[visit].push_str(text),
+        }
+        match direction {
+            Some(_) => self.current = Some(KillAction::CursorKill),
+            None => {}
+        }
+    }
+
+    /// After a multiple-cursor command: put what it killed at its cursors
+    /// on the ring, replacing the entry it joined onto. Returns the entry.
+    pub fn finish_cursor_kills(&mut self) -> Option<String> {
+        match std::mem::take(&mut self.cursor_kills_changed) {
+            true => {}
+            false => return None,
+        }
+        let joined = self.cursor_kills.join("\n");
+        let replace = self.previous == Some(KillAction::CursorKill) && self.current == Some(KillAction::CursorKill);
+        match (replace, self.entries.front_mut()) {
+            (true, Some(newest)) => *newest = joined.clone(),
+            _ => self.push(joined.clone()),
+        }
+        self.yank_pointer = 0;
+        Some(joined)
+    }
+
+    /// What cursor `visit` of `cursors` yanks: its own killed text when the
+    /// entry to yank came from a kill at as many cursors
+    pub fn cursor_entry(&self, visit: usize, cursors: usize) -> Option<&str> {
+        let own = self.cursor_kills.len() == cursors
+            && self.current_entry().is_some_and(|entry| entry == self.cursor_kills.join("\n"));
+        match own {
+            true => self.cursor_kills.get(visit).map(String::as_str),
+            false => self.current_entry(),
+        }
+    }
+
+    /// Text from the system clipboard that differs from what the next yank
+    /// would insert was copied elsewhere: it becomes the newest entry
+    pub fn sync_from_clipboard(&mut self, text: String) {
+        match text.is_empty() || self.entries.get(self.yank_pointer) == Some(&text) {
+            true => {}
+            false => self.push(text),
+        }
+    }
+
+    /// Entries, newest first
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Entry the next yank inserts
+    pub fn current_entry(&self) -> Option<&str> {
+        self.entries.get(self.yank_pointer).map(String::as_str)
+    }
+
+    /// Point the next yank at entry `index` (0 = newest)
+    pub fn set_yank_pointer(&mut self, index: usize) {
+        match index < self.entries.len() {
+            true => self.yank_pointer = index,
+            false => {}
+        }
+    }
+
+    /// Whether the kill ring is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record that the running command yanked text into chars `start..end`
+    fn yanked(&mut self, start: usize, end: usize) {
+        self.current = Some(KillAction::Yank { start, end });
+    }
+
+    /// After a yank: move the yank pointer to the next older entry and
+    /// return the range the previous yank inserted
+    fn rotate(&mut self) -> Option<(usize, usize)> {
+        match self.previous {
+            Some(KillAction::Yank { start, end }) if !self.entries.is_empty() => {
+                self.yank_pointer = (self.yank_pointer + 1) % self.entries.len();
+                Some((start, end))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Editor {
+    // ==================== Kill Ring ====================
+    //
+    // Kills and yanks follow the BufferEdit pattern - see buffer_ops.rs.
+
+    /// Put killed text on the kill ring and the system clipboard
+    pub fn kill_text(&mut self, text: &str, direction: KillDirection) {
+        match self.cursor_visit {
+            Some(visit) => self.kill_ring.kill_at_cursor(visit, text, Some(direction)),
+            None => {
+                let entry = self.kill_ring.kill(text, direction).to_string();
+                self.copy_to_clipboard(&entry);
+            }
+        }
+    }
+
+    /// Put copied text on the kill ring and the system clipboard
+    pub fn copy_text(&mut self, text: &str) {
+        match self.cursor_visit {
+            Some(visit) => self.kill_ring.kill_at_cursor(visit, text, None),
+            None => {
+                self.kill_ring.copy(text);
+                self.copy_to_clipboard(text);
+            }
+        }
+    }
+
+    /// Put text on the system clipboard, reporting a failure in the echo area
+    pub(super) fn copy_to_clipboard(&mut self, text: &str) {
+        match self.clipboard.copy(text) {
+            Ok(()) => {}
+            Err(e) => self.warn(e),
+        }
+    }
+
+    /// Insert text at the cursor as a yank, so M-y can replace it
+    fn insert_yank(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n");
+        self.textarea.insert_str(&text);
+        let (row, col) = self.textarea.cursor();
+        let end = self.textarea.char_index(row, col);
+        self.kill_ring.yanked(end - text.chars().count(), end);
+        self.mark = MarkState::None;
+    }
+
+    /// Yank the newest kill (C-y)
+    ///
+    /// Returns true if text was inserted, false if the kill ring is empty.
+    /// Handles undo state and modification tracking internally.
+    pub fn yank(&mut self) -> bool {
+        // Without a system clipboard the kill ring still works on its own
+        match self.clipboard.paste() {
+            Ok(Some(text)) => self.kill_ring.sync_from_clipboard(text),
+            Ok(None) => {}
+            Err(e) => log::debug!("yank: {}", e),
+        }
+        let entry = match self.cursor_visit {
+            Some(visit) => self.kill_ring.cursor_entry(visit, self.textarea.extra_cursor_count() + 1),
+            None => self.kill_ring.current_entry(),
+        };
+        let text = match entry {
+            Some(text) => text.to_string(),
+            None => return false,
+        };
+        self.save_undo_state();
+        self.insert_yank(&text);
+        self.mark_modified();
+        true
+    }
+
+    /// Replace the text just yanked with the next older kill (M-y)
+    ///
+    /// Returns true if the text was replaced, false if the previous command
+    /// was not a yank. The replacement joins the yank's undo step.
+    pub fn yank_pop(&mut self) -> bool {
+        match self.textarea.extra_cursor_count() {
+            0 => {}
+            _ => {
+                self.message("yank-pop works with a single cursor");
+                return false;
+            }
+        }
+        let (start, end) = match self.kill_ring.rotate() {
+            Some(range) => range,
+            None => {
+                self.message("Previous command was not a yank");
+                return false;
+            }
+        };
+        let text = self.kill_ring.current_entry().unwrap_or_default().to_string();
+        let (start_row, start_col) = self.textarea.char_to_row_col(start);
+        let (end_row, end_col) = self.textarea.char_to_row_col(end);
+        self.textarea.cancel_selection();
+        self.textarea.move_cursor(CursorMove::Jump(start_row, start_col));
+        self.textarea.start_selection();
+        self.textarea.move_cursor(CursorMove::Jump(end_row, end_col));
+        self.insert_yank(&text);
+        self.copy_to_clipboard(&text);
+        // Keep the cursor on screen
+        let (row, col) = self.textarea.cursor();
+        self.move_cursor(CursorMove::Jump(row, col));
+        self.mark_modified();
+        true
+    }
+
+    /// Show the kill ring to pick an entry to yank (M-x browse-kill-ring)
+    pub fn browse_kill_ring(&mut self) {
+        match self.kill_ring.is_empty() {
+            true => {
+                self.message("Kill ring is empty");
+                return;
+            }
+            false => {}
+        }
+        let entries: Vec<String> = self.kill_ring.entries().map(str::to_string).collect();
+        let height = (entries.len() as u16 + 2).clamp(5, self.settings.floating_window_height.max(5));
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(60),
+            height,
+            mode: FloatingMode::KillRing { entries, selected: 0 },
+        });
+        self.focus_floating = true;
+    }
+
+    /// Yank kill ring entry `index` (0 = newest), as picked in browse-kill-ring
+    pub fn yank_kill_ring_entry(&mut self, index: usize) -> bool {
+        self.kill_ring.set_yank_pointer(index);
+        let text = match self.kill_ring.current_entry() {
+            Some(text) => text.to_string(),
+            None => return false,
+        };
+        self.save_undo_state();
+        self.insert_yank(&text);
+        self.copy_to_clipboard(&text);
+        self.mark_modified();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_with;
+
+    /// Run a command the way a key press does
+    fn command(editor: &mut Editor, run: impl FnOnce(&mut Editor) -> bool) -> bool {
+        editor.kill_ring.next_command();
+        run(editor)
+    }
+
+    #[test]
+    fn test_consecutive_kills_join() {
+        let mut editor = editor_with("one two\nthree");
+        command(&mut editor, Editor::cut_to_end_of_line);
+        command(&mut editor, Editor::cut_to_end_of_line);
+        command(&mut editor, Editor::delete_word_forward);
+        assert_eq!(editor.textarea.text(), "");
+        assert_eq!(editor.kill_ring.entries().collect::<Vec<_>>(), ["one two\nthree"]);
+
+        // Any other command in between starts a new entry
+        command(&mut editor, Editor::yank);
+        command(&mut editor, Editor::delete_word_backward);
+        command(&mut editor, Editor::delete_word_backward);
+        assert_eq!(editor.textarea.text(), "one two");
+        assert_eq!(editor.kill_ring.entries().collect::<Vec<_>>(), ["\nthree", "one two\nthree"]);
+    }
+
+    #[test]
+    fn test_yank_pop_cycles_older_kills() {
+        let mut editor = editor_with("a b c");
+        for _ in 0..3 {
+            command(&mut editor, Editor::delete_word_forward);
+            command(&mut editor, Editor::delete_char_forward);
+        }
+        assert_eq!(editor.textarea.text(), "");
+
+        command(&mut editor, Editor::yank);
+        assert_eq!(editor.textarea.text(), "c");
+        command(&mut editor, Editor::yank_pop);
+        assert_eq!(editor.textarea.text(), "b");
+        command(&mut editor, Editor::yank_pop);
+        assert_eq!(editor.textarea.text(), "a");
+        command(&mut editor, Editor::yank_pop);
+        assert_eq!(editor.textarea.text(), "c");
+
+        // M-y only follows a yank
+        command(&mut editor, |editor| {
+            editor.move_cursor(CursorMove::Head);
+            true
+        });
+        assert!(!command(&mut editor, Editor::yank_pop));
+
+        // Undo removes the yank with everything M-y did to it
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "");
+    }
+}
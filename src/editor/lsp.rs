@@ -0,0 +1,426 @@
+//! Language server integration for the editor.
+//!
+//! `lsp_tick` runs once per main-loop iteration: it syncs the active buffer to
+//! its language server and handles whatever the servers sent back. Requests
+//! (hover, definition, references, completion) are asynchronous; their
+//! results open floating windows or move the cursor when they arrive.
+
+use super::{Editor, FloatingMode, FloatingWindow};
+use crate::lsp::{
+    absolute_path, protocol, ClientEvent, CompletionItem, Location, Position, PositionEncoding,
+    RequestKind, Severity,
+};
+use crate::editor::syntax::Language;
+use std::fs;
+use std::path::{Path, PathBuf};
+use super::CursorMove;
+
+/// One row of a location list (references, definitions, diagnostics)
+#[derive(Debug, Clone)]
+pub struct LocationEntry {
+    pub path: PathBuf,
+    pub row: usize,
+    pub col: usize,
+    /// Text shown after the position (source line or diagnostic message)
+    pub text: String,
+}
+
+/// A diagnostic as (start, end) char positions and its severity
+pub type DiagnosticRange = ((usize, usize), (usize, usize), Severity);
+
+/// Characters that make up the word completed by `lsp-complete`
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Editor {
+    // ==================== Language Server ====================
+
+    /// Sync the active buffer to its language server.
+    /// Returns the document path and position encoding when a server is available.
+    fn lsp_sync(&mut self) -> Option<(PathBuf, PositionEncoding)> {
+        let path = absolute_path(self.current_file.as_deref()?);
+        self.lsp.server_command(self.language)?;
+
+        let textarea = &self.textarea;
+        match self.lsp.sync_document(&path, self.language, textarea.version(), || textarea.text()) {
+            Ok(Some(encoding)) => Some((path, encoding)),
+            Ok(None) => None,
+            Err(e) => {
+                self.error(format!("LSP: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Sync the active buffer and process messages from the language servers
+    pub fn lsp_tick(&mut self) {
+        match self.current_file.is_some() {
+            true => {
+                self.lsp_sync();
+            }
+            false => {}
+        }
+
+        for (language, event) in self.lsp.poll() {
+            self.handle_lsp_event(language, event);
+        }
+    }
+
+    /// Cursor position in protocol units
+    fn lsp_cursor_position(&self, encoding: PositionEncoding) -> Position {
+        let (row, col) = self.textarea.cursor();
+        let line = self.textarea.line(row);
+        Position {
+            line: row,
+            character: protocol::char_to_lsp_col(&line, col, encoding),
+        }
+    }
+
+    /// Char position in the active buffer of a protocol position
+    fn buffer_char_pos(&self, position: Position, encoding: PositionEncoding) -> (usize, usize) {
+        let last = self.textarea.len_lines() - 1;
+        match position.line <= last {
+            true => {
+                let line = self.textarea.line(position.line);
+                (position.line, protocol::lsp_col_to_char(&line, position.character, encoding))
+            }
+            false => (last, self.textarea.line_len(last)),
+        }
+    }
+
+    /// Send a request about the cursor position
+    fn lsp_request(&mut self, kind: RequestKind, method: &str) {
+        let reported = self.messages.reported();
+        let (path, encoding) = match self.lsp_sync() {
+            Some(synced) => synced,
+            None => {
+                // Keep any sync error already reported
+                match self.messages.reported() == reported {
+                    true => self.message(format!("No language server for {}", self.language.id())),
+                    false => {}
+                }
+                return;
+            }
+        };
+        let position = self.lsp_cursor_position(encoding);
+        match self.lsp.request_at(self.language, kind, method, &path, position) {
+            Ok(()) => {}
+            Err(e) => self.error(format!("LSP: {}", e)),
+        }
+    }
+
+    /// Show documentation for the symbol at the cursor
+    pub fn lsp_hover(&mut self) {
+        self.lsp_request(RequestKind::Hover, "textDocument/hover");
+    }
+
+    /// Jump to the definition of the symbol at the cursor
+    pub fn lsp_goto_definition(&mut self) {
+        self.lsp_request(RequestKind::Definition, "textDocument/definition");
+    }
+
+    /// List all references to the symbol at the cursor
+    pub fn lsp_find_references(&mut self) {
+        self.lsp_request(RequestKind::References, "textDocument/references");
+    }
+
+    /// Offer completions for the word before the cursor
+    pub fn lsp_complete(&mut self) {
+        self.lsp_request(RequestKind::Completion, "textDocument/completion");
+    }
+
+    /// Restart the language server for the active buffer's language
+    pub fn lsp_restart(&mut self) {
+        self.lsp.restart(self.language);
+        self.message(format!("Restarting language server for {}", self.language.id()));
+    }
+
+    fn handle_lsp_event(&mut self, language: Language, event: ClientEvent) {
+        match event {
+            ClientEvent::Response { kind, result } => match kind {
+                RequestKind::Hover => match protocol::parse_hover(&result) {
+                    Some(text) => self.show_hover(&text),
+                    None => self.message("No documentation found"),
+                },
+                RequestKind::Definition => {
+                    let locations = protocol::parse_locations(&result);
+                    match locations.len() {
+                        0 => self.message("No definition found"),
+                        1 => {
+                            let entry = self.location_entry(&locations[0], language);
+                            self.jump_to_location(&entry.path, entry.row, entry.col);
+                        }
+                        _ => self.show_location_list("Definitions", &locations, language),
+                    }
+                }
+                RequestKind::References => {
+                    let locations = protocol::parse_locations(&result);
+                    match locations.is_empty() {
+                        true => self.message("No references found"),
+                        false => self.show_location_list("References", &locations, language),
+                    }
+                }
+                RequestKind::Completion => {
+                    let items = protocol::parse_completions(&result);
+                    match items.is_empty() {
+                        true => self.message("No completions"),
+                        false => self.show_completions(items),
+                    }
+                }
+                RequestKind::Initialize | RequestKind::Shutdown => {}
+            },
+            ClientEvent::Error { kind, message } => {
+                self.error(format!("LSP {:?}: {}", kind, message));
+            }
+            ClientEvent::Exited => {
+                self.warn(format!("Language server for {} exited", language.id()));
+            }
+            ClientEvent::Notification { .. } => {}
+        }
+    }
+
+    /// Lines of a file: from its buffer when open, otherwise from disk
+    fn lines_of(&self, path: &Path) -> Vec<String> {
+        let is_path = |p: Option<&Path>| p.is_some_and(|p| absolute_path(p) == path);
+        match is_path(self.current_file.as_deref()) {
+            true => return self.textarea.to_lines(),
+            false => {}
+        }
+        match self.buffers.iter().find(|b| is_path(b.current_file.as_deref())) {
+            Some(buffer) => buffer.textarea.to_lines(),
+            None => fs::read_to_string(path)
+                .map(|s| s.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Resolve a protocol location to a char position and source line
+    fn location_entry(&self, location: &Location, language: Language) -> LocationEntry {
+        let lines = self.lines_of(&location.path);
+        let (row, col) = location
+            .position
+            .to_char_pos(&lines, self.lsp.encoding(language));
+        LocationEntry {
+            path: location.path.clone(),
+            row,
+            col,
+            text: lines.get(row).map_or(String::new(), |l| l.trim().to_string()),
+        }
+    }
+
+    /// Open the file (reusing its buffer) and move the cursor to (row, col)
+    pub fn jump_to_location(&mut self, path: &Path, row: usize, col: usize) {
+        let is_current = self
+            .current_file
+            .as_deref()
+            .is_some_and(|p| absolute_path(p) == absolute_path(path));
+        match is_current {
+            true => {}
+            false => {
+                let open_buffer = self
+                    .buffers
+                    .iter()
+                    .position(|b| b.current_file.as_deref().is_some_and(|p| absolute_path(p) == absolute_path(path)));
+                let result = match open_buffer {
+                    Some(idx) => {
+                        self.switch_to_buffer_index(idx);
+                        Ok(())
+                    }
+                    None => self.open_file(path),
+                };
+                match result {
+                    Ok(()) => {}
+                    Err(e) => {
+                        self.error(format!("{}: {}", path.display(), e));
+                        return;
+                    }
+                }
+            }
+        }
+        self.cancel_mark();
+        self.move_cursor(CursorMove::Jump(row, col));
+    }
+
+    fn show_hover(&mut self, text: &str) {
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let width = lines
+            .iter()
+            .map(|l| l.chars().count() as u16 + 2)
+            .max()
+            .unwrap_or(20)
+            .clamp(20, self.settings.floating_window_width.max(40));
+        let height = (lines.len() as u16 + 2).min(self.settings.floating_window_height.max(5));
+        let (x, y) = self.popup_origin();
+
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x,
+            y,
+            width,
+            height,
+            mode: FloatingMode::Hover { lines, scroll: 0 },
+        });
+        self.focus_floating = true;
+    }
+
+    /// Show a list of locations to pick from
+    pub fn show_locations(&mut self, title: &str, entries: Vec<LocationEntry>) {
+        let height = (entries.len() as u16 + 2).clamp(5, self.settings.floating_window_height.max(5));
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(60),
+            height,
+            mode: FloatingMode::Locations {
+                title: title.to_string(),
+                entries,
+                selected: 0,
+            },
+        });
+        self.focus_floating = true;
+    }
+
+    fn show_location_list(&mut self, title: &str, locations: &[Location], language: Language) {
+        let entries = locations
+            .iter()
+            .map(|l| self.location_entry(l, language))
+            .collect();
+        self.show_locations(title, entries);
+    }
+
+    /// List the diagnostics of the active buffer
+    pub fn lsp_show_diagnostics(&mut self) {
+        let path = match &self.current_file {
+            Some(path) => absolute_path(path),
+            None => return,
+        };
+        let encoding = self.lsp.encoding(self.language);
+        let mut diagnostics = self.lsp.diagnostics_for(&path).to_vec();
+        diagnostics.sort_by_key(|d| (d.start, d.severity));
+        let entries: Vec<LocationEntry> = diagnostics
+            .iter()
+            .map(|d| {
+                let (row, col) = self.buffer_char_pos(d.start, encoding);
+                LocationEntry {
+                    path: path.clone(),
+                    row,
+                    col,
+                    text: format!("{}: {}", d.severity.label(), d.message),
+                }
+            })
+            .collect();
+
+        match entries.is_empty() {
+            true => self.message("No diagnostics"),
+            false => self.show_locations("Diagnostics", entries),
+        }
+    }
+
+    fn show_completions(&mut self, items: Vec<CompletionItem>) {
+        let (row, col) = self.textarea.cursor();
+        let chars: Vec<char> = self.textarea.line(row).chars().collect();
+        let mut start_col = col.min(chars.len());
+        while start_col > 0 && is_word_char(chars[start_col - 1]) {
+            start_col -= 1;
+        }
+
+        let width = items
+            .iter()
+            .map(|i| (i.label.chars().count() + i.detail.as_ref().map_or(0, |d| d.chars().count() + 2)) as u16 + 4)
+            .max()
+            .unwrap_or(20)
+            .clamp(20, 60);
+        let height = (items.len() as u16 + 2).min(12);
+        let (x, y) = self.popup_origin();
+
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x,
+            y,
+            width,
+            height,
+            mode: FloatingMode::Completion {
+                items,
+                selected: 0,
+                row,
+                start_col,
+            },
+        });
+        self.focus_floating = true;
+    }
+
+    /// Replace the word being completed with the chosen candidate
+    pub fn accept_completion(&mut self, item: &CompletionItem, row: usize, start_col: usize) {
+        let (cursor_row, cursor_col) = self.textarea.cursor();
+        match cursor_row == row && cursor_col >= start_col {
+            true => {}
+            false => return,
+        }
+        self.save_undo_state();
+        self.cancel_mark();
+        self.textarea.move_cursor(CursorMove::Jump(row, start_col));
+        self.textarea.start_selection();
+        self.textarea.move_cursor(CursorMove::Jump(row, cursor_col));
+        self.textarea.cut();
+        self.textarea.insert_str(&item.insert_text);
+        self.mark_modified();
+    }
+
+    /// Screen position just below the cursor, for popups
+    fn popup_origin(&self) -> (u16, u16) {
+        let (row, col) = self.textarea.cursor();
+        let y = row.saturating_sub(self.scroll_offset) + 1;
+        let x = col + self.gutter_width() as usize;
+        (x.min(u16::MAX as usize) as u16, y.min(u16::MAX as usize) as u16)
+    }
+
+    /// Diagnostic ranges of the active buffer as char positions
+    pub fn diagnostic_ranges(&self) -> Vec<DiagnosticRange> {
+        let path = match &self.current_file {
+            Some(path) => absolute_path(path),
+            None => return Vec::new(),
+        };
+        let encoding = self.lsp.encoding(self.language);
+        self.lsp
+            .diagnostics_for(&path)
+            .iter()
+            .map(|d| {
+                let start = self.buffer_char_pos(d.start, encoding);
+                let end = self.buffer_char_pos(d.end, encoding);
+                // Empty ranges still mark one character
+                let end = match end <= start {
+                    true => (start.0, start.1 + 1),
+                    false => end,
+                };
+                (start, end, d.severity)
+            })
+            .collect()
+    }
+
+    /// Most severe diagnostic on the cursor line
+    pub fn diagnostic_at_cursor(&self) -> Option<(Severity, String)> {
+        let path = absolute_path(self.current_file.as_deref()?);
+        let (row, _) = self.textarea.cursor();
+        self.lsp
+            .diagnostics_for(&path)
+            .iter()
+            .filter(|d| d.start.line <= row && row <= d.end.line)
+            .min_by_key(|d| d.severity)
+            .map(|d| (d.severity, d.message.clone()))
+    }
+
+    /// (errors, warnings) in the active buffer
+    pub fn diagnostic_counts(&self) -> (usize, usize) {
+        let path = match &self.current_file {
+            Some(path) => absolute_path(path),
+            None => return (0, 0),
+        };
+        let diagnostics = self.lsp.diagnostics_for(&path);
+        (
+            diagnostics.iter().filter(|d| d.severity == Severity::Error).count(),
+            diagnostics.iter().filter(|d| d.severity == Severity::Warning).count(),
+        )
+    }
+}
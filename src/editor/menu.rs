@@ -1,8 +1,8 @@
 //! Menu operations for the editor.
 
 use super::{
-    Editor, FloatingMode, FloatingWindow, MenuAction, MenuItem, MenuState, SettingItem,
-    SettingValue,
+    BackupMode, Editor, FloatingMode, FloatingWindow, LineNumbers, MenuAction, MenuItem, MenuState,
+    SettingItem, SettingValue,
 };
 
 impl Editor {
@@ -65,6 +65,38 @@ impl Editor {
                 },
                 description: "Color of selected text".to_string(),
             },
+            SettingItem {
+                name: "Line Numbers".to_string(),
+                value: SettingValue::Choice {
+                    current: LineNumbers::ALL
+                        .iter()
+                        .position(|mode| *mode == self.settings.line_numbers)
+                        .unwrap_or(0),
+                    options: vec![
+                        "Off".to_string(),
+                        "Absolute".to_string(),
+                        "Relative".to_string(),
+                        "Hybrid".to_string(),
+                    ],
+                },
+                description: "Line-number gutter mode".to_string(),
+            },
+            SettingItem {
+                name: "Backups".to_string(),
+                value: SettingValue::Choice {
+                    current: BackupMode::ALL
+                        .iter()
+                        .position(|mode| *mode == self.settings.backup)
+                        .unwrap_or(0),
+                    options: vec!["Off".to_string(), "Simple".to_string(), "Versioned".to_string()],
+                },
+                description: "Backup made before saving over a file".to_string(),
+            },
+            SettingItem {
+                name: "Clipboard".to_string(),
+                value: SettingValue::Info(self.clipboard.backend().name().to_string()),
+                description: "Where copied text goes (detected at startup)".to_string(),
+            },
         ];
 
         let mode = FloatingMode::Settings {
@@ -83,6 +115,17 @@ impl Editor {
         self.focus_floating = true;
     }
 
+    /// Apply a choice changed in the settings menu
+    pub fn apply_choice_setting(&mut self, name: &str, index: usize) {
+        match name {
+            "Cursor Color" => self.settings.cursor_color = self.settings.index_to_color(index, false),
+            "Selection Color" => self.settings.selection_color = self.settings.index_to_color(index, true),
+            "Line Numbers" => self.settings.line_numbers = LineNumbers::ALL.get(index).copied().unwrap_or_default(),
+            "Backups" => self.settings.backup = BackupMode::ALL.get(index).copied().unwrap_or_default(),
+            _ => {}
+        }
+    }
+
     pub fn update_menu_preview(&mut self) {
         let (action_opt, selected_text_opt) = match &self.floating_window {
             Some(fw) => match &fw.mode {
@@ -277,6 +320,7 @@ impl Editor {
     pub fn apply_menu_option(&mut self, action: MenuAction) {
         // Save undo state before any menu operation that modifies text
         self.save_undo_state();
+        let before = self.textarea.rope().clone();
         match action {
             MenuAction::Uppercase => match self.get_selected_text() {
                 Some(text) => {
@@ -343,12 +387,12 @@ impl Editor {
             MenuAction::Base64Decode => match self.get_selected_text() {
                 Some(text) => {
                     use base64::{engine::general_purpose, Engine as _};
-                    match general_purpose::STANDARD.decode(text.as_bytes()) {
+                    match general_purpose::STANDARD.decode(text.trim().as_bytes()) {
                         Ok(decoded_bytes) => match String::from_utf8(decoded_bytes) {
                             Ok(decoded_str) => self.replace_selection(decoded_str),
-                            Err(_) => {}
+                            Err(_) => self.error("Base64 decode: result is not valid UTF-8 text"),
                         },
-                        Err(_) => {}
+                        Err(e) => self.error(format!("Base64 decode failed: {}", e)),
                     }
                 }
                 None => {}
@@ -363,7 +407,7 @@ impl Editor {
             MenuAction::UrlDecode => match self.get_selected_text() {
                 Some(text) => match urlencoding::decode(&text) {
                     Ok(decoded) => self.replace_selection(decoded.to_string()),
-                    Err(_) => {}
+                    Err(_) => self.error("URL decode: result is not valid UTF-8 text"),
                 },
                 None => {}
             },
@@ -411,6 +455,11 @@ impl Editor {
             }
         }
 
+        match *self.textarea.rope() != before {
+            true => self.mark_modified(),
+            false => {}
+        }
+
         // Cancel mark/selection after applying action
         match self.mark.is_active() {
             true => self.cancel_mark(),
@@ -0,0 +1,243 @@
+//! Echo area and the `*Messages*` log.
+//!
+//! Everything the editor wants to tell the user goes through `message`,
+//! `warn` or `error`. The text is shown in the echo area (the first status
+//! bar line) and appended to a history that `view-messages` opens in a
+//! floating window. Informational messages disappear at the next key press;
+//! warnings and errors stay up until their time runs out, so they can't be
+//! missed by typing on. A warning or error that appeared during the current
+//! command is not hidden by an informational message that follows it.
+
+use super::{Editor, FloatingMode, FloatingWindow};
+use ratatui::style::Color;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Messages kept in the `*Messages*` history
+const MAX_MESSAGES: usize = 500;
+
+/// How serious a message is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl MessageLevel {
+    /// How long the message stays in the echo area
+    pub fn duration(self) -> Duration {
+        match self {
+            MessageLevel::Info => Duration::from_secs(5),
+            MessageLevel::Warning => Duration::from_secs(8),
+            MessageLevel::Error => Duration::from_secs(12),
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            MessageLevel::Info => Color::Yellow,
+            MessageLevel::Warning => Color::LightRed,
+            MessageLevel::Error => Color::Red,
+        }
+    }
+}
+
+/// One entry of the message history
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub level: MessageLevel,
+    pub text: String,
+    /// Wall-clock time for the history (HH:MM:SS)
+    pub time: String,
+    /// How many times in a row it was reported
+    pub count: usize,
+}
+
+impl Message {
+    /// Line shown in the `*Messages*` window
+    pub fn history_line(&self) -> String {
+        match self.count {
+            1 => format!("{} {}", self.time, self.text),
+            n => format!("{} {} [{} times]", self.time, self.text, n),
+        }
+    }
+}
+
+/// Message currently in the echo area
+struct Echo {
+    level: MessageLevel,
+    text: String,
+    shown_at: Instant,
+    /// A key was pressed since it appeared
+    seen: bool,
+}
+
+/// Message history plus what the echo area currently shows
+#[derive(Default)]
+pub struct Messages {
+    history: VecDeque<Message>,
+    echo: Option<Echo>,
+    /// Messages reported so far (repeats included)
+    reported: usize,
+}
+
+impl Messages {
+    fn push(&mut self, level: MessageLevel, text: String) {
+        match self.history.back_mut() {
+            Some(last) if last.level == level && last.text == text => last.count += 1,
+            _ => {
+                self.history.push_back(Message {
+                    level,
+                    text: text.clone(),
+                    time: chrono::Local::now().format("%H:%M:%S").to_string(),
+                    count: 1,
+                });
+                match self.history.len() > MAX_MESSAGES {
+                    true => {
+                        self.history.pop_front();
+                    }
+                    false => {}
+                }
+            }
+        }
+        self.reported += 1;
+
+        let keep_current = match (&self.echo, level) {
+            (Some(echo), MessageLevel::Info) => echo.level != MessageLevel::Info && !echo.seen && self.echo().is_some(),
+            _ => false,
+        };
+        match keep_current {
+            true => {}
+            false => {
+                self.echo = Some(Echo {
+                    level,
+                    text,
+                    shown_at: Instant::now(),
+                    seen: false,
+                })
+            }
+        }
+    }
+
+    /// The message to show in the echo area, if it has not expired
+    pub fn echo(&self) -> Option<(MessageLevel, &str)> {
+        match &self.echo {
+            Some(echo) if echo.shown_at.elapsed() < echo.level.duration() => Some((echo.level, echo.text.as_str())),
+            _ => None,
+        }
+    }
+
+    /// A key was pressed: informational messages go away, warnings and
+    /// errors stay until they expire
+    pub fn key_pressed(&mut self) {
+        match &mut self.echo {
+            Some(echo) if echo.level == MessageLevel::Info => self.echo = None,
+            Some(echo) => echo.seen = true,
+            None => {}
+        }
+    }
+
+    /// Number of messages reported so far, to tell whether a call reported one
+    pub fn reported(&self) -> usize {
+        self.reported
+    }
+
+    /// All messages, oldest first
+    pub fn history(&self) -> impl ExactSizeIterator<Item = &Message> {
+        self.history.iter()
+    }
+}
+
+impl Editor {
+    // ==================== Messages ====================
+
+    /// Show an informational message in the echo area
+    pub fn message(&mut self, text: impl Into<String>) {
+        self.messages.push(MessageLevel::Info, text.into());
+    }
+
+    /// Show a warning in the echo area
+    pub fn warn(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        log::warn!("{}", text);
+        self.messages.push(MessageLevel::Warning, text);
+    }
+
+    /// Show an error in the echo area
+    pub fn error(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        log::error!("{}", text);
+        self.messages.push(MessageLevel::Error, text);
+    }
+
+    /// Text currently shown in the echo area
+    pub fn echo_text(&self) -> Option<&str> {
+        self.messages.echo().map(|(_, text)| text)
+    }
+
+    /// Open the `*Messages*` history, scrolled to the latest message
+    pub fn view_messages(&mut self) {
+        let count = self.messages.history().len();
+        let height = self.settings.floating_window_height.max(5);
+        let visible = height.saturating_sub(2) as usize;
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(60),
+            height,
+            mode: FloatingMode::Messages {
+                scroll: count.saturating_sub(visible),
+            },
+        });
+        self.focus_floating = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_and_history() {
+        let mut editor = Editor::new();
+        editor.message("Saved");
+        assert_eq!(editor.echo_text(), Some("Saved"));
+
+        // Key presses dismiss information but not errors
+        editor.messages.key_pressed();
+        assert_eq!(editor.echo_text(), None);
+        editor.error("Disk full");
+        editor.message("Wrote file");
+        assert_eq!(editor.echo_text(), Some("Disk full"));
+        editor.messages.key_pressed();
+        assert_eq!(editor.echo_text(), Some("Disk full"));
+
+        // Once seen, the error gives way to new messages
+        editor.message("Saved");
+        assert_eq!(editor.echo_text(), Some("Saved"));
+
+        // Repeats are counted instead of logged again
+        editor.warn("Disk full");
+        editor.warn("Disk full");
+        let lines: Vec<String> = editor.messages.history().map(|m| m.text.clone()).collect();
+        assert_eq!(lines, ["Saved", "Disk full", "Wrote file", "Saved", "Disk full"]);
+        assert!(editor.messages.history().last().unwrap().history_line().ends_with("Disk full [2 times]"));
+
+        // Messages expire
+        editor.error("old");
+        editor.messages.echo.as_mut().unwrap().shown_at = Instant::now() - Duration::from_secs(60);
+        assert_eq!(editor.echo_text(), None);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut messages = Messages::default();
+        for i in 0..MAX_MESSAGES + 10 {
+            messages.push(MessageLevel::Info, format!("m{}", i));
+        }
+        assert_eq!(messages.history().len(), MAX_MESSAGES);
+        assert_eq!(messages.history().next().unwrap().text, "m10");
+    }
+}
@@ -5,28 +5,85 @@
 
 // Core modules
 mod core;
+pub mod autosave;
+pub mod buffer;
 pub mod buffer_ops;
 pub mod dialogs;
+pub mod diff_view;
+pub mod external;
+pub mod file_format;
+pub mod fold;
+pub mod git;
+pub mod kill_ring;
+pub mod lsp;
+pub mod messages;
+pub mod multi_cursor;
+pub mod outline;
+pub mod search;
 pub mod settings;
+pub mod shell;
 pub mod syntax;
+pub mod text_object;
 pub mod text_widget;
+pub mod textarea;
 pub mod types;
 pub mod undo;
+pub mod undo_history;
 
 // Operation modules
 mod file_ops;
 mod menu;
 mod movement;
+mod replace;
 mod selection;
 
+#[cfg(test)]
+pub(crate) mod test_util;
+
 // Re-export the Editor struct and core types
 pub use core::{Editor, RecenterState};
 
+// Re-export buffer types
+pub use buffer::{Buffer, BufferListEntry};
+
 // Re-export dialog types
-pub use dialogs::{ConfirmationDialog, DeleteFileConfirmation, QuitConfirmation};
+pub use dialogs::{
+    ConfirmationDialog, DeleteFileConfirmation, ExternalChangeConfirmation, KillBufferConfirmation,
+    OverwriteChangedFileConfirmation, QuitConfirmation, RecoverFileConfirmation,
+};
+
+// Re-export the on-disk state of the current file
+pub use external::DiskWatch;
+
+// Re-export file format types
+pub use file_format::{Encoding, FileFormat, LineEnding};
+
+// Re-export the rows folds hide
+pub use fold::FoldedRows;
+
+// Re-export git gutter state
+pub use git::GitGutter;
+
+// Re-export kill ring types
+pub use kill_ring::{KillDirection, KillRing};
+
+// Re-export language server types
+pub use lsp::{DiagnosticRange, LocationEntry};
+
+// Re-export echo area types
+pub use messages::{Message, MessageLevel, Messages};
+
+// Re-export multiple-cursor helpers
+pub use multi_cursor::{runs_at_each_cursor, MULTI_CURSOR_COMMANDS};
+
+// Re-export text object types
+pub use text_object::{TextObject, TextObjectAction};
+
+// Re-export search types
+pub use search::{ISearch, SearchDirection, SearchMatch};
 
 // Re-export settings
-pub use settings::Settings;
+pub use settings::{BackupMode, LineNumbers, Settings};
 
 // Re-export commonly used types
 pub use types::{
@@ -35,5 +92,8 @@ pub use types::{
     SettingItem, SettingValue, StatusBarState,
 };
 
+// Re-export text storage types
+pub use textarea::{CursorMove, Edit, TextArea};
+
 // Re-export undo types
-pub use undo::UndoManager;
+pub use undo::{TypingKind, UndoManager, UndoTreeEntry};
@@ -1,8 +1,8 @@
 //! Cursor movement operations for the editor.
 
-use super::Editor;
+use super::syntax::SyntaxState;
+use super::{CursorMove, Editor, KillDirection};
 use crate::logging;
-use tui_textarea::CursorMove;
 
 impl Editor {
     // ==================== Cursor Movement ====================
@@ -17,7 +17,9 @@ impl Editor {
                 self.textarea.is_selecting()
             );
         }
+        let before = self.textarea.cursor();
         self.textarea.move_cursor(movement);
+        self.skip_folded_rows(movement, before);
         // Adjust scroll only if cursor went off-screen
         self.ensure_cursor_visible();
         if logging::log_movement() || logging::log_selection() {
@@ -29,18 +31,20 @@ impl Editor {
         }
     }
 
-    /// Ensure cursor is visible, adjusting scroll_offset minimally if needed
-    fn ensure_cursor_visible(&mut self) {
+    /// Ensure cursor is visible, adjusting scroll_offset minimally if needed.
+    /// Folded lines take no screen lines.
+    pub(super) fn ensure_cursor_visible(&mut self) {
         let (cursor_row, _) = self.textarea.cursor();
         let viewport_height = self.viewport_height as usize;
+        let folds = self.textarea.folded_rows();
 
         // If cursor is above visible area, scroll up
         if cursor_row < self.scroll_offset {
             self.scroll_offset = cursor_row;
         }
         // If cursor is below visible area, scroll down
-        else if cursor_row >= self.scroll_offset + viewport_height {
-            self.scroll_offset = cursor_row - viewport_height + 1;
+        else if folds.count_shown(self.scroll_offset, cursor_row) > viewport_height {
+            self.scroll_offset = folds.rows_up(cursor_row, viewport_height.saturating_sub(1));
         }
         // Otherwise, cursor is visible, don't change scroll
 
@@ -53,12 +57,14 @@ impl Editor {
         let (cursor_row, _) = self.textarea.cursor();
         let viewport_height = self.viewport_height as usize;
         let half_height = viewport_height / 2;
-        self.scroll_offset = cursor_row.saturating_sub(half_height);
+        self.scroll_offset = self.textarea.folded_rows().rows_up(cursor_row, half_height);
     }
 
     pub fn move_word_forward(&mut self) {
-        // Use tui-textarea's built-in word movement which properly handles selections
+        // TextArea's word movement extends an active selection
+        let before = self.textarea.cursor();
         self.textarea.move_cursor(CursorMove::WordForward);
+        self.skip_folded_rows(CursorMove::WordForward, before);
         self.ensure_cursor_visible();
     }
 
@@ -71,8 +77,10 @@ impl Editor {
                 self.textarea.is_selecting()
             );
         }
-        // Use tui-textarea's built-in word movement which properly handles selections
+        // TextArea's word movement extends an active selection
+        let before = self.textarea.cursor();
         self.textarea.move_cursor(CursorMove::WordBack);
+        self.skip_folded_rows(CursorMove::WordBack, before);
         self.ensure_cursor_visible();
         if logging::log_movement() || logging::log_selection() {
             log::debug!(
@@ -85,29 +93,29 @@ impl Editor {
 
     pub fn is_at_last_line(&self) -> bool {
         let (row, _) = self.textarea.cursor();
-        let lines = self.textarea.lines();
-        row == lines.len() - 1 || lines.is_empty()
+        row == self.textarea.len_lines() - 1
     }
 
-    /// Move cursor up by half page then center (like Vim C-u + zz)
+    /// Move cursor up by half page then center (like Vim C-u + zz).
+    /// Folded lines do not count.
     pub fn page_up(&mut self) {
         let half_page = (self.viewport_height as usize) / 2;
         let (row, col) = self.textarea.cursor();
-        let new_row = row.saturating_sub(half_page);
-        self.textarea.move_cursor(CursorMove::Jump(new_row as u16, col as u16));
+        let new_row = self.textarea.folded_rows().rows_up(row, half_page);
+        self.textarea.move_cursor(CursorMove::Jump(new_row, col));
         // Center the cursor after moving
         self.center_cursor();
         self.last_was_recenter = false;
     }
 
-    /// Move cursor down by half page then center (like Vim C-d + zz)
+    /// Move cursor down by half page then center (like Vim C-d + zz).
+    /// Folded lines do not count.
     pub fn page_down(&mut self) {
         let half_page = (self.viewport_height as usize) / 2;
         let (row, col) = self.textarea.cursor();
-        let lines = self.textarea.lines();
-        let max_row = lines.len().saturating_sub(1);
-        let new_row = (row + half_page).min(max_row);
-        self.textarea.move_cursor(CursorMove::Jump(new_row as u16, col as u16));
+        let max_row = self.textarea.len_lines() - 1;
+        let new_row = self.textarea.folded_rows().rows_down(row, half_page, max_row);
+        self.textarea.move_cursor(CursorMove::Jump(new_row, col));
         // Center the cursor after moving
         self.center_cursor();
         self.last_was_recenter = false;
@@ -119,6 +127,7 @@ impl Editor {
         use super::core::RecenterState;
         let (cursor_row, _) = self.textarea.cursor();
         let viewport_height = self.viewport_height as usize;
+        let folds = self.textarea.folded_rows();
 
         // If last command wasn't recenter, always start with center
         // If it was recenter, cycle to next state
@@ -142,7 +151,7 @@ impl Editor {
             RecenterState::Center => {
                 // Put cursor in center of viewport
                 let half_height = viewport_height / 2;
-                cursor_row.saturating_sub(half_height)
+                folds.rows_up(cursor_row, half_height)
             }
             RecenterState::Top => {
                 // Put cursor at top of viewport (blank space below allowed)
@@ -150,7 +159,7 @@ impl Editor {
             }
             RecenterState::Bottom => {
                 // Put cursor at bottom of viewport (blank space above allowed)
-                cursor_row.saturating_sub(viewport_height.saturating_sub(1))
+                folds.rows_up(cursor_row, viewport_height.saturating_sub(1))
             }
         };
 
@@ -158,21 +167,88 @@ impl Editor {
         self.last_was_recenter = true;
     }
 
+    // ==================== Structural Movement ====================
+    //
+    // These move by the syntax tree; see syntax/structure.rs.
+
+    /// Move to the next sibling syntax node (C-M-f)
+    pub fn forward_sibling_node(&mut self) {
+        let target = self.structural_target(|syntax, _, byte| syntax.sibling_node_start(byte, true));
+        self.move_to_byte(target, "No next sibling");
+    }
+
+    /// Move to the start of the syntax node, or the previous sibling (C-M-b)
+    pub fn backward_sibling_node(&mut self) {
+        let target = self.structural_target(|syntax, _, byte| syntax.sibling_node_start(byte, false));
+        self.move_to_byte(target, "No previous sibling");
+    }
+
+    /// Move to the start of the enclosing syntax node (C-M-u)
+    pub fn up_node(&mut self) {
+        let target = self.structural_target(|syntax, _, byte| syntax.parent_node_start(byte));
+        self.move_to_byte(target, "At top level");
+    }
+
+    /// Move to the beginning of the enclosing function or class (C-M-a)
+    pub fn beginning_of_defun(&mut self) {
+        let target = self.structural_target(|syntax, source, byte| syntax.beginning_of_definition(source, byte));
+        self.move_to_byte(target, "No definition before the cursor");
+    }
+
+    /// Move to the end of the enclosing function or class (C-M-e)
+    pub fn end_of_defun(&mut self) {
+        let target = self.structural_target(|syntax, source, byte| syntax.end_of_definition(source, byte));
+        self.move_to_byte(target, "No definition after the cursor");
+    }
+
+    /// Move to the next function definition (C-M-n)
+    pub fn next_function(&mut self) {
+        let target = self.structural_target(|syntax, source, byte| syntax.function_start(source, byte, true));
+        self.move_to_byte(target, "No next function");
+    }
+
+    /// Move to the previous function definition (C-M-p)
+    pub fn previous_function(&mut self) {
+        let target = self.structural_target(|syntax, source, byte| syntax.function_start(source, byte, false));
+        self.move_to_byte(target, "No previous function");
+    }
+
+    /// Byte offset `find` picks from the syntax tree, the text and the
+    /// cursor's byte offset (None without a grammar)
+    fn structural_target(&mut self, find: impl Fn(&SyntaxState, &str, usize) -> Option<usize>) -> Option<usize> {
+        // The tree is only reparsed before drawing, so it may be behind the text
+        self.ensure_highlights_current();
+        let syntax = self.syntax_state.as_ref()?;
+        let (row, col) = self.textarea.cursor();
+        let byte = self.textarea.row_col_to_byte(row, col);
+        find(syntax, &self.textarea.text(), byte)
+    }
+
+    fn move_to_byte(&mut self, target: Option<usize>, not_found: &str) {
+        match (target, &self.syntax_state) {
+            (Some(byte), _) => {
+                let (row, col) = self.textarea.byte_to_row_col(byte);
+                self.move_cursor(CursorMove::Jump(row, col));
+            }
+            (None, Some(_)) => self.message(not_found),
+            (None, None) => self.message("No syntax tree for this buffer"),
+        }
+    }
+
     // ==================== Word Delete Operations ====================
     //
     // These follow the BufferEdit pattern - see buffer_ops.rs for details.
 
-    /// Delete word forward (M-d)
+    /// Kill word forward (M-d)
     ///
-    /// Returns true if text was deleted, false if at end of document.
+    /// Returns true if text was killed, false if at end of document.
     /// Handles undo state and modification tracking internally.
     pub fn delete_word_forward(&mut self) -> bool {
         let (row, col) = self.textarea.cursor();
-        let lines = self.textarea.lines();
+        let last_row = self.textarea.len_lines() - 1;
 
         // Check if there's anything to delete
-        let at_end = row >= lines.len()
-            || (row == lines.len() - 1 && col >= lines[row].chars().count());
+        let at_end = row > last_row || (row == last_row && col >= self.textarea.line_len(row));
 
         if at_end {
             return false;
@@ -180,13 +256,14 @@ impl Editor {
 
         self.save_undo_state();
         self.textarea.delete_next_word();
+        self.kill_last_edit(KillDirection::Forward);
         self.mark_modified();
         true
     }
 
-    /// Delete word backward (M-Backspace)
+    /// Kill word backward (M-Backspace)
     ///
-    /// Returns true if text was deleted, false if at start of document.
+    /// Returns true if text was killed, false if at start of document.
     /// Handles undo state and modification tracking internally.
     pub fn delete_word_backward(&mut self) -> bool {
         let (row, col) = self.textarea.cursor();
@@ -200,21 +277,30 @@ impl Editor {
 
         self.save_undo_state();
         self.textarea.delete_word();
+        self.kill_last_edit(KillDirection::Backward);
         self.mark_modified();
         true
     }
 
+    /// Put the text removed by the last edit on the kill ring
+    fn kill_last_edit(&mut self, direction: KillDirection) {
+        let removed = match self.textarea.last_edit() {
+            Some(edit) if !edit.removed.is_empty() => edit.removed.clone(),
+            _ => return,
+        };
+        self.kill_text(&removed, direction);
+    }
+
     /// Delete character forward (C-d)
     ///
     /// Returns true if text was deleted, false if at end of document.
     /// Handles undo state and modification tracking internally.
     pub fn delete_char_forward(&mut self) -> bool {
         let (row, col) = self.textarea.cursor();
-        let lines = self.textarea.lines();
+        let last_row = self.textarea.len_lines() - 1;
 
         // Check if there's anything to delete
-        let at_end = row >= lines.len()
-            || (row == lines.len() - 1 && col >= lines[row].chars().count());
+        let at_end = row > last_row || (row == last_row && col >= self.textarea.line_len(row));
 
         if at_end {
             return false;
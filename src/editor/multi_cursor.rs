@@ -0,0 +1,339 @@
+//! Multiple cursors.
+//!
+//! Besides the cursor, the text area can hold extra cursors, each with its
+//! own selection (see `TextArea::extra_cursors`). Typing and the editing
+//! commands in `MULTI_CURSOR_COMMANDS`, as well as the text transforms of
+//! the M-q menu and the text object commands, run once at every cursor, in
+//! document order. Everything they change is undone as one step.
+//!
+//! Running at a cursor swaps it with the main cursor, so the commands
+//! themselves only ever deal with one cursor. C-g (or Esc) first drops the
+//! selections and then the extra cursors.
+
+use super::{CursorMove, Editor, MarkState, MenuAction, TextObjectAction};
+
+/// Commands that run at every cursor when there are several
+pub const MULTI_CURSOR_COMMANDS: &[&str] = &[
+    // Movement
+    "forward-char",
+    "backward-char",
+    "next-line",
+    "previous-line",
+    "beginning-of-line",
+    "end-of-line",
+    "forward-word",
+    "backward-word",
+    "forward-sibling-node",
+    "backward-sibling-node",
+    "up-node",
+    "beginning-of-defun",
+    "end-of-defun",
+    "next-function",
+    "previous-function",
+    // Editing
+    "kill-line",
+    "kill-line-backward",
+    "kill-word",
+    "kill-word-backward",
+    "yank",
+    "delete-char",
+    "delete-char-backward",
+    // Selection
+    "set-mark",
+    "kill-region",
+    "copy-region",
+];
+
+/// Whether a command runs at every cursor
+pub fn runs_at_each_cursor(command: &str) -> bool {
+    MULTI_CURSOR_COMMANDS.contains(&command)
+        || MenuAction::from_command(command).is_some()
+        || TextObjectAction::from_command(command).is_some()
+}
+
+impl Editor {
+    // ==================== Multiple Cursors ====================
+
+    /// Run `run` at every cursor, in document order, with that cursor (and
+    /// its selection) as the main one. The changes join the current undo
+    /// step, so callers save the undo state first.
+    pub fn at_each_cursor(&mut self, mut run: impl FnMut(&mut Self)) {
+        match self.textarea.extra_cursor_count() {
+            0 => {
+                run(self);
+                return;
+            }
+            _ => {}
+        }
+
+        // None is the main cursor, Some(i) extra cursor i
+        let (row, col) = self.textarea.cursor();
+        let mut order: Vec<(usize, Option<usize>)> = vec![(self.textarea.char_index(row, col), None)];
+        order.extend(self.textarea.extra_cursor_indices().into_iter().enumerate().map(|(i, at)| (at, Some(i))));
+        order.sort();
+
+        let last_key = self.last_key;
+        let scroll_offset = self.scroll_offset;
+        let mut main = (self.mark, self.last_key);
+        self.hold_undo_step();
+        for (visit, (_, cursor)) in order.into_iter().enumerate() {
+            self.cursor_visit = Some(visit);
+            match cursor {
+                Some(i) => {
+                    self.textarea.swap_cursor(i);
+                    self.mark = match self.textarea.selection_anchor() {
+                        Some((row, col)) => MarkState::Active { row, col },
+                        None => MarkState::None,
+                    };
+                    self.last_key = last_key;
+                    run(self);
+                    self.textarea.swap_cursor(i);
+                }
+                None => {
+                    (self.mark, self.last_key) = main;
+                    run(self);
+                    main = (self.mark, self.last_key);
+                }
+            }
+        }
+        self.cursor_visit = None;
+        (self.mark, self.last_key) = main;
+        self.release_undo_step(self.textarea.cursor());
+        self.textarea.merge_cursors();
+
+        match self.kill_ring.finish_cursor_kills() {
+            Some(entry) => self.copy_to_clipboard(&entry),
+            None => {}
+        }
+        // Follow the main cursor rather than whichever cursor ran last
+        self.scroll_offset = scroll_offset;
+        let (row, col) = self.textarea.cursor();
+        self.move_cursor(CursorMove::Jump(row, col));
+    }
+
+    /// Add a cursor on the next line (C-c n)
+    pub fn add_cursor_below(&mut self) -> bool {
+        self.add_cursor_vertically(CursorMove::Down)
+    }
+
+    /// Add a cursor on the previous line (C-c p)
+    pub fn add_cursor_above(&mut self) -> bool {
+        self.add_cursor_vertically(CursorMove::Up)
+    }
+
+    /// Leave a cursor where the cursor is and move the cursor a line up or down
+    fn add_cursor_vertically(&mut self, movement: CursorMove) -> bool {
+        let (row, _) = self.textarea.cursor();
+        let no_line = match movement {
+            CursorMove::Up => row == 0,
+            _ => row + 1 >= self.textarea.len_lines(),
+        };
+        match no_line {
+            true => {
+                self.message("No line to add a cursor on");
+                return false;
+            }
+            false => {}
+        }
+        self.textarea.cancel_selection();
+        self.mark = MarkState::None;
+        self.textarea.add_cursor();
+        self.move_cursor(movement);
+        self.textarea.merge_cursors();
+        true
+    }
+
+    /// Add a cursor at the next occurrence of the selected text and select
+    /// it (C-c d). The search wraps around and skips occurrences that
+    /// already have a cursor.
+    pub fn add_cursor_next_match(&mut self) -> bool {
+        let (text, (_, end)) = match (self.get_selected_text(), self.textarea.selection_range()) {
+            (Some(text), Some(range)) => (text, range),
+            _ => {
+                self.message("Select the text to add a cursor at first");
+                return false;
+            }
+        };
+
+        let whole = self.textarea.text();
+        let from = self.textarea.row_col_to_byte(end.0, end.1);
+        let mut cursors = self.textarea.extra_cursor_indices();
+        cursors.push(self.textarea.char_index(end.0, end.1));
+        let rope = self.textarea.rope();
+        let next = whole[from..]
+            .match_indices(&text)
+            .map(|(i, _)| from + i)
+            .chain(whole[..from].match_indices(&text).map(|(i, _)| i))
+            .map(|start| {
+                let start = rope.byte_to_char(start);
+                (start, start + text.chars().count())
+            })
+            .find(|(start, end)| !cursors.contains(start) && !cursors.contains(end));
+        match next {
+            Some((start, end)) => {
+                self.textarea.add_cursor();
+                self.select_chars(start, end);
+                true
+            }
+            None => {
+                self.message("No more matches");
+                false
+            }
+        }
+    }
+
+    /// Put a cursor at each of `positions`, selecting `len` chars there
+    /// (jump mode with C-c a). The match nearest the cursor gets the main
+    /// cursor; overlapping matches are skipped.
+    pub fn add_cursors_at(&mut self, positions: &[(usize, usize)], len: usize) -> bool {
+        let mut starts: Vec<usize> = Vec::new();
+        for (row, col) in positions {
+            let start = self.textarea.char_index(*row, *col);
+            match starts.last() {
+                Some(previous) if start < previous + len => {}
+                _ => starts.push(start),
+            }
+        }
+        let (row, col) = self.textarea.cursor();
+        let cursor = self.textarea.char_index(row, col);
+        let main = match starts.iter().min_by_key(|start| start.abs_diff(cursor)) {
+            Some(start) => *start,
+            None => return false,
+        };
+
+        self.textarea.clear_extra_cursors();
+        for start in starts.iter().filter(|start| **start != main) {
+            self.select_chars(*start, start + len);
+            self.textarea.add_cursor();
+        }
+        self.select_chars(main, main + len);
+        self.message(format!("{} cursors", starts.len()));
+        true
+    }
+
+    /// Drop the selections at every cursor, or when there are none, the
+    /// extra cursors (C-g). Returns false without extra cursors.
+    pub fn cancel_extra_cursors(&mut self) -> bool {
+        match self.textarea.extra_cursor_count() {
+            0 => return false,
+            _ => {}
+        }
+        let selecting = self.mark.is_active()
+            || self.textarea.extra_cursors().iter().any(|(_, selection)| selection.is_some());
+        match selecting {
+            true => self.at_each_cursor(|editor| editor.cancel_mark()),
+            false => self.textarea.clear_extra_cursors(),
+        }
+        true
+    }
+
+    /// Select chars `start..end`, leaving the cursor at the end
+    pub(super) fn select_chars(&mut self, start: usize, end: usize) {
+        let (row, col) = self.textarea.char_to_row_col(start);
+        self.textarea.cancel_selection();
+        self.textarea.move_cursor(CursorMove::Jump(row, col));
+        self.textarea.start_selection();
+        self.mark = MarkState::Active { row, col };
+        let (row, col) = self.textarea.char_to_row_col(end);
+        self.move_cursor(CursorMove::Jump(row, col));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_with;
+
+    /// Run a command the way a key press does
+    fn command(editor: &mut Editor, name: &str) {
+        editor.kill_ring.next_command();
+        editor.save_undo_state();
+        editor.at_each_cursor(|editor| match name {
+            "kill-line" => {
+                editor.cut_to_end_of_line();
+            }
+            "yank" => {
+                editor.yank();
+            }
+            "upcase-region" => editor.apply_menu_option(MenuAction::Uppercase),
+            _ => {
+                editor.textarea.insert_str(name);
+            }
+        });
+        editor.mark_modified();
+    }
+
+    #[test]
+    fn test_typing_at_every_cursor_undoes_as_one_step() {
+        let mut editor = editor_with("one\ntwo\nthree");
+        assert!(editor.add_cursor_below());
+        assert!(editor.add_cursor_below());
+        assert!(!editor.add_cursor_below());
+        assert_eq!(editor.textarea.extra_cursor_count(), 2);
+
+        command(&mut editor, "> ");
+        assert_eq!(editor.textarea.text(), "> one\n> two\n> three");
+        assert_eq!(editor.textarea.cursor(), (2, 2));
+        assert!(editor.undo());
+        assert_eq!(editor.textarea.text(), "one\ntwo\nthree");
+        assert!(!editor.undo());
+    }
+
+    #[test]
+    fn test_kill_and_yank_at_every_cursor() {
+        let mut editor = editor_with("a = 1\nb = 2\nc = 3");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 1));
+        editor.add_cursor_below();
+        editor.add_cursor_below();
+
+        command(&mut editor, "kill-line");
+        assert_eq!(editor.textarea.text(), "a\nb\nc");
+        assert_eq!(editor.kill_ring.current_entry(), Some(" = 1\n = 2\n = 3"));
+
+        // Each cursor gets back what it killed
+        command(&mut editor, "yank");
+        assert_eq!(editor.textarea.text(), "a = 1\nb = 2\nc = 3");
+
+        // With a single cursor the whole entry is yanked
+        editor.textarea.clear_extra_cursors();
+        editor.textarea.move_cursor(CursorMove::End);
+        command(&mut editor, "yank");
+        assert_eq!(editor.textarea.text(), "a = 1\nb = 2\nc = 3 = 1\n = 2\n = 3");
+    }
+
+    #[test]
+    fn test_transform_each_selected_match() {
+        let mut editor = editor_with("let foo = foo + bar(foo);");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 4));
+        editor.set_mark();
+        editor.textarea.move_cursor(CursorMove::Jump(0, 7));
+        assert!(editor.add_cursor_next_match());
+        assert!(editor.add_cursor_next_match());
+        assert!(!editor.add_cursor_next_match());
+        assert_eq!(editor.textarea.extra_cursor_count(), 2);
+
+        command(&mut editor, "upcase-region");
+        assert_eq!(editor.textarea.text(), "let FOO = FOO + bar(FOO);");
+        assert!(!editor.mark.is_active());
+        assert!(editor.textarea.extra_cursors().iter().all(|(_, selection)| selection.is_none()));
+
+        // C-g drops the extra cursors once nothing is selected
+        assert!(editor.cancel_extra_cursors());
+        assert_eq!(editor.textarea.extra_cursor_count(), 0);
+        assert!(!editor.cancel_extra_cursors());
+    }
+
+    #[test]
+    fn test_cursors_at_all_matches() {
+        let mut editor = editor_with("aa x aaa\nx aa");
+        editor.textarea.move_cursor(CursorMove::Jump(1, 4));
+        let positions = crate::editor::JumpMode::match_positions(editor.textarea.lines(), "aa");
+        assert!(editor.add_cursors_at(&positions, 2));
+        // "aaa" holds one non-overlapping match
+        assert_eq!(editor.textarea.extra_cursor_count(), 2);
+        assert_eq!(editor.textarea.selection_range(), Some(((1, 2), (1, 4))));
+
+        command(&mut editor, "b");
+        assert_eq!(editor.textarea.text(), "b x ba\nx b");
+    }
+}
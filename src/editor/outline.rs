@@ -0,0 +1,130 @@
+//! Outline of the symbols in the active buffer (like Emacs' imenu).
+//!
+//! The symbols come from the tag queries of `SyntaxState::symbols`:
+//! functions, types, methods, Markdown headings and so on, indented by
+//! how deeply they nest. Typing narrows the list by name and Enter jumps
+//! to the chosen symbol.
+
+use super::syntax::SymbolKind;
+use super::{CursorMove, Editor, FloatingMode, FloatingWindow};
+
+/// A symbol listed in the outline
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineEntry {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// Number of symbols around this one
+    pub depth: usize,
+    /// Position of the symbol's name
+    pub row: usize,
+    pub col: usize,
+}
+
+/// Indices of the entries whose name contains `query`, ignoring case
+pub fn filter_outline(entries: &[OutlineEntry], query: &str) -> Vec<usize> {
+    let query = query.to_lowercase();
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.name.to_lowercase().contains(&query))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+impl Editor {
+    // ==================== Outline ====================
+
+    /// List the symbols of the buffer to jump to one (C-c o). The symbol
+    /// the cursor is in (or after) starts out selected.
+    pub fn open_outline(&mut self) {
+        // The tree is only reparsed before drawing, so it may be behind the text
+        self.ensure_highlights_current();
+        let symbols = match &self.syntax_state {
+            Some(syntax) => syntax.symbols(&self.textarea.text()),
+            None => {
+                self.message("No syntax tree for this buffer");
+                return;
+            }
+        };
+        match symbols.is_empty() {
+            true => {
+                self.message("No symbols in this buffer");
+                return;
+            }
+            false => {}
+        }
+
+        let entries: Vec<OutlineEntry> = symbols
+            .into_iter()
+            .map(|symbol| {
+                let (row, col) = self.textarea.byte_to_row_col(symbol.name_byte);
+                OutlineEntry { kind: symbol.kind, name: symbol.name, depth: symbol.depth, row, col }
+            })
+            .collect();
+        let (row, _) = self.textarea.cursor();
+        let selected = entries.iter().rposition(|entry| entry.row <= row).unwrap_or(0);
+
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 0,
+            y: 0,
+            width: 80,
+            height: 1,
+            mode: FloatingMode::Outline {
+                input: String::new(),
+                cursor_pos: 0,
+                filtered: (0..entries.len()).collect(),
+                entries,
+                selected,
+            },
+        });
+        self.focus_floating = true;
+    }
+
+    /// Move the cursor to the name of an outline entry
+    pub fn goto_outline_entry(&mut self, entry: &OutlineEntry) {
+        self.cancel_mark();
+        self.move_cursor(CursorMove::Jump(entry.row, entry.col));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::syntax::{Language, SyntaxState};
+    use crate::editor::TextArea;
+
+    #[test]
+    fn test_outline_selects_and_jumps() {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::from_text("struct S;\n\nimpl S {\n    fn one(&self) {\n        1;\n    }\n}\n\nfn two() {}\n");
+        editor.syntax_state = SyntaxState::new(Language::Rust);
+        editor.viewport_height = 20;
+        editor.textarea.move_cursor(CursorMove::Jump(4, 8));
+
+        editor.open_outline();
+        let (entries, selected) = match &editor.floating_window.as_ref().unwrap().mode {
+            FloatingMode::Outline { entries, selected, .. } => (entries.clone(), *selected),
+            _ => panic!("outline should be open"),
+        };
+        let names: Vec<(&str, usize)> = entries.iter().map(|e| (e.name.as_str(), e.depth)).collect();
+        assert_eq!(names, vec![("S", 0), ("S", 0), ("one", 1), ("two", 0)]);
+        // The cursor is inside `one`
+        assert_eq!(selected, 2);
+
+        assert_eq!(filter_outline(&entries, "TW"), vec![3]);
+        assert_eq!(filter_outline(&entries, "s"), vec![0, 1]);
+
+        editor.goto_outline_entry(&entries[3]);
+        assert_eq!(editor.textarea.cursor(), (8, 3));
+    }
+
+    #[test]
+    fn test_outline_without_symbols() {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::from_text("{\"a\": 1}");
+        editor.syntax_state = SyntaxState::new(Language::Json);
+        editor.open_outline();
+        assert!(editor.floating_window.is_none());
+    }
+}
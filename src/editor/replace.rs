@@ -0,0 +1,465 @@
+//! Query-replace (M-%) and regex query-replace (C-M-%).
+//!
+//! The search string and replacement are read with two minibuffer prompts,
+//! then a `QueryReplace` confirmation dialog steps through the matches:
+//! `y` replaces, `n` skips, `!` replaces all remaining and `q` stops.
+//! The whole session is recorded as a single undo step.
+
+use super::search::is_case_insensitive;
+use super::types::ConfirmationStep;
+use super::{
+    ConfirmationDialog, Editor, FloatingMode, FloatingWindow, MinibufferCallback, ResponseResult,
+    ResponseType,
+};
+use regex::{Captures, Regex, RegexBuilder};
+use super::CursorMove;
+
+/// Build the regex used to find matches. Literal searches are escaped;
+/// both use smart case like isearch.
+pub fn build_replace_regex(from: &str, is_regex: bool) -> Result<Regex, regex::Error> {
+    let pattern = match is_regex {
+        true => from.to_string(),
+        false => regex::escape(from),
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(is_case_insensitive(from))
+        .multi_line(true)
+        .build()
+}
+
+/// Expand group references in a regex replacement.
+/// Supports Emacs-style `\1` and `\&` as well as `$1` and `${name}`.
+pub fn expand_replacement(caps: &Captures, template: &str) -> String {
+    // Translate backslash references into the regex crate's `$` syntax
+    let mut translated = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('\\', Some(d)) if d.is_ascii_digit() => {
+                translated.push_str(&format!("${{{}}}", d));
+                chars.next();
+            }
+            ('\\', Some('&')) => {
+                translated.push_str("${0}");
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                translated.push('\\');
+                chars.next();
+            }
+            _ => translated.push(c),
+        }
+    }
+
+    let mut expanded = String::new();
+    caps.expand(&translated, &mut expanded);
+    expanded
+}
+
+/// Byte length of the character starting at `offset`, or None at end of text
+fn char_len_at(text: &str, offset: usize) -> Option<usize> {
+    text.get(offset..)
+        .and_then(|rest| rest.chars().next())
+        .map(|c| c.len_utf8())
+}
+
+/// An interactive query-replace session
+pub struct QueryReplace {
+    regex: Regex,
+    is_regex: bool,
+    from: String,
+    replacement: String,
+    /// Byte range of the match currently being asked about
+    current: Option<(usize, usize)>,
+    /// Number of replacements made so far
+    replaced: usize,
+    /// Whether this session's undo group has been started
+    undo_group: bool,
+    /// Copy of the buffer text, so each step doesn't rebuild it from the rope
+    text: String,
+    /// Text area version `text` was taken at
+    version: Option<u64>,
+}
+
+impl QueryReplace {
+    pub fn new(from: &str, replacement: &str, is_regex: bool) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: build_replace_regex(from, is_regex)?,
+            is_regex,
+            from: from.to_string(),
+            replacement: replacement.to_string(),
+            current: None,
+            replaced: 0,
+            undo_group: false,
+            text: String::new(),
+            version: None,
+        })
+    }
+
+    /// Refresh the cached text if the buffer changed since it was taken
+    fn sync_text(&mut self, editor: &Editor) {
+        let version = editor.textarea.version();
+        match self.version == Some(version) {
+            true => {}
+            false => {
+                self.text = editor.textarea.text();
+                self.version = Some(version);
+            }
+        }
+    }
+
+    /// The replacement for the match at `start`
+    fn replacement_at(&self, start: usize) -> Option<String> {
+        match self.is_regex {
+            true => self
+                .regex
+                .captures_at(&self.text, start)
+                .map(|caps| expand_replacement(&caps, &self.replacement)),
+            false => Some(self.replacement.clone()),
+        }
+    }
+
+    /// Offset to continue searching from after a match ending at `end`.
+    /// Steps over a character after an empty match so we don't loop forever.
+    fn next_offset(&self, start: usize, end: usize) -> Option<usize> {
+        match start == end {
+            true => char_len_at(&self.text, end).map(|len| end + len),
+            false => Some(end),
+        }
+    }
+
+    /// Open this session's undo group if it isn't already
+    fn ensure_undo_group(&mut self, editor: &mut Editor) {
+        match self.undo_group {
+            true => {}
+            false => {
+                editor.begin_undo_group();
+                self.undo_group = true;
+            }
+        }
+    }
+
+    /// Find the next match at or after `offset`, move the cursor past it and
+    /// highlight it. Returns false when there are no more matches.
+    fn find_from(&mut self, editor: &mut Editor, offset: usize) -> bool {
+        self.sync_text(editor);
+        self.current = match offset <= self.text.len() {
+            true => self.regex.find_at(&self.text, offset).map(|m| (m.start(), m.end())),
+            false => None,
+        };
+
+        match self.current {
+            Some((start, end)) => {
+                let start_pos = editor.textarea.byte_to_row_col(start);
+                let end_pos = editor.textarea.byte_to_row_col(end);
+                editor.replace_highlight = Some((start_pos, end_pos));
+                editor.move_cursor(CursorMove::Jump(end_pos.0, end_pos.1));
+                true
+            }
+            None => {
+                editor.replace_highlight = None;
+                false
+            }
+        }
+    }
+
+    /// Replace the current match. Returns the offset to continue searching from.
+    fn replace_current(&mut self, editor: &mut Editor) -> Option<usize> {
+        let (start, end) = self.current?;
+        self.sync_text(editor);
+        let replacement = self.replacement_at(start)?;
+        self.ensure_undo_group(editor);
+
+        let (row, col) = editor.textarea.byte_to_row_col(start);
+        editor.textarea.move_cursor(CursorMove::Jump(row, col));
+        editor.textarea.delete_str(self.text[start..end].chars().count());
+        editor.textarea.insert_str(&replacement);
+        editor.modified = true;
+        self.replaced += 1;
+
+        // Make the same edit to the cached text instead of copying the buffer again
+        self.text.replace_range(start..end, &replacement);
+        self.version = Some(editor.textarea.version());
+
+        let next = start + replacement.len();
+        match start == end {
+            true => self.next_offset(next, next),
+            false => Some(next),
+        }
+    }
+
+    /// Replace the current match and every one after it. The matches are
+    /// collected up front and replaced back to front, so earlier offsets stay valid.
+    fn replace_all(&mut self, editor: &mut Editor) {
+        self.sync_text(editor);
+        let mut matches = Vec::new();
+        let mut offset = self.current.map(|(start, _)| start);
+        while let Some((start, end)) = offset
+            .filter(|&offset| offset <= self.text.len())
+            .and_then(|offset| self.regex.find_at(&self.text, offset))
+            .map(|m| (m.start(), m.end()))
+        {
+            match self.replacement_at(start) {
+                Some(replacement) => matches.push((start, end, replacement)),
+                None => break,
+            }
+            offset = self.next_offset(start, end);
+        }
+
+        let last_end = match matches.last() {
+            Some(&(_, end, _)) => end,
+            None => return,
+        };
+        self.ensure_undo_group(editor);
+        for (start, end, replacement) in matches.iter().rev() {
+            let (row, col) = editor.textarea.byte_to_row_col(*start);
+            editor.textarea.move_cursor(CursorMove::Jump(row, col));
+            editor.textarea.delete_str(self.text[*start..*end].chars().count());
+            editor.textarea.insert_str(replacement);
+        }
+        editor.modified = true;
+        self.replaced += matches.len();
+        self.current = None;
+
+        // Leave the cursor after the last replacement, as stepping through would
+        let tail = self.text.len() - last_end;
+        let (row, col) = editor.textarea.byte_to_row_col(editor.textarea.rope().len_bytes() - tail);
+        editor.move_cursor(CursorMove::Jump(row, col));
+        editor.replace_highlight = None;
+    }
+
+    /// Offset to continue searching from when skipping the current match
+    fn skip_current(&mut self, editor: &Editor) -> Option<usize> {
+        let (start, end) = self.current?;
+        self.sync_text(editor);
+        self.next_offset(start, end)
+    }
+
+    /// Move on to the next match, finishing the dialog if there is none
+    fn advance(&mut self, editor: &mut Editor, next: Option<usize>) -> ResponseResult {
+        match next {
+            Some(offset) => match self.find_from(editor, offset) {
+                true => ResponseResult::Stay,
+                false => ResponseResult::Finish,
+            },
+            None => {
+                self.current = None;
+                editor.replace_highlight = None;
+                ResponseResult::Finish
+            }
+        }
+    }
+
+    fn finish(&self, editor: &mut Editor) {
+        match self.undo_group {
+            true => editor.end_undo_group(),
+            false => {}
+        }
+        editor.replace_highlight = None;
+        match self.replaced > 0 {
+            true => editor.update_highlights(),
+            false => {}
+        }
+        editor.message(format!("Replaced {} occurrence(s) of '{}'", self.replaced, self.from));
+    }
+}
+
+impl ConfirmationDialog for QueryReplace {
+    fn steps(&self) -> Vec<ConfirmationStep> {
+        let kind = match self.is_regex {
+            true => "Query replacing regexp",
+            false => "Query replacing",
+        };
+        vec![ConfirmationStep {
+            prompt: format!("{} '{}' with '{}'", kind, self.from, self.replacement),
+            response_type: ResponseType::Choice(vec![
+                ('y', "replace".to_string()),
+                ('n', "skip".to_string()),
+                ('!', "replace all".to_string()),
+                ('q', "quit".to_string()),
+            ]),
+        }]
+    }
+
+    fn handle_response(
+        &mut self,
+        _step_index: usize,
+        response: &str,
+        editor: &mut Editor,
+    ) -> ResponseResult {
+        match response {
+            "y" => {
+                let next = self.replace_current(editor);
+                editor.update_highlights();
+                self.advance(editor, next)
+            }
+            "n" => {
+                let next = self.skip_current(editor);
+                self.advance(editor, next)
+            }
+            "!" => {
+                self.replace_all(editor);
+                ResponseResult::Finish
+            }
+            "q" => ResponseResult::Finish,
+            _ => ResponseResult::Stay,
+        }
+    }
+
+    fn on_complete(&self, editor: &mut Editor) -> Result<(), String> {
+        self.finish(editor);
+        Ok(())
+    }
+
+    fn on_cancel(&self, editor: &mut Editor) {
+        self.finish(editor);
+    }
+}
+
+impl Editor {
+    // ==================== Query Replace ====================
+
+    /// Ask for the string to replace (M-% / C-M-%)
+    pub fn query_replace_prompt(&mut self, is_regex: bool) {
+        let prompt = match is_regex {
+            true => "Query replace regexp: ",
+            false => "Query replace: ",
+        };
+        self.open_minibuffer(prompt, "", MinibufferCallback::QueryReplaceFrom { regex: is_regex });
+    }
+
+    /// Ask for the replacement once the search string is known
+    pub fn query_replace_to_prompt(&mut self, from: String, is_regex: bool) {
+        match from.is_empty() {
+            true => return,
+            false => {}
+        }
+        let prompt = match is_regex {
+            true => format!("Query replace regexp {} with: ", from),
+            false => format!("Query replace {} with: ", from),
+        };
+        self.open_minibuffer(&prompt, "", MinibufferCallback::QueryReplaceTo { from, regex: is_regex });
+    }
+
+    /// Start stepping through matches from the cursor
+    pub fn start_query_replace(&mut self, from: &str, to: &str, is_regex: bool) {
+        let mut session = match QueryReplace::new(from, to, is_regex) {
+            Ok(session) => session,
+            Err(e) => {
+                self.error(format!("Invalid regexp: {}", e));
+                return;
+            }
+        };
+
+        // Replacing must not delete an active selection
+        match self.mark.is_active() {
+            true => self.cancel_mark(),
+            false => {}
+        }
+
+        let (row, col) = self.textarea.cursor();
+        let offset = self.textarea.row_col_to_byte(row, col);
+        match session.find_from(self, offset) {
+            true => self.start_confirmation(Box::new(session)),
+            false => self.message(format!("No matches for '{}'", from)),
+        }
+    }
+
+    /// Open a plain text minibuffer prompt
+    pub fn open_minibuffer(&mut self, prompt: &str, initial: &str, callback: MinibufferCallback) {
+        let completions = callback.completions(initial);
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 0,
+            y: 0,
+            width: 80,
+            height: 1,
+            mode: FloatingMode::Minibuffer {
+                prompt: prompt.to_string(),
+                input: initial.to_string(),
+                cursor_pos: initial.chars().count(),
+                completions,
+                selected_completion: None,
+                callback,
+            },
+        });
+        self.focus_floating = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_with;
+
+    #[test]
+    fn test_expand_replacement_references() {
+        let re = Regex::new(r"(\w+)-(\w+)").unwrap();
+        let caps = re.captures("foo-bar").unwrap();
+        assert_eq!(expand_replacement(&caps, r"\2-\1"), "bar-foo");
+        assert_eq!(expand_replacement(&caps, "${2}_$1"), "bar_foo");
+        assert_eq!(expand_replacement(&caps, r"<\&>"), "<foo-bar>");
+    }
+
+    #[test]
+    fn test_query_replace_yes_no() {
+        let mut editor = editor_with("foo bar foo\nfoo");
+        let mut session = QueryReplace::new("foo", "baz", false).unwrap();
+        assert!(session.find_from(&mut editor, 0));
+
+        assert!(matches!(session.handle_response(0, "y", &mut editor), ResponseResult::Stay));
+        assert!(matches!(session.handle_response(0, "n", &mut editor), ResponseResult::Stay));
+        assert!(matches!(session.handle_response(0, "y", &mut editor), ResponseResult::Finish));
+        assert_eq!(editor.textarea.to_lines(), ["baz bar foo", "baz"]);
+        assert!(editor.modified);
+
+        // The whole session is a single undo step
+        editor.undo();
+        assert_eq!(editor.textarea.to_lines(), ["foo bar foo", "foo"]);
+    }
+
+    #[test]
+    fn test_regex_replace_all() {
+        let mut editor = editor_with("a1 b22\nc333");
+        let mut session = QueryReplace::new(r"([a-z])(\d+)", r"\2$1", true).unwrap();
+        assert!(session.find_from(&mut editor, 0));
+        assert!(matches!(session.handle_response(0, "!", &mut editor), ResponseResult::Finish));
+        assert_eq!(editor.textarea.to_lines(), ["1a 22b", "333c"]);
+        session.on_complete(&mut editor).unwrap();
+        assert_eq!(editor.echo_text(), Some("Replaced 3 occurrence(s) of '([a-z])(\\d+)'"));
+    }
+
+    #[test]
+    fn test_replace_all_after_stepping() {
+        let mut editor = editor_with("foo foo\nfoo foo x");
+        let mut session = QueryReplace::new("foo", "quux", false).unwrap();
+        assert!(session.find_from(&mut editor, 0));
+        session.handle_response(0, "n", &mut editor);
+        session.handle_response(0, "y", &mut editor);
+        assert!(matches!(session.handle_response(0, "!", &mut editor), ResponseResult::Finish));
+        assert_eq!(editor.textarea.to_lines(), ["foo quux", "quux quux x"]);
+        assert_eq!(editor.textarea.cursor(), (1, 9));
+        session.on_complete(&mut editor).unwrap();
+        assert_eq!(editor.echo_text(), Some("Replaced 3 occurrence(s) of 'foo'"));
+
+        editor.undo();
+        assert_eq!(editor.textarea.to_lines(), ["foo foo", "foo foo x"]);
+    }
+
+    #[test]
+    fn test_query_replace_without_matches() {
+        let mut editor = editor_with("foo");
+        editor.start_query_replace("bar", "baz", false);
+        assert!(editor.floating_window.is_none());
+        assert_eq!(editor.echo_text(), Some("No matches for 'bar'"));
+    }
+
+    #[test]
+    fn test_replace_with_empty_matches_terminates() {
+        let mut editor = editor_with("ab\ncd");
+        let mut session = QueryReplace::new("^", "> ", true).unwrap();
+        assert!(session.find_from(&mut editor, 0));
+        session.handle_response(0, "!", &mut editor);
+        assert_eq!(editor.textarea.to_lines(), ["> ab", "> cd"]);
+    }
+}
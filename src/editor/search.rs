@@ -0,0 +1,456 @@
+//! Incremental search (C-s / C-r), Emacs isearch style.
+//!
+//! While a search is active every keystroke updates the pattern, moves the
+//! cursor to the nearest match and refreshes the list of matches that the
+//! `EditorWidget` highlights. Repeating C-s/C-r cycles through the matches
+//! with wrap-around, C-g restores the starting position and Enter exits,
+//! leaving the mark where the search started.
+
+use super::{Editor, MarkState};
+use super::CursorMove;
+use std::borrow::Cow;
+
+/// Direction of an incremental search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+/// A single match in the buffer (positions are in characters)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl SearchMatch {
+    /// Position just past the end of the match
+    pub fn end(&self) -> (usize, usize) {
+        (self.row, self.col + self.len)
+    }
+}
+
+/// State of an active incremental search
+#[derive(Clone, Debug)]
+pub struct ISearch {
+    /// Current search pattern
+    pub pattern: String,
+    /// Direction of the last search step
+    pub direction: SearchDirection,
+    /// Cursor position when the search started (restored on C-g)
+    pub start: (usize, usize),
+    /// Scroll offset when the search started (restored on C-g)
+    pub start_scroll: usize,
+    /// All matches of the pattern in the buffer, in buffer order
+    pub matches: Vec<SearchMatch>,
+    /// Index of the current match in `matches`
+    pub current: Option<usize>,
+    /// Whether the search has wrapped around the buffer
+    pub wrapped: bool,
+}
+
+impl ISearch {
+    pub fn new(direction: SearchDirection, start: (usize, usize), start_scroll: usize) -> Self {
+        Self {
+            pattern: String::new(),
+            direction,
+            start,
+            start_scroll,
+            matches: Vec::new(),
+            current: None,
+            wrapped: false,
+        }
+    }
+
+    /// Whether the pattern is non-empty but has no matches
+    pub fn is_failing(&self) -> bool {
+        !self.pattern.is_empty() && self.matches.is_empty()
+    }
+
+    /// Prompt shown in the echo area, e.g. "Wrapped I-search backward: "
+    pub fn prompt(&self) -> String {
+        let mut prompt = String::new();
+        match self.is_failing() {
+            true => prompt.push_str("Failing "),
+            false => {}
+        }
+        match self.wrapped {
+            true => prompt.push_str("Wrapped "),
+            false => {}
+        }
+        prompt.push_str("I-search");
+        match self.direction {
+            SearchDirection::Forward => {}
+            SearchDirection::Backward => prompt.push_str(" backward"),
+        }
+        prompt.push_str(": ");
+        prompt
+    }
+
+    /// Find the match containing the given position, if any.
+    /// Returns the match and whether it is the current one.
+    pub fn match_at(&self, row: usize, col: usize) -> Option<(SearchMatch, bool)> {
+        // All matches have the same length, so only the last match starting
+        // at or before the position can contain it
+        let idx = self
+            .matches
+            .partition_point(|m| (m.row, m.col) <= (row, col))
+            .checked_sub(1)?;
+        let m = self.matches[idx];
+        match m.row == row && col < m.col + m.len {
+            true => Some((m, self.current == Some(idx))),
+            false => None,
+        }
+    }
+
+    /// Index of the match to select when searching from `anchor`.
+    /// Forward picks the first match at or after the anchor, backward the last
+    /// match at or before it. Wraps around (setting `wrapped`) if needed.
+    fn select_from(&mut self, anchor: (usize, usize)) -> Option<usize> {
+        match self.matches.is_empty() {
+            true => return None,
+            false => {}
+        }
+        let found = match self.direction {
+            SearchDirection::Forward => self
+                .matches
+                .iter()
+                .position(|m| (m.row, m.col) >= anchor),
+            SearchDirection::Backward => self
+                .matches
+                .iter()
+                .rposition(|m| (m.row, m.col) <= anchor),
+        };
+        match found {
+            Some(idx) => Some(idx),
+            None => {
+                self.wrapped = true;
+                match self.direction {
+                    SearchDirection::Forward => Some(0),
+                    SearchDirection::Backward => Some(self.matches.len() - 1),
+                }
+            }
+        }
+    }
+}
+
+/// Whether a search for `pattern` should ignore case (Emacs-style smart case:
+/// an all-lowercase pattern matches case-insensitively)
+pub fn is_case_insensitive(pattern: &str) -> bool {
+    !pattern.chars().any(|c| c.is_uppercase())
+}
+
+/// Most matches a search collects, so a short pattern in a huge buffer
+/// stays responsive. Matches past these are not highlighted or visited.
+pub const MAX_SEARCH_MATCHES: usize = 10_000;
+
+/// Find the first `MAX_SEARCH_MATCHES` (possibly overlapping) literal
+/// matches of `pattern` in `text`. Matches never span lines.
+pub fn find_matches(text: &str, pattern: &str) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    match pattern.is_empty() || pattern.contains('\n') {
+        true => return matches,
+        false => {}
+    }
+
+    // Folding maps each char to one char, so positions carry over
+    let fold_case = is_case_insensitive(pattern);
+    let fold = |c: char| match fold_case {
+        true => c.to_lowercase().next().unwrap_or(c),
+        false => c,
+    };
+    let hay: Cow<str> = match fold_case {
+        true => Cow::Owned(text.chars().map(fold).collect()),
+        false => Cow::Borrowed(text),
+    };
+    let needle: String = pattern.chars().map(fold).collect();
+    let len = needle.chars().count();
+
+    // Row and column of byte `scanned` of `hay`
+    let (mut row, mut col, mut scanned) = (0, 0, 0);
+    let mut from = 0;
+    while matches.len() < MAX_SEARCH_MATCHES {
+        let at = match hay[from..].find(&needle) {
+            Some(i) => from + i,
+            None => break,
+        };
+        for c in hay[scanned..at].chars() {
+            match c {
+                '\n' => {
+                    row += 1;
+                    col = 0;
+                }
+                _ => col += 1,
+            }
+        }
+        scanned = at;
+        matches.push(SearchMatch { row, col, len });
+        // The next match may start inside this one
+        from = at + hay[at..].chars().next().map_or(1, char::len_utf8);
+    }
+    matches
+}
+
+impl Editor {
+    // ==================== Incremental Search ====================
+
+    /// Start an incremental search, or move to the next match if one is active
+    pub fn isearch_start(&mut self, direction: SearchDirection) {
+        match self.isearch.is_some() {
+            true => self.isearch_repeat(direction),
+            false => {
+                self.jump_mode = None;
+                self.isearch = Some(ISearch::new(direction, self.textarea.cursor(), self.scroll_offset));
+            }
+        }
+    }
+
+    /// Move to the next match in `direction` (C-s / C-r while searching).
+    /// With an empty pattern, reuses the previous search string.
+    pub fn isearch_repeat(&mut self, direction: SearchDirection) {
+        let is = match &mut self.isearch {
+            Some(is) => is,
+            None => return,
+        };
+
+        match is.pattern.is_empty() {
+            true => {
+                match self.last_search.is_empty() {
+                    true => return,
+                    false => {}
+                }
+                is.pattern = self.last_search.clone();
+                is.direction = direction;
+                self.isearch_refresh();
+                return;
+            }
+            false => {}
+        }
+
+        let changed_direction = is.direction != direction;
+        is.direction = direction;
+        let count = is.matches.len();
+        is.current = match (is.current, count) {
+            (_, 0) => None,
+            // Emacs: the first C-r after C-s just flips direction and stays on the match
+            (Some(idx), _) if changed_direction => Some(idx),
+            (Some(idx), _) => match direction {
+                SearchDirection::Forward => match idx + 1 < count {
+                    true => Some(idx + 1),
+                    false => {
+                        is.wrapped = true;
+                        Some(0)
+                    }
+                },
+                SearchDirection::Backward => match idx > 0 {
+                    true => Some(idx - 1),
+                    false => {
+                        is.wrapped = true;
+                        Some(count - 1)
+                    }
+                },
+            },
+            (None, _) => {
+                let anchor = is.start;
+                is.select_from(anchor)
+            }
+        };
+        self.isearch_move_to_current();
+    }
+
+    /// Append a character to the search pattern
+    pub fn isearch_push_char(&mut self, c: char) {
+        match &mut self.isearch {
+            Some(is) => is.pattern.push(c),
+            None => return,
+        }
+        self.isearch_refresh();
+    }
+
+    /// Remove the last character from the search pattern
+    pub fn isearch_pop_char(&mut self) {
+        match &mut self.isearch {
+            Some(is) => {
+                is.pattern.pop();
+            }
+            None => return,
+        }
+        self.isearch_refresh();
+    }
+
+    /// Recompute matches for the current pattern and move to the nearest one.
+    /// Searching continues from the current match so extending the pattern
+    /// doesn't jump backwards.
+    fn isearch_refresh(&mut self) {
+        let text = self.textarea.text();
+        let is = match &mut self.isearch {
+            Some(is) => is,
+            None => return,
+        };
+
+        let anchor = is
+            .current
+            .and_then(|i| is.matches.get(i))
+            .map(|m| (m.row, m.col))
+            .unwrap_or(is.start);
+
+        is.matches = find_matches(&text, &is.pattern);
+        is.current = is.select_from(anchor);
+
+        match is.pattern.is_empty() {
+            true => {
+                let (row, col) = is.start;
+                is.wrapped = false;
+                self.move_cursor(CursorMove::Jump(row, col));
+            }
+            false => self.isearch_move_to_current(),
+        }
+    }
+
+    /// Put the cursor on the current match: after it when searching forward,
+    /// at its start when searching backward
+    fn isearch_move_to_current(&mut self) {
+        let target = match &self.isearch {
+            Some(is) => is
+                .current
+                .and_then(|i| is.matches.get(i))
+                .map(|m| match is.direction {
+                    SearchDirection::Forward => m.end(),
+                    SearchDirection::Backward => (m.row, m.col),
+                }),
+            None => None,
+        };
+        match target {
+            Some((row, col)) => self.move_cursor(CursorMove::Jump(row, col)),
+            None => {}
+        }
+    }
+
+    /// Finish the search (Enter), leaving the cursor on the match and the
+    /// mark at the start position
+    pub fn isearch_exit(&mut self) {
+        match self.isearch.take() {
+            Some(is) => {
+                match is.pattern.is_empty() {
+                    true => {}
+                    false => self.last_search = is.pattern.clone(),
+                }
+                match self.mark.is_active() || self.textarea.cursor() == is.start {
+                    true => {}
+                    false => {
+                        self.mark = MarkState::Set {
+                            row: is.start.0,
+                            col: is.start.1,
+                        };
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Abort the search (C-g), restoring the original cursor position
+    pub fn isearch_abort(&mut self) {
+        match self.isearch.take() {
+            Some(is) => {
+                let (row, col) = is.start;
+                self.textarea.move_cursor(CursorMove::Jump(row, col));
+                self.scroll_offset = is.start_scroll;
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches_smart_case() {
+        let text = "Foo foo\nfOO";
+        assert_eq!(find_matches(text, "foo").len(), 3);
+        assert_eq!(
+            find_matches(text, "Foo"),
+            vec![SearchMatch { row: 0, col: 0, len: 3 }]
+        );
+        assert!(find_matches(text, "").is_empty());
+    }
+
+    #[test]
+    fn test_find_matches_positions() {
+        let text = "héé\naaa\n";
+        assert_eq!(
+            find_matches(text, "aa"),
+            vec![
+                SearchMatch { row: 1, col: 0, len: 2 },
+                SearchMatch { row: 1, col: 1, len: 2 },
+            ]
+        );
+        assert_eq!(
+            find_matches(text, "é"),
+            vec![
+                SearchMatch { row: 0, col: 1, len: 1 },
+                SearchMatch { row: 0, col: 2, len: 1 },
+            ]
+        );
+        assert!(find_matches(text, "é\na").is_empty());
+        assert_eq!(find_matches(&"x".repeat(2 * MAX_SEARCH_MATCHES), "x").len(), MAX_SEARCH_MATCHES);
+    }
+
+    #[test]
+    fn test_isearch_forward_wraps() {
+        let mut editor = Editor::new();
+        editor.textarea.insert_str("ab ab\nab");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 1));
+
+        editor.isearch_start(SearchDirection::Forward);
+        editor.isearch_push_char('a');
+        editor.isearch_push_char('b');
+        // First match after the start position is the second "ab" on line 0
+        assert_eq!(editor.textarea.cursor(), (0, 5));
+
+        editor.isearch_repeat(SearchDirection::Forward);
+        assert_eq!(editor.textarea.cursor(), (1, 2));
+
+        editor.isearch_repeat(SearchDirection::Forward);
+        assert_eq!(editor.textarea.cursor(), (0, 2));
+        assert!(editor.isearch.as_ref().unwrap().wrapped);
+
+        editor.isearch_exit();
+        assert!(editor.isearch.is_none());
+        assert_eq!(editor.mark.position(), Some((0, 1)));
+        assert_eq!(editor.last_search, "ab");
+    }
+
+    #[test]
+    fn test_isearch_abort_restores_position() {
+        let mut editor = Editor::new();
+        editor.textarea.insert_str("one two\nthree");
+        editor.textarea.move_cursor(CursorMove::Jump(1, 2));
+
+        editor.isearch_start(SearchDirection::Backward);
+        editor.isearch_push_char('o');
+        assert_eq!(editor.textarea.cursor(), (0, 6));
+
+        editor.isearch_abort();
+        assert!(editor.isearch.is_none());
+        assert_eq!(editor.textarea.cursor(), (1, 2));
+    }
+
+    #[test]
+    fn test_match_at() {
+        let mut is = ISearch::new(SearchDirection::Forward, (0, 0), 0);
+        is.matches = vec![
+            SearchMatch { row: 0, col: 2, len: 2 },
+            SearchMatch { row: 1, col: 0, len: 2 },
+        ];
+        is.current = Some(1);
+        assert_eq!(is.match_at(0, 1), None);
+        assert_eq!(is.match_at(0, 3).map(|(_, cur)| cur), Some(false));
+        assert_eq!(is.match_at(1, 1).map(|(_, cur)| cur), Some(true));
+        assert_eq!(is.match_at(1, 2), None);
+    }
+}
@@ -1,10 +1,8 @@
 //! Selection and clipboard operations for the editor.
 
-use super::{Editor, MarkState};
+use super::{CursorMove, Editor, KillDirection, MarkState};
 use crate::logging;
 use ratatui::crossterm::event::{KeyCode, KeyModifiers};
-use std::cmp::min;
-use tui_textarea::CursorMove;
 
 impl Editor {
     // ==================== Mark/Selection Operations ====================
@@ -22,11 +20,10 @@ impl Editor {
         }
 
         // Validate cursor position is within document bounds
-        let lines = self.textarea.lines();
-        if cursor_pos.0 >= lines.len() {
+        if cursor_pos.0 >= self.textarea.len_lines() {
             return;
         }
-        if cursor_pos.0 < lines.len() && cursor_pos.1 > lines[cursor_pos.0].chars().count() {
+        if cursor_pos.1 > self.textarea.line_len(cursor_pos.0) {
             return;
         }
 
@@ -96,8 +93,8 @@ impl Editor {
         let current_cursor = self.textarea.cursor();
 
         // Validate both positions are still within document bounds
-        let lines = self.textarea.lines();
-        if mark_pos.0 >= lines.len() || current_cursor.0 >= lines.len() {
+        let line_count = self.textarea.len_lines();
+        if mark_pos.0 >= line_count || current_cursor.0 >= line_count {
             self.mark = MarkState::None;
             self.textarea.cancel_selection();
             return;
@@ -123,17 +120,13 @@ impl Editor {
             self.textarea.cancel_selection();
 
             // Move cursor to current position (new anchor)
-            let jump_row = min(current_cursor.0, u16::MAX as usize) as u16;
-            let jump_col = min(current_cursor.1, u16::MAX as usize) as u16;
-            self.textarea.move_cursor(CursorMove::Jump(jump_row, jump_col));
+            self.textarea.move_cursor(CursorMove::Jump(current_cursor.0, current_cursor.1));
 
             // Start selection from current cursor position
             self.textarea.start_selection();
 
             // Move cursor to the saved mark (where cursor will end up)
-            let mark_row = min(mark_pos.0, u16::MAX as usize) as u16;
-            let mark_col = min(mark_pos.1, u16::MAX as usize) as u16;
-            self.textarea.move_cursor(CursorMove::Jump(mark_row, mark_col));
+            self.textarea.move_cursor(CursorMove::Jump(mark_pos.0, mark_pos.1));
 
             self.mark = MarkState::Active {
                 row: new_mark_pos.0,
@@ -141,15 +134,11 @@ impl Editor {
             };
         } else {
             // No active selection, create one between cursor and mark
-            let jump_row = min(current_cursor.0, u16::MAX as usize) as u16;
-            let jump_col = min(current_cursor.1, u16::MAX as usize) as u16;
-            self.textarea.move_cursor(CursorMove::Jump(jump_row, jump_col));
+            self.textarea.move_cursor(CursorMove::Jump(current_cursor.0, current_cursor.1));
 
             self.textarea.start_selection();
 
-            let mark_row = min(mark_pos.0, u16::MAX as usize) as u16;
-            let mark_col = min(mark_pos.1, u16::MAX as usize) as u16;
-            self.textarea.move_cursor(CursorMove::Jump(mark_row, mark_col));
+            self.textarea.move_cursor(CursorMove::Jump(mark_pos.0, mark_pos.1));
 
             self.mark = MarkState::Active {
                 row: new_mark_pos.0,
@@ -166,52 +155,7 @@ impl Editor {
             return None;
         }
 
-        let (start, end) = if start > end {
-            (end, start)
-        } else {
-            (start, end)
-        };
-
-        let lines = self.textarea.lines();
-        let mut selected = String::new();
-
-        for row in start.0..=end.0 {
-            if row >= lines.len() {
-                break;
-            }
-
-            let line = &lines[row];
-            let start_col = if row == start.0 { start.1 } else { 0 };
-            let end_col = if row == end.0 {
-                end.1.min(line.chars().count())
-            } else {
-                line.chars().count()
-            };
-
-            // Convert character indices to byte indices for proper UTF-8 slicing
-            let mut start_byte = 0;
-            let mut end_byte = line.len();
-
-            for (char_pos, (byte_idx, _)) in line.char_indices().enumerate() {
-                if char_pos == start_col {
-                    start_byte = byte_idx;
-                }
-                if char_pos == end_col {
-                    end_byte = byte_idx;
-                    break;
-                }
-            }
-
-            if start_byte <= end_byte && end_byte <= line.len() {
-                selected.push_str(&line[start_byte..end_byte]);
-            }
-
-            if row < end.0 {
-                selected.push('\n');
-            }
-        }
-
-        Some(selected)
+        Some(self.textarea.text_range(start, end))
     }
 
     // ==================== Clipboard Operations ====================
@@ -226,7 +170,7 @@ impl Editor {
     //
     // See buffer_ops.rs for full documentation.
 
-    /// Cut selected text to system clipboard (C-w)
+    /// Kill selected text (C-w)
     ///
     /// Returns true if text was actually cut, false if no selection.
     /// Handles undo state and modification tracking internally.
@@ -234,7 +178,7 @@ impl Editor {
         match self.get_selected_text() {
             Some(text) => {
                 self.save_undo_state();
-                self.clipboard.copy(&text);
+                self.kill_text(&text, KillDirection::Forward);
                 self.textarea.cut();
                 self.mark = MarkState::None;
                 self.mark_modified();
@@ -244,36 +188,20 @@ impl Editor {
         }
     }
 
-    /// Copy selected text to system clipboard (M-w)
+    /// Copy selected text to the kill ring (M-w)
     ///
     /// Does not modify buffer, so no undo state or modification tracking needed.
     pub fn copy_region(&mut self) {
         match self.get_selected_text() {
             Some(text) => {
-                self.clipboard.copy(&text);
+                self.copy_text(&text);
                 self.cancel_mark();
             }
             None => {}
         }
     }
 
-    /// Paste from system clipboard (C-y)
-    ///
-    /// Returns true if text was actually pasted, false if clipboard empty.
-    /// Handles undo state and modification tracking internally.
-    pub fn paste(&mut self) -> bool {
-        match self.clipboard.paste() {
-            Some(text) => {
-                self.save_undo_state();
-                self.textarea.insert_str(&text);
-                self.mark_modified();
-                true
-            }
-            None => false,
-        }
-    }
-
-    /// Cut from cursor to end of line to clipboard (C-k)
+    /// Kill from cursor to end of line (C-k)
     ///
     /// Returns true if text was actually cut, false if nothing to cut.
     /// Handles undo state and modification tracking internally.
@@ -281,17 +209,15 @@ impl Editor {
         let (row, col) = self.textarea.cursor();
 
         let (cut_text, should_move_to_end) = {
-            let lines = self.textarea.lines();
-
-            if row >= lines.len() {
+            if row >= self.textarea.len_lines() {
                 return false;
             }
 
-            let line = &lines[row];
+            let line_len = self.textarea.line_len(row);
 
-            if col < line.chars().count() {
-                (Self::safe_string_slice(line, col, line.chars().count()), true)
-            } else if row + 1 < lines.len() {
+            if col < line_len {
+                (self.textarea.text_range((row, col), (row, line_len)), true)
+            } else if row + 1 < self.textarea.len_lines() {
                 ("\n".to_string(), false)
             } else {
                 return false;
@@ -300,7 +226,7 @@ impl Editor {
 
         if !cut_text.is_empty() {
             self.save_undo_state();
-            self.clipboard.copy(&cut_text);
+            self.kill_text(&cut_text, KillDirection::Forward);
 
             self.textarea.start_selection();
             if should_move_to_end {
@@ -319,7 +245,7 @@ impl Editor {
         }
     }
 
-    /// Cut from beginning of line to cursor to clipboard (C-u)
+    /// Kill from beginning of line to cursor (C-u)
     ///
     /// Returns true if text was actually cut, false if nothing to cut.
     /// Handles undo state and modification tracking internally.
@@ -327,25 +253,20 @@ impl Editor {
         let (row, col) = self.textarea.cursor();
 
         let cut_text = {
-            let lines = self.textarea.lines();
-
-            if row >= lines.len() || col == 0 {
+            if row >= self.textarea.len_lines() || col == 0 {
                 return false;
             }
 
-            let line = &lines[row];
-            Self::safe_string_slice(line, 0, col)
+            self.textarea.text_range((row, 0), (row, col))
         };
 
         if !cut_text.is_empty() {
             self.save_undo_state();
-            self.clipboard.copy(&cut_text);
+            self.kill_text(&cut_text, KillDirection::Backward);
 
             self.textarea.move_cursor(CursorMove::Head);
             self.textarea.start_selection();
-            for _ in 0..col {
-                self.textarea.move_cursor(CursorMove::Forward);
-            }
+            self.textarea.move_cursor(CursorMove::Jump(row, col));
 
             self.textarea.cut();
             self.mark = MarkState::None;
@@ -369,14 +290,12 @@ impl Editor {
             None => return, // No syntax state, nothing to do
         };
 
-        let source = self.textarea.lines().join("\n");
-
         // Get current selection or cursor position
         let (start_byte, end_byte) = match self.textarea.selection_range() {
             Some(((start_row, start_col), (end_row, end_col))) => {
                 // Convert row/col to byte offsets
-                let start = self.row_col_to_byte(&source, start_row, start_col);
-                let end = self.row_col_to_byte(&source, end_row, end_col);
+                let start = self.textarea.row_col_to_byte(start_row, start_col);
+                let end = self.textarea.row_col_to_byte(end_row, end_col);
                 match start <= end {
                     true => (start, end),
                     false => (end, start),
@@ -385,7 +304,7 @@ impl Editor {
             None => {
                 // No selection, use cursor position
                 let (row, col) = self.textarea.cursor();
-                let byte_pos = self.row_col_to_byte(&source, row, col);
+                let byte_pos = self.textarea.row_col_to_byte(row, col);
                 (byte_pos, byte_pos)
             }
         };
@@ -403,7 +322,7 @@ impl Editor {
                 self.selection_history.push((start_byte, end_byte));
 
                 // Convert byte range back to row/col and set selection
-                self.set_selection_from_bytes(&source, new_range.0, new_range.1);
+                self.set_selection_from_bytes(new_range.0, new_range.1);
             }
             false => {}
         }
@@ -414,12 +333,10 @@ impl Editor {
     /// If there's selection history, pops the previous selection.
     /// Otherwise, tries to shrink to the first child node.
     pub fn shrink_selection(&mut self) {
-        let source = self.textarea.lines().join("\n");
-
         // First, try to restore from history
         match self.selection_history.pop() {
             Some((start_byte, end_byte)) => {
-                self.set_selection_from_bytes(&source, start_byte, end_byte);
+                self.set_selection_from_bytes(start_byte, end_byte);
                 return;
             }
             None => {}
@@ -434,8 +351,8 @@ impl Editor {
         // Get current selection
         let (start_byte, end_byte) = match self.textarea.selection_range() {
             Some(((start_row, start_col), (end_row, end_col))) => {
-                let start = self.row_col_to_byte(&source, start_row, start_col);
-                let end = self.row_col_to_byte(&source, end_row, end_col);
+                let start = self.textarea.row_col_to_byte(start_row, start_col);
+                let end = self.textarea.row_col_to_byte(end_row, end_col);
                 match start <= end {
                     true => (start, end),
                     false => (end, start),
@@ -450,7 +367,7 @@ impl Editor {
                 // Only shrink if we're actually getting a smaller range
                 match new_start > start_byte || new_end < end_byte {
                     true => {
-                        self.set_selection_from_bytes(&source, new_start, new_end);
+                        self.set_selection_from_bytes(new_start, new_end);
                     }
                     false => {}
                 }
@@ -466,78 +383,15 @@ impl Editor {
 
     // ==================== Helper Methods ====================
 
-    /// Convert row/col to byte offset in source
-    fn row_col_to_byte(&self, source: &str, row: usize, col: usize) -> usize {
-        let mut byte_offset = 0;
-        for (line_idx, line) in source.lines().enumerate() {
-            match line_idx == row {
-                true => {
-                    // Count bytes up to col
-                    for (char_idx, ch) in line.chars().enumerate() {
-                        match char_idx >= col {
-                            true => break,
-                            false => byte_offset += ch.len_utf8(),
-                        }
-                    }
-                    return byte_offset;
-                }
-                false => {
-                    byte_offset += line.len() + 1; // +1 for newline
-                }
-            }
-        }
-        byte_offset
-    }
-
-    /// Convert byte offset to row/col
-    fn byte_to_row_col(&self, source: &str, byte_offset: usize) -> (usize, usize) {
-        let mut current_byte = 0;
-        for (line_idx, line) in source.lines().enumerate() {
-            let line_end = current_byte + line.len();
-            match byte_offset <= line_end {
-                true => {
-                    // This is the line - find column
-                    let offset_in_line = byte_offset - current_byte;
-                    let mut col = 0;
-                    let mut byte_count = 0;
-                    for ch in line.chars() {
-                        match byte_count >= offset_in_line {
-                            true => break,
-                            false => {
-                                byte_count += ch.len_utf8();
-                                col += 1;
-                            }
-                        }
-                    }
-                    return (line_idx, col);
-                }
-                false => {
-                    current_byte = line_end + 1; // +1 for newline
-                }
-            }
-        }
-        // Past end of document
-        let line_count = source.lines().count();
-        match line_count {
-            0 => (0, 0),
-            n => (n - 1, source.lines().last().map(|l| l.chars().count()).unwrap_or(0)),
-        }
-    }
-
     /// Set selection from byte range
-    fn set_selection_from_bytes(&mut self, source: &str, start_byte: usize, end_byte: usize) {
-        let (start_row, start_col) = self.byte_to_row_col(source, start_byte);
-        let (end_row, end_col) = self.byte_to_row_col(source, end_byte);
+    fn set_selection_from_bytes(&mut self, start_byte: usize, end_byte: usize) {
+        let (start_row, start_col) = self.textarea.byte_to_row_col(start_byte);
+        let (end_row, end_col) = self.textarea.byte_to_row_col(end_byte);
 
         // Move cursor to start, start selection, move to end
-        let start_row_u16 = min(start_row, u16::MAX as usize) as u16;
-        let start_col_u16 = min(start_col, u16::MAX as usize) as u16;
-        let end_row_u16 = min(end_row, u16::MAX as usize) as u16;
-        let end_col_u16 = min(end_col, u16::MAX as usize) as u16;
-
-        self.textarea.move_cursor(CursorMove::Jump(start_row_u16, start_col_u16));
+        self.textarea.move_cursor(CursorMove::Jump(start_row, start_col));
         self.textarea.start_selection();
-        self.textarea.move_cursor(CursorMove::Jump(end_row_u16, end_col_u16));
+        self.textarea.move_cursor(CursorMove::Jump(end_row, end_col));
 
         self.mark = MarkState::Active {
             row: start_row,
@@ -1,8 +1,94 @@
 //! Editor settings and configuration.
 //!
 //! This module contains the Settings struct and color management utilities.
+//! Settings are persisted in the user config file (see `crate::config`).
 
+use super::Editor;
+use crate::config;
 use ratatui::style::Color;
+use std::ops::RangeInclusive;
+
+/// Floating window sizes accepted by the config file and the settings menu
+pub const WINDOW_SIZE_RANGE: RangeInclusive<u16> = 10..=500;
+
+/// How much the settings menu changes a window size per key press
+pub const WINDOW_SIZE_STEP: u16 = 5;
+
+/// How the line-number gutter numbers lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineNumbers {
+    /// No line numbers
+    #[default]
+    Off,
+    /// Line numbers counted from the top of the buffer
+    Absolute,
+    /// Distance from the cursor line (0 on the cursor line)
+    Relative,
+    /// Relative numbers, with the absolute number on the cursor line
+    Hybrid,
+}
+
+impl LineNumbers {
+    /// All modes, in settings-menu order
+    pub const ALL: [LineNumbers; 4] = [Self::Off, Self::Absolute, Self::Relative, Self::Hybrid];
+
+    /// Name used in the config file and settings menu
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Absolute => "absolute",
+            Self::Relative => "relative",
+            Self::Hybrid => "hybrid",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Number shown for `row` when the cursor is on `cursor_row`
+    pub fn number_for(self, row: usize, cursor_row: usize) -> Option<usize> {
+        match self {
+            Self::Off => None,
+            Self::Absolute => Some(row + 1),
+            Self::Relative => Some(row.abs_diff(cursor_row)),
+            Self::Hybrid => match row == cursor_row {
+                true => Some(row + 1),
+                false => Some(row.abs_diff(cursor_row)),
+            },
+        }
+    }
+}
+
+/// Which backup, if any, is made of a file before saving over it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackupMode {
+    /// No backups
+    #[default]
+    Off,
+    /// A single `file~` next to the file
+    Simple,
+    /// Numbered copies in the backup directory
+    Versioned,
+}
+
+impl BackupMode {
+    /// All modes, in settings-menu order
+    pub const ALL: [BackupMode; 3] = [Self::Off, Self::Simple, Self::Versioned];
+
+    /// Name used in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Simple => "simple",
+            Self::Versioned => "versioned",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
 
 /// Editor settings for appearance and behavior
 pub struct Settings {
@@ -18,8 +104,24 @@ pub struct Settings {
     pub wrap_indicator: String,
     /// Color for the wrap indicator
     pub wrap_indicator_color: Color,
+    /// Line-number gutter mode
+    pub line_numbers: LineNumbers,
+    /// Backup made before a file is overwritten
+    pub backup: BackupMode,
+    /// Directory for versioned backups (empty = `<state dir>/jae/backups`)
+    pub backup_dir: String,
+    /// Seconds between auto-saves of modified buffers (0 = off)
+    pub autosave_interval_secs: u64,
+    /// Directory for auto-save recovery files (empty = `<data dir>/jae/autosave`)
+    pub autosave_dir: String,
+    /// Size limit of all saved undo histories in megabytes (0 = do not keep undo history)
+    pub undo_history_max_mb: u64,
+    /// Directory for saved undo histories (empty = `<cache dir>/jae/undo`)
+    pub undo_history_dir: String,
     /// Maximum time for syntax parsing in milliseconds (0 = no limit)
     pub parse_timeout_ms: u64,
+    /// Delay before jump mode shows labels, in milliseconds
+    pub jump_timeout_ms: u64,
 }
 
 impl Default for Settings {
@@ -34,7 +136,15 @@ impl Default for Settings {
             soft_wrap: false,
             wrap_indicator: "↪ ".to_string(),
             wrap_indicator_color: Color::DarkGray,
+            line_numbers: LineNumbers::Off,
+            backup: BackupMode::Off,
+            backup_dir: String::new(),
+            autosave_interval_secs: 30,
+            autosave_dir: String::new(),
+            undo_history_max_mb: 50,
+            undo_history_dir: String::new(),
             parse_timeout_ms: 100, // 100ms default timeout
+            jump_timeout_ms: 500,  // 500ms like avy
         }
     }
 }
@@ -83,3 +193,70 @@ impl Settings {
         }
     }
 }
+
+impl Editor {
+    // ==================== Config File ====================
+
+    /// Load settings and key bindings from the user config file, showing any warnings
+    pub fn load_config(&mut self) {
+        let path = match config::config_path() {
+            Some(path) => path,
+            None => return,
+        };
+        let warnings = match config::read_config_table(&path) {
+            Ok(table) => self.apply_config_table(&table),
+            Err(e) => vec![e],
+        };
+
+        for warning in &warnings {
+            self.warn(warning.clone());
+        }
+        match warnings.len() {
+            0 | 1 => {}
+            n => self.warn(format!("{} config warnings, see *Messages* (C-c m)", n)),
+        }
+    }
+
+    /// Apply a parsed config table: top-level settings and the `[prefixes]`,
+    /// `[lsp]` and `[keys]` sections
+    pub fn apply_config_table(&mut self, table: &toml::Table) -> Vec<String> {
+        let mut settings = Settings::default();
+        let mut warnings = config::apply_settings(&mut settings, table);
+        self.settings = settings;
+
+        // Prefixes first so [keys] can bind into user-defined prefixes
+        match table.get("prefixes") {
+            Some(toml::Value::Table(prefixes)) => warnings.extend(self.keymap.apply_prefixes(prefixes)),
+            Some(_) => warnings.push("config: prefixes: expected a table".to_string()),
+            None => {}
+        }
+        match table.get("lsp") {
+            Some(toml::Value::Table(lsp)) => warnings.extend(self.lsp.apply_config(lsp)),
+            Some(_) => warnings.push("config: lsp: expected a table".to_string()),
+            None => {}
+        }
+        match table.get("keys") {
+            Some(toml::Value::Table(keys)) => {
+                warnings.extend(self.keymap.apply_config(keys, &self.status_bar.command_registry))
+            }
+            Some(_) => warnings.push("config: keys: expected a table".to_string()),
+            None => {}
+        }
+        warnings
+    }
+
+    /// Write the current settings to the user config file
+    pub fn save_settings(&mut self) {
+        let path = match config::config_path() {
+            Some(path) => path,
+            None => {
+                self.error("No config directory available");
+                return;
+            }
+        };
+        match config::save_settings(&self.settings, &path) {
+            Ok(()) => self.message(format!("Wrote {}", path.display())),
+            Err(e) => self.error(format!("Failed to save settings: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Running external shell commands (M-!, M-|).
+//!
+//! Commands run through `sh -c`. The region (or the whole buffer when there
+//! is no selection) is written to the command's stdin; its stdout is either
+//! shown in an output pane or replaces the text that was sent. A failing
+//! command never changes the buffer: its exit status and stderr are shown
+//! instead.
+
+use super::{Editor, FloatingMode, FloatingWindow, MinibufferCallback};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use super::CursorMove;
+
+/// Result of running a shell command
+#[derive(Debug)]
+pub struct ShellOutput {
+    /// Exit code (None when killed by a signal)
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ShellOutput {
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    pub fn status_text(&self) -> String {
+        status_text(self.status)
+    }
+}
+
+/// Short description of an exit status for titles and messages
+pub fn status_text(status: Option<i32>) -> String {
+    match status {
+        Some(code) => format!("exit {}", code),
+        None => "killed".to_string(),
+    }
+}
+
+/// Run `command` with `sh -c`, feeding `input` to its stdin
+pub fn run_shell_command(command: &str, input: Option<&str>) -> io::Result<ShellOutput> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Write stdin from a thread so a command that fills its stdout pipe
+    // before reading all input cannot deadlock us
+    let writer = match (input, child.stdin.take()) {
+        (Some(text), Some(mut stdin)) => {
+            let text = text.to_string();
+            Some(std::thread::spawn(move || {
+                // The command may exit without reading everything (e.g. `head`)
+                let _ = stdin.write_all(text.as_bytes());
+            }))
+        }
+        _ => None,
+    };
+
+    let output = child.wait_with_output()?;
+    match writer {
+        Some(handle) => {
+            let _ = handle.join();
+        }
+        None => {}
+    }
+
+    Ok(ShellOutput {
+        status: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+/// Drop the output's trailing newline when the text it replaces had none,
+/// so filtering `foo` through `tr a-z A-Z` gives `FOO`, not `FOO\n`
+fn replacement_text(stdout: &str, input_ends_with_newline: bool) -> &str {
+    match input_ends_with_newline {
+        true => stdout,
+        false => stdout.strip_suffix('\n').unwrap_or(stdout),
+    }
+}
+
+impl Editor {
+    // ==================== Shell Commands ====================
+
+    /// Ask for a command whose output is inserted at the cursor (M-!)
+    pub fn shell_command_prompt(&mut self) {
+        self.open_minibuffer("Shell command: ", "", MinibufferCallback::ShellCommand);
+    }
+
+    /// Ask for a command to run on the region, or the whole buffer without one.
+    /// With `replace` the output replaces that text (C-c |), otherwise it is
+    /// shown in an output pane (M-|).
+    pub fn shell_command_on_region_prompt(&mut self, replace: bool) {
+        let prompt = match (self.get_selected_text().is_some(), replace) {
+            (true, false) => "Shell command on region: ",
+            (true, true) => "Replace region with command: ",
+            (false, false) => "Shell command on buffer: ",
+            (false, true) => "Replace buffer with command: ",
+        };
+        self.open_minibuffer(prompt, "", MinibufferCallback::ShellCommandOnRegion { replace });
+    }
+
+    /// Run a command and insert its output at the cursor
+    pub fn shell_command(&mut self, command: &str) {
+        let output = match self.run_for_editor(command, None) {
+            Some(output) => output,
+            None => return,
+        };
+
+        self.save_undo_state();
+        match self.mark.is_active() {
+            true => self.cancel_mark(),
+            false => {}
+        }
+        match output.stdout.is_empty() {
+            true => {}
+            false => {
+                self.textarea.insert_str(&output.stdout);
+                self.mark_modified();
+            }
+        }
+        self.report_stderr(&output);
+    }
+
+    /// Run a command on the region (or whole buffer), showing or substituting its output
+    pub fn shell_command_on_region(&mut self, command: &str, replace: bool) {
+        let (input, whole_buffer) = match self.get_selected_text() {
+            Some(text) => (text, false),
+            // Files end with a newline even though the buffer lines don't show it
+            None => (self.textarea.text() + "\n", true),
+        };
+        let output = match self.run_for_editor(command, Some(&input)) {
+            Some(output) => output,
+            None => return,
+        };
+
+        match replace {
+            false => {
+                self.show_shell_output(command, &output);
+                return;
+            }
+            true => {}
+        }
+
+        let text = replacement_text(&output.stdout, input.ends_with('\n') && !whole_buffer);
+        self.save_undo_state();
+        let before = self.textarea.rope().clone();
+        match whole_buffer {
+            true => {
+                let (row, col) = self.textarea.cursor();
+                self.textarea.select_all();
+                self.textarea.cut();
+                self.textarea.insert_str(text);
+                self.textarea.move_cursor(CursorMove::Jump(row, col));
+            }
+            false => self.replace_selection(text.to_string()),
+        }
+        match *self.textarea.rope() != before {
+            true => self.mark_modified(),
+            false => {}
+        }
+        self.report_stderr(&output);
+    }
+
+    /// Run a command, reporting spawn errors and failures in the UI.
+    /// Returns the output only when the command succeeded.
+    fn run_for_editor(&mut self, command: &str, input: Option<&str>) -> Option<ShellOutput> {
+        match command.trim().is_empty() {
+            true => return None,
+            false => {}
+        }
+        match run_shell_command(command, input) {
+            Ok(output) if output.success() => Some(output),
+            Ok(output) => {
+                self.show_shell_output(command, &output);
+                None
+            }
+            Err(e) => {
+                self.error(format!("Failed to run '{}': {}", command, e));
+                None
+            }
+        }
+    }
+
+    /// Show the first line of a successful command's stderr
+    fn report_stderr(&mut self, output: &ShellOutput) {
+        match output.stderr.lines().find(|l| !l.trim().is_empty()) {
+            Some(line) => self.message(line.to_string()),
+            None => {}
+        }
+    }
+
+    /// Open the output pane with a command's stdout and stderr
+    pub fn show_shell_output(&mut self, command: &str, output: &ShellOutput) {
+        let stdout: Vec<String> = output.stdout.lines().map(str::to_string).collect();
+        let stderr: Vec<String> = output.stderr.lines().map(str::to_string).collect();
+        match (stdout.is_empty(), stderr.is_empty(), output.success()) {
+            (true, true, true) => {
+                self.message(format!("'{}' finished with no output", command));
+                return;
+            }
+            (true, true, false) => {
+                self.error(format!("'{}' failed ({})", command, output.status_text()));
+                return;
+            }
+            _ => {}
+        }
+
+        let height = ((stdout.len() + stderr.len()) as u16 + 2).clamp(5, self.settings.floating_window_height.max(5));
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(60),
+            height,
+            mode: FloatingMode::ShellOutput {
+                command: command.to_string(),
+                status: output.status,
+                stdout,
+                stderr,
+                scroll: 0,
+            },
+        });
+        self.focus_floating = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_with;
+
+    #[test]
+    fn test_run_shell_command() {
+        let output = run_shell_command("tr a-z A-Z", Some("hello\n")).unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout, "HELLO\n");
+
+        let output = run_shell_command("echo oops >&2; exit 3", None).unwrap();
+        assert_eq!(output.status, Some(3));
+        assert_eq!(output.stderr, "oops\n");
+    }
+
+    #[test]
+    fn test_filter_region_and_buffer() {
+        // Region without a trailing newline keeps none
+        let mut editor = editor_with("one two\nthree");
+        editor.set_mark();
+        editor.textarea.move_cursor(CursorMove::Jump(0, 3));
+        editor.shell_command_on_region("tr a-z A-Z", true);
+        assert_eq!(editor.textarea.to_lines(), ["ONE two", "three"]);
+        assert!(editor.modified);
+
+        // No selection: the whole buffer is filtered
+        let mut editor = editor_with("b\nc\na");
+        editor.shell_command_on_region("sort", true);
+        assert_eq!(editor.textarea.to_lines(), ["a", "b", "c"]);
+
+        // Failure leaves the buffer alone and shows stderr
+        editor.shell_command_on_region("echo bad >&2; exit 1", true);
+        assert_eq!(editor.textarea.to_lines(), ["a", "b", "c"]);
+        match &editor.floating_window.as_ref().unwrap().mode {
+            FloatingMode::ShellOutput { status, stderr, .. } => {
+                assert_eq!(*status, Some(1));
+                assert_eq!(stderr, &["bad"]);
+            }
+            _ => panic!("expected the output pane"),
+        }
+    }
+
+    #[test]
+    fn test_shell_command_inserts_output() {
+        let mut editor = editor_with("x");
+        editor.shell_command("printf hi");
+        assert_eq!(editor.textarea.to_lines(), ["hix"]);
+
+        editor.shell_command_on_region("wc -l", false);
+        match &editor.floating_window.as_ref().unwrap().mode {
+            FloatingMode::ShellOutput { stdout, .. } => assert_eq!(stdout[0].trim(), "1"),
+            _ => panic!("expected the output pane"),
+        }
+    }
+}
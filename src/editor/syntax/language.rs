@@ -3,7 +3,7 @@
 use std::path::Path;
 
 /// Supported languages for syntax operations.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Language {
     Rust,
     Python,
@@ -42,4 +42,47 @@ impl Language {
             _ => Language::PlainText,
         }
     }
+
+    /// LSP language identifier (also the key used in the `[lsp]` config section)
+    pub fn id(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+            Language::Tsx => "typescriptreact",
+            Language::Go => "go",
+            Language::C => "c",
+            Language::Cpp => "cpp",
+            Language::Json => "json",
+            Language::Markdown => "markdown",
+            Language::Html => "html",
+            Language::Css => "css",
+            Language::Java => "java",
+            Language::PlainText => "plaintext",
+        }
+    }
+
+    /// Look up a language by its identifier
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|l| l.id() == id)
+    }
+
+    /// Every language, in declaration order
+    pub const ALL: &'static [Language] = &[
+        Language::Rust,
+        Language::Python,
+        Language::JavaScript,
+        Language::TypeScript,
+        Language::Tsx,
+        Language::Go,
+        Language::C,
+        Language::Cpp,
+        Language::Json,
+        Language::Markdown,
+        Language::Html,
+        Language::Css,
+        Language::Java,
+        Language::PlainText,
+    ];
 }
@@ -4,15 +4,21 @@
 //! - Language detection from file extensions
 //! - Unified syntax state with incremental parsing
 //! - Syntax highlighting with caching
+//! - Structural navigation (siblings, parents and definitions)
+//! - Symbols for the buffer outline
 
 mod highlight;
 mod language;
+mod structure;
+mod tags;
 
 pub use highlight::{highlight_style, HighlightResult, HighlightSpan, HIGHLIGHT_NAMES};
 pub use language::Language;
+pub use structure::Definition;
+pub use tags::{Symbol, SymbolKind};
 
 use ratatui::style::Style;
-use tree_sitter::{InputEdit, Parser, Tree};
+use tree_sitter::{InputEdit, Parser, Query, Tree};
 use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter};
 
 /// Unified syntax state managing both parsing and highlighting.
@@ -26,6 +32,10 @@ pub struct SyntaxState {
     tree: Option<Tree>,
     config: HighlightConfiguration,
     highlighter: Highlighter,
+    /// Query marking functions and classes (see `structure`)
+    definitions: Option<Query>,
+    /// Query capturing the named symbols for the outline (see `tags`)
+    tags: Option<Query>,
     /// Cached highlight spans (invalidated on edit)
     cached_spans: Vec<HighlightSpan>,
     /// Whether cache is valid
@@ -82,12 +92,19 @@ impl SyntaxState {
                     }
                 };
 
+                let definitions = parser
+                    .language()
+                    .and_then(|grammar| structure::definitions_query(lang, &grammar));
+                let tags = parser.language().and_then(|grammar| tags::tags_query(lang, &grammar));
+
                 Some(Self {
                     language: lang,
                     parser,
                     tree: None,
                     config,
                     highlighter: Highlighter::new(),
+                    definitions,
+                    tags,
                     cached_spans: Vec::new(),
                     cache_valid: false,
                 })
@@ -0,0 +1,331 @@
+//! Structural navigation over the syntax tree.
+//!
+//! Sibling and parent movement walk the tree directly. Definitions come from
+//! a query per language whose `@function` and `@class` captures mark the
+//! functions and the classes (structs, traits, modules, ...) around them.
+//! The data and markup languages have no functions; there the entries take
+//! their place: JSON pairs, Markdown sections, HTML elements and CSS rules.
+
+use super::{Language, SyntaxState};
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Node, Query, QueryCursor};
+
+/// Kind of definition a query capture marks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Definition {
+    Function,
+    Class,
+}
+
+impl Definition {
+    fn capture_name(&self) -> &'static str {
+        match self {
+            Definition::Function => "function",
+            Definition::Class => "class",
+        }
+    }
+}
+
+const RUST_DEFINITIONS: &str = r#"
+[(function_item) (function_signature_item)] @function
+[(impl_item) (struct_item) (enum_item) (union_item) (trait_item) (mod_item)] @class
+"#;
+
+const PYTHON_DEFINITIONS: &str = r#"
+(function_definition) @function
+(class_definition) @class
+"#;
+
+const JAVASCRIPT_DEFINITIONS: &str = r#"
+[(function_declaration) (generator_function_declaration) (method_definition)] @function
+(lexical_declaration (variable_declarator value: [(arrow_function) (function_expression)])) @function
+[(class_declaration) (class)] @class
+"#;
+
+const TYPESCRIPT_DEFINITIONS: &str = r#"
+[(function_declaration) (generator_function_declaration) (method_definition)] @function
+[(function_signature) (method_signature) (abstract_method_signature)] @function
+(lexical_declaration (variable_declarator value: [(arrow_function) (function_expression)])) @function
+[(class_declaration) (class) (abstract_class_declaration)] @class
+[(interface_declaration) (enum_declaration) (internal_module)] @class
+"#;
+
+const GO_DEFINITIONS: &str = r#"
+[(function_declaration) (method_declaration)] @function
+(type_declaration) @class
+"#;
+
+const C_DEFINITIONS: &str = r#"
+(function_definition) @function
+[(struct_specifier body: (_)) (union_specifier body: (_)) (enum_specifier body: (_))] @class
+"#;
+
+const CPP_DEFINITIONS: &str = r#"
+(function_definition) @function
+[(struct_specifier body: (_)) (union_specifier body: (_)) (enum_specifier body: (_))] @class
+[(class_specifier body: (_)) (namespace_definition)] @class
+"#;
+
+const JAVA_DEFINITIONS: &str = r#"
+[(method_declaration) (constructor_declaration)] @function
+[(class_declaration) (interface_declaration) (enum_declaration) (record_declaration)] @class
+(annotation_type_declaration) @class
+"#;
+
+const JSON_DEFINITIONS: &str = r#"
+(pair) @function
+"#;
+
+const MARKDOWN_DEFINITIONS: &str = r#"
+(section) @function
+"#;
+
+const HTML_DEFINITIONS: &str = r#"
+[(element) (script_element) (style_element)] @function
+"#;
+
+const CSS_DEFINITIONS: &str = r#"
+(rule_set) @function
+[(media_statement) (keyframes_statement) (supports_statement)] @class
+"#;
+
+/// Query source marking the definitions of `language`
+pub fn definitions_source(language: Language) -> &'static str {
+    match language {
+        Language::Rust => RUST_DEFINITIONS,
+        Language::Python => PYTHON_DEFINITIONS,
+        Language::JavaScript => JAVASCRIPT_DEFINITIONS,
+        Language::TypeScript | Language::Tsx => TYPESCRIPT_DEFINITIONS,
+        Language::Go => GO_DEFINITIONS,
+        Language::C => C_DEFINITIONS,
+        Language::Cpp => CPP_DEFINITIONS,
+        Language::Java => JAVA_DEFINITIONS,
+        Language::Json => JSON_DEFINITIONS,
+        Language::Markdown => MARKDOWN_DEFINITIONS,
+        Language::Html => HTML_DEFINITIONS,
+        Language::Css => CSS_DEFINITIONS,
+        Language::PlainText => "",
+    }
+}
+
+/// Compile the definitions query of `language` for `grammar`
+pub fn definitions_query(language: Language, grammar: &tree_sitter::Language) -> Option<Query> {
+    match Query::new(grammar, definitions_source(language)) {
+        Ok(query) => Some(query),
+        Err(e) => {
+            log::warn!("Failed to build definitions query for {:?}: {}", language, e);
+            None
+        }
+    }
+}
+
+impl SyntaxState {
+    // ==================== Structural Navigation ====================
+
+    /// Named node the cursor at `byte` is on: the largest one starting
+    /// there, or the smallest one around it
+    fn node_at(&self, byte: usize) -> Option<Node<'_>> {
+        let root = self.tree.as_ref()?.root_node();
+        let mut node = root.named_descendant_for_byte_range(byte, byte)?;
+        while let Some(parent) = node.parent() {
+            match node.start_byte() == byte && parent.start_byte() == byte && parent.parent().is_some() {
+                true => node = parent,
+                false => break,
+            }
+        }
+        Some(node)
+    }
+
+    /// Whether `byte` is inside `node` but on none of its children (in
+    /// the whitespace between them)
+    fn between_children(node: Node, byte: usize) -> bool {
+        let mut cursor = node.walk();
+        let mut children = node.children(&mut cursor);
+        node.start_byte() < byte && !children.any(|child| child.start_byte() <= byte && byte < child.end_byte())
+    }
+
+    /// Start of the next (or previous) sibling of the node at `byte`.
+    /// Backward from inside a node goes to its own start first; from
+    /// between nodes it goes to the nearest one in that direction.
+    pub fn sibling_node_start(&self, byte: usize, forward: bool) -> Option<usize> {
+        let node = self.node_at(byte)?;
+        let mut cursor = node.walk();
+        let sibling = match (Self::between_children(node, byte), forward) {
+            (true, true) => node.named_children(&mut cursor).find(|child| child.start_byte() >= byte),
+            (true, false) => node.named_children(&mut cursor).filter(|child| child.start_byte() < byte).last(),
+            (false, true) => node.next_named_sibling(),
+            (false, false) => match node.start_byte() < byte {
+                true => Some(node),
+                false => node.prev_named_sibling(),
+            },
+        };
+        sibling.map(|sibling| sibling.start_byte())
+    }
+
+    /// Start of the node around the node at `byte` (None at the top level)
+    pub fn parent_node_start(&self, byte: usize) -> Option<usize> {
+        let node = self.node_at(byte)?;
+        let parent = match Self::between_children(node, byte) {
+            true => node,
+            false => node.parent()?,
+        };
+        // The root spans the whole file
+        parent.parent().map(|_| parent.start_byte())
+    }
+
+    /// Byte ranges of the definitions of `kind` (or of every kind), in
+    /// document order
+    pub fn definitions(&self, source: &str, kind: Option<Definition>) -> Vec<(usize, usize)> {
+        let (tree, query) = match (&self.tree, &self.definitions) {
+            (Some(tree), Some(query)) => (tree, query),
+            _ => return Vec::new(),
+        };
+        let wanted: Vec<u32> = [Definition::Function, Definition::Class]
+            .into_iter()
+            .filter(|k| kind.is_none_or(|kind| kind == *k))
+            .filter_map(|k| query.capture_index_for_name(k.capture_name()))
+            .collect();
+
+        let mut ranges = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(query, tree.root_node(), source.as_bytes());
+        while let Some(found) = matches.next() {
+            for capture in found.captures.iter().filter(|c| wanted.contains(&c.index)) {
+                ranges.push((capture.node.start_byte(), capture.node.end_byte()));
+            }
+        }
+        ranges.sort_by_key(|(start, end)| (*start, std::cmp::Reverse(*end)));
+        ranges.dedup();
+        ranges
+    }
+
+    /// Innermost definition around `byte` that `accepts` takes
+    fn enclosing_definition(
+        &self,
+        source: &str,
+        byte: usize,
+        accepts: impl Fn(&(usize, usize)) -> bool,
+    ) -> Option<(usize, usize)> {
+        // Sorted by start, so the last one around `byte` is the innermost
+        self.definitions(source, None).into_iter().filter(accepts).rfind(|(start, end)| *start <= byte && byte <= *end)
+    }
+
+    /// Start of the function or class around `byte`, or when `byte` is at
+    /// its start or outside any, of the previous definition
+    pub fn beginning_of_definition(&self, source: &str, byte: usize) -> Option<usize> {
+        match self.enclosing_definition(source, byte, |(start, _)| *start < byte) {
+            Some((start, _)) => Some(start),
+            None => self.definitions(source, None).into_iter().map(|(start, _)| start).rfind(|start| *start < byte),
+        }
+    }
+
+    /// End of the function or class around `byte`, or when `byte` is at
+    /// its end or outside any, of the next definition
+    pub fn end_of_definition(&self, source: &str, byte: usize) -> Option<usize> {
+        match self.enclosing_definition(source, byte, |(_, end)| byte < *end) {
+            Some((_, end)) => Some(end),
+            None => self.definitions(source, None).into_iter().find(|(start, _)| *start > byte).map(|(_, end)| end),
+        }
+    }
+
+    /// Start of the next (or previous) function definition after `byte`
+    pub fn function_start(&self, source: &str, byte: usize, forward: bool) -> Option<usize> {
+        let mut starts = self.definitions(source, Some(Definition::Function)).into_iter().map(|(start, _)| start);
+        match forward {
+            true => starts.find(|start| *start > byte),
+            false => starts.rfind(|start| *start < byte),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(language: Language, source: &str) -> SyntaxState {
+        let mut state = SyntaxState::new(language).expect("language has a grammar");
+        state.parse(source);
+        state
+    }
+
+    #[test]
+    fn test_every_language_has_definitions() {
+        let samples = [
+            (Language::Rust, "fn a() {}"),
+            (Language::Python, "def a():\n    pass\n"),
+            (Language::JavaScript, "function a() {}"),
+            (Language::TypeScript, "function a(): void {}"),
+            (Language::Tsx, "function a() { return <div />; }"),
+            (Language::Go, "package main\nfunc a() {}\n"),
+            (Language::C, "void a(void) {}"),
+            (Language::Cpp, "void a() {}"),
+            (Language::Json, "{\"a\": 1}"),
+            (Language::Markdown, "# A\n\ntext\n"),
+            (Language::Html, "<p>a</p>"),
+            (Language::Css, "p { color: red; }"),
+            (Language::Java, "class A { void a() {} }"),
+        ];
+        for language in Language::ALL.iter().filter(|l| **l != Language::PlainText) {
+            let (_, source) = samples.iter().find(|(l, _)| l == language).expect("every language has a sample");
+            let state = parsed(*language, source);
+            assert!(state.definitions.is_some(), "{:?} query should compile", language);
+            assert!(
+                !state.definitions(source, Some(Definition::Function)).is_empty(),
+                "{:?} should find a function",
+                language
+            );
+        }
+    }
+
+    #[test]
+    fn test_sibling_and_parent_nodes() {
+        let source = "fn main() {\n    let a = 1;\n    let b = 2;\n\n    call(a, b);\n}\n";
+        let state = parsed(Language::Rust, source);
+        let let_a = source.find("let a").unwrap();
+        let let_b = source.find("let b").unwrap();
+        let call = source.find("call").unwrap();
+
+        assert_eq!(state.sibling_node_start(let_a, true), Some(let_b));
+        assert_eq!(state.sibling_node_start(let_b, true), Some(call));
+        assert_eq!(state.sibling_node_start(call, true), None);
+        assert_eq!(state.sibling_node_start(call, false), Some(let_b));
+        // From the blank line, to the nearest statement either way
+        assert_eq!(state.sibling_node_start(call - 5, true), Some(call));
+        assert_eq!(state.sibling_node_start(call - 5, false), Some(let_b));
+        // From inside a node, back to its start
+        assert_eq!(state.sibling_node_start(let_b + 1, false), Some(let_b));
+
+        // Up: argument -> arguments -> call, then the block and the function
+        let b_arg = source.find("b);").unwrap();
+        let args = source.find("(a, b)").unwrap();
+        assert_eq!(state.parent_node_start(b_arg), Some(args));
+        assert_eq!(state.parent_node_start(args), Some(call));
+        assert_eq!(state.parent_node_start(0), None);
+    }
+
+    #[test]
+    fn test_definition_movement() {
+        let source = "struct S;\n\nimpl S {\n    fn one(&self) {\n        1;\n    }\n\n    fn two(&self) {}\n}\n\nfn three() {}\n";
+        let state = parsed(Language::Rust, source);
+        let impl_s = source.find("impl").unwrap();
+        let one = source.find("fn one").unwrap();
+        let two = source.find("fn two").unwrap();
+        let three = source.find("fn three").unwrap();
+        let inside_one = source.find("1;").unwrap();
+        let end_one = source.find("}\n\n    fn two").unwrap() + 1;
+        let end_impl = source.find("}\n\nfn three").unwrap() + 1;
+
+        assert_eq!(state.beginning_of_definition(source, inside_one), Some(one));
+        assert_eq!(state.beginning_of_definition(source, one), Some(impl_s));
+        assert_eq!(state.beginning_of_definition(source, three - 1), Some(two));
+        assert_eq!(state.end_of_definition(source, inside_one), Some(end_one));
+        assert_eq!(state.end_of_definition(source, end_one), Some(end_impl));
+        assert_eq!(state.end_of_definition(source, 0), Some(source.find(";").unwrap() + 1));
+
+        assert_eq!(state.function_start(source, 0, true), Some(one));
+        assert_eq!(state.function_start(source, inside_one, true), Some(two));
+        assert_eq!(state.function_start(source, two, true), Some(three));
+        assert_eq!(state.function_start(source, three, true), None);
+        assert_eq!(state.function_start(source, three, false), Some(two));
+    }
+}
@@ -0,0 +1,309 @@
+//! Symbols for the outline of a buffer.
+//!
+//! A tag query per language captures each symbol's definition node as
+//! `@definition.<kind>` and its name as `@name`, in the style of the
+//! `tags.scm` files that ship with the grammars. When two patterns capture
+//! the same node (a Rust function inside an impl is both a function and a
+//! method), the one further down the query wins, so the specific patterns
+//! come last. Symbols nest by their definition nodes, which gives Markdown
+//! headings the shape of the document's sections.
+
+use super::{Language, SyntaxState};
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Query, QueryCursor};
+
+/// What a symbol defines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Class,
+    Interface,
+    Type,
+    Impl,
+    Module,
+    Macro,
+    Heading,
+}
+
+impl SymbolKind {
+    /// Kind of the `@definition.<kind>` capture called `name`
+    fn from_capture(name: &str) -> Option<Self> {
+        match name.strip_prefix("definition.")? {
+            "function" => Some(SymbolKind::Function),
+            "method" => Some(SymbolKind::Method),
+            "class" => Some(SymbolKind::Class),
+            "interface" => Some(SymbolKind::Interface),
+            "type" => Some(SymbolKind::Type),
+            "impl" => Some(SymbolKind::Impl),
+            "module" => Some(SymbolKind::Module),
+            "macro" => Some(SymbolKind::Macro),
+            "heading" => Some(SymbolKind::Heading),
+            _ => None,
+        }
+    }
+
+    /// Short name shown in the outline
+    pub fn label(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "fn",
+            SymbolKind::Method => "method",
+            SymbolKind::Class => "class",
+            SymbolKind::Interface => "interface",
+            SymbolKind::Type => "type",
+            SymbolKind::Impl => "impl",
+            SymbolKind::Module => "module",
+            SymbolKind::Macro => "macro",
+            SymbolKind::Heading => "#",
+        }
+    }
+}
+
+/// A named definition in the buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// Byte offset of the name
+    pub name_byte: usize,
+    /// Number of symbols around this one
+    pub depth: usize,
+}
+
+const RUST_TAGS: &str = r#"
+(function_item name: (identifier) @name) @definition.function
+(function_signature_item name: (identifier) @name) @definition.function
+[(struct_item name: (_) @name) (enum_item name: (_) @name) (union_item name: (_) @name)] @definition.class
+(type_item name: (_) @name) @definition.type
+(trait_item name: (_) @name) @definition.interface
+(impl_item type: (_) @name) @definition.impl
+(mod_item name: (_) @name) @definition.module
+(macro_definition name: (_) @name) @definition.macro
+(declaration_list [(function_item name: (identifier) @name) (function_signature_item name: (identifier) @name)] @definition.method)
+"#;
+
+const PYTHON_TAGS: &str = r#"
+(function_definition name: (identifier) @name) @definition.function
+(class_definition name: (identifier) @name) @definition.class
+(class_definition body: (block (function_definition name: (identifier) @name) @definition.method))
+(class_definition body: (block (decorated_definition (function_definition name: (identifier) @name) @definition.method)))
+"#;
+
+const JAVASCRIPT_TAGS: &str = r#"
+[(function_declaration name: (_) @name) (generator_function_declaration name: (_) @name)] @definition.function
+(lexical_declaration (variable_declarator name: (identifier) @name value: [(arrow_function) (function_expression)])) @definition.function
+(method_definition name: (_) @name) @definition.method
+(class_declaration name: (_) @name) @definition.class
+"#;
+
+const TYPESCRIPT_TAGS: &str = r#"
+[(function_declaration name: (_) @name) (generator_function_declaration name: (_) @name)] @definition.function
+(function_signature name: (_) @name) @definition.function
+(lexical_declaration (variable_declarator name: (identifier) @name value: [(arrow_function) (function_expression)])) @definition.function
+[(method_definition name: (_) @name) (method_signature name: (_) @name) (abstract_method_signature name: (_) @name)] @definition.method
+[(class_declaration name: (_) @name) (abstract_class_declaration name: (_) @name)] @definition.class
+(interface_declaration name: (_) @name) @definition.interface
+[(type_alias_declaration name: (_) @name) (enum_declaration name: (_) @name)] @definition.type
+(internal_module name: (_) @name) @definition.module
+"#;
+
+const GO_TAGS: &str = r#"
+(function_declaration name: (identifier) @name) @definition.function
+(method_declaration name: (field_identifier) @name) @definition.method
+(type_spec name: (type_identifier) @name) @definition.type
+(type_spec name: (type_identifier) @name type: (struct_type)) @definition.class
+(type_spec name: (type_identifier) @name type: (interface_type)) @definition.interface
+"#;
+
+const C_TAGS: &str = r#"
+(function_definition declarator: (function_declarator declarator: (identifier) @name)) @definition.function
+(function_definition declarator: (pointer_declarator declarator: (function_declarator declarator: (identifier) @name))) @definition.function
+[(struct_specifier name: (_) @name body: (_)) (union_specifier name: (_) @name body: (_))] @definition.class
+(enum_specifier name: (_) @name body: (_)) @definition.type
+(type_definition declarator: (type_identifier) @name) @definition.type
+"#;
+
+const CPP_TAGS: &str = r#"
+(function_definition declarator: (function_declarator declarator: (identifier) @name)) @definition.function
+(function_definition declarator: (pointer_declarator declarator: (function_declarator declarator: (identifier) @name))) @definition.function
+(function_definition declarator: (function_declarator declarator: [(qualified_identifier) (field_identifier)] @name)) @definition.method
+[(struct_specifier name: (_) @name body: (_)) (union_specifier name: (_) @name body: (_))] @definition.class
+(class_specifier name: (_) @name body: (_)) @definition.class
+(enum_specifier name: (_) @name body: (_)) @definition.type
+(type_definition declarator: (type_identifier) @name) @definition.type
+(namespace_definition name: (_) @name) @definition.module
+"#;
+
+const JAVA_TAGS: &str = r#"
+[(method_declaration name: (_) @name) (constructor_declaration name: (_) @name)] @definition.method
+[(class_declaration name: (_) @name) (record_declaration name: (_) @name)] @definition.class
+[(interface_declaration name: (_) @name) (annotation_type_declaration name: (_) @name)] @definition.interface
+(enum_declaration name: (_) @name) @definition.type
+"#;
+
+const MARKDOWN_TAGS: &str = r#"
+(section . (atx_heading heading_content: (_) @name)) @definition.heading
+(setext_heading heading_content: (_) @name) @definition.heading
+"#;
+
+/// Query source capturing the symbols of `language` (empty for the data
+/// and markup languages without any)
+pub fn tags_source(language: Language) -> &'static str {
+    match language {
+        Language::Rust => RUST_TAGS,
+        Language::Python => PYTHON_TAGS,
+        Language::JavaScript => JAVASCRIPT_TAGS,
+        Language::TypeScript | Language::Tsx => TYPESCRIPT_TAGS,
+        Language::Go => GO_TAGS,
+        Language::C => C_TAGS,
+        Language::Cpp => CPP_TAGS,
+        Language::Java => JAVA_TAGS,
+        Language::Markdown => MARKDOWN_TAGS,
+        Language::Json | Language::Html | Language::Css | Language::PlainText => "",
+    }
+}
+
+/// Compile the tag query of `language` for `grammar`
+pub fn tags_query(language: Language, grammar: &tree_sitter::Language) -> Option<Query> {
+    match Query::new(grammar, tags_source(language)) {
+        Ok(query) => Some(query),
+        Err(e) => {
+            log::warn!("Failed to build tags query for {:?}: {}", language, e);
+            None
+        }
+    }
+}
+
+impl SyntaxState {
+    // ==================== Symbols ====================
+
+    /// Symbols of the buffer in document order, each with the number of
+    /// symbols it is nested in
+    pub fn symbols(&self, source: &str) -> Vec<Symbol> {
+        let (tree, query) = match (&self.tree, &self.tags) {
+            (Some(tree), Some(query)) => (tree, query),
+            _ => return Vec::new(),
+        };
+        let name_index = query.capture_index_for_name("name");
+        let kinds: Vec<Option<SymbolKind>> =
+            query.capture_names().iter().map(|name| SymbolKind::from_capture(name)).collect();
+
+        // Each symbol with the byte range of its definition and its pattern
+        let mut found: Vec<((usize, usize), usize, Symbol)> = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches = cursor.matches(query, tree.root_node(), source.as_bytes());
+        while let Some(m) = matches.next() {
+            let name = m.captures.iter().find(|c| Some(c.index) == name_index);
+            let definition = m.captures.iter().find_map(|c| kinds[c.index as usize].map(|kind| (c.node, kind)));
+            match (name, definition) {
+                (Some(name), Some((node, kind))) => {
+                    let symbol = Symbol {
+                        kind,
+                        name: source[name.node.byte_range()].split_whitespace().collect::<Vec<_>>().join(" "),
+                        name_byte: name.node.start_byte(),
+                        depth: 0,
+                    };
+                    found.push(((node.start_byte(), node.end_byte()), m.pattern_index, symbol));
+                }
+                _ => {}
+            }
+        }
+
+        // Outer definitions first, and for the same node the latest pattern
+        found.sort_by_key(|((start, end), pattern, _)| (*start, std::cmp::Reverse(*end), std::cmp::Reverse(*pattern)));
+        found.dedup_by_key(|(range, _, _)| *range);
+
+        // Ends of the definitions around the current one
+        let mut around: Vec<usize> = Vec::new();
+        found
+            .into_iter()
+            .map(|((start, end), _, symbol)| {
+                while around.last().is_some_and(|outer_end| *outer_end <= start) {
+                    around.pop();
+                }
+                let depth = around.len();
+                around.push(end);
+                Symbol { depth, ..symbol }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(language: Language, source: &str) -> Vec<(SymbolKind, String, usize)> {
+        let mut state = SyntaxState::new(language).expect("language has a grammar");
+        assert!(state.tags.is_some(), "{:?} query should compile", language);
+        state.parse(source);
+        state.symbols(source).into_iter().map(|s| (s.kind, s.name, s.depth)).collect()
+    }
+
+    #[test]
+    fn test_every_language_has_a_tags_query() {
+        let samples = [
+            (Language::Rust, "fn a() {}"),
+            (Language::Python, "def a():\n    pass\n"),
+            (Language::JavaScript, "const a = () => 1;"),
+            (Language::TypeScript, "interface A { a(): void; }"),
+            (Language::Tsx, "function a() { return <div />; }"),
+            (Language::Go, "package main\ntype A struct{}\n"),
+            (Language::C, "int *a(void) { return 0; }"),
+            (Language::Cpp, "void A::a() {}"),
+            (Language::Java, "class A { A() {} }"),
+            (Language::Markdown, "# A\n"),
+        ];
+        for language in Language::ALL.iter().filter(|l| **l != Language::PlainText) {
+            match samples.iter().find(|(l, _)| l == language) {
+                Some((_, source)) => assert!(!symbols(*language, source).is_empty(), "{:?} should find a symbol", language),
+                None => assert!(SyntaxState::new(*language).expect("language has a grammar").tags.is_some()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_rust_symbols() {
+        let source = "struct S;\n\nimpl S {\n    fn one(&self) {}\n}\n\ntrait T {\n    fn two(&self);\n}\n\nfn three() {}\n";
+        assert_eq!(
+            symbols(Language::Rust, source),
+            vec![
+                (SymbolKind::Class, "S".to_string(), 0),
+                (SymbolKind::Impl, "S".to_string(), 0),
+                (SymbolKind::Method, "one".to_string(), 1),
+                (SymbolKind::Interface, "T".to_string(), 0),
+                (SymbolKind::Method, "two".to_string(), 1),
+                (SymbolKind::Function, "three".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_headings_nest() {
+        let source = "# Title\n\nintro\n\n## Install\n\n### From source\n\n## Usage\n\nText\n---\n";
+        assert_eq!(
+            symbols(Language::Markdown, source),
+            vec![
+                (SymbolKind::Heading, "Title".to_string(), 0),
+                (SymbolKind::Heading, "Install".to_string(), 1),
+                (SymbolKind::Heading, "From source".to_string(), 2),
+                (SymbolKind::Heading, "Usage".to_string(), 1),
+                // Setext headings start no section of their own
+                (SymbolKind::Heading, "Text".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python_methods() {
+        let source = "class A:\n    @property\n    def x(self):\n        pass\n\ndef f():\n    pass\n";
+        assert_eq!(
+            symbols(Language::Python, source),
+            vec![
+                (SymbolKind::Class, "A".to_string(), 0),
+                (SymbolKind::Method, "x".to_string(), 1),
+                (SymbolKind::Function, "f".to_string(), 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! Helpers shared by the editor unit tests.
+
+use super::{Editor, TextArea};
+use crate::clipboard::{ClipboardBackend, ClipboardManager};
+use std::path::Path;
+
+/// An editor holding `text`, with the cursor at the start and a clipboard
+/// that stays inside the process
+pub(crate) fn editor_with(text: &str) -> Editor {
+    let mut editor = Editor::new();
+    editor.textarea = TextArea::from_text(text);
+    editor.clipboard = ClipboardManager::with_backend(ClipboardBackend::Internal);
+    editor
+}
+
+/// An empty editor that keeps its recovery files and undo histories in
+/// `dir` rather than the user's directories
+pub(crate) fn editor_in(dir: &Path) -> Editor {
+    let mut editor = Editor::new();
+    editor.settings.autosave_dir = dir.join("autosave").display().to_string();
+    editor.settings.undo_history_dir = dir.join("undo").display().to_string();
+    editor
+}
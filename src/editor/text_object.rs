@@ -0,0 +1,623 @@
+//! Text objects (like vim's `ci"` or helix's `maf`).
+//!
+//! Commands select, change, delete or copy the brackets, quotes, tag or
+//! word around the cursor. "Inside" is the text between the delimiters,
+//! "around" includes them (for a word: the word and the blanks after it).
+//! With a selection the object has to enclose it, so repeating a select
+//! command grows the selection to the next enclosing pair.
+//!
+//! When the buffer has a grammar, pairs are looked up in the syntax tree
+//! (`SyntaxState::get_node_range_at` and its parents), so a bracket inside
+//! a string or comment does not throw matching off. Plain text, and pairs
+//! the tree has no node for, fall back to matching the delimiters in the
+//! text. Only `TEXT_OBJECT_REACH` chars on either side of the cursor are
+//! searched, so huge buffers stay responsive. Change, delete and copy follow the `BufferEdit` pattern (see
+//! buffer_ops.rs).
+
+use super::{Editor, KillDirection, MarkState};
+
+/// Something the cursor can be inside of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    Parens,
+    Brackets,
+    Braces,
+    Angles,
+    DoubleQuotes,
+    SingleQuotes,
+    Backticks,
+    Tag,
+    Word,
+}
+
+impl TextObject {
+    /// Every object with its follow-up keys under M-m and M-a
+    pub const KEYS: &'static [(TextObject, &'static [char])] = &[
+        (TextObject::Parens, &['(', ')', 'b']),
+        (TextObject::Brackets, &['[', ']']),
+        (TextObject::Braces, &['{', '}', 'B']),
+        (TextObject::Angles, &['<', '>']),
+        (TextObject::DoubleQuotes, &['"']),
+        (TextObject::SingleQuotes, &['\'']),
+        (TextObject::Backticks, &['`']),
+        (TextObject::Tag, &['t']),
+        (TextObject::Word, &['w']),
+    ];
+
+    /// Name used in messages
+    pub fn name(self) -> &'static str {
+        match self {
+            TextObject::Parens => "parentheses",
+            TextObject::Brackets => "brackets",
+            TextObject::Braces => "braces",
+            TextObject::Angles => "angle brackets",
+            TextObject::DoubleQuotes => "double quotes",
+            TextObject::SingleQuotes => "single quotes",
+            TextObject::Backticks => "backticks",
+            TextObject::Tag => "tag",
+            TextObject::Word => "word",
+        }
+    }
+
+    /// Opening and closing delimiter of a bracket or quote pair
+    fn delimiters(self) -> Option<(char, char)> {
+        match self {
+            TextObject::Parens => Some(('(', ')')),
+            TextObject::Brackets => Some(('[', ']')),
+            TextObject::Braces => Some(('{', '}')),
+            TextObject::Angles => Some(('<', '>')),
+            TextObject::DoubleQuotes => Some(('"', '"')),
+            TextObject::SingleQuotes => Some(('\'', '\'')),
+            TextObject::Backticks => Some(('`', '`')),
+            TextObject::Tag | TextObject::Word => None,
+        }
+    }
+}
+
+/// What a text object command does with the object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObjectAction {
+    SelectInside,
+    SelectAround,
+    /// Delete the contents without killing them, to type over them
+    ChangeInside,
+    DeleteInside,
+    CopyInside,
+}
+
+impl TextObjectAction {
+    /// Every action and object, paired with the command that runs it
+    pub const COMMANDS: &'static [(TextObjectAction, TextObject, &'static str)] = &[
+        (TextObjectAction::SelectInside, TextObject::Parens, "select-inside-parens"),
+        (TextObjectAction::SelectInside, TextObject::Brackets, "select-inside-brackets"),
+        (TextObjectAction::SelectInside, TextObject::Braces, "select-inside-braces"),
+        (TextObjectAction::SelectInside, TextObject::Angles, "select-inside-angles"),
+        (TextObjectAction::SelectInside, TextObject::DoubleQuotes, "select-inside-double-quotes"),
+        (TextObjectAction::SelectInside, TextObject::SingleQuotes, "select-inside-single-quotes"),
+        (TextObjectAction::SelectInside, TextObject::Backticks, "select-inside-backticks"),
+        (TextObjectAction::SelectInside, TextObject::Tag, "select-inside-tag"),
+        (TextObjectAction::SelectInside, TextObject::Word, "select-inside-word"),
+        (TextObjectAction::SelectAround, TextObject::Parens, "select-around-parens"),
+        (TextObjectAction::SelectAround, TextObject::Brackets, "select-around-brackets"),
+        (TextObjectAction::SelectAround, TextObject::Braces, "select-around-braces"),
+        (TextObjectAction::SelectAround, TextObject::Angles, "select-around-angles"),
+        (TextObjectAction::SelectAround, TextObject::DoubleQuotes, "select-around-double-quotes"),
+        (TextObjectAction::SelectAround, TextObject::SingleQuotes, "select-around-single-quotes"),
+        (TextObjectAction::SelectAround, TextObject::Backticks, "select-around-backticks"),
+        (TextObjectAction::SelectAround, TextObject::Tag, "select-around-tag"),
+        (TextObjectAction::SelectAround, TextObject::Word, "select-around-word"),
+        (TextObjectAction::ChangeInside, TextObject::Parens, "change-inside-parens"),
+        (TextObjectAction::ChangeInside, TextObject::Brackets, "change-inside-brackets"),
+        (TextObjectAction::ChangeInside, TextObject::Braces, "change-inside-braces"),
+        (TextObjectAction::ChangeInside, TextObject::Angles, "change-inside-angles"),
+        (TextObjectAction::ChangeInside, TextObject::DoubleQuotes, "change-inside-double-quotes"),
+        (TextObjectAction::ChangeInside, TextObject::SingleQuotes, "change-inside-single-quotes"),
+        (TextObjectAction::ChangeInside, TextObject::Backticks, "change-inside-backticks"),
+        (TextObjectAction::ChangeInside, TextObject::Tag, "change-inside-tag"),
+        (TextObjectAction::ChangeInside, TextObject::Word, "change-inside-word"),
+        (TextObjectAction::DeleteInside, TextObject::Parens, "delete-inside-parens"),
+        (TextObjectAction::DeleteInside, TextObject::Brackets, "delete-inside-brackets"),
+        (TextObjectAction::DeleteInside, TextObject::Braces, "delete-inside-braces"),
+        (TextObjectAction::DeleteInside, TextObject::Angles, "delete-inside-angles"),
+        (TextObjectAction::DeleteInside, TextObject::DoubleQuotes, "delete-inside-double-quotes"),
+        (TextObjectAction::DeleteInside, TextObject::SingleQuotes, "delete-inside-single-quotes"),
+        (TextObjectAction::DeleteInside, TextObject::Backticks, "delete-inside-backticks"),
+        (TextObjectAction::DeleteInside, TextObject::Tag, "delete-inside-tag"),
+        (TextObjectAction::DeleteInside, TextObject::Word, "delete-inside-word"),
+        (TextObjectAction::CopyInside, TextObject::Parens, "copy-inside-parens"),
+        (TextObjectAction::CopyInside, TextObject::Brackets, "copy-inside-brackets"),
+        (TextObjectAction::CopyInside, TextObject::Braces, "copy-inside-braces"),
+        (TextObjectAction::CopyInside, TextObject::Angles, "copy-inside-angles"),
+        (TextObjectAction::CopyInside, TextObject::DoubleQuotes, "copy-inside-double-quotes"),
+        (TextObjectAction::CopyInside, TextObject::SingleQuotes, "copy-inside-single-quotes"),
+        (TextObjectAction::CopyInside, TextObject::Backticks, "copy-inside-backticks"),
+        (TextObjectAction::CopyInside, TextObject::Tag, "copy-inside-tag"),
+        (TextObjectAction::CopyInside, TextObject::Word, "copy-inside-word"),
+    ];
+
+    /// Find the action and object run by a command name
+    pub fn from_command(name: &str) -> Option<(TextObjectAction, TextObject)> {
+        Self::COMMANDS
+            .iter()
+            .find(|(_, _, command)| *command == name)
+            .map(|(action, object, _)| (*action, *object))
+    }
+
+    /// The command running this action on `object`
+    pub fn command(self, object: TextObject) -> &'static str {
+        Self::COMMANDS
+            .iter()
+            .find(|(action, obj, _)| *action == self && *obj == object)
+            .map(|(_, _, command)| *command)
+            .unwrap_or_default()
+    }
+
+    /// Description shown in the command palette
+    pub fn description(self) -> &'static str {
+        match self {
+            TextObjectAction::SelectInside => "Select the contents of the text object around the cursor",
+            TextObjectAction::SelectAround => "Select the text object around the cursor with its delimiters",
+            TextObjectAction::ChangeInside => "Delete the contents of the text object to type over them",
+            TextObjectAction::DeleteInside => "Kill the contents of the text object around the cursor",
+            TextObjectAction::CopyInside => "Copy the contents of the text object around the cursor",
+        }
+    }
+}
+
+/// How many chars on either side of the cursor are searched for objects
+const TEXT_OBJECT_REACH: usize = 64 * 1024;
+
+/// Where an object was found, as char ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    /// Including the delimiters
+    around: (usize, usize),
+    /// Between the delimiters
+    inside: (usize, usize),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The object if chars `start..end` are exactly one, delimiters included
+/// (used for syntax tree nodes)
+fn delimited_span(text: &[char], object: TextObject, start: usize, end: usize) -> Option<Span> {
+    match object.delimiters() {
+        Some((open, close)) => match end >= start + 2 && text[start] == open && text[end - 1] == close {
+            true => Some(Span {
+                around: (start, end),
+                inside: (start + 1, end - 1),
+            }),
+            false => None,
+        },
+        None => match object {
+            TextObject::Tag => tag_span(text, start).filter(|span| span.around == (start, end)),
+            _ => None,
+        },
+    }
+}
+
+/// Index of the bracket closing the one at `open_at`
+fn matching_close(text: &[char], open_at: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.iter().enumerate().skip(open_at + 1) {
+        match *c {
+            c if c == close && depth == 0 => return Some(i),
+            c if c == close => depth -= 1,
+            c if c == open => depth += 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Bracket pairs enclosing `at`, innermost first, that `accepts` takes
+fn bracket_span(
+    text: &[char],
+    (open, close): (char, char),
+    at: usize,
+    accepts: &dyn Fn(&Span) -> bool,
+) -> Option<Span> {
+    let mut depth = 0;
+    // A bracket under the cursor belongs to the pair
+    let mut i = match text.get(at) == Some(&open) {
+        true => at + 1,
+        false => at,
+    };
+    while i > 0 {
+        i -= 1;
+        match text[i] {
+            c if c == close => depth += 1,
+            c if c == open && depth > 0 => depth -= 1,
+            c if c == open => {
+                let close_at = matching_close(text, i, open, close)?;
+                let span = Span {
+                    around: (i, close_at + 1),
+                    inside: (i + 1, close_at),
+                };
+                match accepts(&span) {
+                    true => return Some(span),
+                    false => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Quote pair on the line of `at` enclosing it. Quotes pair up from the
+/// start of the line; escaped ones are skipped.
+fn quote_span(text: &[char], quote: char, at: usize, accepts: &dyn Fn(&Span) -> bool) -> Option<Span> {
+    let line_start = text[..at.min(text.len())].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1);
+    let line_end = text[line_start..].iter().position(|c| *c == '\n').map_or(text.len(), |i| line_start + i);
+    let quotes: Vec<usize> = (line_start..line_end)
+        .filter(|i| text[*i] == quote && (*i == line_start || text[i - 1] != '\\'))
+        .collect();
+    quotes
+        .chunks_exact(2)
+        .map(|pair| Span {
+            around: (pair[0], pair[1] + 1),
+            inside: (pair[0] + 1, pair[1]),
+        })
+        .find(|span| accepts(span))
+}
+
+/// Name of the tag opened at `at` (None unless `<name` starts there)
+fn tag_name(text: &[char], at: usize) -> Option<String> {
+    match text.get(at) == Some(&'<') && text.get(at + 1).is_some_and(|c| c.is_alphabetic()) {
+        true => Some(
+            text[at + 1..]
+                .iter()
+                .take_while(|c| c.is_alphanumeric() || **c == '-' || **c == ':')
+                .collect(),
+        ),
+        false => None,
+    }
+}
+
+/// The element whose opening tag starts at `at`, up to its closing tag
+fn tag_span(text: &[char], at: usize) -> Option<Span> {
+    let name: Vec<char> = tag_name(text, at)?.chars().collect();
+    let open_end = at + text[at..].iter().position(|c| *c == '>')? + 1;
+    match text[open_end - 2] == '/' {
+        true => return None, // <br/> has no contents
+        false => {}
+    }
+
+    // Find the closing tag, counting nested elements of the same name
+    let mut depth = 0;
+    let mut i = open_end;
+    while i < text.len() {
+        let closing = text[i..].starts_with(&['<', '/']) && text[i + 2..].starts_with(&name);
+        match (closing, tag_name(text, i)) {
+            (true, _) if depth == 0 => {
+                let close_end = i + text[i..].iter().position(|c| *c == '>')? + 1;
+                return Some(Span {
+                    around: (at, close_end),
+                    inside: (open_end, i),
+                });
+            }
+            (true, _) => depth -= 1,
+            (false, Some(nested)) if nested.chars().eq(name.iter().copied()) => depth += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Element enclosing `at`, innermost first, that `accepts` takes
+fn enclosing_tag_span(text: &[char], at: usize, accepts: &dyn Fn(&Span) -> bool) -> Option<Span> {
+    (0..=at.min(text.len().saturating_sub(1)))
+        .rev()
+        .filter_map(|i| tag_span(text, i))
+        .find(|span| accepts(span))
+}
+
+/// Word at (or just before) `at`; around it also takes the blanks after
+/// the word, or before it when none follow
+fn word_span(text: &[char], at: usize) -> Option<Span> {
+    let at = match text.get(at).is_some_and(|c| is_word_char(*c)) {
+        true => at,
+        false if at > 0 && text.get(at - 1).is_some_and(|c| is_word_char(*c)) => at - 1,
+        false => return None,
+    };
+    let start = text[..at].iter().rposition(|c| !is_word_char(*c)).map_or(0, |i| i + 1);
+    let end = text[at..].iter().position(|c| !is_word_char(*c)).map_or(text.len(), |i| at + i);
+    let is_blank = |c: &char| *c == ' ' || *c == '\t';
+    let after = text[end..].iter().take_while(|c| is_blank(c)).count();
+    let before = match after {
+        0 => text[..start].iter().rev().take_while(|c| is_blank(c)).count(),
+        _ => 0,
+    };
+    Some(Span {
+        around: (start - before, end + after),
+        inside: (start, end),
+    })
+}
+
+impl Editor {
+    // ==================== Text Objects ====================
+
+    /// Chars covered by `object` around the cursor, with the delimiters when
+    /// `around` is set. With a selection, the object must be larger than it.
+    pub fn text_object_range(&mut self, object: TextObject, around: bool) -> Option<(usize, usize)> {
+        let selection = self.textarea.selection_range().and_then(|(start, end)| match start == end {
+            true => None,
+            false => Some((
+                self.textarea.char_index(start.0, start.1),
+                self.textarea.char_index(end.0, end.1),
+            )),
+        });
+        let (row, col) = self.textarea.cursor();
+        let (start, end) = match selection {
+            Some(range) => range,
+            None => {
+                let at = self.textarea.char_index(row, col);
+                (at, at)
+            }
+        };
+
+        // Only the text around the cursor is scanned; positions below are
+        // relative to `base`
+        let rope = self.textarea.rope();
+        let base = start.saturating_sub(TEXT_OBJECT_REACH);
+        let text: Vec<char> = rope.slice(base..(end + TEXT_OBJECT_REACH).min(rope.len_chars())).chars().collect();
+        let (start, end) = (start - base, end - base);
+        let selection = selection.map(|_| (start, end));
+
+        let pick = move |span: &Span| match around {
+            true => span.around,
+            false => span.inside,
+        };
+        let accepts = move |span: &Span| match selection {
+            Some((start, end)) => {
+                let target = pick(span);
+                target.0 <= start && end <= target.1 && target != (start, end)
+            }
+            None => span.around.0 <= start && start < span.around.1,
+        };
+
+        let span = match object {
+            TextObject::Word => word_span(&text, start).filter(|span| accepts(span)),
+            _ => self.syntax_text_object(&text, base, object, (start, end), &accepts).or_else(|| match object.delimiters() {
+                Some((open, close)) if open == close => quote_span(&text, open, start, &accepts),
+                Some(pair) => bracket_span(&text, pair, start, &accepts),
+                None => enclosing_tag_span(&text, start, &accepts),
+            }),
+        };
+        span.map(|span| {
+            let (start, end) = pick(&span);
+            (base + start, base + end)
+        })
+    }
+
+    /// Innermost syntax node around chars `start..end` that is `object`
+    /// and that `accepts` takes (None without a grammar). `text` is the
+    /// buffer from char `base` on.
+    fn syntax_text_object(
+        &mut self,
+        text: &[char],
+        base: usize,
+        object: TextObject,
+        (start, end): (usize, usize),
+        accepts: &dyn Fn(&Span) -> bool,
+    ) -> Option<Span> {
+        // The tree is only reparsed before drawing, so it may be behind the text
+        self.ensure_highlights_current();
+        let syntax = self.syntax_state.as_ref()?;
+        let rope = self.textarea.rope();
+        let mut node = syntax.get_node_range_at(rope.char_to_byte(base + start), rope.char_to_byte(base + end))?;
+        loop {
+            let (node_start, node_end) = (rope.byte_to_char(node.0), rope.byte_to_char(node.1));
+            // Parents only grow, so none of them fits in the text either
+            match node_start >= base && node_end - base <= text.len() {
+                true => {}
+                false => return None,
+            }
+            let span = delimited_span(text, object, node_start - base, node_end - base);
+            match span {
+                Some(span) if accepts(&span) => return Some(span),
+                _ => {}
+            }
+            let parent = syntax.get_parent_node_range(node.0, node.1)?;
+            match parent == node {
+                true => return None,
+                false => node = parent,
+            }
+        }
+    }
+
+    /// Select `object` around the cursor (M-m and M-a)
+    pub fn select_text_object(&mut self, object: TextObject, around: bool) -> bool {
+        match self.text_object_range(object, around) {
+            Some((start, end)) => {
+                self.select_chars(start, end);
+                true
+            }
+            None => {
+                self.message(format!("No {} around the cursor", object.name()));
+                false
+            }
+        }
+    }
+
+    /// Contents of `object` around the cursor, when there are any
+    fn text_object_contents(&mut self, object: TextObject) -> Option<(usize, usize, String)> {
+        match self.text_object_range(object, false) {
+            Some((start, end)) if start < end => {
+                let text = self.textarea.rope().slice(start..end).to_string();
+                Some((start, end, text))
+            }
+            Some(_) => None,
+            None => {
+                self.message(format!("No {} around the cursor", object.name()));
+                None
+            }
+        }
+    }
+
+    /// Delete the contents of `object`, leaving the cursor there to type
+    /// new ones. The kill ring is left alone, so a yank still inserts what
+    /// was killed before.
+    ///
+    /// Returns true if text was deleted.
+    pub fn change_inside(&mut self, object: TextObject) -> bool {
+        match self.text_object_contents(object) {
+            Some((start, end, _)) => {
+                self.save_undo_state();
+                self.select_chars(start, end);
+                self.textarea.cut();
+                self.mark = MarkState::None;
+                self.mark_modified();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Kill the contents of `object`
+    ///
+    /// Returns true if text was killed.
+    pub fn delete_inside(&mut self, object: TextObject) -> bool {
+        match self.text_object_contents(object) {
+            Some((start, end, text)) => {
+                self.save_undo_state();
+                self.kill_text(&text, KillDirection::Forward);
+                self.select_chars(start, end);
+                self.textarea.cut();
+                self.mark = MarkState::None;
+                self.mark_modified();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copy the contents of `object` to the kill ring
+    ///
+    /// Does not modify the buffer, so no undo state or modification tracking needed.
+    pub fn copy_inside(&mut self, object: TextObject) -> bool {
+        match self.text_object_contents(object) {
+            Some((_, _, text)) => {
+                self.copy_text(&text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run a text object command
+    pub fn run_text_object(&mut self, action: TextObjectAction, object: TextObject) -> bool {
+        match action {
+            TextObjectAction::SelectInside => self.select_text_object(object, false),
+            TextObjectAction::SelectAround => self.select_text_object(object, true),
+            TextObjectAction::ChangeInside => self.change_inside(object),
+            TextObjectAction::DeleteInside => self.delete_inside(object),
+            TextObjectAction::CopyInside => self.copy_inside(object),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::syntax::{Language, SyntaxState};
+    use crate::editor::test_util::editor_with;
+    use crate::editor::CursorMove;
+
+    fn selected(editor: &Editor) -> Option<String> {
+        editor.get_selected_text()
+    }
+
+    #[test]
+    fn test_pairs_in_plain_text() {
+        let mut editor = editor_with("f(a, [b], (c d))");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 12));
+        assert!(editor.select_text_object(TextObject::Parens, false));
+        assert_eq!(selected(&editor).as_deref(), Some("c d"));
+        // Repeating grows to the enclosing pair
+        assert!(editor.select_text_object(TextObject::Parens, true));
+        assert_eq!(selected(&editor).as_deref(), Some("(c d)"));
+        assert!(editor.select_text_object(TextObject::Parens, false));
+        assert_eq!(selected(&editor).as_deref(), Some("a, [b], (c d)"));
+        assert!(!editor.select_text_object(TextObject::Braces, false));
+
+        // On the opening bracket
+        let mut editor = editor_with("x [a [b]] y");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 2));
+        assert!(editor.select_text_object(TextObject::Brackets, true));
+        assert_eq!(selected(&editor).as_deref(), Some("[a [b]]"));
+
+        let mut editor = editor_with(r#"say "hi \"you\"" and 'a'"#);
+
+        editor.textarea.move_cursor(CursorMove::Jump(0, 6));
+        assert!(editor.select_text_object(TextObject::DoubleQuotes, false));
+        assert_eq!(selected(&editor).as_deref(), Some(r#"hi \"you\""#));
+
+        let mut editor = editor_with("<ul><li>one <b>two</b></li></ul>");
+
+        editor.textarea.move_cursor(CursorMove::Jump(0, 9));
+        assert!(editor.select_text_object(TextObject::Tag, false));
+        assert_eq!(selected(&editor).as_deref(), Some("one <b>two</b>"));
+        assert!(editor.select_text_object(TextObject::Tag, true));
+        assert_eq!(selected(&editor).as_deref(), Some("<li>one <b>two</b></li>"));
+
+        let mut editor = editor_with("one two  three");
+
+        editor.textarea.move_cursor(CursorMove::Jump(0, 5));
+        assert!(editor.select_text_object(TextObject::Word, true));
+        assert_eq!(selected(&editor).as_deref(), Some("two  "));
+    }
+
+    #[test]
+    fn test_pairs_follow_the_syntax_tree() {
+        // The ")" inside the string does not close the call
+        let mut editor = editor_with("fn main() { call(\"a)\", b); }");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 23));
+        editor.language = Language::Rust;
+        editor.syntax_state = SyntaxState::new(Language::Rust);
+        assert!(editor.select_text_object(TextObject::Parens, false));
+        assert_eq!(selected(&editor).as_deref(), Some("\"a)\", b"));
+        assert!(editor.select_text_object(TextObject::Braces, true));
+        assert_eq!(selected(&editor).as_deref(), Some("{ call(\"a)\", b); }"));
+    }
+
+    #[test]
+    fn test_change_delete_and_copy_inside() {
+        let mut editor = editor_with("call(one, two)");
+        editor.textarea.move_cursor(CursorMove::Jump(0, 6));
+        assert!(editor.copy_inside(TextObject::Parens));
+        assert_eq!(editor.kill_ring.current_entry(), Some("one, two"));
+        assert_eq!(editor.textarea.text(), "call(one, two)");
+
+        editor.kill_ring.next_command();
+        assert!(editor.change_inside(TextObject::Word));
+        assert_eq!(editor.textarea.text(), "call(, two)");
+        assert_eq!(editor.textarea.cursor(), (0, 5));
+        assert_eq!(editor.kill_ring.current_entry(), Some("one, two"));
+
+        editor.kill_ring.next_command();
+        assert!(editor.delete_inside(TextObject::Parens));
+        assert_eq!(editor.textarea.text(), "call()");
+        assert_eq!(editor.kill_ring.current_entry(), Some(", two"));
+        // Nothing left inside
+        assert!(!editor.delete_inside(TextObject::Parens));
+        assert!(editor.undo());
+        assert_eq!(editor.textarea.text(), "call(, two)");
+    }
+
+    #[test]
+    fn test_only_text_near_the_cursor_is_scanned() {
+        let filler = "x".repeat(TEXT_OBJECT_REACH);
+        let text = format!("({}\n{}[a b]\n{})", filler, filler, filler);
+        let mut editor = editor_with(&text);
+        editor.textarea.move_cursor(CursorMove::Jump(1, TEXT_OBJECT_REACH + 2));
+        assert!(editor.select_text_object(TextObject::Brackets, false));
+        assert_eq!(selected(&editor).as_deref(), Some("a b"));
+        // The parens are out of reach
+        assert!(!editor.select_text_object(TextObject::Parens, false));
+    }
+}
@@ -3,21 +3,58 @@
 //! This widget renders text with syntax highlighting while preserving
 //! selection and cursor rendering from the underlying textarea.
 //! Supports both horizontal scrolling and soft word wrapping (Helix-style).
+//! A gutter left of the text shows line numbers and git change markers.
+//! Folded lines are left out; the line a fold shows on ends in a placeholder.
 
+use super::fold::FoldedRows;
+use super::lsp::DiagnosticRange;
+use super::textarea::CursorView;
 use super::Editor;
+use crate::git::LineChange;
+use crate::lsp::Severity;
 use ratatui::buffer::Buffer;
 use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::widgets::Widget;
 
+/// Color used for a diagnostic severity
+pub fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+        Severity::Information => Color::Blue,
+        Severity::Hint => Color::DarkGray,
+    }
+}
+
+/// Gutter marker and color for a line changed against HEAD
+fn git_marker_style(change: LineChange) -> (char, Color) {
+    match change {
+        LineChange::Added => ('▎', Color::Green),
+        LineChange::Modified => ('▎', Color::Yellow),
+        LineChange::Removed => ('▁', Color::Red),
+    }
+}
+
 /// Widget for rendering the editor with syntax highlighting.
 pub struct EditorWidget<'a> {
     editor: &'a Editor,
+    /// Language server diagnostics as char ranges
+    diagnostics: Vec<DiagnosticRange>,
+    /// Extra cursors with their selections
+    extra_cursors: Vec<CursorView>,
+    /// Rows hidden by folds
+    folds: FoldedRows,
 }
 
 impl<'a> EditorWidget<'a> {
     pub fn new(editor: &'a Editor) -> Self {
-        Self { editor }
+        Self {
+            editor,
+            diagnostics: editor.diagnostic_ranges(),
+            extra_cursors: editor.textarea.extra_cursors(),
+            folds: editor.textarea.folded_rows(),
+        }
     }
 }
 
@@ -27,6 +64,8 @@ struct VisualLine<'a> {
     doc_line: usize,
     /// Start column in the document line
     start_col: usize,
+    /// Byte offset of the start column in the document line
+    start_byte: usize,
     /// The text content for this visual line
     text: &'a str,
     /// Whether this is a wrapped continuation (needs wrap indicator)
@@ -35,25 +74,30 @@ struct VisualLine<'a> {
 
 impl Widget for EditorWidget<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let lines = self.editor.textarea.lines();
-        if lines.is_empty() {
-            return;
-        }
+        // Split off the gutter
+        let gutter_width = self.editor.gutter_width().min(area.width);
+        let gutter_area = Rect { width: gutter_width, ..area };
+        let area = Rect {
+            x: area.x + gutter_width,
+            width: area.width - gutter_width,
+            ..area
+        };
 
         let (cursor_row, cursor_col) = self.editor.textarea.cursor();
         let viewport_width = area.width as usize;
         let viewport_height = area.height as usize;
 
         // Choose rendering mode based on soft_wrap setting
-        match self.editor.settings.soft_wrap {
-            true => self.render_with_soft_wrap(area, buf, &lines, cursor_row, cursor_col, viewport_width, viewport_height),
-            false => self.render_with_h_scroll(area, buf, &lines, cursor_row, cursor_col, viewport_width, viewport_height),
-        }
+        let screen_lines = match self.editor.settings.soft_wrap {
+            true => self.render_with_soft_wrap(area, buf, cursor_row, cursor_col, viewport_width, viewport_height),
+            false => self.render_with_h_scroll(area, buf, cursor_row, cursor_col, viewport_width, viewport_height),
+        };
+        self.render_gutter(gutter_area, buf, &screen_lines, cursor_row);
 
         // Render jump mode overlays if active
         match &self.editor.jump_mode {
             Some(jump_mode) => {
-                self.render_jump_mode(area, buf, &lines, jump_mode);
+                self.render_jump_mode(area, buf, jump_mode, &screen_lines);
             }
             None => {}
         }
@@ -66,19 +110,71 @@ impl EditorWidget<'_> {
         self.editor.scroll_offset
     }
 
+    /// Document rows shown from the scroll offset on, at most `count`
+    fn shown_rows(&self, count: usize) -> Vec<usize> {
+        let len_lines = self.editor.textarea.len_lines();
+        self.folds
+            .shown_rows(self.get_scroll_offset())
+            .take_while(|row| *row < len_lines)
+            .take(count)
+            .collect()
+    }
+
+    /// After the line at `row` ends at `x`: when a fold starts there, say
+    /// how many lines it hides (one cell past the end, which may hold the cursor)
+    fn render_fold_placeholder(&self, buf: &mut Buffer, area: Rect, row: usize, x: u16, y: u16) {
+        match self.folds.hidden_below(row) {
+            Some(hidden) => {
+                let x = x + 1;
+                match x < area.x + area.width {
+                    true => {
+                        let text = format!("⋯ {} lines", hidden);
+                        let width = (area.x + area.width - x) as usize;
+                        buf.set_stringn(x, y, text, width, Style::default().fg(Color::DarkGray));
+                    }
+                    false => {}
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Byte offset where a line starts (the text length past the last line)
+    fn line_start_byte(&self, row: usize) -> usize {
+        let textarea = &self.editor.textarea;
+        match row < textarea.len_lines() {
+            true => textarea.row_col_to_byte(row, 0),
+            false => textarea.len_bytes(),
+        }
+    }
+
+    /// Highlight styles for each byte of the document range `start..=end`,
+    /// so rendering only pays for the visible text
+    fn byte_styles(&self, start: usize, end: usize) -> Vec<Style> {
+        let mut styles = vec![Style::default(); end - start + 1];
+        for span in &self.editor.cached_highlights {
+            for i in span.start.max(start)..span.end.min(end + 1) {
+                styles[i - start] = span.style;
+            }
+        }
+        styles
+    }
+
     /// Render with horizontal scrolling (original behavior)
     fn render_with_h_scroll(
         &self,
         area: Rect,
         buf: &mut Buffer,
-        lines: &[String],
         cursor_row: usize,
         cursor_col: usize,
         viewport_width: usize,
         viewport_height: usize,
-    ) {
+    ) -> Vec<Option<usize>> {
+        let textarea = &self.editor.textarea;
         // Calculate vertical scroll offset based on cursor position and recenter state
-        let scroll_offset = self.get_scroll_offset();
+        let rows = self.shown_rows(viewport_height);
+        let scroll_offset = rows.first().copied().unwrap_or(self.get_scroll_offset());
+        let end_line = rows.last().map_or(scroll_offset, |row| row + 1);
 
         // Calculate horizontal scroll offset to keep cursor in view
         let h_scroll_offset = if cursor_col >= viewport_width {
@@ -88,40 +184,23 @@ impl EditorWidget<'_> {
             0
         };
 
-        let source = lines.join("\n");
-        let highlight_spans = &self.editor.cached_highlights;
-        let selection_range = self.editor.textarea.selection_range();
+        let selection_range = textarea.selection_range();
 
-        // Build a map of byte offset -> style for quick lookup
-        let mut byte_styles: Vec<Style> = vec![Style::default(); source.len() + 1];
-        for span in highlight_spans {
-            for i in span.start..span.end.min(byte_styles.len()) {
-                byte_styles[i] = span.style;
-            }
-        }
+        // Build a map of byte offset -> style for the visible lines
+        let first_byte = self.line_start_byte(scroll_offset);
+        let byte_styles = self.byte_styles(first_byte, self.line_start_byte(end_line).max(first_byte));
 
-        let mut current_byte = 0;
-
-        // Skip to scroll offset
-        for i in 0..scroll_offset {
-            if i < lines.len() {
-                current_byte += lines[i].len() + 1;
-            }
-        }
+        let mut screen_lines = Vec::new();
 
         // Render visible lines
-        for (screen_row, line_idx) in (scroll_offset..).take(viewport_height).enumerate() {
-            if line_idx >= lines.len() {
-                break;
-            }
-
-            let line = &lines[line_idx];
+        for (screen_row, &line_idx) in rows.iter().enumerate() {
+            let line = textarea.line(line_idx);
             let y = area.y + screen_row as u16;
-            let line_start_byte = current_byte;
+            screen_lines.push(Some(line_idx));
 
             let mut x = area.x;
             // Track byte offset incrementally instead of O(n²) recalculation
-            let mut byte_offset = line_start_byte;
+            let mut byte_offset = self.line_start_byte(line_idx) - first_byte;
             for (col, ch) in line.chars().enumerate() {
                 if col < h_scroll_offset {
                     byte_offset += ch.len_utf8();
@@ -134,6 +213,18 @@ impl EditorWidget<'_> {
 
                 let mut style = byte_styles.get(byte_offset).copied().unwrap_or_default();
 
+                // Diagnostic underline
+                match self.diagnostic_style(line_idx, col) {
+                    Some(diagnostic_style) => style = style.patch(diagnostic_style),
+                    None => {}
+                }
+
+                // Search match overlay
+                match self.search_match_style(line_idx, col) {
+                    Some(match_style) => style = style.patch(match_style),
+                    None => {}
+                }
+
                 // Selection overlay
                 match selection_range {
                     Some((sel_start, sel_end)) => {
@@ -156,6 +247,12 @@ impl EditorWidget<'_> {
                     None => {}
                 }
 
+                // Extra cursors and their selections
+                match self.extra_cursor_style(line_idx, col) {
+                    Some(cursor_style) => style = style.patch(cursor_style),
+                    None => {}
+                }
+
                 // Cursor
                 match line_idx == cursor_row && col == cursor_col {
                     true => {
@@ -203,7 +300,7 @@ impl EditorWidget<'_> {
                         false => line_end_pos >= sel_end && line_end_pos < sel_start,
                     };
 
-                    match in_selection && line_idx < lines.len() - 1 && line_end_col >= h_scroll_offset {
+                    match in_selection && line_idx < textarea.len_lines() - 1 && line_end_col >= h_scroll_offset {
                         true => {
                             let end_x = area.x + (line_end_col - h_scroll_offset) as u16;
                             match end_x < area.x + area.width && !(line_idx == cursor_row && cursor_col >= line_end_col) {
@@ -226,11 +323,22 @@ impl EditorWidget<'_> {
                 None => {}
             }
 
-            current_byte += line.len() + 1;
+            // Extra cursor at end of line
+            match self.extra_cursor_at_end(line_idx, line.chars().count()) && x < area.x + area.width {
+                true => match buf.cell_mut((x, y)) {
+                    Some(cell) => {
+                        cell.set_char(' ').set_style(self.extra_cursor_cell_style());
+                    }
+                    None => {}
+                },
+                false => {}
+            }
+
+            self.render_fold_placeholder(buf, area, line_idx, x, y);
         }
 
         // Handle cursor on empty document
-        match lines.len() == 1 && lines[0].is_empty() && cursor_row == 0 && cursor_col == 0 {
+        match self.editor.textarea.is_empty() && cursor_row == 0 && cursor_col == 0 {
             true => match buf.cell_mut((area.x, area.y)) {
                 Some(cell) => {
                     cell.set_char(' ').set_style(
@@ -244,6 +352,8 @@ impl EditorWidget<'_> {
             },
             false => {}
         }
+
+        screen_lines
     }
 
     /// Render with soft word wrapping (Helix-style)
@@ -251,39 +361,43 @@ impl EditorWidget<'_> {
         &self,
         area: Rect,
         buf: &mut Buffer,
-        lines: &[String],
         cursor_row: usize,
         cursor_col: usize,
         viewport_width: usize,
         viewport_height: usize,
-    ) {
+    ) -> Vec<Option<usize>> {
         let wrap_indicator = &self.editor.settings.wrap_indicator;
         let wrap_indicator_len = wrap_indicator.chars().count();
         // Effective width for text (accounting for wrap indicator on continuation lines)
         let text_width = viewport_width.saturating_sub(wrap_indicator_len);
 
         if text_width == 0 {
-            return;
+            return Vec::new();
         }
 
-        let source = lines.join("\n");
-        let highlight_spans = &self.editor.cached_highlights;
-        let selection_range = self.editor.textarea.selection_range();
+        let textarea = &self.editor.textarea;
+        let selection_range = textarea.selection_range();
 
-        // Build byte offset -> style map
-        let mut byte_styles: Vec<Style> = vec![Style::default(); source.len() + 1];
-        for span in highlight_spans {
-            for i in span.start..span.end.min(byte_styles.len()) {
-                byte_styles[i] = span.style;
-            }
-        }
+        // The scroll offset is the first visible document line; each line
+        // takes at least one screen row, so no more than a screen of lines shows
+        let rows = self.shown_rows(viewport_height);
+        let scroll_offset = rows.first().copied().unwrap_or(self.get_scroll_offset());
+        let end_line = rows.last().map_or(scroll_offset, |row| row + 1);
+        let doc_lines: Vec<_> = rows.iter().map(|row| textarea.line(*row)).collect();
+
+        // Build byte offset -> style map for the visible lines
+        let first_byte = self.line_start_byte(scroll_offset);
+        let byte_styles = self.byte_styles(first_byte, self.line_start_byte(end_line).max(first_byte));
 
         // Build visual lines and find cursor's visual position
         let mut visual_lines: Vec<VisualLine> = Vec::new();
-        let mut cursor_visual_row = 0;
+        let mut cursor_visual_row = usize::MAX;
         let mut cursor_visual_col = 0;
 
-        for (doc_line_idx, line) in lines.iter().enumerate() {
+        for (&doc_line_idx, line) in rows.iter().zip(doc_lines.iter()) {
+            if visual_lines.len() >= viewport_height {
+                break;
+            }
             let line_chars: Vec<char> = line.chars().collect();
             let line_len = line_chars.len();
 
@@ -296,6 +410,7 @@ impl EditorWidget<'_> {
                 visual_lines.push(VisualLine {
                     doc_line: doc_line_idx,
                     start_col: 0,
+                    start_byte: 0,
                     text: "",
                     is_wrapped: false,
                 });
@@ -336,6 +451,7 @@ impl EditorWidget<'_> {
                     visual_lines.push(VisualLine {
                         doc_line: doc_line_idx,
                         start_col: col,
+                        start_byte: start_byte_idx,
                         text: segment_text,
                         is_wrapped: !is_first_segment,
                     });
@@ -346,29 +462,15 @@ impl EditorWidget<'_> {
             }
         }
 
-        // For soft wrap, we use visual rows but the scroll_offset is managed elsewhere
-        // This is a simplification - soft wrap may need refinement
-        let scroll_offset = self.get_scroll_offset();
+        let mut screen_lines = Vec::new();
 
-        // Track which document line we're processing for byte offset calculations
-        let mut last_doc_line: Option<usize> = None;
-        let mut line_byte_offset: usize = 0;
+        for (visual_row, visual_line) in visual_lines.iter().enumerate().take(viewport_height) {
+            let y = area.y + visual_row as u16;
 
-        for (visual_row, visual_line) in visual_lines.iter().enumerate().skip(scroll_offset).take(viewport_height) {
-            let screen_row = (visual_row - scroll_offset) as u16;
-            let y = area.y + screen_row;
-
-            // Update byte offset when we move to a new document line
-            match last_doc_line {
-                Some(last) if last != visual_line.doc_line => {
-                    line_byte_offset = lines.iter().take(visual_line.doc_line).map(|l| l.len() + 1).sum();
-                }
-                None => {
-                    line_byte_offset = lines.iter().take(visual_line.doc_line).map(|l| l.len() + 1).sum();
-                }
-                Some(_) => {}
-            }
-            last_doc_line = Some(visual_line.doc_line);
+            screen_lines.push(match visual_line.is_wrapped {
+                true => None,
+                false => Some(visual_line.doc_line),
+            });
 
             let mut x = area.x;
 
@@ -390,14 +492,7 @@ impl EditorWidget<'_> {
             }
 
             // Draw the text content
-            // Precompute segment start byte offset instead of O(n²) recalculation per char
-            let segment_start_byte: usize = line_byte_offset
-                + lines[visual_line.doc_line]
-                    .chars()
-                    .take(visual_line.start_col)
-                    .map(|c| c.len_utf8())
-                    .sum::<usize>();
-            let mut char_byte_offset = segment_start_byte;
+            let mut char_byte_offset = self.line_start_byte(visual_line.doc_line) - first_byte + visual_line.start_byte;
 
             for (local_col, ch) in visual_line.text.chars().enumerate() {
                 if x >= area.x + area.width {
@@ -407,6 +502,18 @@ impl EditorWidget<'_> {
                 let doc_col = visual_line.start_col + local_col;
                 let mut style = byte_styles.get(char_byte_offset).copied().unwrap_or_default();
 
+                // Diagnostic underline
+                match self.diagnostic_style(visual_line.doc_line, doc_col) {
+                    Some(diagnostic_style) => style = style.patch(diagnostic_style),
+                    None => {}
+                }
+
+                // Search match overlay
+                match self.search_match_style(visual_line.doc_line, doc_col) {
+                    Some(match_style) => style = style.patch(match_style),
+                    None => {}
+                }
+
                 // Selection overlay
                 match selection_range {
                     Some((sel_start, sel_end)) => {
@@ -429,6 +536,12 @@ impl EditorWidget<'_> {
                     None => {}
                 }
 
+                // Extra cursors and their selections
+                match self.extra_cursor_style(visual_line.doc_line, doc_col) {
+                    Some(cursor_style) => style = style.patch(cursor_style),
+                    None => {}
+                }
+
                 // Cursor
                 let visual_col = match visual_line.is_wrapped {
                     true => wrap_indicator_len + local_col,
@@ -460,6 +573,17 @@ impl EditorWidget<'_> {
                 .map(|next| next.doc_line != visual_line.doc_line)
                 .unwrap_or(true);
 
+            let line_end_col = visual_line.start_col + visual_line.text.chars().count();
+            match is_last_segment_of_line && self.extra_cursor_at_end(visual_line.doc_line, line_end_col) && x < area.x + area.width {
+                true => match buf.cell_mut((x, y)) {
+                    Some(cell) => {
+                        cell.set_char(' ').set_style(self.extra_cursor_cell_style());
+                    }
+                    None => {}
+                },
+                false => {}
+            }
+
             match is_last_segment_of_line
                 && visual_row == cursor_visual_row
                 && cursor_col >= visual_line.start_col + visual_line.text.chars().count()
@@ -478,10 +602,15 @@ impl EditorWidget<'_> {
                 },
                 false => {}
             }
+
+            match is_last_segment_of_line {
+                true => self.render_fold_placeholder(buf, area, visual_line.doc_line, x, y),
+                false => {}
+            }
         }
 
         // Handle cursor on empty document
-        match lines.len() == 1 && lines[0].is_empty() && cursor_row == 0 && cursor_col == 0 {
+        match self.editor.textarea.is_empty() && cursor_row == 0 && cursor_col == 0 {
             true => match buf.cell_mut((area.x, area.y)) {
                 Some(cell) => {
                     cell.set_char(' ').set_style(
@@ -495,6 +624,118 @@ impl EditorWidget<'_> {
             },
             false => {}
         }
+
+        screen_lines
+    }
+
+    /// Draw the gutter for the rendered screen lines
+    /// (`None` marks wrapped continuations, which get a blank gutter)
+    fn render_gutter(&self, area: Rect, buf: &mut Buffer, screen_lines: &[Option<usize>], cursor_row: usize) {
+        let number_width = self.editor.line_number_width();
+        let marker_x = match number_width {
+            0 => area.x,
+            _ => area.x + number_width as u16 + 1,
+        };
+
+        for (screen_row, doc_line) in screen_lines.iter().enumerate().take(area.height as usize) {
+            let row = match doc_line {
+                Some(row) => *row,
+                None => continue,
+            };
+            let y = area.y + screen_row as u16;
+
+            // Line number, right-aligned; the cursor line stands out
+            match self.editor.settings.line_numbers.number_for(row, cursor_row) {
+                Some(number) => {
+                    let style = match row == cursor_row {
+                        true => Style::default().fg(Color::Yellow),
+                        false => Style::default().fg(Color::DarkGray),
+                    };
+                    let text = format!("{:>width$}", number, width = number_width);
+                    buf.set_stringn(area.x, y, text, area.width as usize, style);
+                }
+                None => {}
+            }
+
+            // Git change marker
+            match (self.editor.git_marker(row), marker_x < area.x + area.width) {
+                (Some(change), true) => match buf.cell_mut((marker_x, y)) {
+                    Some(cell) => {
+                        let (ch, color) = git_marker_style(change);
+                        cell.set_char(ch).set_style(Style::default().fg(color));
+                    }
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Underline for a character covered by a diagnostic (most severe wins)
+    fn diagnostic_style(&self, row: usize, col: usize) -> Option<Style> {
+        let severity = self
+            .diagnostics
+            .iter()
+            .filter(|(start, end, _)| (row, col) >= *start && (row, col) < *end)
+            .map(|(_, _, severity)| *severity)
+            .min()?;
+        Some(
+            Style::default()
+                .add_modifier(Modifier::UNDERLINED)
+                .underline_color(severity_color(severity)),
+        )
+    }
+
+    /// Style of an extra cursor
+    fn extra_cursor_cell_style(&self) -> Style {
+        Style::default().bg(self.editor.settings.cursor_color).fg(Color::White)
+    }
+
+    /// Style for a character under an extra cursor or inside its selection
+    fn extra_cursor_style(&self, row: usize, col: usize) -> Option<Style> {
+        match self.extra_cursors.iter().any(|(cursor, _)| *cursor == (row, col)) {
+            true => return Some(self.extra_cursor_cell_style()),
+            false => {}
+        }
+        self.extra_cursors
+            .iter()
+            .filter_map(|(_, selection)| *selection)
+            .any(|(start, end)| (row, col) >= start && (row, col) < end)
+            .then(|| Style::default().bg(self.editor.settings.selection_color).fg(Color::White))
+    }
+
+    /// Whether an extra cursor sits at the end of a line `line_len` chars long
+    fn extra_cursor_at_end(&self, row: usize, line_len: usize) -> bool {
+        self.extra_cursors
+            .iter()
+            .any(|((cursor_row, cursor_col), _)| *cursor_row == row && *cursor_col >= line_len)
+    }
+
+    /// Highlight style for a character inside a search or query-replace match.
+    /// The current match stands out from the other matches.
+    fn search_match_style(&self, row: usize, col: usize) -> Option<Style> {
+        // Match offered by query-replace
+        match self.editor.replace_highlight {
+            Some((start, end)) if (row, col) >= start && (row, col) < end => {
+                return Some(
+                    Style::default()
+                        .bg(Color::Yellow)
+                        .fg(Color::Black)
+                        .add_modifier(Modifier::BOLD),
+                );
+            }
+            _ => {}
+        }
+
+        let isearch = self.editor.isearch.as_ref()?;
+        let (_, is_current) = isearch.match_at(row, col)?;
+        Some(match is_current {
+            true => Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            false => Style::default().bg(Color::Cyan).fg(Color::Black),
+        })
     }
 
     /// Render jump mode overlays (labels and match highlights)
@@ -502,12 +743,11 @@ impl EditorWidget<'_> {
         &self,
         area: Rect,
         buf: &mut Buffer,
-        lines: &[String],
         jump_mode: &super::JumpMode,
+        screen_lines: &[Option<usize>],
     ) {
         use super::JumpPhase;
 
-        let scroll_offset = self.get_scroll_offset();
         let viewport_height = area.height as usize;
 
         // Style for match highlights (during typing phase)
@@ -545,15 +785,14 @@ impl EditorWidget<'_> {
 
         // Render each target
         for target in &jump_mode.targets {
-            // Check if target is in visible viewport
-            match target.row >= scroll_offset && target.row < scroll_offset + viewport_height {
-                true => {
-                    let screen_row = (target.row - scroll_offset) as u16;
-                    let y = area.y + screen_row;
+            // Check if target is in visible viewport (folded lines are not)
+            match screen_lines.iter().position(|line| *line == Some(target.row)) {
+                Some(screen_row) => {
+                    let y = area.y + screen_row as u16;
 
                     // Get the line to calculate column position
-                    match lines.get(target.row) {
-                        Some(_line) => {
+                    match target.row < self.editor.textarea.len_lines() {
+                        true => {
                             // Calculate screen x position (accounting for h_scroll if needed)
                             let screen_col = target.col as u16;
                             let x = area.x + screen_col;
@@ -597,11 +836,80 @@ impl EditorWidget<'_> {
                                 false => {}
                             }
                         }
-                        None => {}
+                        false => {}
                     }
                 }
-                false => {}
+                None => {}
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::LineNumbers;
+    use crate::editor::{CursorMove, TextArea};
+
+    fn render(editor: &Editor, width: u16, height: u16) -> Vec<String> {
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        EditorWidget::new(editor).render(area, &mut buf);
+        (0..height)
+            .map(|y| (0..width).map(|x| buf[(x, y)].symbol()).collect::<String>())
+            .collect()
+    }
+
+    #[test]
+    fn test_line_number_modes() {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        editor.textarea.move_cursor(CursorMove::Down);
+
+        editor.settings.line_numbers = LineNumbers::Absolute;
+        assert_eq!(render(&editor, 6, 3), vec!["  1 a ", "  2 b ", "  3 c "]);
+        editor.settings.line_numbers = LineNumbers::Relative;
+        assert_eq!(render(&editor, 6, 3), vec!["  1 a ", "  0 b ", "  1 c "]);
+        editor.settings.line_numbers = LineNumbers::Hybrid;
+        assert_eq!(render(&editor, 6, 3), vec!["  1 a ", "  2 b ", "  1 c "]);
+    }
+
+    #[test]
+    fn test_gutter_with_wrap_and_scroll() {
+        let mut editor = Editor::new();
+        let mut lines: Vec<String> = (0..1000).map(|_| String::new()).collect();
+        lines[0] = "abcdefghij".to_string();
+        editor.textarea = TextArea::new(lines);
+        editor.settings.line_numbers = LineNumbers::Absolute;
+
+        // The gutter grows to fit 4 digits
+        assert_eq!(editor.gutter_width(), 5);
+
+        // Wrapped continuations get a blank gutter
+        editor.settings.soft_wrap = true;
+        editor.settings.wrap_indicator = ">".to_string();
+        assert_eq!(render(&editor, 11, 3), vec!["   1 abcdef", "     >ghij ", "   2       "]);
+
+        // Horizontal scrolling only shifts the text, not the gutter
+        editor.settings.soft_wrap = false;
+        editor.textarea.move_cursor(CursorMove::End);
+        let rendered = render(&editor, 11, 1);
+        assert!(rendered[0].starts_with("   1 "), "{:?}", rendered);
+        assert!(rendered[0].contains('j'), "{:?}", rendered);
+    }
+
+    #[test]
+    fn test_fold_placeholder() {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::from_text("fn a() {\n    1;\n}\nb");
+        editor.settings.line_numbers = LineNumbers::Absolute;
+        assert!(editor.textarea.fold(0, 17));
+
+        // The folded lines collapse into the header line, numbers keep counting
+        assert_eq!(render(&editor, 20, 3), vec!["  1 fn a() { ⋯ 2 lin", "  4 b               ", "                    "]);
+
+        // Soft wrap gives the same picture
+        editor.settings.soft_wrap = true;
+        assert_eq!(render(&editor, 20, 2), vec!["  1 fn a() { ⋯ 2 lin", "  4 b               "]);
+    }
+}
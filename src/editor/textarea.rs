@@ -0,0 +1,923 @@
+//! Rope-backed text storage for a buffer.
+//!
+//! `TextArea` keeps the text of one buffer in a `ropey::Rope` together with
+//! the cursor and selection. Edits, line lookups and conversions between
+//! (row, col) positions and byte offsets are O(log n), so multi-hundred-MB
+//! files stay usable, and cloning the text (for undo snapshots) is O(1).
+//!
+//! Positions are (row, col) pairs with the column counted in chars. Only
+//! `\n` separates lines; `\r` and other Unicode line breaks are plain text.
+//!
+//! Every change to the text is logged as an `Edit` until `take_edits` is
+//! called; the undo history is built from these deltas.
+//!
+//! Besides the cursor there can be extra cursors, each with its own
+//! selection. Edits shift them like the text around them, and
+//! `swap_cursor` makes one of them the cursor so an edit can run there.
+//! Folds (see `fold.rs`) are char ranges that follow edits the same way.
+
+use super::fold::FoldedRows;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ropey::Rope;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Columns between tab stops when Tab inserts spaces
+const TAB_WIDTH: usize = 4;
+
+/// Source of `TextArea::version` values (shared by all buffers so versions never collide)
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A cursor movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMove {
+    /// One char forward, wrapping to the next line
+    Forward,
+    /// One char back, wrapping to the previous line
+    Back,
+    Up,
+    Down,
+    /// Start of the line
+    Head,
+    /// End of the line
+    End,
+    /// First line
+    Top,
+    /// Last line
+    Bottom,
+    /// Start of the next word
+    WordForward,
+    /// Start of the previous word
+    WordBack,
+    /// To (row, col), clamped to the text
+    Jump(usize, usize),
+}
+
+/// A change to the text: `removed` starting at char `at` was replaced by `inserted`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub at: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Edit {
+    /// The edit that undoes this one
+    pub fn inverse(&self) -> Edit {
+        Edit {
+            at: self.at,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+}
+
+/// An extra cursor, as char indices so edits can shift it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ExtraCursor {
+    at: usize,
+    anchor: Option<usize>,
+}
+
+/// A cursor position with its selection as (start, end)
+pub type CursorView = ((usize, usize), Option<((usize, usize), (usize, usize))>);
+
+/// The text of a buffer with its cursor and selection
+#[derive(Debug, Clone)]
+pub struct TextArea {
+    text: Rope,
+    cursor: (usize, usize),
+    /// Selection anchor; the selection runs from here to the cursor
+    selection_start: Option<(usize, usize)>,
+    /// Extra cursors (multiple-cursor editing), in no particular order
+    extra_cursors: Vec<ExtraCursor>,
+    /// Folded char ranges, in no particular order
+    folds: Vec<(usize, usize)>,
+    version: u64,
+    /// Edits made since the last `take_edits`
+    edits: Vec<Edit>,
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        Self::from_rope(Rope::new())
+    }
+}
+
+impl TextArea {
+    /// Text area holding the given lines, cursor at the start
+    pub fn new(lines: Vec<String>) -> Self {
+        Self::from_text(&lines.join("\n"))
+    }
+
+    /// Text area holding `\n`-separated text, cursor at the start
+    pub fn from_text(text: &str) -> Self {
+        Self::from_rope(Rope::from_str(text))
+    }
+
+    /// Text area holding a rope, cursor at the start
+    pub fn from_rope(text: Rope) -> Self {
+        Self {
+            text,
+            cursor: (0, 0),
+            selection_start: None,
+            extra_cursors: Vec::new(),
+            folds: Vec::new(),
+            version: next_version(),
+            edits: Vec::new(),
+        }
+    }
+
+    // ==================== Text Access ====================
+
+    /// The underlying rope
+    pub fn rope(&self) -> &Rope {
+        &self.text
+    }
+
+    /// The whole text as one string (O(n); prefer `rope` or `line` where possible)
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    /// Identifies the current text: changes on every edit, and copies of
+    /// a text area (such as undo snapshots) keep the version of their text
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of lines (at least 1; a trailing `\n` starts an empty last line)
+    pub fn len_lines(&self) -> usize {
+        self.text.len_lines()
+    }
+
+    /// Length of the text in bytes
+    pub fn len_bytes(&self) -> usize {
+        self.text.len_bytes()
+    }
+
+    /// Whether the text is empty
+    pub fn is_empty(&self) -> bool {
+        self.text.len_chars() == 0
+    }
+
+    /// A line without its `\n` (empty for rows past the end).
+    /// Borrowed when the line is stored contiguously.
+    pub fn line(&self, row: usize) -> Cow<'_, str> {
+        match row < self.len_lines() {
+            true => {
+                let line = self.text.line(row);
+                let len = line.len_chars();
+                match len > 0 && line.char(len - 1) == '\n' {
+                    true => line.slice(..len - 1).into(),
+                    false => line.into(),
+                }
+            }
+            false => Cow::Borrowed(""),
+        }
+    }
+
+    /// Length of a line in chars, without its `\n`
+    pub fn line_len(&self, row: usize) -> usize {
+        match row < self.len_lines() {
+            true => {
+                let line = self.text.line(row);
+                let len = line.len_chars();
+                match len > 0 && line.char(len - 1) == '\n' {
+                    true => len - 1,
+                    false => len,
+                }
+            }
+            false => 0,
+        }
+    }
+
+    /// All lines, in order
+    pub fn lines(&self) -> impl Iterator<Item = Cow<'_, str>> + '_ {
+        (0..self.len_lines()).map(|row| self.line(row))
+    }
+
+    /// All lines copied out (O(n), for code that works on whole line lists)
+    pub fn to_lines(&self) -> Vec<String> {
+        self.lines().map(Cow::into_owned).collect()
+    }
+
+    // ==================== Position Conversions ====================
+
+    /// Char index of a position, clamped to the text
+    pub fn char_index(&self, row: usize, col: usize) -> usize {
+        let row = row.min(self.len_lines() - 1);
+        self.text.line_to_char(row) + col.min(self.line_len(row))
+    }
+
+    /// Position of a char index, clamped to the text
+    pub fn char_to_row_col(&self, char_idx: usize) -> (usize, usize) {
+        let char_idx = char_idx.min(self.text.len_chars());
+        let row = self.text.char_to_line(char_idx);
+        (row, char_idx - self.text.line_to_char(row))
+    }
+
+    /// Byte offset of a position, clamped to the text
+    pub fn row_col_to_byte(&self, row: usize, col: usize) -> usize {
+        self.text.char_to_byte(self.char_index(row, col))
+    }
+
+    /// Position of a byte offset, clamped to the text.
+    /// An offset inside a multi-byte char maps to that char.
+    pub fn byte_to_row_col(&self, byte: usize) -> (usize, usize) {
+        let byte = byte.min(self.text.len_bytes());
+        self.char_to_row_col(self.text.byte_to_char(byte))
+    }
+
+    /// Text between two positions
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let start = self.char_index(start.0, start.1);
+        let end = self.char_index(end.0, end.1);
+        self.text.slice(start.min(end)..start.max(end)).to_string()
+    }
+
+    // ==================== Cursor and Selection ====================
+
+    /// Cursor position as (row, col)
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Start selecting from the cursor
+    pub fn start_selection(&mut self) {
+        self.selection_start = Some(self.cursor);
+    }
+
+    /// Drop the selection (the text is kept)
+    pub fn cancel_selection(&mut self) {
+        self.selection_start = None;
+    }
+
+    /// Where the selection started (None without a selection)
+    pub fn selection_anchor(&self) -> Option<(usize, usize)> {
+        self.selection_start
+    }
+
+    /// Whether a selection is active
+    pub fn is_selecting(&self) -> bool {
+        self.selection_start.is_some()
+    }
+
+    /// Select the whole text, leaving the cursor at the end
+    pub fn select_all(&mut self) {
+        self.move_cursor(CursorMove::Jump(usize::MAX, usize::MAX));
+        self.selection_start = Some((0, 0));
+    }
+
+    /// The selection as (start, end) with start <= end
+    pub fn selection_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        self.selection_start.map(|anchor| match anchor > self.cursor {
+            true => (self.cursor, anchor),
+            false => (anchor, self.cursor),
+        })
+    }
+
+    /// Move the cursor, extending the selection when one is active
+    pub fn move_cursor(&mut self, movement: CursorMove) {
+        match self.next_cursor(movement) {
+            Some(cursor) => self.cursor = cursor,
+            None => {}
+        }
+    }
+
+    /// Where a movement takes the cursor (None when it cannot move)
+    fn next_cursor(&self, movement: CursorMove) -> Option<(usize, usize)> {
+        let (row, col) = self.cursor;
+        let last_row = self.len_lines() - 1;
+        let fit_col = |row: usize, col: usize| col.min(self.line_len(row));
+
+        match movement {
+            CursorMove::Forward if col >= self.line_len(row) => (row < last_row).then_some((row + 1, 0)),
+            CursorMove::Forward => Some((row, col + 1)),
+            CursorMove::Back if col == 0 => {
+                let row = row.checked_sub(1)?;
+                Some((row, self.line_len(row)))
+            }
+            CursorMove::Back => Some((row, col - 1)),
+            CursorMove::Up => {
+                let row = row.checked_sub(1)?;
+                Some((row, fit_col(row, col)))
+            }
+            CursorMove::Down => (row < last_row).then(|| (row + 1, fit_col(row + 1, col))),
+            CursorMove::Head => Some((row, 0)),
+            CursorMove::End => Some((row, self.line_len(row))),
+            CursorMove::Top => Some((0, fit_col(0, col))),
+            CursorMove::Bottom => Some((last_row, fit_col(last_row, col))),
+            CursorMove::WordForward => match find_word_start_forward(&self.line(row), col) {
+                Some(col) => Some((row, col)),
+                None if row < last_row => Some((row + 1, 0)),
+                None => Some((row, self.line_len(row))),
+            },
+            CursorMove::WordBack => match find_word_start_backward(&self.line(row), col) {
+                Some(col) => Some((row, col)),
+                None if row > 0 => Some((row - 1, self.line_len(row - 1))),
+                None => Some((row, 0)),
+            },
+            CursorMove::Jump(row, col) => {
+                let row = row.min(last_row);
+                Some((row, fit_col(row, col)))
+            }
+        }
+    }
+
+    // ==================== Extra Cursors ====================
+
+    /// Number of extra cursors
+    pub fn extra_cursor_count(&self) -> usize {
+        self.extra_cursors.len()
+    }
+
+    /// Extra cursor positions with their selections (start <= end)
+    pub fn extra_cursors(&self) -> Vec<CursorView> {
+        self.extra_cursors
+            .iter()
+            .map(|extra| {
+                let selection = extra
+                    .anchor
+                    .map(|anchor| (self.char_to_row_col(anchor.min(extra.at)), self.char_to_row_col(anchor.max(extra.at))));
+                (self.char_to_row_col(extra.at), selection)
+            })
+            .collect()
+    }
+
+    /// Leave an extra cursor (with the selection) where the cursor is
+    pub fn add_cursor(&mut self) {
+        let at = self.char_index(self.cursor.0, self.cursor.1);
+        let anchor = self.selection_start.map(|(row, col)| self.char_index(row, col));
+        match self.extra_cursors.iter().any(|extra| extra.at == at) {
+            true => {}
+            false => self.extra_cursors.push(ExtraCursor { at, anchor }),
+        }
+    }
+
+    /// Remove all extra cursors
+    pub fn clear_extra_cursors(&mut self) {
+        self.extra_cursors.clear();
+    }
+
+    /// Exchange the cursor and its selection with extra cursor `index`
+    pub fn swap_cursor(&mut self, index: usize) {
+        let at = self.char_index(self.cursor.0, self.cursor.1);
+        let anchor = self.selection_start.map(|(row, col)| self.char_index(row, col));
+        let extra = std::mem::replace(&mut self.extra_cursors[index], ExtraCursor { at, anchor });
+        self.cursor = self.char_to_row_col(extra.at);
+        self.selection_start = extra.anchor.map(|anchor| self.char_to_row_col(anchor));
+    }
+
+    /// Char indices of the extra cursors (in storage order, for `swap_cursor`)
+    pub fn extra_cursor_indices(&self) -> Vec<usize> {
+        self.extra_cursors.iter().map(|extra| extra.at).collect()
+    }
+
+    /// Remove extra cursors that ended up on the cursor or on each other
+    pub fn merge_cursors(&mut self) {
+        let mut seen = vec![self.char_index(self.cursor.0, self.cursor.1)];
+        self.extra_cursors.retain(|extra| match seen.contains(&extra.at) {
+            true => false,
+            false => {
+                seen.push(extra.at);
+                true
+            }
+        });
+    }
+
+    // ==================== Folds ====================
+
+    /// First and last row of the chars `start..end`. A range ending at the
+    /// start of a line (after its `\n`) ends on the line before.
+    pub fn fold_rows(&self, start: usize, end: usize) -> (usize, usize) {
+        let (first, _) = self.char_to_row_col(start);
+        let (last, col) = self.char_to_row_col(end);
+        match col == 0 && last > first {
+            true => (first, last - 1),
+            false => (first, last),
+        }
+    }
+
+    /// Fold the chars `start..end`. Returns false when already folded.
+    pub fn fold(&mut self, start: usize, end: usize) -> bool {
+        match self.is_folded(start, end) {
+            true => false,
+            false => {
+                self.folds.push((start, end));
+                true
+            }
+        }
+    }
+
+    /// Whether the chars `start..end` are folded
+    pub fn is_folded(&self, start: usize, end: usize) -> bool {
+        self.folds.contains(&(start, end))
+    }
+
+    /// Open the folds whose (first row, last row) `opens` picks.
+    /// Returns how many were opened.
+    pub fn unfold_where(&mut self, mut opens: impl FnMut((usize, usize)) -> bool) -> usize {
+        let before = self.folds.len();
+        let folds = std::mem::take(&mut self.folds);
+        self.folds = folds
+            .into_iter()
+            .filter(|(start, end)| !opens(self.fold_rows(*start, *end)))
+            .collect();
+        before - self.folds.len()
+    }
+
+    /// The rows the folds hide
+    pub fn folded_rows(&self) -> FoldedRows {
+        FoldedRows::new(self.folds.iter().map(|(start, end)| self.fold_rows(*start, *end)).collect())
+    }
+
+    /// All lines, with the ones folds hide left empty
+    pub fn shown_lines(&self) -> impl Iterator<Item = Cow<'_, str>> + '_ {
+        let folds = self.folded_rows();
+        self.lines().enumerate().map(move |(row, line)| match folds.is_hidden(row) {
+            true => Cow::Borrowed(""),
+            false => line,
+        })
+    }
+
+    // ==================== Editing ====================
+
+    /// Replace the chars in `start..end` with `text` and put the cursor after it
+    fn splice(&mut self, start: usize, end: usize, text: &str) {
+        match start < end {
+            true => self.text.remove(start..end),
+            false => {}
+        }
+        self.text.insert(start, text);
+        // Extra cursors after the change move with the text; ones in the
+        // removed text end up where it was
+        let inserted = text.chars().count();
+        let shift = |at: usize| match (at <= start, at >= end) {
+            (true, _) => at,
+            (false, true) => at - (end - start) + inserted,
+            (false, false) => start,
+        };
+        for extra in &mut self.extra_cursors {
+            extra.at = shift(extra.at);
+            extra.anchor = extra.anchor.map(shift);
+        }
+        // Folds move with the text and grow or shrink with edits inside
+        // them; an edit across one of their ends opens them
+        self.folds.retain_mut(|fold| match (end <= fold.0, start >= fold.1) {
+            (true, _) => {
+                *fold = (fold.0 - (end - start) + inserted, fold.1 - (end - start) + inserted);
+                true
+            }
+            (false, true) => true,
+            (false, false) if start >= fold.0 && end <= fold.1 => {
+                fold.1 = fold.1 - (end - start) + inserted;
+                true
+            }
+            (false, false) => false,
+        });
+        self.cursor = self.char_to_row_col(start + text.chars().count());
+        self.version = next_version();
+    }
+
+    /// Replace the chars in `start..end` with `text`, logging the edit
+    fn replace_chars(&mut self, start: usize, end: usize, text: &str) {
+        match start == end && text.is_empty() {
+            true => return,
+            false => {}
+        }
+        self.edits.push(Edit {
+            at: start,
+            removed: self.text.slice(start..end).to_string(),
+            inserted: text.to_string(),
+        });
+        self.splice(start, end, text);
+    }
+
+    /// The most recent edit not yet taken by `take_edits`
+    pub fn last_edit(&self) -> Option<&Edit> {
+        self.edits.last()
+    }
+
+    /// Edits made since the last call, oldest first
+    pub fn take_edits(&mut self) -> Vec<Edit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Apply an edit without logging it (for undo and redo).
+    /// The cursor ends up after the inserted text.
+    pub fn apply_edit(&mut self, edit: &Edit) {
+        self.cancel_selection();
+        let end = edit.at + edit.removed.chars().count();
+        self.splice(edit.at, end, &edit.inserted);
+    }
+
+    /// Replace the whole text, keeping the cursor where it was (clamped)
+    pub fn set_text(&mut self, text: &str) {
+        let (row, col) = self.cursor;
+        self.cancel_selection();
+        self.replace_chars(0, self.text.len_chars(), text);
+        self.move_cursor(CursorMove::Jump(row, col));
+    }
+
+    /// Delete the selected text. Returns false when nothing was selected.
+    fn delete_selection(&mut self) -> bool {
+        let range = self.selection_range();
+        self.cancel_selection();
+        match range {
+            Some((start, end)) if start != end => {
+                let start = self.char_index(start.0, start.1);
+                let end = self.char_index(end.0, end.1);
+                self.replace_chars(start, end, "");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Delete the selected text (the caller copies it first).
+    /// Returns false when nothing was selected.
+    pub fn cut(&mut self) -> bool {
+        self.delete_selection()
+    }
+
+    /// Insert a char at the cursor, replacing the selection
+    pub fn insert_char(&mut self, c: char) {
+        match c {
+            '\n' | '\r' => self.insert_newline(),
+            _ => {
+                self.delete_selection();
+                let at = self.char_index(self.cursor.0, self.cursor.1);
+                self.replace_chars(at, at, c.encode_utf8(&mut [0; 4]));
+            }
+        }
+    }
+
+    /// Insert text at the cursor, replacing the selection.
+    /// CRLF line breaks are inserted as `\n`.
+    pub fn insert_str(&mut self, text: &str) -> bool {
+        let deleted = self.delete_selection();
+        match text.is_empty() {
+            true => deleted,
+            false => {
+                let text = text.replace("\r\n", "\n");
+                let at = self.char_index(self.cursor.0, self.cursor.1);
+                self.replace_chars(at, at, &text);
+                true
+            }
+        }
+    }
+
+    /// Split the line at the cursor, replacing the selection
+    pub fn insert_newline(&mut self) {
+        self.delete_selection();
+        let at = self.char_index(self.cursor.0, self.cursor.1);
+        self.replace_chars(at, at, "\n");
+    }
+
+    /// Insert spaces up to the next tab stop, replacing the selection
+    pub fn insert_tab(&mut self) -> bool {
+        self.delete_selection();
+        let spaces = TAB_WIDTH - self.cursor.1 % TAB_WIDTH;
+        self.insert_str(&" ".repeat(spaces))
+    }
+
+    /// Delete the char before the cursor (joining lines at column 0),
+    /// or the selection
+    pub fn delete_char(&mut self) -> bool {
+        match self.delete_selection() {
+            true => true,
+            false => {
+                let at = self.char_index(self.cursor.0, self.cursor.1);
+                match at {
+                    0 => false,
+                    _ => {
+                        self.replace_chars(at - 1, at, "");
+                        true
+                    }
+                }
+            }
+        }
+    }
+
+    /// Delete the char under the cursor (joining lines at the end of a line),
+    /// or the selection
+    pub fn delete_next_char(&mut self) -> bool {
+        match self.delete_selection() {
+            true => true,
+            false => {
+                let at = self.char_index(self.cursor.0, self.cursor.1);
+                match at < self.text.len_chars() {
+                    true => {
+                        self.replace_chars(at, at + 1, "");
+                        true
+                    }
+                    false => false,
+                }
+            }
+        }
+    }
+
+    /// Delete up to `chars` chars after the cursor, or the selection
+    pub fn delete_str(&mut self, chars: usize) -> bool {
+        match self.delete_selection() {
+            true => true,
+            false => {
+                let at = self.char_index(self.cursor.0, self.cursor.1);
+                let end = at.saturating_add(chars).min(self.text.len_chars());
+                match at < end {
+                    true => {
+                        self.replace_chars(at, end, "");
+                        true
+                    }
+                    false => false,
+                }
+            }
+        }
+    }
+
+    /// Delete back to the start of the word before the cursor, or the selection
+    pub fn delete_word(&mut self) -> bool {
+        match self.delete_selection() {
+            true => return true,
+            false => {}
+        }
+        let (row, col) = self.cursor;
+        match col {
+            0 => self.delete_char(),
+            _ => {
+                let start = find_word_start_backward(&self.line(row), col).unwrap_or(0);
+                let line_start = self.text.line_to_char(row);
+                self.replace_chars(line_start + start, line_start + col, "");
+                true
+            }
+        }
+    }
+
+    /// Delete forward to the end of the word after the cursor, or the selection
+    pub fn delete_next_word(&mut self) -> bool {
+        match self.delete_selection() {
+            true => return true,
+            false => {}
+        }
+        let (row, col) = self.cursor;
+        let line_len = self.line_len(row);
+        match col < line_len {
+            true => {
+                let end = find_word_exclusive_end_forward(&self.line(row), col).unwrap_or(line_len);
+                let line_start = self.text.line_to_char(row);
+                self.replace_chars(line_start + col, line_start + end, "");
+                true
+            }
+            false => self.delete_next_char(),
+        }
+    }
+
+    /// Apply a plain typing key (chars, Tab, Backspace, Delete, Enter).
+    /// Returns whether the text changed; other keys are ignored.
+    pub fn input(&mut self, key: KeyEvent) -> bool {
+        let plain = !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char(c) if plain => {
+                self.insert_char(c);
+                true
+            }
+            KeyCode::Tab if plain => self.insert_tab(),
+            KeyCode::Backspace => self.delete_char(),
+            KeyCode::Delete => self.delete_next_char(),
+            KeyCode::Enter => {
+                self.insert_newline();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// ==================== Word Boundaries ====================
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum CharKind {
+    Space,
+    Punct,
+    Other,
+}
+
+impl CharKind {
+    fn new(c: char) -> Self {
+        match (c.is_whitespace(), c.is_ascii_punctuation()) {
+            (true, _) => Self::Space,
+            (false, true) => Self::Punct,
+            (false, false) => Self::Other,
+        }
+    }
+}
+
+/// Column of the next word start after `start_col`
+fn find_word_start_forward(line: &str, start_col: usize) -> Option<usize> {
+    let mut chars = line.chars().enumerate().skip(start_col);
+    let mut prev = CharKind::new(chars.next()?.1);
+    for (col, c) in chars {
+        let cur = CharKind::new(c);
+        match cur != CharKind::Space && prev != cur {
+            true => return Some(col),
+            false => prev = cur,
+        }
+    }
+    None
+}
+
+/// Column just past the end of the word at or after `start_col`
+fn find_word_exclusive_end_forward(line: &str, start_col: usize) -> Option<usize> {
+    let mut chars = line.chars().enumerate().skip(start_col);
+    let mut prev = CharKind::new(chars.next()?.1);
+    for (col, c) in chars {
+        let cur = CharKind::new(c);
+        match prev != CharKind::Space && prev != cur {
+            true => return Some(col),
+            false => prev = cur,
+        }
+    }
+    None
+}
+
+/// Column of the start of the word before `start_col`
+fn find_word_start_backward(line: &str, start_col: usize) -> Option<usize> {
+    let mut chars = line.chars().take(start_col).collect::<Vec<_>>().into_iter().rev().enumerate();
+    let mut cur = CharKind::new(chars.next()?.1);
+    for (i, c) in chars {
+        let next = CharKind::new(c);
+        match cur != CharKind::Space && next != cur {
+            true => return Some(start_col - i),
+            false => cur = next,
+        }
+    }
+    (cur != CharKind::Space).then_some(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_area(text: &str) -> TextArea {
+        TextArea::from_text(text)
+    }
+
+    #[test]
+    fn test_lines_and_conversions() {
+        let textarea = text_area("héllo\n\nwörld\n");
+        assert_eq!(textarea.len_lines(), 4);
+        assert_eq!(textarea.line(0), "héllo");
+        assert_eq!(textarea.line(1), "");
+        assert_eq!(textarea.line(3), "");
+        assert_eq!(textarea.line(9), "");
+        assert_eq!(textarea.line_len(2), 5);
+        assert_eq!(textarea.to_lines(), ["héllo", "", "wörld", ""]);
+
+        assert_eq!(textarea.row_col_to_byte(0, 2), 3);
+        assert_eq!(textarea.row_col_to_byte(2, 0), 8);
+        assert_eq!(textarea.row_col_to_byte(0, 99), 6);
+        assert_eq!(textarea.byte_to_row_col(3), (0, 2));
+        assert_eq!(textarea.byte_to_row_col(8), (2, 0));
+        assert_eq!(textarea.byte_to_row_col(999), (3, 0));
+    }
+
+    #[test]
+    fn test_carriage_returns_are_not_line_breaks() {
+        let textarea = text_area("a\rb\u{2028}c");
+        assert_eq!(textarea.len_lines(), 1);
+        assert_eq!(textarea.line_len(0), 5);
+    }
+
+    #[test]
+    fn test_editing_moves_cursor_and_version() {
+        let mut textarea = TextArea::default();
+        let version = textarea.version();
+        textarea.insert_str("ab\r\ncd");
+        assert_eq!(textarea.text(), "ab\ncd");
+        assert_eq!(textarea.cursor(), (1, 2));
+        assert_ne!(textarea.version(), version);
+
+        textarea.move_cursor(CursorMove::Head);
+        assert!(textarea.delete_char());
+        assert_eq!(textarea.text(), "abcd");
+        assert_eq!(textarea.cursor(), (0, 2));
+
+        textarea.insert_newline();
+        textarea.insert_tab();
+        assert_eq!(textarea.to_lines(), ["ab", "    cd"]);
+
+        // The clone shares the version of its text
+        assert_eq!(textarea.clone().version(), textarea.version());
+    }
+
+    #[test]
+    fn test_edits_are_logged_and_invertible() {
+        let mut textarea = text_area("hello world");
+        textarea.move_cursor(CursorMove::Jump(0, 5));
+        textarea.insert_char('!');
+        textarea.delete_next_char();
+        assert_eq!(textarea.text(), "hello!world");
+
+        let edits = textarea.take_edits();
+        assert_eq!(
+            edits,
+            [
+                Edit { at: 5, removed: String::new(), inserted: "!".to_string() },
+                Edit { at: 6, removed: " ".to_string(), inserted: String::new() },
+            ]
+        );
+        assert!(textarea.take_edits().is_empty());
+
+        for edit in edits.iter().rev() {
+            textarea.apply_edit(&edit.inverse());
+        }
+        assert_eq!(textarea.text(), "hello world");
+        assert!(textarea.take_edits().is_empty());
+
+        textarea.set_text("hi");
+        assert_eq!(textarea.cursor(), (0, 2));
+        assert_eq!(textarea.take_edits()[0].removed, "hello world");
+    }
+
+    #[test]
+    fn test_selection_cut_and_replace() {
+        let mut textarea = text_area("one two\nthree");
+        textarea.move_cursor(CursorMove::Jump(0, 4));
+        textarea.start_selection();
+        textarea.move_cursor(CursorMove::Down);
+        assert_eq!(textarea.selection_range(), Some(((0, 4), (1, 4))));
+        assert_eq!(textarea.text_range((0, 4), (1, 4)), "two\nthre");
+
+        textarea.insert_char('X');
+        assert_eq!(textarea.text(), "one Xe");
+        assert!(!textarea.is_selecting());
+
+        textarea.select_all();
+        assert!(textarea.cut());
+        assert!(textarea.is_empty());
+        assert!(!textarea.cut());
+    }
+
+    #[test]
+    fn test_word_movement_and_deletion() {
+        let mut textarea = text_area("foo.bar baz\nqux");
+        textarea.move_cursor(CursorMove::WordForward);
+        assert_eq!(textarea.cursor(), (0, 3));
+        textarea.move_cursor(CursorMove::End);
+        textarea.move_cursor(CursorMove::WordForward);
+        assert_eq!(textarea.cursor(), (1, 0));
+        textarea.move_cursor(CursorMove::WordBack);
+        assert_eq!(textarea.cursor(), (0, 11));
+
+        assert!(textarea.delete_word());
+        assert_eq!(textarea.line(0), "foo.bar ");
+        textarea.move_cursor(CursorMove::Head);
+        assert!(textarea.delete_next_word());
+        assert_eq!(textarea.line(0), ".bar ");
+        textarea.move_cursor(CursorMove::End);
+        assert!(textarea.delete_next_word());
+        assert_eq!(textarea.text(), ".bar qux");
+    }
+
+    #[test]
+    fn test_movement_is_clamped() {
+        let mut textarea = text_area("long line\nab");
+        textarea.move_cursor(CursorMove::Jump(0, 8));
+        textarea.move_cursor(CursorMove::Down);
+        assert_eq!(textarea.cursor(), (1, 2));
+        textarea.move_cursor(CursorMove::Down);
+        assert_eq!(textarea.cursor(), (1, 2));
+        textarea.move_cursor(CursorMove::Forward);
+        assert_eq!(textarea.cursor(), (1, 2));
+        textarea.move_cursor(CursorMove::Jump(usize::MAX, 0));
+        assert_eq!(textarea.cursor(), (1, 0));
+        textarea.move_cursor(CursorMove::Back);
+        assert_eq!(textarea.cursor(), (0, 9));
+    }
+
+    #[test]
+    fn test_extra_cursors_follow_edits() {
+        let mut textarea = text_area("ab\ncd\nef");
+        textarea.add_cursor();
+        textarea.move_cursor(CursorMove::Jump(2, 1));
+        textarea.add_cursor();
+        textarea.move_cursor(CursorMove::Jump(1, 1));
+        assert!(textarea.insert_str("XY"));
+        assert_eq!(textarea.text(), "ab\ncXYd\nef");
+        assert_eq!(textarea.extra_cursors(), vec![((0, 0), None), ((2, 1), None)]);
+
+        // Swapping makes an extra cursor the cursor until swapped back
+        textarea.swap_cursor(1);
+        assert_eq!(textarea.cursor(), (2, 1));
+        textarea.move_cursor(CursorMove::Jump(0, 0));
+        textarea.swap_cursor(1);
+        assert_eq!(textarea.cursor(), (1, 3));
+        textarea.merge_cursors();
+        assert_eq!(textarea.extra_cursor_count(), 1);
+    }
+}
@@ -35,6 +35,38 @@ pub enum MenuAction {
     CountLines,
 }
 
+impl MenuAction {
+    /// Every action, paired with the command that runs it directly
+    pub const COMMANDS: &'static [(MenuAction, &'static str)] = &[
+        (MenuAction::Uppercase, "upcase-region"),
+        (MenuAction::Lowercase, "downcase-region"),
+        (MenuAction::Capitalize, "capitalize-region"),
+        (MenuAction::Reverse, "reverse-region"),
+        (MenuAction::Base64Encode, "base64-encode-region"),
+        (MenuAction::Base64Decode, "base64-decode-region"),
+        (MenuAction::UrlEncode, "url-encode-region"),
+        (MenuAction::UrlDecode, "url-decode-region"),
+        (MenuAction::InsertDate, "insert-date"),
+        (MenuAction::InsertTime, "insert-time"),
+        (MenuAction::InsertDateTime, "insert-date-time"),
+        (MenuAction::InsertLorem, "insert-lorem"),
+        (MenuAction::InsertBullets, "insert-bullets"),
+        (MenuAction::InsertNumbers, "insert-numbers"),
+        (MenuAction::InsertTodo, "insert-todo"),
+        (MenuAction::CountWords, "count-words"),
+        (MenuAction::CountChars, "count-chars"),
+        (MenuAction::CountLines, "count-lines"),
+    ];
+
+    /// Find the action run by a command name
+    pub fn from_command(name: &str) -> Option<MenuAction> {
+        Self::COMMANDS
+            .iter()
+            .find(|(_, command)| *command == name)
+            .map(|(action, _)| action.clone())
+    }
+}
+
 /// A menu item - either an action or a category containing more items
 #[derive(Clone)]
 pub enum MenuItem {
@@ -101,6 +133,67 @@ pub enum MinibufferCallback {
     OpenFile,
     SaveFileAs,
     DeleteFile,
+    /// Switch to one of the given buffer names
+    SwitchToBuffer(Vec<String>),
+    /// Kill one of the given buffer names
+    KillBuffer(Vec<String>),
+    /// Read the string to search for in query-replace
+    QueryReplaceFrom { regex: bool },
+    /// Read the replacement for `from` in query-replace
+    QueryReplaceTo { from: String, regex: bool },
+    /// Read a shell command whose output is inserted at the cursor
+    ShellCommand,
+    /// Read a shell command to run on the region (or buffer)
+    ShellCommandOnRegion { replace: bool },
+    /// Read the encoding to save the file in
+    SetEncoding,
+    /// Read the nesting level to fold every definition at
+    FoldLevel,
+}
+
+impl MinibufferCallback {
+    /// Whether the input is a filesystem path (enables directory navigation)
+    pub fn is_path_input(&self) -> bool {
+        matches!(
+            self,
+            MinibufferCallback::OpenFile
+                | MinibufferCallback::SaveFileAs
+                | MinibufferCallback::DeleteFile
+        )
+    }
+
+    /// Completion candidates for the current input
+    pub fn completions(&self, input: &str) -> Vec<String> {
+        match self {
+            MinibufferCallback::OpenFile
+            | MinibufferCallback::SaveFileAs
+            | MinibufferCallback::DeleteFile => super::Editor::get_path_completions(input),
+            MinibufferCallback::SwitchToBuffer(names) | MinibufferCallback::KillBuffer(names) => {
+                filter_candidates(names, input)
+            }
+            MinibufferCallback::QueryReplaceFrom { .. }
+            | MinibufferCallback::QueryReplaceTo { .. }
+            | MinibufferCallback::ShellCommand
+            | MinibufferCallback::ShellCommandOnRegion { .. }
+            | MinibufferCallback::FoldLevel => Vec::new(),
+            MinibufferCallback::SetEncoding => {
+                let names: Vec<String> = super::Encoding::ALL.iter().map(|e| e.name().to_string()).collect();
+                filter_candidates(&names, input)
+            }
+        }
+    }
+}
+
+/// Filter candidates by case-insensitive substring, prefix matches first
+pub fn filter_candidates(candidates: &[String], input: &str) -> Vec<String> {
+    let query = input.to_lowercase();
+    let (mut prefix, mut rest): (Vec<String>, Vec<String>) = candidates
+        .iter()
+        .filter(|c| c.to_lowercase().contains(&query))
+        .cloned()
+        .partition(|c| c.to_lowercase().starts_with(&query));
+    prefix.append(&mut rest);
+    prefix
 }
 
 /// Defines what kind of response a confirmation step expects
@@ -152,6 +245,8 @@ pub enum SettingValue {
     Bool(bool),
     Number(u16),
     Choice { current: usize, options: Vec<String> },
+    /// Shown but not editable
+    Info(String),
 }
 
 /// The different modes a floating window can be in
@@ -188,6 +283,69 @@ pub enum FloatingMode {
         filtered_commands: Vec<CommandInfo>,
         selected: usize,
     },
+    /// List of open buffers (C-x C-b)
+    BufferList {
+        entries: Vec<super::buffer::BufferListEntry>,
+        selected: usize,
+    },
+    /// Documentation from the language server
+    Hover {
+        lines: Vec<String>,
+        scroll: usize,
+    },
+    /// Locations to jump to (references, definitions, diagnostics)
+    Locations {
+        title: String,
+        entries: Vec<super::lsp::LocationEntry>,
+        selected: usize,
+    },
+    /// Completion candidates for the word before the cursor
+    Completion {
+        items: Vec<crate::lsp::CompletionItem>,
+        selected: usize,
+        /// Position where the completed word starts
+        row: usize,
+        start_col: usize,
+    },
+    /// Output of a shell command (stderr shown after stdout)
+    ShellOutput {
+        command: String,
+        /// Exit code (None when killed by a signal)
+        status: Option<i32>,
+        stdout: Vec<String>,
+        stderr: Vec<String>,
+        scroll: usize,
+    },
+    /// The *Messages* history
+    Messages {
+        scroll: usize,
+    },
+    /// The undo history of the active buffer
+    UndoTree {
+        entries: Vec<super::undo::UndoTreeEntry>,
+        selected: usize,
+    },
+    /// Kill ring entries, newest first
+    KillRing {
+        entries: Vec<String>,
+        selected: usize,
+    },
+    /// Symbols of the buffer, narrowed by name as you type
+    Outline {
+        input: String,
+        cursor_pos: usize,
+        entries: Vec<super::outline::OutlineEntry>,
+        /// Indices of the entries matching `input`
+        filtered: Vec<usize>,
+        selected: usize,
+    },
+    /// Read-only diff; closing it asks `dialog` again
+    Diff {
+        title: String,
+        lines: Vec<String>,
+        scroll: usize,
+        dialog: Box<dyn super::dialogs::ConfirmationDialog>,
+    },
 }
 
 /// A floating window that can display various modes
@@ -289,7 +447,7 @@ impl StatusBarState {
     }
 
     /// Get the display name of the active prefix
-    pub fn prefix_display_name(&self) -> Option<&'static str> {
+    pub fn prefix_display_name(&self) -> Option<&str> {
         self.active_prefix.as_ref().map(|p| p.display_name())
     }
 
@@ -359,6 +517,8 @@ pub struct JumpMode {
     pub last_keystroke_ms: u64,
     /// Timeout in milliseconds before showing labels
     pub timeout_ms: u64,
+    /// Enter puts a cursor at every match instead of showing labels
+    pub add_cursors: bool,
 }
 
 impl JumpMode {
@@ -370,6 +530,7 @@ impl JumpMode {
             targets: Vec::new(),
             last_keystroke_ms: 0,
             timeout_ms: 500, // 500ms default like avy
+            add_cursors: false,
         }
     }
 
@@ -403,11 +564,31 @@ impl JumpMode {
     }
 
     /// Find all matches of pattern in the text and assign labels
-    pub fn find_matches(&mut self, lines: &[String], pattern: &str) {
+    pub fn find_matches<S: AsRef<str>>(&mut self, lines: impl IntoIterator<Item = S>, pattern: &str) {
         self.targets.clear();
+        let positions = Self::match_positions(lines, pattern);
 
+        // Generate labels for all matches
+        let labels = Self::generate_labels(positions.len());
+
+        for (i, (row, col)) in positions.into_iter().enumerate() {
+            match labels.get(i) {
+                Some(label) => {
+                    self.targets.push(JumpTarget {
+                        row,
+                        col,
+                        label: label.clone(),
+                    });
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Positions of all case-insensitive matches of pattern in the text
+    pub fn match_positions<S: AsRef<str>>(lines: impl IntoIterator<Item = S>, pattern: &str) -> Vec<(usize, usize)> {
         match pattern.is_empty() {
-            true => return,
+            true => return Vec::new(),
             false => {}
         }
 
@@ -415,7 +596,8 @@ impl JumpMode {
         let mut positions: Vec<(usize, usize)> = Vec::new();
 
         // Find all case-insensitive matches
-        for (row, line) in lines.iter().enumerate() {
+        for (row, line) in lines.into_iter().enumerate() {
+            let line = line.as_ref();
             let line_lower = line.to_lowercase();
             let mut search_start = 0;
 
@@ -430,22 +612,7 @@ impl JumpMode {
                 }
             }
         }
-
-        // Generate labels for all matches
-        let labels = Self::generate_labels(positions.len());
-
-        for (i, (row, col)) in positions.into_iter().enumerate() {
-            match labels.get(i) {
-                Some(label) => {
-                    self.targets.push(JumpTarget {
-                        row,
-                        col,
-                        label: label.clone(),
-                    });
-                }
-                None => break,
-            }
-        }
+        positions
     }
 
     /// Find target by label prefix (for multi-char labels)
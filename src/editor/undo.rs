@@ -1,92 +1,427 @@
-//! Custom undo/redo system for JAE editor.
+//! Undo/redo history for JAE editor.
 //!
-//! This module provides a simple linear undo/redo system that stores
-//! snapshots of the editor content and cursor position.
+//! The `TextArea` logs every change to the text as an `Edit` (the text
+//! removed and inserted at a char index). The history stores these deltas,
+//! not copies of the buffer, grouped into undo steps:
+//! - consecutive typing coalesces into word-sized steps, broken by pauses,
+//!   cursor jumps and switching between inserting and deleting
+//! - any other command is one step (`save_undo_state` ends the current step)
+//! - `begin_undo_group`/`end_undo_group` make a compound command one step
+//!
+//! Steps form a tree. Undoing and then editing starts a new branch rather
+//! than discarding the undone steps, and the undo-tree view can go back to
+//! any state.
+//!
+//! Histories can be converted to and from JSON, so they can be kept across
+//! sessions (see `undo_history.rs`).
+
+use super::{CursorMove, Edit, FloatingMode, FloatingWindow};
+use ratatui::crossterm::event::KeyCode;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A pause in typing longer than this starts a new undo step
+const TYPING_GROUP_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// What a typed key does, for grouping consecutive typing into undo steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypingKind {
+    /// Inserting a char (Enter and Tab insert whitespace)
+    Insert(char),
+    /// Backspace or Delete
+    Delete,
+}
+
+impl TypingKind {
+    /// What a text input key does (see `is_text_input_key`)
+    pub fn for_key(code: KeyCode) -> Self {
+        match code {
+            KeyCode::Char(c) => Self::Insert(c),
+            KeyCode::Enter => Self::Insert('\n'),
+            KeyCode::Tab => Self::Insert('\t'),
+            _ => Self::Delete,
+        }
+    }
+
+    /// Whether typing `self` right after `previous` may join its undo step.
+    /// Inserting and deleting don't mix, and a word typed after whitespace
+    /// starts a new step.
+    fn continues(self, previous: TypingKind) -> bool {
+        match (previous, self) {
+            (TypingKind::Insert(prev), TypingKind::Insert(c)) => !prev.is_whitespace() || c.is_whitespace(),
+            (TypingKind::Delete, TypingKind::Delete) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Merge `next` into `edit` when it continues it (typing forward, or
+/// deleting backward or forward from the same spot)
+fn merge_edit(edit: &mut Edit, next: &Edit) -> bool {
+    let inserted_len = edit.inserted.chars().count();
+    match (edit.removed.is_empty(), next.removed.is_empty(), next.inserted.is_empty()) {
+        // Typing on after the inserted text
+        (true, true, false) if next.at == edit.at + inserted_len => {
+            edit.inserted.push_str(&next.inserted);
+            true
+        }
+        // Deleting more before (Backspace) or after (Delete) the deleted text
+        (false, false, true) if edit.inserted.is_empty() => {
+            match (next.at + next.removed.chars().count() == edit.at, next.at == edit.at) {
+                (true, _) => {
+                    edit.removed.insert_str(0, &next.removed);
+                    edit.at = next.at;
+                    true
+                }
+                (false, true) => {
+                    edit.removed.push_str(&next.removed);
+                    true
+                }
+                (false, false) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// One undo step: the edits that lead from its parent's state to its own
+#[derive(Debug, Clone)]
+struct UndoNode {
+    parent: Option<usize>,
+    /// Child that redo goes to (the most recently made or visited)
+    redo_child: Option<usize>,
+    edits: Vec<Edit>,
+    /// Cursor before and after the edits
+    cursor_before: (usize, usize),
+    cursor_after: (usize, usize),
+    created: SystemTime,
+}
+
+impl UndoNode {
+    fn root() -> Self {
+        Self {
+            parent: None,
+            redo_child: None,
+            edits: Vec::new(),
+            cursor_before: (0, 0),
+            cursor_after: (0, 0),
+            created: SystemTime::now(),
+        }
+    }
+
+    /// Short description of the step for the undo-tree view
+    fn describe(&self) -> String {
+        let quote = |text: &str| {
+            let mut quoted: String = text.chars().take(24).map(|c| if c == '\n' { '⏎' } else { c }).collect();
+            match text.chars().count() > 24 {
+                true => quoted.push('…'),
+                false => {}
+            }
+            format!("\"{}\"", quoted)
+        };
+        match self.edits.as_slice() {
+            [] => "original".to_string(),
+            [edit] if edit.removed.is_empty() => format!("insert {}", quote(&edit.inserted)),
+            [edit] if edit.inserted.is_empty() => format!("delete {}", quote(&edit.removed)),
+            [edit] => format!("replace {} with {}", quote(&edit.removed), quote(&edit.inserted)),
+            edits => format!("{} edits", edits.len()),
+        }
+    }
 
-use ratatui::style::{Color, Modifier, Style};
-use tui_textarea::{CursorMove, TextArea};
+    fn to_json(&self) -> Value {
+        let edits: Vec<Value> = self.edits.iter().map(|e| json!([e.at, e.removed, e.inserted])).collect();
+        let created = self.created.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        json!({
+            "parent": self.parent,
+            "redo": self.redo_child,
+            "edits": edits,
+            "before": [self.cursor_before.0, self.cursor_before.1],
+            "after": [self.cursor_after.0, self.cursor_after.1],
+            "created": created,
+        })
+    }
 
-/// A snapshot of the editor state at a point in time
+    fn from_json(value: &Value) -> Option<Self> {
+        let index = |v: &Value| v.as_u64().map(|n| n as usize);
+        let optional = |v: &Value| match v {
+            Value::Null => Some(None),
+            v => index(v).map(Some),
+        };
+        let cursor = |v: &Value| Some((index(v.get(0)?)?, index(v.get(1)?)?));
+        let mut edits = Vec::new();
+        for edit in value.get("edits")?.as_array()? {
+            edits.push(Edit {
+                at: index(edit.get(0)?)?,
+                removed: edit.get(1)?.as_str()?.to_string(),
+                inserted: edit.get(2)?.as_str()?.to_string(),
+            });
+        }
+        Some(Self {
+            parent: optional(value.get("parent")?)?,
+            redo_child: optional(value.get("redo")?)?,
+            edits,
+            cursor_before: cursor(value.get("before")?)?,
+            cursor_after: cursor(value.get("after")?)?,
+            created: UNIX_EPOCH + Duration::from_secs(value.get("created")?.as_u64()?),
+        })
+    }
+}
+
+/// Edits to apply (in order) to move one step through the history
+struct UndoStep {
+    edits: Vec<Edit>,
+    cursor: (usize, usize),
+}
+
+/// A line of the undo-tree view
 #[derive(Debug, Clone)]
-pub struct EditorSnapshot {
-    /// The text content as lines
-    pub lines: Vec<String>,
-    /// Cursor position (row, col)
-    pub cursor: (usize, usize),
+pub struct UndoTreeEntry {
+    /// History state this line stands for
+    pub node: usize,
+    /// Branch nesting (0 for the first line of history)
+    pub depth: usize,
+    /// Whether this line starts a branch off an earlier state
+    pub branch: bool,
+    /// Whether the buffer is in this state
+    pub current: bool,
+    pub label: String,
 }
 
-/// Manages undo/redo history with a linear stack
+/// Manages the undo tree of one buffer
 pub struct UndoManager {
-    /// Stack of past states (for undo)
-    undo_stack: Vec<EditorSnapshot>,
-    /// Stack of future states (for redo)
-    redo_stack: Vec<EditorSnapshot>,
-    /// Maximum number of undo states to keep
-    max_history: usize,
+    /// Node 0 is the state the history started from
+    nodes: Vec<UndoNode>,
+    /// Node of the buffer's current state
+    current: usize,
+    /// Whether new edits join the current node (false after a step boundary)
+    open: bool,
+    /// Last typed key, while the current node is a typing step
+    typing: Option<(TypingKind, Instant)>,
+    /// Nesting depth of explicit undo groups
+    group_depth: usize,
+    /// Cursor at the last step boundary, where the next step starts
+    boundary_cursor: Option<(usize, usize)>,
 }
 
 impl UndoManager {
-    /// Create a new undo manager with default history size
+    /// Create an empty history
     pub fn new() -> Self {
         Self {
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            max_history: 100,
+            nodes: vec![UndoNode::root()],
+            current: 0,
+            open: false,
+            typing: None,
+            group_depth: 0,
+            boundary_cursor: None,
         }
     }
 
-    /// Save the current state before an edit operation
-    pub fn save_state(&mut self, snapshot: EditorSnapshot) {
-        // Clear redo stack when new edit is made
-        self.redo_stack.clear();
+    /// Add edits to the current step, starting a new step after a boundary.
+    /// `cursor` is the cursor after the edits.
+    pub fn record(&mut self, edits: Vec<Edit>, cursor: (usize, usize)) {
+        match edits.is_empty() {
+            true => return,
+            false => {}
+        }
+        match self.open {
+            true => {}
+            false => {
+                let node = self.nodes.len();
+                self.nodes.push(UndoNode {
+                    parent: Some(self.current),
+                    redo_child: None,
+                    edits: Vec::new(),
+                    cursor_before: self.boundary_cursor.take().unwrap_or(cursor),
+                    cursor_after: cursor,
+                    created: SystemTime::now(),
+                });
+                self.nodes[self.current].redo_child = Some(node);
+                self.current = node;
+                self.open = true;
+            }
+        }
 
-        // Add to undo stack
-        self.undo_stack.push(snapshot);
+        let node = &mut self.nodes[self.current];
+        for edit in edits {
+            let merged = match node.edits.last_mut() {
+                Some(last) => merge_edit(last, &edit),
+                None => false,
+            };
+            match merged {
+                true => {}
+                false => node.edits.push(edit),
+            }
+        }
+        node.cursor_after = cursor;
+    }
 
-        // Trim if exceeding max history
-        if self.undo_stack.len() > self.max_history {
-            self.undo_stack.remove(0);
+    /// End the current step (unless inside an explicit group).
+    /// `cursor` is where the next step starts.
+    pub fn close_step(&mut self, cursor: (usize, usize)) {
+        match self.group_depth {
+            0 => {
+                self.open = false;
+                self.typing = None;
+                self.boundary_cursor = Some(cursor);
+            }
+            _ => {}
         }
     }
 
-    /// Undo: restore previous state, returns the state to restore to
-    pub fn undo(&mut self, current: EditorSnapshot) -> Option<EditorSnapshot> {
-        if let Some(previous) = self.undo_stack.pop() {
-            // Save current state to redo stack
-            self.redo_stack.push(current);
-            Some(previous)
-        } else {
-            None
+    /// Whether a typed key may join the current step
+    fn continues_typing(&self, kind: TypingKind, cursor: (usize, usize), now: Instant) -> bool {
+        match self.typing {
+            Some((previous, at)) => {
+                self.open
+                    && kind.continues(previous)
+                    && now.duration_since(at) < TYPING_GROUP_TIMEOUT
+                    && self.nodes[self.current].cursor_after == cursor
+            }
+            None => false,
         }
     }
 
-    /// Redo: restore next state, returns the state to restore to
-    pub fn redo(&mut self, current: EditorSnapshot) -> Option<EditorSnapshot> {
-        if let Some(next) = self.redo_stack.pop() {
-            // Save current state to undo stack
-            self.undo_stack.push(current);
-            Some(next)
-        } else {
-            None
+    /// Step back to the parent state
+    fn undo(&mut self) -> Option<UndoStep> {
+        let node = &self.nodes[self.current];
+        let parent = node.parent?;
+        let step = UndoStep {
+            edits: node.edits.iter().rev().map(Edit::inverse).collect(),
+            cursor: node.cursor_before,
+        };
+        self.nodes[parent].redo_child = Some(self.current);
+        self.current = parent;
+        self.open = false;
+        self.typing = None;
+        Some(step)
+    }
+
+    /// Step forward to the redo child
+    fn redo(&mut self) -> Option<UndoStep> {
+        let child = self.nodes[self.current].redo_child?;
+        let node = &self.nodes[child];
+        let step = UndoStep {
+            edits: node.edits.clone(),
+            cursor: node.cursor_after,
+        };
+        self.current = child;
+        self.open = false;
+        self.typing = None;
+        Some(step)
+    }
+
+    /// Steps that lead from the current state to `target`: undo up to the
+    /// closest common ancestor, then redo down the target's branch
+    fn steps_to(&mut self, target: usize) -> Vec<UndoStep> {
+        let mut path = Vec::new();
+        let mut node = Some(target);
+        while let Some(n) = node {
+            path.push(n);
+            node = self.nodes[n].parent;
+        }
+
+        let mut steps = Vec::new();
+        while !path.contains(&self.current) {
+            match self.undo() {
+                Some(step) => steps.push(step),
+                None => break,
+            }
         }
+        let down: Vec<usize> = path.iter().take_while(|&&n| n != self.current).copied().collect();
+        for child in down.into_iter().rev() {
+            self.nodes[self.current].redo_child = Some(child);
+            match self.redo() {
+                Some(step) => steps.push(step),
+                None => break,
+            }
+        }
+        steps
     }
 
     /// Check if undo is available
     #[allow(dead_code)]
     pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+        self.nodes[self.current].parent.is_some()
     }
 
     /// Check if redo is available
     #[allow(dead_code)]
     pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
+        self.nodes[self.current].redo_child.is_some()
+    }
+
+    /// Lines of the undo-tree view: each branch under the state it starts from
+    pub fn tree_entries(&self) -> Vec<UndoTreeEntry> {
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            match node.parent {
+                Some(parent) => children[parent].push(i),
+                None => {}
+            }
+        }
+
+        // Depth-first, iteratively since histories can be long
+        let mut entries = Vec::new();
+        let mut stack = vec![(0, 0, false)];
+        while let Some((node, depth, branch)) = stack.pop() {
+            let age = format_age(self.nodes[node].created.elapsed().unwrap_or_default());
+            entries.push(UndoTreeEntry {
+                node,
+                depth,
+                branch,
+                current: node == self.current,
+                label: format!("#{} {} ({})", node, self.nodes[node].describe(), age),
+            });
+            // The first child continues this line; later ones are branches
+            for (i, &child) in children[node].iter().enumerate().rev() {
+                match i {
+                    0 => stack.push((child, depth, false)),
+                    _ => stack.push((child, depth + 1, true)),
+                }
+            }
+        }
+        entries
+    }
+
+    /// The history as JSON (the open step and typing state are not kept)
+    pub fn to_json(&self) -> Value {
+        let nodes: Vec<Value> = self.nodes.iter().map(UndoNode::to_json).collect();
+        json!({ "current": self.current, "nodes": nodes })
+    }
+
+    /// Read a history written by `to_json`, None if it is malformed
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let mut nodes = Vec::new();
+        for node in value.get("nodes")?.as_array()? {
+            nodes.push(UndoNode::from_json(node)?);
+        }
+        let current = value.get("current")?.as_u64()? as usize;
+
+        // Node 0 is the root and parents come before their children, which
+        // keeps the tree free of cycles
+        let valid = current < nodes.len()
+            && nodes.iter().enumerate().all(|(i, node)| match (i, node.parent) {
+                (0, parent) => parent.is_none(),
+                (_, Some(parent)) => parent < i,
+                (_, None) => false,
+            })
+            && nodes.iter().enumerate().all(|(i, node)| match node.redo_child {
+                Some(child) => nodes.get(child).is_some_and(|c| c.parent == Some(i)),
+                None => true,
+            });
+        match valid {
+            true => Some(Self {
+                nodes,
+                current,
+                ..Self::new()
+            }),
+            false => None,
+        }
     }
 
     /// Clear all history (e.g., when opening a new file)
     pub fn clear(&mut self) {
-        self.undo_stack.clear();
-        self.redo_stack.clear();
+        *self = Self::new();
     }
 }
 
@@ -96,143 +431,253 @@ impl Default for UndoManager {
     }
 }
 
+/// How long ago something happened, briefly
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        0 => "now".to_string(),
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s => format!("{}h ago", s / 3600),
+    }
+}
+
 // ==================== Editor Undo/Redo Methods ====================
 
 use super::Editor;
 
 impl Editor {
-    /// Create a snapshot of the current editor state
-    fn create_snapshot(&self) -> EditorSnapshot {
-        EditorSnapshot {
-            lines: self.textarea.lines().iter().map(|s| s.to_string()).collect(),
-            cursor: self.textarea.cursor(),
-        }
+    /// Move the text area's logged edits into the undo history
+    pub(super) fn sync_undo(&mut self) {
+        let edits = self.textarea.take_edits();
+        // The cursor may have moved on since, so take it from the last edit
+        let cursor = match edits.last() {
+            Some(edit) => self.textarea.char_to_row_col(edit.at + edit.inserted.chars().count()),
+            None => return,
+        };
+        self.undo_manager.record(edits, cursor);
     }
 
-    /// Restore editor state from a snapshot
-    fn restore_snapshot(&mut self, snapshot: EditorSnapshot) {
-        // Recreate textarea with the snapshot's content
-        let mut new_textarea = if snapshot.lines.is_empty() {
-            TextArea::default()
-        } else {
-            TextArea::new(snapshot.lines)
-        };
+    /// Apply a step from the history to the buffer
+    fn apply_undo_step(&mut self, step: UndoStep) {
+        for edit in &step.edits {
+            self.textarea.apply_edit(edit);
+        }
+        self.cancel_mark();
+        let (row, col) = step.cursor;
+        self.move_cursor(CursorMove::Jump(row, col));
+        self.mark_modified();
+    }
 
-        // Copy over style settings
-        new_textarea.set_cursor_style(
-            Style::default()
-                .bg(self.settings.cursor_color)
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        );
-        new_textarea.set_cursor_line_style(Style::default());
-        new_textarea.set_selection_style(
-            Style::default()
-                .bg(self.settings.selection_color)
-                .fg(Color::White),
-        );
-
-        self.textarea = new_textarea;
-
-        // Restore cursor position
-        let (target_row, target_col) = snapshot.cursor;
-        // Move to beginning first
-        self.textarea.move_cursor(CursorMove::Top);
-        self.textarea.move_cursor(CursorMove::Head);
-        // Move to target row
-        for _ in 0..target_row {
-            self.textarea.move_cursor(CursorMove::Down);
-        }
-        // Move to target column
-        for _ in 0..target_col {
-            self.textarea.move_cursor(CursorMove::Forward);
-        }
-    }
-
-    /// Save current state to undo history (call before making changes)
+    /// Start a new undo step (call before making changes)
     pub fn save_undo_state(&mut self) {
-        let snapshot = self.create_snapshot();
-        self.undo_manager.save_state(snapshot);
+        self.sync_undo();
+        self.undo_manager.close_step(self.textarea.cursor());
+    }
+
+    /// Start a new undo step for a typed key unless it continues the
+    /// typing of the current step
+    pub fn save_typing_undo_state(&mut self, kind: TypingKind) {
+        self.sync_undo();
+        let now = Instant::now();
+        let cursor = self.textarea.cursor();
+        match self.undo_manager.continues_typing(kind, cursor, now) {
+            true => {}
+            false => self.undo_manager.close_step(cursor),
+        }
+        self.undo_manager.typing = Some((kind, now));
+    }
+
+    /// Start a compound command: everything until the matching
+    /// `end_undo_group` is undone as one step. Groups nest.
+    pub fn begin_undo_group(&mut self) {
+        self.save_undo_state();
+        self.undo_manager.group_depth += 1;
+    }
+
+    /// End a compound command started with `begin_undo_group`
+    pub fn end_undo_group(&mut self) {
+        self.undo_manager.group_depth = self.undo_manager.group_depth.saturating_sub(1);
+        self.save_undo_state();
+    }
+
+    /// Keep the current step open while a command runs at every cursor.
+    /// Unlike `begin_undo_group` this does not end the step, so typing at
+    /// several cursors still joins the typing before it.
+    pub(super) fn hold_undo_step(&mut self) {
+        self.undo_manager.group_depth += 1;
+    }
+
+    /// End `hold_undo_step`. The step ends with the cursor at `cursor`
+    /// rather than at the last cursor edited.
+    pub(super) fn release_undo_step(&mut self, cursor: (usize, usize)) {
+        self.sync_undo();
+        self.undo_manager.group_depth = self.undo_manager.group_depth.saturating_sub(1);
+        match self.undo_manager.open {
+            true => {
+                let current = self.undo_manager.current;
+                self.undo_manager.nodes[current].cursor_after = cursor;
+            }
+            false => {}
+        }
     }
 
     /// Undo the last edit
     pub fn undo(&mut self) -> bool {
-        let current = self.create_snapshot();
-        if let Some(previous) = self.undo_manager.undo(current) {
-            self.restore_snapshot(previous);
-            true
-        } else {
-            false
+        self.sync_undo();
+        match self.undo_manager.undo() {
+            Some(step) => {
+                self.apply_undo_step(step);
+                true
+            }
+            None => false,
         }
     }
 
     /// Redo the last undo
     pub fn redo(&mut self) -> bool {
-        let current = self.create_snapshot();
-        if let Some(next) = self.undo_manager.redo(current) {
-            self.restore_snapshot(next);
-            true
-        } else {
-            false
+        self.sync_undo();
+        match self.undo_manager.redo() {
+            Some(step) => {
+                self.apply_undo_step(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show the undo tree (C-x u)
+    pub fn view_undo_tree(&mut self) {
+        self.sync_undo();
+        let entries = self.undo_manager.tree_entries();
+        let selected = entries.iter().position(|e| e.current).unwrap_or(0);
+        let height = (entries.len() as u16 + 2).clamp(5, self.settings.floating_window_height.max(5));
+        self.floating_window = Some(FloatingWindow {
+            visible: true,
+            x: 2,
+            y: 1,
+            width: self.settings.floating_window_width.max(60),
+            height,
+            mode: FloatingMode::UndoTree { entries, selected },
+        });
+        self.focus_floating = true;
+    }
+
+    /// Put the buffer in the state of an undo-tree node
+    pub fn undo_tree_goto(&mut self, node: usize) {
+        self.sync_undo();
+        let steps = self.undo_manager.steps_to(node);
+        match steps.is_empty() {
+            true => return,
+            false => {}
+        }
+        for step in steps {
+            self.apply_undo_step(step);
         }
+        self.message(format!("Undo tree: state #{}", node));
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::editor::test_util::editor_with;
 
-    fn make_snapshot(text: &str, cursor: (usize, usize)) -> EditorSnapshot {
-        EditorSnapshot {
-            lines: text.lines().map(String::from).collect(),
-            cursor,
+    fn type_str(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.save_typing_undo_state(TypingKind::Insert(c));
+            editor.textarea.insert_char(c);
         }
     }
 
     #[test]
-    fn test_undo_redo_basic() {
-        let mut manager = UndoManager::new();
-
-        // Save initial state
-        let state1 = make_snapshot("hello", (0, 5));
-        manager.save_state(state1.clone());
-
-        // Save after edit
-        let state2 = make_snapshot("hello world", (0, 11));
-        manager.save_state(state2.clone());
-
-        // Current state
-        let current = make_snapshot("hello world!", (0, 12));
-
-        // Undo should restore state2
-        let restored = manager.undo(current.clone()).unwrap();
-        assert_eq!(restored.lines, vec!["hello world"]);
-
-        // Undo again should restore state1
-        let current2 = make_snapshot("hello world", (0, 11));
-        let restored2 = manager.undo(current2).unwrap();
-        assert_eq!(restored2.lines, vec!["hello"]);
-
-        // Redo should restore state2
-        let current3 = make_snapshot("hello", (0, 5));
-        let restored3 = manager.redo(current3).unwrap();
-        assert_eq!(restored3.lines, vec!["hello world"]);
+    fn test_typing_groups_by_word() {
+        let mut editor = editor_with("");
+        type_str(&mut editor, "hello world");
+        editor.save_typing_undo_state(TypingKind::Delete);
+        editor.textarea.delete_char();
+        editor.save_typing_undo_state(TypingKind::Delete);
+        editor.textarea.delete_char();
+        assert_eq!(editor.textarea.text(), "hello wor");
+
+        // Both deletes, then "world", then "hello "
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "hello world");
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "hello ");
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "");
+        assert!(!editor.undo());
+
+        editor.redo();
+        editor.redo();
+        assert_eq!(editor.textarea.text(), "hello world");
+        assert_eq!(editor.textarea.cursor(), (0, 11));
     }
 
     #[test]
-    fn test_redo_cleared_on_new_edit() {
-        let mut manager = UndoManager::new();
-
-        manager.save_state(make_snapshot("a", (0, 1)));
-        manager.save_state(make_snapshot("ab", (0, 2)));
-
-        let current = make_snapshot("abc", (0, 3));
-        manager.undo(current);
+    fn test_cursor_jump_breaks_typing_group() {
+        let mut editor = editor_with("");
+        type_str(&mut editor, "ab");
+        editor.textarea.move_cursor(CursorMove::Head);
+        type_str(&mut editor, "cd");
+        assert_eq!(editor.textarea.text(), "cdab");
+
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "ab");
+        assert_eq!(editor.textarea.cursor(), (0, 0));
+    }
 
-        assert!(manager.can_redo());
+    #[test]
+    fn test_commands_and_explicit_groups() {
+        let mut editor = editor_with("one two");
+        editor.textarea.move_cursor(CursorMove::End);
+        editor.save_undo_state();
+        editor.textarea.delete_word();
+        editor.save_undo_state();
+        editor.textarea.delete_word();
+        assert_eq!(editor.textarea.text(), "");
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "one ");
+        editor.redo();
+
+        editor.begin_undo_group();
+        editor.save_undo_state();
+        editor.textarea.insert_str("a");
+        editor.begin_undo_group();
+        type_str(&mut editor, " b c");
+        editor.end_undo_group();
+        editor.end_undo_group();
+        type_str(&mut editor, "d");
+        assert_eq!(editor.textarea.text(), "a b cd");
+
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.textarea.text(), "");
+    }
 
-        // New edit should clear redo
-        manager.save_state(make_snapshot("abx", (0, 3)));
-        assert!(!manager.can_redo());
+    #[test]
+    fn test_branches_are_kept() {
+        let mut editor = editor_with("");
+        type_str(&mut editor, "first");
+        editor.undo();
+        type_str(&mut editor, "second");
+
+        // Undo and redo follow the newest branch
+        editor.undo();
+        editor.redo();
+        assert_eq!(editor.textarea.text(), "second");
+
+        // The undone branch can still be reached from the tree
+        let entries = editor.undo_manager.tree_entries();
+        let labels: Vec<&str> = entries.iter().map(|e| e.label.split(" (").next().unwrap()).collect();
+        assert_eq!(labels, ["#0 original", "#1 insert \"first\"", "#2 insert \"second\""]);
+        assert!(entries[2].branch && entries[2].current);
+
+        editor.undo_tree_goto(1);
+        assert_eq!(editor.textarea.text(), "first");
+        editor.undo_tree_goto(0);
+        assert_eq!(editor.textarea.text(), "");
+        editor.redo();
+        assert_eq!(editor.textarea.text(), "first");
     }
 }
@@ -0,0 +1,191 @@
+//! Undo history kept across sessions.
+//!
+//! Saving a file also writes its undo history (see `UndoManager::to_json`)
+//! to a cache directory, together with a hash of the saved text. When the
+//! file is opened again and its text still has that hash, the history is
+//! restored, so undo reaches back past the restart. A file changed by
+//! another program has a different hash and starts with an empty history.
+//!
+//! All histories together are kept under `undo_history_max_mb`; the least
+//! recently saved ones are deleted first. `purge-undo-history` deletes the
+//! histories of files that no longer exist or have changed.
+
+use super::{Editor, FileFormat, UndoManager};
+use crate::fileio;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Version of the history file layout
+const HISTORY_VERSION: u64 = 1;
+
+/// Hash of the text a history belongs to
+fn text_hash(text: &ropey::Rope) -> u64 {
+    fileio::content_hash(text.chunks())
+}
+
+/// Read a history file: the file it belongs to, the hash of that file's
+/// text and the history itself
+fn read_history(history: &Path) -> Option<(PathBuf, u64, Value)> {
+    let value: Value = serde_json::from_slice(&fs::read(history).ok()?).ok()?;
+    match value.get("version")?.as_u64()? == HISTORY_VERSION {
+        true => {}
+        false => return None,
+    }
+    let path = PathBuf::from(value.get("path")?.as_str()?);
+    let hash = value.get("hash")?.as_u64()?;
+    Some((path, hash, value.get("history")?.clone()))
+}
+
+impl Editor {
+    // ==================== Persistent Undo History ====================
+
+    /// Directory undo histories are saved to (None when they are not kept)
+    pub fn undo_history_dir(&self) -> Option<PathBuf> {
+        match (self.settings.undo_history_max_mb, self.settings.undo_history_dir.is_empty()) {
+            (0, _) => None,
+            (_, true) => fileio::default_undo_dir(),
+            (_, false) => Some(Self::expand_path(&self.settings.undo_history_dir)),
+        }
+    }
+
+    /// After saving: write the undo history of the buffer, which now has
+    /// the same text as `path`. A failure is reported; the file is saved anyway.
+    pub fn save_undo_history(&mut self, path: &Path) {
+        let dir = match self.undo_history_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        self.sync_undo();
+        let path = crate::lsp::absolute_path(path);
+        let contents = json!({
+            "version": HISTORY_VERSION,
+            "path": path.display().to_string(),
+            "hash": text_hash(self.textarea.rope()),
+            "history": self.undo_manager.to_json(),
+        });
+        let max_bytes = self.settings.undo_history_max_mb.saturating_mul(1024 * 1024);
+        match fileio::write_undo_history(&dir, &path, contents.to_string().as_bytes(), max_bytes) {
+            Ok(_) => {}
+            Err(e) => self.warn(format!("Could not save undo history of {}: {}", path.display(), e)),
+        }
+    }
+
+    /// After opening a file: restore its saved undo history if the file
+    /// still has the text the history was saved with
+    pub fn restore_undo_history(&mut self, path: &Path) {
+        let dir = match self.undo_history_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let path = crate::lsp::absolute_path(path);
+        let history = match read_history(&fileio::undo_history_path(&dir, &path)) {
+            Some((saved_path, hash, history)) if saved_path == path && hash == text_hash(self.textarea.rope()) => {
+                history
+            }
+            _ => return,
+        };
+        match UndoManager::from_json(&history) {
+            Some(manager) => self.undo_manager = manager,
+            None => self.warn(format!("Ignoring malformed undo history of {}", path.display())),
+        }
+    }
+
+    /// Delete saved undo histories of files that were deleted or changed
+    /// since (M-x purge-undo-history). Returns how many were deleted.
+    pub fn purge_undo_histories(&mut self) -> usize {
+        let dir = match self.undo_history_dir() {
+            Some(dir) => dir,
+            None => {
+                self.message("Undo history is not kept (undo_history_max_mb = 0)");
+                return 0;
+            }
+        };
+
+        let mut purged = 0;
+        for history in fileio::undo_history_files(&dir) {
+            let current = match read_history(&history) {
+                Some((path, hash, _)) => match fs::read(&path) {
+                    Ok(bytes) => fileio::content_hash([FileFormat::decode(&bytes).0.as_str()]) == hash,
+                    Err(_) => false,
+                },
+                None => false,
+            };
+            match current {
+                true => {}
+                false => match fs::remove_file(&history) {
+                    Ok(()) => purged += 1,
+                    Err(e) => self.warn(format!("Could not remove {}: {}", history.display(), e)),
+                },
+            }
+        }
+        self.message(format!("Purged {} stale undo histories", purged));
+        purged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_util::editor_in;
+    use crate::test_util::TempDir;
+
+    fn edit(editor: &mut Editor, text: &str) {
+        editor.save_undo_state();
+        editor.textarea.insert_str(text);
+        editor.mark_modified();
+    }
+
+    #[test]
+    fn test_history_survives_reopening() {
+        let dir = TempDir::new("undo-history-reopen");
+        let path = dir.join("a.txt");
+        fs::write(&path, "one\n").unwrap();
+
+        let mut editor = editor_in(&dir);
+        editor.open_file(&path).unwrap();
+        edit(&mut editor, "two ");
+        edit(&mut editor, "three ");
+        editor.save_file().unwrap();
+
+        let mut editor = editor_in(&dir);
+        editor.open_file(&path).unwrap();
+        assert_eq!(editor.textarea.text(), "two three one");
+        assert!(editor.undo());
+        assert_eq!(editor.textarea.text(), "two one");
+        assert!(editor.undo());
+        assert_eq!(editor.textarea.text(), "one");
+        assert!(!editor.undo());
+        assert!(editor.redo());
+        assert_eq!(editor.textarea.text(), "two one");
+
+        // Changed outside the editor: the history no longer applies
+        fs::write(&path, "other\n").unwrap();
+        let mut editor = editor_in(&dir);
+        editor.open_file(&path).unwrap();
+        assert!(!editor.undo());
+
+        assert_eq!(editor.purge_undo_histories(), 1);
+        assert!(fileio::undo_history_files(&dir.join("undo")).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_history_is_rejected() {
+        let mut manager = UndoManager::new();
+        let insert = crate::editor::Edit {
+            at: 0,
+            removed: String::new(),
+            inserted: "x".to_string(),
+        };
+        manager.record(vec![insert], (0, 1));
+        let mut value = manager.to_json();
+        assert!(UndoManager::from_json(&value).is_some());
+
+        // A node that is its own parent would make undo loop forever
+        value["nodes"][1]["parent"] = json!(1);
+        assert!(UndoManager::from_json(&value).is_none());
+        value["nodes"][1]["parent"] = json!(0);
+        value["current"] = json!(5);
+        assert!(UndoManager::from_json(&value).is_none());
+    }
+}
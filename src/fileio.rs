@@ -0,0 +1,430 @@
+//! Crash-safe file writing, backup files, auto-save recovery files and
+//! detection of changes made by other programs.
+//!
+//! `write_atomic` never leaves a half-written file behind: the new contents
+//! go to a temporary file in the same directory, which is synced and then
+//! renamed over the target. Readers see either the old or the new file.
+//!
+//! Recovery files hold auto-saved copies of modified buffers. They live in
+//! one directory, named after the absolute path of the file they belong to,
+//! and are removed once the buffer is saved or killed.
+//!
+//! Undo history files are named the same way in their own directory, which
+//! is kept under a size cap by deleting the least recently written ones.
+
+use crate::editor::BackupMode;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What a file looked like on disk when it was read or written. Any
+/// difference means another program changed (or replaced) the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    /// Inode number (0 where unavailable); changes when the file is replaced
+    inode: u64,
+}
+
+/// Stamp of the file at `path` (None when it does not exist)
+pub fn disk_stamp(path: &Path) -> Option<DiskStamp> {
+    let meta = fs::metadata(path).ok()?;
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(&meta);
+    #[cfg(not(unix))]
+    let inode = 0;
+    Some(DiskStamp {
+        modified: meta.modified().ok(),
+        len: meta.len(),
+        inode,
+    })
+}
+
+/// Versioned backups kept per file (older ones are deleted)
+const KEPT_BACKUP_VERSIONS: usize = 10;
+
+/// Default directory for versioned backups (`<state dir>/jae/backups`)
+pub fn default_backup_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("jae").join("backups"))
+}
+
+/// Default directory for auto-save recovery files (`<data dir>/jae/autosave`)
+pub fn default_autosave_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("jae").join("autosave"))
+}
+
+/// Default directory for saved undo histories (`<cache dir>/jae/undo`)
+pub fn default_undo_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("jae").join("undo"))
+}
+
+/// Hash of a text given as chunks (64-bit FNV-1a). Unlike `DefaultHasher`
+/// it never changes between builds, so it can be stored in files.
+pub fn content_hash<'a>(chunks: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for chunk in chunks {
+        for byte in chunk.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// The path a file is known by: canonical when the file exists, so that
+/// `a.txt`, `./a.txt` and its absolute path name the same buffer, and
+/// absolute for a new file
+pub fn normalize_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| crate::lsp::absolute_path(path))
+}
+
+/// The file that is actually written: symlinks are followed so saving
+/// through a link updates its target instead of replacing the link
+fn resolve_target(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Replace `path` with `bytes` atomically, keeping the permissions of the
+/// existing file
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let target = resolve_target(path);
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let tmp = dir.join(format!(".{}.jae-tmp-{}", name.to_string_lossy(), std::process::id()));
+
+    let permissions = match fs::metadata(&target) {
+        Ok(meta) => Some(meta.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    // Left over from a crashed save by this process id
+    let _ = fs::remove_file(&tmp);
+    let result = (|| {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+        file.write_all(bytes)?;
+        match &permissions {
+            Some(permissions) => file.set_permissions(permissions.clone())?,
+            None => {}
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, &target)
+    })();
+
+    match result {
+        Ok(()) => {
+            // Make the rename itself durable (best effort, not possible everywhere)
+            match fs::File::open(&dir) {
+                Ok(dir) => {
+                    let _ = dir.sync_all();
+                }
+                Err(_) => {}
+            }
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// Longest backup stem, leaving room for `.~N~`, `#…#` or `.undo` within
+/// the usual 255-byte limit on file names
+const MAX_STEM_LEN: usize = 200;
+
+/// Name of the versioned backups and recovery file of a file: its absolute
+/// path with `/` replaced by `!` (as Emacs does), so files never clash.
+/// Longer paths keep their end, after a hash of the whole stem.
+fn versioned_backup_stem(path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let stem = absolute.to_string_lossy().replace(['/', '\\'], "!");
+    match stem.len() > MAX_STEM_LEN {
+        true => {
+            let mut cut = stem.len() - (MAX_STEM_LEN - 17);
+            while !stem.is_char_boundary(cut) {
+                cut += 1;
+            }
+            format!("{:016x}!{}", content_hash([stem.as_str()]), &stem[cut..])
+        }
+        false => stem,
+    }
+}
+
+/// Version numbers of existing `<stem>.~N~` backups in `dir`, ascending
+fn backup_versions(dir: &Path, stem: &str) -> Vec<u64> {
+    let prefix = format!("{}.~", stem);
+    let mut versions: Vec<u64> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.strip_prefix(&prefix)?.strip_suffix('~')?.parse().ok()
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    versions.sort_unstable();
+    versions
+}
+
+/// Copy the current contents of `path` to a backup before it is overwritten.
+/// Returns the backup path, or None when there was nothing to back up.
+pub fn make_backup(path: &Path, mode: BackupMode, backup_dir: Option<&Path>) -> io::Result<Option<PathBuf>> {
+    let target = resolve_target(path);
+    match target.is_file() {
+        true => {}
+        false => return Ok(None),
+    }
+
+    let backup = match mode {
+        BackupMode::Off => return Ok(None),
+        BackupMode::Simple => {
+            let mut name = target.file_name().unwrap_or_default().to_os_string();
+            name.push("~");
+            target.with_file_name(name)
+        }
+        BackupMode::Versioned => {
+            let dir = backup_dir
+                .map(Path::to_path_buf)
+                .or_else(default_backup_dir)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no backup directory"))?;
+            fs::create_dir_all(&dir)?;
+            let stem = versioned_backup_stem(&target);
+            let versions = backup_versions(&dir, &stem);
+
+            // Drop the oldest versions, keeping room for the new one
+            let excess = (versions.len() + 1).saturating_sub(KEPT_BACKUP_VERSIONS);
+            for version in &versions[..excess] {
+                let _ = fs::remove_file(dir.join(format!("{}.~{}~", stem, version)));
+            }
+
+            let next = versions.last().map_or(1, |v| v + 1);
+            dir.join(format!("{}.~{}~", stem, next))
+        }
+    };
+
+    fs::copy(&target, &backup)?;
+    Ok(Some(backup))
+}
+
+/// Recovery file holding the auto-saved contents of `path` (`#<stem>#`)
+pub fn recovery_file_path(dir: &Path, path: &Path) -> PathBuf {
+    dir.join(format!("#{}#", versioned_backup_stem(path)))
+}
+
+/// The recovery file of `path`, if there is one that was written after the
+/// file itself was last modified
+pub fn newer_recovery_file(dir: &Path, path: &Path) -> Option<PathBuf> {
+    let recovery = recovery_file_path(dir, path);
+    let recovered_at = fs::metadata(&recovery).and_then(|m| m.modified()).ok()?;
+    match fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified_at) if modified_at >= recovered_at => None,
+        _ => Some(recovery),
+    }
+}
+
+/// Write a recovery file, creating the directory when needed
+pub fn write_recovery_file(dir: &Path, path: &Path, bytes: &[u8]) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let recovery = recovery_file_path(dir, path);
+    write_atomic(&recovery, bytes)?;
+    Ok(recovery)
+}
+
+/// Delete the recovery file of `path` (a missing one is fine)
+pub fn remove_recovery_file(dir: &Path, path: &Path) -> io::Result<()> {
+    match fs::remove_file(recovery_file_path(dir, path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Undo history file of `path` (`<stem>.undo`)
+pub fn undo_history_path(dir: &Path, path: &Path) -> PathBuf {
+    dir.join(format!("{}.undo", versioned_backup_stem(path)))
+}
+
+/// Undo history files in `dir`
+pub fn undo_history_files(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "undo"))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Write the undo history of `path`, then delete the least recently written
+/// histories until all of them fit in `max_bytes`. A history that is too big
+/// on its own is not written (and its old one is removed).
+pub fn write_undo_history(dir: &Path, path: &Path, bytes: &[u8], max_bytes: u64) -> io::Result<PathBuf> {
+    let history = undo_history_path(dir, path);
+    match bytes.len() as u64 > max_bytes {
+        true => {
+            let _ = fs::remove_file(&history);
+            return Err(io::Error::other("history is larger than the size limit"));
+        }
+        false => {}
+    }
+    fs::create_dir_all(dir)?;
+    write_atomic(&history, bytes)?;
+
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = undo_history_files(dir)
+        .into_iter()
+        .filter_map(|file| {
+            let meta = fs::metadata(&file).ok()?;
+            Some((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), meta.len(), file))
+        })
+        .collect();
+    files.sort();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    for (_, len, file) in files {
+        match total > max_bytes && file != history {
+            true => {
+                let _ = fs::remove_file(&file);
+                total -= len;
+            }
+            false => {}
+        }
+    }
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_contents() {
+        let dir = TempDir::new("fileio-atomic");
+        let path = dir.join("file.txt");
+
+        write_atomic(&path, b"new file").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new file");
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_mode_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = TempDir::new("fileio-mode");
+        let path = dir.join("script.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+        let link = dir.join("link.sh");
+        symlink(&path, &link).unwrap();
+
+        write_atomic(&link, b"new").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o750);
+    }
+
+    #[test]
+    fn test_backups() {
+        let dir = TempDir::new("fileio-backup");
+        let path = dir.join("notes.txt");
+        let backups = dir.join("backups");
+
+        // Nothing to back up for a new file
+        assert_eq!(make_backup(&path, BackupMode::Simple, None).unwrap(), None);
+
+        fs::write(&path, "v1").unwrap();
+        let simple = make_backup(&path, BackupMode::Simple, None).unwrap().unwrap();
+        assert_eq!(simple, dir.join("notes.txt~"));
+        assert_eq!(fs::read_to_string(&simple).unwrap(), "v1");
+
+        for i in 0..12 {
+            fs::write(&path, format!("v{}", i)).unwrap();
+            make_backup(&path, BackupMode::Versioned, Some(&backups)).unwrap();
+        }
+        let stem = versioned_backup_stem(&path);
+        let versions = backup_versions(&backups, &stem);
+        assert_eq!(versions, (3..=12).collect::<Vec<u64>>());
+        let latest = backups.join(format!("{}.~12~", stem));
+        assert_eq!(fs::read_to_string(latest).unwrap(), "v11");
+    }
+
+    #[test]
+    fn test_long_backup_stems_are_shortened() {
+        assert_eq!(versioned_backup_stem(Path::new("/tmp/a.txt")), "!tmp!a.txt");
+
+        let deep = |top: &str| format!("/{}/{}/notes.txt", top, "é".repeat(150));
+        let stem = versioned_backup_stem(Path::new(&deep("one")));
+        assert!(stem.len() <= MAX_STEM_LEN);
+        assert!(stem.ends_with("éé!notes.txt"));
+        assert_ne!(stem, versioned_backup_stem(Path::new(&deep("two"))));
+    }
+
+    #[test]
+    fn test_recovery_files() {
+        let dir = TempDir::new("fileio-recovery");
+        let path = dir.join("draft.txt");
+        let autosave = dir.join("autosave");
+        fs::write(&path, "saved").unwrap();
+        assert_eq!(newer_recovery_file(&autosave, &path), None);
+
+        let recovery = write_recovery_file(&autosave, &path, b"unsaved").unwrap();
+        let written = fs::metadata(&recovery).unwrap().modified().unwrap();
+        let set_file_time = |time| fs::File::options().write(true).open(&path).unwrap().set_modified(time).unwrap();
+        set_file_time(written - std::time::Duration::from_secs(1));
+        assert_eq!(newer_recovery_file(&autosave, &path), Some(recovery.clone()));
+        assert_eq!(fs::read(&recovery).unwrap(), b"unsaved");
+
+        // Saving the file afterwards makes the recovery file stale
+        set_file_time(written + std::time::Duration::from_secs(1));
+        assert_eq!(newer_recovery_file(&autosave, &path), None);
+
+        remove_recovery_file(&autosave, &path).unwrap();
+        assert!(!recovery.exists());
+        remove_recovery_file(&autosave, &path).unwrap();
+    }
+
+    #[test]
+    fn test_undo_histories_are_capped() {
+        let dir = TempDir::new("fileio-undo");
+        let set_time = |file: &Path, secs| {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            fs::File::options().write(true).open(file).unwrap().set_modified(time).unwrap();
+        };
+
+        let a = write_undo_history(&dir, Path::new("/a.txt"), &[b'a'; 40], 100).unwrap();
+        set_time(&a, 1);
+        let b = write_undo_history(&dir, Path::new("/b.txt"), &[b'b'; 40], 100).unwrap();
+        set_time(&b, 2);
+        assert_eq!(undo_history_files(&dir).len(), 2);
+
+        // The oldest history makes room for the new one
+        let c = write_undo_history(&dir, Path::new("/c.txt"), &[b'c'; 40], 100).unwrap();
+        assert!(!a.exists() && b.exists() && c.exists());
+
+        // A history over the limit replaces nothing
+        assert!(write_undo_history(&dir, Path::new("/c.txt"), &[b'c'; 101], 100).is_err());
+        assert!(!c.exists() && b.exists());
+
+        assert_eq!(content_hash(["ab", "c"]), content_hash(["abc"]));
+        assert_ne!(content_hash(["abc"]), content_hash(["abd"]));
+    }
+}
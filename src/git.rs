@@ -0,0 +1,123 @@
+//! Git integration for gutter markers.
+//!
+//! There is deliberately no git UI: the only thing jae asks git for is the
+//! HEAD version of a file (`git show HEAD:path`), which is diffed against the
+//! buffer to mark added, modified and removed lines.
+
+use crate::editor::FileFormat;
+use similar::{capture_diff_slices_deadline, Algorithm, DiffTag};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a diff may take before it settles for a coarser result
+const DIFF_DEADLINE: Duration = Duration::from_millis(50);
+
+/// How a run of buffer lines differs from HEAD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines were deleted just before `Hunk::start`
+    Removed,
+}
+
+/// A contiguous change against HEAD, in buffer rows.
+/// `end` is exclusive; removals are empty (`start == end`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub start: usize,
+    pub end: usize,
+    pub change: LineChange,
+}
+
+impl Hunk {
+    /// Row the hunk starts on, clamped to the buffer for removals at the end
+    pub fn row(&self, line_count: usize) -> usize {
+        self.start.min(line_count.saturating_sub(1))
+    }
+}
+
+/// Contents of a file as of HEAD, decoded like the file itself, or None
+/// when the file is not tracked (or not in a repository, or git is not
+/// installed)
+pub fn head_text(path: &Path) -> Option<String> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path.file_name()?.to_str()?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("HEAD:./{}", name))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => Some(FileFormat::decode(&output.stdout).0),
+        false => None,
+    }
+}
+
+/// Diff the buffer lines against the HEAD text. Huge or very different
+/// buffers hit `DIFF_DEADLINE` and get larger hunks rather than a stall.
+pub fn diff_hunks(base: &str, lines: &[String]) -> Vec<Hunk> {
+    let old: Vec<&str> = base.lines().collect();
+    let new: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let deadline = Instant::now() + DIFF_DEADLINE;
+
+    capture_diff_slices_deadline(Algorithm::Myers, &old, &new, Some(deadline))
+        .iter()
+        .filter_map(|op| {
+            let (tag, _, range) = op.as_tag_tuple();
+            let change = match tag {
+                DiffTag::Equal => return None,
+                DiffTag::Insert => LineChange::Added,
+                DiffTag::Delete => LineChange::Removed,
+                DiffTag::Replace => LineChange::Modified,
+            };
+            Some(Hunk {
+                start: range.start,
+                end: range.end,
+                change,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_diff_hunks() {
+        let base = "a\nb\nc\nd\ne\n";
+
+        assert_eq!(diff_hunks(base, &lines("a\nb\nc\nd\ne")), vec![]);
+        assert_eq!(
+            diff_hunks(base, &lines("a\nnew\nb\nc\nd\ne")),
+            vec![Hunk { start: 1, end: 2, change: LineChange::Added }]
+        );
+        assert_eq!(
+            diff_hunks(base, &lines("a\nB\nc\nd\ne")),
+            vec![Hunk { start: 1, end: 2, change: LineChange::Modified }]
+        );
+        assert_eq!(
+            diff_hunks(base, &lines("a\nb\nd\ne")),
+            vec![Hunk { start: 2, end: 2, change: LineChange::Removed }]
+        );
+
+        // Removal at the end is shown on the last line
+        let hunks = diff_hunks(base, &lines("a\nb\nc"));
+        assert_eq!(hunks, vec![Hunk { start: 3, end: 3, change: LineChange::Removed }]);
+        assert_eq!(hunks[0].row(3), 2);
+    }
+}
@@ -1,10 +1,15 @@
-use crate::commands::CtrlXPrefix;
+use crate::commands::KeyPrefix;
 use crate::editor::buffer_ops::is_text_input_key;
-use crate::editor::{Editor, JumpMode, JumpPhase};
+use crate::editor::settings::{WINDOW_SIZE_RANGE, WINDOW_SIZE_STEP};
+use crate::editor::{
+    runs_at_each_cursor, Editor, JumpMode, JumpPhase, LineEnding, MenuAction, SearchDirection, TextObjectAction,
+    TypingKind,
+};
+use crate::keymap::KeyLookup;
 use crate::logging;
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tui_textarea::{CursorMove, Input};
+use crate::editor::CursorMove;
 
 /// Get current time in milliseconds since Unix epoch
 fn current_time_ms() -> u64 {
@@ -37,7 +42,7 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
         // Cancel with ESC or C-g
         (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
             editor.jump_mode = None;
-            return JumpModeResult::Cancelled;
+            JumpModeResult::Cancelled
         }
         // Backspace - remove last character from pattern (accept any modifiers)
         (KeyCode::Backspace, _) => {
@@ -46,9 +51,8 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
                     jm.pattern.pop();
                     jm.last_keystroke_ms = current_time_ms();
                     // Update targets based on new pattern
-                    let lines: Vec<String> = editor.textarea.lines().iter().map(|s| s.to_string()).collect();
                     let pattern = jm.pattern.clone();
-                    jm.find_matches(&lines, &pattern);
+                    jm.find_matches(editor.textarea.shown_lines(), &pattern);
                     // If pattern is empty, cancel jump mode
                     match jm.pattern.is_empty() {
                         true => {
@@ -60,7 +64,7 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
                 }
                 None => {}
             }
-            return JumpModeResult::Handled;
+            JumpModeResult::Handled
         }
         // Enter - immediately transition to selecting phase
         (KeyCode::Enter, _) => {
@@ -72,15 +76,25 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
                             editor.jump_mode = None;
                             return JumpModeResult::Cancelled;
                         }
-                        false => {
-                            // Transition to selecting phase
-                            jm.phase = JumpPhase::Selecting;
-                        }
+                        false => match jm.add_cursors {
+                            true => {
+                                // Put a cursor at every match instead
+                                let pattern = jm.pattern.clone();
+                                editor.jump_mode = None;
+                                let positions = JumpMode::match_positions(editor.textarea.shown_lines(), &pattern);
+                                editor.add_cursors_at(&positions, pattern.chars().count());
+                                return JumpModeResult::Jumped;
+                            }
+                            false => {
+                                // Transition to selecting phase
+                                jm.phase = JumpPhase::Selecting;
+                            }
+                        },
                     }
                 }
                 None => {}
             }
-            return JumpModeResult::Handled;
+            JumpModeResult::Handled
         }
         // Regular character input
         (KeyCode::Char(c), KeyModifiers::NONE) | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
@@ -91,13 +105,12 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
                             jm.pattern.push(c);
                             jm.last_keystroke_ms = current_time_ms();
                             // Update targets based on new pattern
-                            let lines: Vec<String> = editor.textarea.lines().iter().map(|s| s.to_string()).collect();
                             let pattern = jm.pattern.clone();
-                            jm.find_matches(&lines, &pattern);
+                            jm.find_matches(editor.textarea.shown_lines(), &pattern);
                         }
                         None => {}
                     }
-                    return JumpModeResult::Handled;
+                    JumpModeResult::Handled
                 }
                 JumpPhase::Selecting => {
                     // Try to match against labels
@@ -109,8 +122,8 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
                             let col = t.col;
                             editor.jump_mode = None;
                             // Move cursor to target position
-                            editor.textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
-                            return JumpModeResult::Jumped;
+                            editor.textarea.move_cursor(CursorMove::Jump(row, col));
+                            JumpModeResult::Jumped
                         }
                         None => {
                             // Check if this is a prefix for multi-char labels
@@ -133,12 +146,12 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
                                         }
                                         None => {}
                                     }
-                                    return JumpModeResult::Handled;
+                                    JumpModeResult::Handled
                                 }
                                 false => {
                                     // Invalid label, cancel
                                     editor.jump_mode = None;
-                                    return JumpModeResult::Cancelled;
+                                    JumpModeResult::Cancelled
                                 }
                             }
                         }
@@ -149,9 +162,56 @@ fn handle_jump_mode_input(editor: &mut Editor, key: &KeyEvent) -> JumpModeResult
         _ => {
             // Unknown key in jump mode, cancel
             editor.jump_mode = None;
-            return JumpModeResult::Cancelled;
+            JumpModeResult::Cancelled
+        }
+    }
+}
+
+/// Result of handling incremental search input
+enum ISearchResult {
+    /// Search handled the key, continue running
+    Handled,
+    /// Search not active
+    NotActive,
+    /// Search finished by a non-search key, which still needs normal handling
+    Exited,
+}
+
+/// Handle input when incremental search is active
+fn handle_isearch_input(editor: &mut Editor, key: &KeyEvent) -> ISearchResult {
+    match editor.isearch {
+        Some(_) => {}
+        None => return ISearchResult::NotActive,
+    }
+
+    match (key.code, key.modifiers) {
+        (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+            editor.isearch_repeat(SearchDirection::Forward);
+        }
+        (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+            editor.isearch_repeat(SearchDirection::Backward);
+        }
+        // Cancel and go back to where the search started
+        (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+            editor.isearch_abort();
+        }
+        // Accept the current match
+        (KeyCode::Enter, _) => {
+            editor.isearch_exit();
+        }
+        (KeyCode::Backspace, _) => {
+            editor.isearch_pop_char();
+        }
+        (KeyCode::Char(c), KeyModifiers::NONE) | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
+            editor.isearch_push_char(c);
+        }
+        // Any other key ends the search and is then handled as usual
+        _ => {
+            editor.isearch_exit();
+            return ISearchResult::Exited;
         }
     }
+    ISearchResult::Handled
 }
 
 /// Check if jump mode should transition from typing to selecting based on timeout
@@ -162,7 +222,8 @@ pub fn check_jump_mode_timeout(editor: &mut Editor) {
                 JumpPhase::Typing => {
                     let now = current_time_ms();
                     let elapsed = now.saturating_sub(jm.last_keystroke_ms);
-                    match elapsed >= jm.timeout_ms && !jm.pattern.is_empty() && !jm.targets.is_empty() {
+                    // When adding cursors, Enter ends the pattern instead
+                    match elapsed >= jm.timeout_ms && !jm.pattern.is_empty() && !jm.targets.is_empty() && !jm.add_cursors {
                         true => {
                             jm.phase = JumpPhase::Selecting;
                         }
@@ -349,6 +410,11 @@ pub fn handle_input(editor: &mut Editor, key: KeyEvent) -> bool {
         log::debug!("Key input: {:?} modifiers: {:?}", key.code, key.modifiers);
     }
 
+    // Any key press dismisses the previous informational message
+    editor.messages.key_pressed();
+    // Consecutive kills and M-y depend on what the previous key did
+    editor.kill_ring.next_command();
+
     // C-x C-q: Ultimate force quit - bypasses everything, exits immediately
     // This is the "kill switch" that always works regardless of editor state
     if editor.last_key == Some((KeyCode::Char('x'), KeyModifiers::CONTROL))
@@ -382,6 +448,12 @@ pub fn handle_input(editor: &mut Editor, key: KeyEvent) -> bool {
         }
     }
 
+    // Handle incremental search input
+    match handle_isearch_input(editor, &key) {
+        ISearchResult::Handled => return true,
+        ISearchResult::Exited | ISearchResult::NotActive => {}
+    }
+
     // Track if we had a floating window before should_quit
     let had_floating = editor.floating_window.is_some();
 
@@ -448,208 +520,57 @@ pub fn handle_input(editor: &mut Editor, key: KeyEvent) -> bool {
         return true;
     }
 
-    // Handle C-x prefix activation
-    if key.code == KeyCode::Char('x') && key.modifiers == KeyModifiers::CONTROL {
-        editor.status_bar.activate_prefix(Box::new(CtrlXPrefix));
-        editor.last_key = Some((KeyCode::Char('x'), KeyModifiers::CONTROL));
-        return true;
-    }
-
-    // Clear last_key for any key that isn't C-SPC
-    if !matches!((key.code, key.modifiers), (KeyCode::Char(' '), KeyModifiers::CONTROL)) {
+    // C-g and ESC are handled by should_quit above - don't pass them to the textarea
+    if matches!((key.code, key.modifiers), (KeyCode::Char('g'), KeyModifiers::CONTROL) | (KeyCode::Esc, _)) {
         editor.last_key = None;
+        return true;
     }
 
-    // Handle Emacs keybindings
-    match (key.code, key.modifiers) {
-        // C-g and ESC are handled by should_quit above - don't pass to default
-        // This prevents them from being sent to textarea.input_without_shortcuts
-        (KeyCode::Char('g'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
-            // Already handled by should_quit, just return
-        }
-
-        // Basic movement - these don't reset kill sequence
-        (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
-            editor.move_cursor(CursorMove::Forward);
-        }
-        (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
-            editor.move_cursor(CursorMove::Back);
-        }
-        (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
-            if editor.is_at_last_line() {
-                // At the end of document, insert a newline
-                editor.save_undo_state();
-                editor.textarea.move_cursor(CursorMove::End);
-                editor.textarea.insert_newline();
-                // Text input resets selection
-                editor.mark_modified();
-            } else {
-                // Normal case: just move down
-                editor.move_cursor(CursorMove::Down);
-            }
-        }
-        (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
-            editor.move_cursor(CursorMove::Up);
-        }
-        (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
-            editor.move_cursor(CursorMove::Head);
-        }
-        (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
-            if logging::log_keys() {
-                log::debug!("C-e pressed, calling move_cursor(End)");
-            }
-            editor.move_cursor(CursorMove::End);
-        }
-
-        // Page up/down
-        (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
-            editor.page_up();
-        }
-        (KeyCode::Char('u'), KeyModifiers::ALT) => {
-            editor.page_down();
-        }
-
-        // Recenter (C-l) - cycles through center/top/bottom
-        (KeyCode::Char('l'), KeyModifiers::CONTROL) => {
-            editor.recenter();
-        }
-
-        // Word movement - these don't reset kill sequence
-        (KeyCode::Char('f'), KeyModifiers::ALT) => {
-            editor.move_word_forward();
-        }
-        (KeyCode::Char('b'), KeyModifiers::ALT) => {
-            if logging::log_keys() {
-                log::debug!("M-b pressed, calling move_word_backward");
-            }
-            editor.move_word_backward();
+    // Switch focus to floating window with Shift+Tab
+    if key.code == KeyCode::BackTab {
+        if editor.floating_window.is_some() && !editor.focus_floating {
+            editor.focus_floating = true;
         }
+        return true;
+    }
 
-        // Floating window
-        (KeyCode::Char('q'), KeyModifiers::ALT) => {
-            editor.toggle_floating_window();
-        }
-        // Settings menu
-        (KeyCode::Char('?'), KeyModifiers::ALT) => {
-            editor.open_settings_menu();
-        }
-        // M-x command palette
-        (KeyCode::Char('x'), KeyModifiers::ALT) => {
-            editor.open_command_palette();
-        }
-        // Switch focus to floating window with Shift+Tab
-        (KeyCode::BackTab, _) => {
-            if editor.floating_window.is_some() && !editor.focus_floating {
-                editor.focus_floating = true;
-            }
+    // Resolve the key through the keymap (defaults plus config overrides)
+    match editor.keymap.lookup(&key) {
+        Some(KeyLookup::Prefix(prefix)) => {
+            let prefix = prefix.clone();
+            let trigger = prefix.trigger();
+            editor.last_key = Some((trigger.key, trigger.modifiers));
+            editor.status_bar.activate_prefix(Box::new(prefix));
+            return true;
         }
-
-        // Selection and mark - doesn't reset kill sequence
-        (KeyCode::Char(' '), KeyModifiers::CONTROL) => {
-            if logging::log_keys() || logging::log_selection() {
-                log::debug!("C-SPC pressed, calling set_mark");
-            }
-            editor.set_mark();
-            if logging::log_selection() {
-                log::debug!(
-                    "After set_mark: mark={:?}, is_selecting={}, selection_range={:?}",
-                    editor.mark,
-                    editor.textarea.is_selecting(),
-                    editor.textarea.selection_range()
-                );
+        Some(KeyLookup::Command(command)) => {
+            // set-mark keeps last_key to detect C-SPC C-SPC
+            match command {
+                "set-mark" => {}
+                _ => editor.last_key = None,
             }
-            // Store this key for detecting C-SPC C-SPC, but only if we didn't
-            // just toggle off a selection (set_mark clears last_key in that case)
-            if editor.last_key.is_some() || editor.mark.is_active() {
-                editor.last_key = Some((KeyCode::Char(' '), KeyModifiers::CONTROL));
-            }
-        }
-        // C-x prefix for commands
-        (KeyCode::Char('x'), KeyModifiers::CONTROL) => {
-            // Store for potential C-x sequences
-            editor.last_key = Some((KeyCode::Char('x'), KeyModifiers::CONTROL));
-        }
-
-        // Kill and yank operations
-        // All these operations are self-contained: they handle undo state and
-        // modification tracking internally. See buffer_ops.rs for the pattern.
-        (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
-            editor.cut_region();
-        }
-        (KeyCode::Char('w'), KeyModifiers::ALT) => {
-            editor.copy_region();
-        }
-        (KeyCode::Char('y'), KeyModifiers::CONTROL) => {
-            editor.paste();
-        }
-        (KeyCode::Char('k'), KeyModifiers::CONTROL) => {
-            editor.cut_to_end_of_line();
-        }
-
-        // Undo (C-z)
-        (KeyCode::Char('z'), mods) if mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::SHIFT) => {
-            editor.undo();
-        }
-
-        // Redo (M-z)
-        (KeyCode::Char('z'), mods) if mods.contains(KeyModifiers::ALT) => {
-            editor.redo();
-        }
-
-        // Syntax-aware selection expansion (Alt-o, like Helix)
-        (KeyCode::Char('o'), KeyModifiers::ALT) => {
-            editor.expand_selection();
-        }
-
-        // Syntax-aware selection shrink (Alt-i, like Helix)
-        (KeyCode::Char('i'), KeyModifiers::ALT) => {
-            editor.shrink_selection();
-        }
-
-        // Avy-like jump mode (M-j for "jump")
-        (KeyCode::Char('j'), mods) if mods.contains(KeyModifiers::ALT) => {
-            // Ensure clean state
-            editor.jump_mode = None;
-            let mut jm = JumpMode::new();
-            jm.last_keystroke_ms = current_time_ms();
-            editor.jump_mode = Some(jm);
-            log::debug!("Jump mode activated");
-        }
-
-        // Delete operations (self-contained)
-        (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
-            editor.delete_char_forward();
-        }
-        (KeyCode::Char('h'), KeyModifiers::CONTROL) => {
-            editor.delete_char_backward();
-        }
-        (KeyCode::Char('d'), mods) if mods.contains(KeyModifiers::ALT) => {
-            editor.delete_word_forward();
-        }
-        (KeyCode::Backspace, mods) if mods.contains(KeyModifiers::ALT) => {
-            editor.delete_word_backward();
+            return execute_command(editor, command);
         }
+        None => {}
+    }
 
-        // Default: pass through to textarea (without tui-textarea's default shortcuts)
-        _ => {
-            // Use the is_text_input_key helper to determine if this key actually
-            // modifies the buffer. Control/Alt+letter that aren't handled above
-            // are ignored (e.g., C-t does nothing, shouldn't mark modified).
-            // See buffer_ops.rs for documentation.
-            if is_text_input_key(key.code, key.modifiers) {
-                editor.save_undo_state();
-                let event = ratatui::crossterm::event::Event::Key(key);
-                let input: Input = event.into();
-                editor.textarea.input_without_shortcuts(input);
-                editor.mark_modified();
-            } else {
-                // Non-text input key that we don't handle - just pass through
-                // but don't save undo or mark modified
-                let event = ratatui::crossterm::event::Event::Key(key);
-                let input: Input = event.into();
-                editor.textarea.input_without_shortcuts(input);
-            }
-        }
+    editor.last_key = None;
+
+    // Default: pass through to textarea (plain typing keys only)
+    // Use the is_text_input_key helper to determine if this key actually
+    // modifies the buffer. Control/Alt+letter that aren't bound in the keymap
+    // are ignored (e.g., C-v does nothing, shouldn't mark modified).
+    // See buffer_ops.rs for documentation.
+    if is_text_input_key(key.code, key.modifiers) {
+        editor.save_typing_undo_state(TypingKind::for_key(key.code));
+        editor.at_each_cursor(|editor| {
+            editor.textarea.input(key);
+        });
+        editor.mark_modified();
+    } else {
+        // Non-text input key that we don't handle - just pass through
+        // but don't save undo or mark modified
+        editor.textarea.input(key);
     }
 
     true
@@ -665,17 +586,13 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                         editor.floating_window = None;
                         editor.focus_floating = false;
                     }
-                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
-                        if state.selected > 0 {
-                            state.selected -= 1;
-                            editor.update_menu_preview();
-                        }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) if state.selected > 0 => {
+                        state.selected -= 1;
+                        editor.update_menu_preview();
                     }
-                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
-                        if state.selected < state.items.len() - 1 {
-                            state.selected += 1;
-                            editor.update_menu_preview();
-                        }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if state.selected < state.items.len() - 1 => {
+                        state.selected += 1;
+                        editor.update_menu_preview();
                     }
                     // Enter category with C-l
                     (KeyCode::Char('l'), KeyModifiers::CONTROL) | (KeyCode::Enter, _) => {
@@ -688,7 +605,8 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                                 let action_to_apply = action;
                                 editor.floating_window = None;
                                 editor.focus_floating = false;
-                                editor.apply_menu_option(action_to_apply);
+                                editor.save_undo_state();
+                                editor.at_each_cursor(|editor| editor.apply_menu_option(action_to_apply.clone()));
                                 return true;
                             }
                             None => {}
@@ -713,15 +631,17 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                         editor.floating_window = None;
                         editor.focus_floating = false;
                     }
-                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
-                        if *selected > 0 {
-                            *selected -= 1;
-                        }
+                    // Persist the current settings to the config file
+                    (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                        let _ = fw; // End borrow
+                        editor.save_settings();
+                        return true;
                     }
-                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
-                        if *selected < items.len() - 1 {
-                            *selected += 1;
-                        }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) if *selected > 0 => {
+                        *selected -= 1;
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *selected < items.len() - 1 => {
+                        *selected += 1;
                     }
                     // Toggle boolean or adjust number values
                     (KeyCode::Enter, _) | (KeyCode::Char(' '), _) => {
@@ -748,9 +668,9 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                                 let name = item.name.clone();
                                 match &mut item.value {
                                     crate::editor::SettingValue::Number(n) => {
-                                        match *n > 10 {
+                                        match *n > *WINDOW_SIZE_RANGE.start() {
                                             true => {
-                                                *n -= 5;
+                                                *n = (*n - WINDOW_SIZE_STEP).max(*WINDOW_SIZE_RANGE.start());
                                                 match name.as_str() {
                                                     "Window Width" => editor.settings.floating_window_width = *n,
                                                     "Window Height" => editor.settings.floating_window_height = *n,
@@ -772,30 +692,19 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                             None => {}
                         }
                         // Extract the values we need before calling editor methods
-                        let update_info = items.get(selected_idx).and_then(|item| {
-                            match (&item.value, item.name.as_str()) {
-                                (crate::editor::SettingValue::Choice { current, .. }, "Cursor Color") => {
-                                    Some((true, *current))
-                                }
-                                (crate::editor::SettingValue::Choice { current, .. }, "Selection Color") => {
-                                    Some((false, *current))
-                                }
-                                _ => None
+                        let update_info = items.get(selected_idx).and_then(|item| match &item.value {
+                            crate::editor::SettingValue::Choice { current, .. } => {
+                                Some((item.name.clone(), *current))
                             }
+                            _ => None,
                         });
 
                         // Drop the mutable reference to fw before calling editor methods
                         let _ = fw; // End borrow
 
-                        // Now update colors
+                        // Now apply the choice (colors, line numbers)
                         match update_info {
-                            Some((is_cursor, index)) => {
-                                match is_cursor {
-                                    true => editor.settings.cursor_color = editor.settings.index_to_color(index, false),
-                                    false => editor.settings.selection_color = editor.settings.index_to_color(index, true),
-                                }
-                                editor.update_textarea_colors();
-                            }
+                            Some((name, index)) => editor.apply_choice_setting(&name, index),
                             None => {}
                         }
 
@@ -808,9 +717,9 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                                 let name = item.name.clone();
                                 match &mut item.value {
                                     crate::editor::SettingValue::Number(n) => {
-                                        match *n < 100 {
+                                        match *n < *WINDOW_SIZE_RANGE.end() {
                                             true => {
-                                                *n += 5;
+                                                *n = (*n + WINDOW_SIZE_STEP).min(*WINDOW_SIZE_RANGE.end());
                                                 match name.as_str() {
                                                     "Window Width" => editor.settings.floating_window_width = *n,
                                                     "Window Height" => editor.settings.floating_window_height = *n,
@@ -832,30 +741,19 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                             None => {}
                         }
                         // Extract the values we need before calling editor methods
-                        let update_info = items.get(selected_idx).and_then(|item| {
-                            match (&item.value, item.name.as_str()) {
-                                (crate::editor::SettingValue::Choice { current, .. }, "Cursor Color") => {
-                                    Some((true, *current))
-                                }
-                                (crate::editor::SettingValue::Choice { current, .. }, "Selection Color") => {
-                                    Some((false, *current))
-                                }
-                                _ => None
+                        let update_info = items.get(selected_idx).and_then(|item| match &item.value {
+                            crate::editor::SettingValue::Choice { current, .. } => {
+                                Some((item.name.clone(), *current))
                             }
+                            _ => None,
                         });
 
                         // Drop the mutable reference to fw before calling editor methods
                         let _ = fw; // End borrow
 
-                        // Now update colors
+                        // Now apply the choice (colors, line numbers)
                         match update_info {
-                            Some((is_cursor, index)) => {
-                                match is_cursor {
-                                    true => editor.settings.cursor_color = editor.settings.index_to_color(index, false),
-                                    false => editor.settings.selection_color = editor.settings.index_to_color(index, true),
-                                }
-                                editor.update_textarea_colors();
-                            }
+                            Some((name, index)) => editor.apply_choice_setting(&name, index),
                             None => {}
                         }
 
@@ -870,9 +768,11 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                 cursor_pos,
                 completions,
                 selected_completion,
+                callback,
                 ..
             } => {
                 log::debug!("Minibuffer key: {:?}", key.code);
+                let callback = callback.clone();
 
                 // Helper: check if path ends with / (is a directory)
                 let is_directory = |path: &str| path.ends_with('/');
@@ -881,24 +781,19 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                 if matches!((key.code, key.modifiers), (KeyCode::Tab, _)) {
                     if !completions.is_empty() {
                         // If we have a selected completion that's a directory, enter it
-                        match *selected_completion {
-                            Some(idx) => match completions.get(idx) {
-                                Some(comp) => {
-                                    if is_directory(comp) {
-                                        // Enter the directory
-                                        *input = comp.clone();
-                                        *cursor_pos = input.chars().count();
-                                        *completions = crate::editor::Editor::get_path_completions(input);
-                                        *selected_completion = match completions.is_empty() {
-                                            true => None,
-                                            false => Some(0),
-                                        };
-                                        return true;
-                                    }
-                                }
-                                None => {}
-                            },
-                            None => {}
+                        match selected_completion.and_then(|idx| completions.get(idx)) {
+                            Some(comp) if callback.is_path_input() && is_directory(comp) => {
+                                // Enter the directory
+                                *input = comp.clone();
+                                *cursor_pos = input.chars().count();
+                                *completions = callback.completions(input);
+                                *selected_completion = match completions.is_empty() {
+                                    true => None,
+                                    false => Some(0),
+                                };
+                                return true;
+                            }
+                            _ => {}
                         }
 
                         // Otherwise, cycle to next completion and update input
@@ -920,7 +815,7 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                         }
                     } else {
                         // No completions, refresh them
-                        *completions = crate::editor::Editor::get_path_completions(input);
+                        *completions = callback.completions(input);
                         if !completions.is_empty() {
                             *selected_completion = Some(0);
                             // Update input to first completion
@@ -985,9 +880,9 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                 // Handle Enter - enter directory or open file
                 if matches!((key.code, key.modifiers), (KeyCode::Enter, _)) {
                     // Check if current input is a directory
-                    if is_directory(input) {
+                    if callback.is_path_input() && is_directory(input) {
                         // Already in a directory, refresh completions to show contents
-                        *completions = crate::editor::Editor::get_path_completions(input);
+                        *completions = callback.completions(input);
                         *selected_completion = match completions.is_empty() {
                             true => None,
                             false => Some(0),
@@ -1005,11 +900,11 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
 
                     // Check if input path is a directory (without trailing /)
                     let expanded = crate::editor::Editor::expand_path(input);
-                    if expanded.is_dir() {
+                    if callback.is_path_input() && expanded.is_dir() {
                         // Add trailing / and enter directory
                         input.push('/');
                         *cursor_pos = input.chars().count();
-                        *completions = crate::editor::Editor::get_path_completions(input);
+                        *completions = callback.completions(input);
                         *selected_completion = if completions.is_empty() { None } else { Some(0) };
                         return true;
                     }
@@ -1045,7 +940,7 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                     }
                     MinibufferKeyResult::Handled => {
                         // Refresh completions after any input change
-                        *completions = crate::editor::Editor::get_path_completions(input);
+                        *completions = callback.completions(input);
                         *selected_completion = if completions.is_empty() { None } else { Some(0) };
                     }
                     MinibufferKeyResult::NotHandled => {}
@@ -1173,62 +1068,336 @@ fn handle_floating_input(editor: &mut Editor, key: KeyEvent) -> bool {
                 }
             }
 
-            crate::editor::FloatingMode::Confirm {
-                steps,
-                current_index,
-                ..
+            crate::editor::FloatingMode::Outline {
+                input,
+                cursor_pos,
+                entries,
+                filtered,
+                selected,
             } => {
-                // Check what type of response we're expecting
-                let response_type = steps.get(*current_index)
-                    .map(|s| s.response_type.clone());
-                let total_steps = steps.len();
-                let idx = *current_index;
-
-                // Determine what action to take based on response type and key
-                let action = match response_type {
-                    Some(crate::editor::ResponseType::Binary) => {
-                        match (key.code, key.modifiers) {
-                            (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
-                                Some(ConfirmAction::Cancel)
-                            }
-                            (KeyCode::Char('y'), _) => Some(ConfirmAction::Respond("y".to_string())),
-                            (KeyCode::Char('n'), _) => Some(ConfirmAction::Respond("n".to_string())),
-                            _ => None,
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                        if *selected > 0 {
+                            *selected -= 1;
                         }
                     }
-                    Some(crate::editor::ResponseType::Choice(options)) => {
-                        match (key.code, key.modifiers) {
-                            (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
-                                Some(ConfirmAction::Cancel)
-                            }
-                            (KeyCode::Char(c), _) => {
-                                if options.iter().any(|(k, _)| *k == c) {
-                                    Some(ConfirmAction::Respond(c.to_string()))
-                                } else {
-                                    None
-                                }
-                            }
-                            _ => None,
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                        if *selected + 1 < filtered.len() {
+                            *selected += 1;
                         }
                     }
-                    None => None,
-                };
-
-                // Drop the borrow before processing the action
-                let _ = fw; // End borrow
-
-                // Now process the action with full access to editor
-                match action {
-                    Some(action) => process_confirm_action(editor, action, idx, total_steps),
-                    None => {}
+                    // Jump to the selected symbol
+                    (KeyCode::Enter, _) => {
+                        let entry = filtered.get(*selected).map(|i| entries[*i].clone());
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        match entry {
+                            Some(entry) => editor.goto_outline_entry(&entry),
+                            None => {}
+                        }
+                    }
+                    // Narrow the symbols by name
+                    _ => match handle_string_edit_key(&key, input, cursor_pos) {
+                        MinibufferKeyResult::Cancel => {
+                            editor.floating_window = None;
+                            editor.focus_floating = false;
+                        }
+                        MinibufferKeyResult::Handled => {
+                            *filtered = crate::editor::outline::filter_outline(entries, input);
+                            *selected = 0;
+                        }
+                        MinibufferKeyResult::Execute | MinibufferKeyResult::NotHandled => {}
+                    },
                 }
             }
-        },
-        None => {}
-    }
-
-    true
-}
+
+            crate::editor::FloatingMode::BufferList { entries, selected } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) if *selected > 0 => {
+                        *selected -= 1;
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *selected + 1 < entries.len() => {
+                        *selected += 1;
+                    }
+                    // Switch to the selected buffer (index 0 is the active buffer)
+                    (KeyCode::Enter, _) => {
+                        let idx = *selected;
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        match idx {
+                            0 => {}
+                            i => editor.switch_to_buffer_index(i - 1),
+                        }
+                    }
+                    // Kill the selected buffer
+                    (KeyCode::Char('k'), KeyModifiers::NONE) => {
+                        let name = entries.get(*selected).map(|e| e.name.clone());
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        match name {
+                            Some(name) => editor.request_kill_buffer(&name),
+                            None => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::Hover { lines, scroll } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                        *scroll = scroll.saturating_sub(1);
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                        if *scroll + 1 < lines.len() {
+                            *scroll += 1;
+                        }
+                    }
+                    // Any other key dismisses the documentation
+                    _ => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                }
+            }
+
+            crate::editor::FloatingMode::ShellOutput { stdout, stderr, scroll, .. } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                        *scroll = scroll.saturating_sub(1);
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *scroll + 1 < stdout.len() + stderr.len() => {
+                        *scroll += 1;
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::Messages { scroll } => {
+                let count = editor.messages.history().len();
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                        *scroll = scroll.saturating_sub(1);
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *scroll + 1 < count => {
+                        *scroll += 1;
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::Diff { lines, scroll, .. } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        // Back to the question the diff was opened from
+                        editor.close_diff();
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                        *scroll = scroll.saturating_sub(1);
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *scroll + 1 < lines.len() => {
+                        *scroll += 1;
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::Locations { entries, selected, .. } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) if *selected > 0 => {
+                        *selected -= 1;
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *selected + 1 < entries.len() => {
+                        *selected += 1;
+                    }
+                    // Jump to the selected location
+                    (KeyCode::Enter, _) => {
+                        let entry = entries.get(*selected).cloned();
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        match entry {
+                            Some(entry) => editor.jump_to_location(&entry.path, entry.row, entry.col),
+                            None => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::UndoTree { entries, selected } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) if *selected > 0 => {
+                        *selected -= 1;
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *selected + 1 < entries.len() => {
+                        *selected += 1;
+                    }
+                    // Put the buffer in the selected state
+                    (KeyCode::Enter, _) => {
+                        let node = entries.get(*selected).map(|e| e.node);
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        match node {
+                            Some(node) => editor.undo_tree_goto(node),
+                            None => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::KillRing { entries, selected } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _)
+                    | (KeyCode::Char('g'), KeyModifiers::CONTROL)
+                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) if *selected > 0 => {
+                        *selected -= 1;
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if *selected + 1 < entries.len() => {
+                        *selected += 1;
+                    }
+                    // Yank the selected entry
+                    (KeyCode::Enter, _) => {
+                        let index = *selected;
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        editor.yank_kill_ring_entry(index);
+                    }
+                    _ => {}
+                }
+            }
+
+            crate::editor::FloatingMode::Completion { items, selected, row, start_col } => {
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                    }
+                    (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                        if *selected > 0 {
+                            *selected -= 1;
+                        }
+                    }
+                    (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                        if *selected + 1 < items.len() {
+                            *selected += 1;
+                        }
+                    }
+                    (KeyCode::Enter, _) | (KeyCode::Tab, _) => {
+                        let item = items.get(*selected).cloned();
+                        let (row, start_col) = (*row, *start_col);
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        match item {
+                            Some(item) => editor.accept_completion(&item, row, start_col),
+                            None => {}
+                        }
+                    }
+                    // Any other key closes the popup and is handled normally
+                    _ => {
+                        editor.floating_window = None;
+                        editor.focus_floating = false;
+                        return handle_input(editor, key);
+                    }
+                }
+            }
+
+            crate::editor::FloatingMode::Confirm {
+                steps,
+                current_index,
+                ..
+            } => {
+                // Check what type of response we're expecting
+                let response_type = steps.get(*current_index)
+                    .map(|s| s.response_type.clone());
+                let total_steps = steps.len();
+                let idx = *current_index;
+
+                // Determine what action to take based on response type and key
+                let action = match response_type {
+                    Some(crate::editor::ResponseType::Binary) => {
+                        match (key.code, key.modifiers) {
+                            (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                                Some(ConfirmAction::Cancel)
+                            }
+                            (KeyCode::Char('y'), _) => Some(ConfirmAction::Respond("y".to_string())),
+                            (KeyCode::Char('n'), _) => Some(ConfirmAction::Respond("n".to_string())),
+                            _ => None,
+                        }
+                    }
+                    Some(crate::editor::ResponseType::Choice(options)) => {
+                        match (key.code, key.modifiers) {
+                            (KeyCode::Esc, _) | (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                                Some(ConfirmAction::Cancel)
+                            }
+                            (KeyCode::Char(c), _) => {
+                                if options.iter().any(|(k, _)| *k == c) {
+                                    Some(ConfirmAction::Respond(c.to_string()))
+                                } else {
+                                    None
+                                }
+                            }
+                            _ => None,
+                        }
+                    }
+                    None => None,
+                };
+
+                // Drop the borrow before processing the action
+                let _ = fw; // End borrow
+
+                // Now process the action with full access to editor
+                match action {
+                    Some(action) => process_confirm_action(editor, action, idx, total_steps),
+                    None => {}
+                }
+            }
+        },
+        None => {}
+    }
+
+    true
+}
 
 /// Action to take in a confirmation dialog
 enum ConfirmAction {
@@ -1254,11 +1423,24 @@ fn process_confirm_action(
                     crate::editor::FloatingMode::Confirm { ref mut dialog, .. } => {
                         let result = dialog.handle_response(current_index, &response, editor);
 
-                        // Put the window back (might be modified by handle_response)
-                        editor.floating_window = Some(fw);
+                        match editor.floating_window.is_some() {
+                            // handle_response opened another dialog (e.g. saving asks
+                            // about a file changed on disk), which takes over
+                            true => {
+                                match result {
+                                    crate::editor::ResponseResult::Cancel => dialog.on_cancel(editor),
+                                    _ => {}
+                                }
+                                editor.focus_floating = true;
+                            }
+                            false => {
+                                // Put the window back (might be modified by handle_response)
+                                editor.floating_window = Some(fw);
 
-                        // Apply the result
-                        apply_confirm_result(editor, result, total_steps, current_index);
+                                // Apply the result
+                                apply_confirm_result(editor, result, total_steps, current_index);
+                            }
+                        }
                     }
                     _ => {
                         // Put the window back if not a Confirm mode
@@ -1302,13 +1484,16 @@ fn apply_confirm_result(
                 match editor.floating_window.take() {
                     Some(fw) => match fw.mode {
                         crate::editor::FloatingMode::Confirm { dialog, .. } => {
-                            let _ = dialog.on_complete(editor);
+                            match dialog.on_complete(editor) {
+                                Ok(()) => {}
+                                Err(e) => editor.error(e),
+                            }
                         }
                         _ => {}
                     },
                     None => {}
                 }
-                editor.focus_floating = false;
+                editor.focus_floating = editor.floating_window.is_some();
             } else {
                 // Advance to next step
                 match &mut editor.floating_window {
@@ -1333,13 +1518,17 @@ fn apply_confirm_result(
             match editor.floating_window.take() {
                 Some(fw) => match fw.mode {
                     crate::editor::FloatingMode::Confirm { dialog, .. } => {
-                        let _ = dialog.on_complete(editor);
+                        match dialog.on_complete(editor) {
+                            Ok(()) => {}
+                            Err(e) => editor.error(e),
+                        }
                     }
                     _ => {}
                 },
                 None => {}
             }
-            editor.focus_floating = false;
+            // on_complete may have opened another window (e.g. a diff to review)
+            editor.focus_floating = editor.floating_window.is_some();
         }
     }
 }
@@ -1361,11 +1550,13 @@ fn should_quit(editor: &mut Editor, key: &KeyEvent) -> bool {
 
     match (key.code, key.modifiers) {
         (KeyCode::Esc, _) => {
-            if editor.mark.is_active() {
+            if editor.cancel_extra_cursors() {
+                false
+            } else if editor.mark.is_active() {
                 editor.cancel_mark();
                 false
-            } else if editor.modified {
-                // Buffer modified, show confirmation
+            } else if editor.has_modified_buffers() {
+                // Some buffer modified, show confirmation
                 editor.start_quit_confirmation();
                 false
             } else {
@@ -1373,11 +1564,13 @@ fn should_quit(editor: &mut Editor, key: &KeyEvent) -> bool {
             }
         }
         (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
-            if editor.mark.is_active() {
+            if editor.cancel_extra_cursors() {
+                false
+            } else if editor.mark.is_active() {
                 editor.cancel_mark();
                 false
-            } else if editor.modified {
-                // Buffer modified, show confirmation
+            } else if editor.has_modified_buffers() {
+                // Some buffer modified, show confirmation
                 editor.start_quit_confirmation();
                 false
             } else {
@@ -1391,6 +1584,17 @@ fn should_quit(editor: &mut Editor, key: &KeyEvent) -> bool {
 /// Execute a command by name
 fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
     log::info!("Command: {}", command_name);
+    // With several cursors, editing commands run at each of them
+    match editor.textarea.extra_cursor_count() > 0 && editor.cursor_visit.is_none() && runs_at_each_cursor(command_name) {
+        true => {
+            editor.save_undo_state();
+            editor.at_each_cursor(|editor| {
+                execute_command(editor, command_name);
+            });
+            return true;
+        }
+        false => {}
+    }
     match command_name {
         // File commands
         "open-file" => {
@@ -1398,7 +1602,10 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             true
         }
         "save-file" => {
-            let _ = editor.save_file();
+            match editor.save_file() {
+                Ok(()) => {}
+                Err(e) => editor.error(format!("Failed to save: {}", e)),
+            }
             true
         }
         "save-file-as" => {
@@ -1409,6 +1616,18 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             editor.delete_file_prompt();
             true
         }
+        "switch-to-buffer" => {
+            editor.switch_to_buffer_prompt();
+            true
+        }
+        "list-buffers" => {
+            editor.list_buffers();
+            true
+        }
+        "kill-buffer" => {
+            editor.kill_buffer_prompt();
+            true
+        }
 
         // Selection commands
         "swap-cursor-mark" => {
@@ -1433,6 +1652,10 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             editor.open_command_palette();
             true
         }
+        "save-settings" => {
+            editor.save_settings();
+            true
+        }
 
         // Movement commands
         "forward-char" => {
@@ -1475,6 +1698,38 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             editor.move_word_backward();
             true
         }
+        "forward-sibling-node" => {
+            editor.forward_sibling_node();
+            true
+        }
+        "backward-sibling-node" => {
+            editor.backward_sibling_node();
+            true
+        }
+        "up-node" => {
+            editor.up_node();
+            true
+        }
+        "beginning-of-defun" => {
+            editor.beginning_of_defun();
+            true
+        }
+        "end-of-defun" => {
+            editor.end_of_defun();
+            true
+        }
+        "next-function" => {
+            editor.next_function();
+            true
+        }
+        "previous-function" => {
+            editor.previous_function();
+            true
+        }
+        "outline" => {
+            editor.open_outline();
+            true
+        }
         "page-up" => {
             editor.page_up();
             true
@@ -1487,6 +1742,14 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             editor.recenter();
             true
         }
+        "isearch-forward" => {
+            editor.isearch_start(SearchDirection::Forward);
+            true
+        }
+        "isearch-backward" => {
+            editor.isearch_start(SearchDirection::Backward);
+            true
+        }
 
         // Edit commands
         // All these operations are self-contained: they handle undo state and
@@ -1500,13 +1763,101 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             true
         }
         "yank" => {
-            editor.paste();
+            editor.yank();
+            true
+        }
+        "yank-pop" => {
+            editor.yank_pop();
+            true
+        }
+        "browse-kill-ring" => {
+            editor.browse_kill_ring();
+            true
+        }
+        "query-replace" => {
+            editor.query_replace_prompt(false);
+            true
+        }
+        "query-replace-regexp" => {
+            editor.query_replace_prompt(true);
+            true
+        }
+
+        "delete-char" => {
+            editor.delete_char_forward();
+            true
+        }
+        "delete-char-backward" => {
+            editor.delete_char_backward();
+            true
+        }
+        "kill-word" => {
+            editor.delete_word_forward();
+            true
+        }
+        "kill-word-backward" => {
+            editor.delete_word_backward();
             true
         }
 
         // Selection commands
         "set-mark" => {
+            if logging::log_keys() || logging::log_selection() {
+                log::debug!("set-mark: calling set_mark");
+            }
             editor.set_mark();
+            if logging::log_selection() {
+                log::debug!(
+                    "After set_mark: mark={:?}, is_selecting={}, selection_range={:?}",
+                    editor.mark,
+                    editor.textarea.is_selecting(),
+                    editor.textarea.selection_range()
+                );
+            }
+            // Store this key for detecting C-SPC C-SPC, but only if we didn't
+            // just toggle off a selection (set_mark clears last_key in that case)
+            if editor.last_key.is_some() || editor.mark.is_active() {
+                editor.last_key = Some((KeyCode::Char(' '), KeyModifiers::CONTROL));
+            }
+            true
+        }
+        "expand-selection" => {
+            editor.expand_selection();
+            true
+        }
+        "shrink-selection" => {
+            editor.shrink_selection();
+            true
+        }
+        "jump-to-char" => {
+            // Ensure clean state
+            editor.jump_mode = None;
+            let mut jm = JumpMode::new();
+            jm.timeout_ms = editor.settings.jump_timeout_ms;
+            jm.last_keystroke_ms = current_time_ms();
+            editor.jump_mode = Some(jm);
+            log::debug!("Jump mode activated");
+            true
+        }
+        "add-cursor-below" => {
+            editor.add_cursor_below();
+            true
+        }
+        "add-cursor-above" => {
+            editor.add_cursor_above();
+            true
+        }
+        "add-cursor-next-match" => {
+            editor.add_cursor_next_match();
+            true
+        }
+        "add-cursors-all-matches" => {
+            editor.jump_mode = None;
+            let mut jm = JumpMode::new();
+            jm.add_cursors = true;
+            jm.last_keystroke_ms = current_time_ms();
+            editor.jump_mode = Some(jm);
+            editor.message("Type the text to put cursors at, then Enter");
             true
         }
         "kill-region" => {
@@ -1525,17 +1876,203 @@ fn execute_command(editor: &mut Editor, command_name: &str) -> bool {
             editor.redo();
             true
         }
+        "undo-tree" => {
+            editor.view_undo_tree();
+            true
+        }
 
         // Display commands
         "toggle-soft-wrap" => {
             editor.toggle_soft_wrap();
             true
         }
+        "toggle-fold" => editor.toggle_fold(),
+        "fold" => editor.fold_at_cursor(),
+        "unfold" => editor.unfold_at_cursor(),
+        "fold-all-at-level" => {
+            editor.fold_all_at_level_prompt();
+            true
+        }
+        "unfold-all" => {
+            editor.unfold_all();
+            true
+        }
+
+        // Language server commands
+        "lsp-hover" => {
+            editor.lsp_hover();
+            true
+        }
+        "lsp-goto-definition" => {
+            editor.lsp_goto_definition();
+            true
+        }
+        "lsp-find-references" => {
+            editor.lsp_find_references();
+            true
+        }
+        "lsp-complete" => {
+            editor.lsp_complete();
+            true
+        }
+        "lsp-diagnostics" => {
+            editor.lsp_show_diagnostics();
+            true
+        }
+        "view-messages" => {
+            editor.view_messages();
+            true
+        }
+        "lsp-restart" => {
+            editor.lsp_restart();
+            true
+        }
 
-        // Unknown command
+        // File format commands
+        "set-line-ending-lf" => {
+            editor.set_line_ending(LineEnding::Lf);
+            true
+        }
+        "set-line-ending-crlf" => {
+            editor.set_line_ending(LineEnding::Crlf);
+            true
+        }
+        "toggle-final-newline" => {
+            editor.toggle_final_newline();
+            true
+        }
+        "toggle-bom" => {
+            editor.toggle_bom();
+            true
+        }
+        "set-encoding" => {
+            editor.set_encoding_prompt();
+            true
+        }
+        "recover-this-file" => {
+            editor.recover_this_file();
+            true
+        }
+        "purge-undo-history" => {
+            editor.purge_undo_histories();
+            true
+        }
+        "revert-buffer" => {
+            match editor.reload_file() {
+                Ok(()) => {}
+                Err(e) => editor.error(format!("Revert failed: {}", e)),
+            }
+            true
+        }
+
+        // Shell commands
+        "shell-command" => {
+            editor.shell_command_prompt();
+            true
+        }
+        "shell-command-on-region" => {
+            editor.shell_command_on_region_prompt(false);
+            true
+        }
+        "shell-command-replace-region" => {
+            editor.shell_command_on_region_prompt(true);
+            true
+        }
+
+        // Git gutter commands
+        "next-hunk" => {
+            editor.next_hunk();
+            true
+        }
+        "previous-hunk" => {
+            editor.previous_hunk();
+            true
+        }
+
+        // Text commands (the M-q menu actions) and text objects, otherwise unknown
         _ => {
-            // Command not found - just return true to continue
+            match (MenuAction::from_command(command_name), TextObjectAction::from_command(command_name)) {
+                (Some(action), _) => editor.apply_menu_option(action),
+                (None, Some((action, object))) => {
+                    editor.run_text_object(action, object);
+                }
+                // Command not found - just continue
+                (None, None) => {}
+            }
             true
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::TextArea;
+
+    fn press(editor: &mut Editor, code: KeyCode) -> (usize, usize) {
+        handle_input(editor, KeyEvent::new(code, KeyModifiers::NONE));
+        editor.textarea.cursor()
+    }
+
+    #[test]
+    fn test_settings_menu_adjusts_window_sizes_within_config_range() {
+        let mut editor = Editor::new();
+        editor.settings.floating_window_width = 498;
+        editor.open_settings_menu();
+        press(&mut editor, KeyCode::Down);
+        press(&mut editor, KeyCode::Down);
+
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(editor.settings.floating_window_width, 500);
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(editor.settings.floating_window_width, 500);
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(editor.settings.floating_window_width, 495);
+    }
+
+    #[test]
+    fn test_save_on_quit_asks_about_changed_file() {
+        let dir = crate::test_util::TempDir::new("keys-quit");
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "one\n").unwrap();
+
+        let mut editor = crate::editor::test_util::editor_in(&dir);
+        editor.open_file(&path).unwrap();
+        editor.textarea.insert_str("my ");
+        editor.mark_modified();
+        std::fs::write(&path, "theirs\n").unwrap();
+
+        editor.start_quit_confirmation();
+        handle_input(&mut editor, KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        handle_input(&mut editor, KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+
+        // The save failed and said why; the overwrite question is up instead
+        assert!(!editor.pending_quit);
+        assert!(editor.echo_text().is_some_and(|text| text.contains("changed on disk")));
+        let prompt = match editor.floating_window.as_ref().map(|fw| &fw.mode) {
+            Some(crate::editor::FloatingMode::Confirm { steps, .. }) => steps[0].prompt.clone(),
+            _ => panic!("expected the overwrite question"),
+        };
+        assert!(prompt.contains("save anyway?"), "{}", prompt);
+
+        handle_input(&mut editor, KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "my one\n");
+    }
+
+    #[test]
+    fn test_cursor_keys_move_the_cursor() {
+        let mut editor = Editor::new();
+        editor.textarea = TextArea::from_text("one\ntwo\nthree");
+        editor.viewport_height = 20;
+
+        assert_eq!(press(&mut editor, KeyCode::Right), (0, 1));
+        assert_eq!(press(&mut editor, KeyCode::Down), (1, 1));
+        assert_eq!(press(&mut editor, KeyCode::End), (1, 3));
+        assert_eq!(press(&mut editor, KeyCode::Home), (1, 0));
+        assert_eq!(press(&mut editor, KeyCode::Left), (0, 3));
+        assert_eq!(press(&mut editor, KeyCode::Up), (0, 3));
+        assert_eq!(press(&mut editor, KeyCode::PageDown).0, 2);
+        assert_eq!(press(&mut editor, KeyCode::PageUp).0, 0);
+        assert!(!editor.modified);
+    }
+}
@@ -0,0 +1,454 @@
+//! Keymap: resolves keys and key sequences to command names.
+//!
+//! The default bindings are plain data (`DEFAULT_BINDINGS` plus the prefix
+//! tables such as `CtrlXPrefix`). Users can override, add or remove bindings
+//! in the `[keys]` section of the config file:
+//!
+//! ```toml
+//! [keys]
+//! "C-o" = "undo"
+//! "C-x C-o" = "open-file"
+//! "C-z" = ""          # unbind
+//! ```
+//!
+//! New prefix keys are declared in the `[prefixes]` section, mapping the
+//! trigger key to the name shown in which-key, and then filled from `[keys]`:
+//!
+//! ```toml
+//! [prefixes]
+//! "C-o" = "C-o (open)"
+//!
+//! [keys]
+//! "C-o f" = "open-file"
+//! ```
+//!
+//! Prefix tables are handed to which-key as `KeyPrefix` implementations, so
+//! the which-key display always shows the remapped bindings.
+
+use crate::commands::{
+    CommandRegistry, CtrlCPrefix, CtrlTPrefix, CtrlXPrefix, KeyCombo, KeyPrefix, Keybinding,
+    PrefixBinding, SelectAroundPrefix, SelectInsidePrefix,
+};
+use ratatui::crossterm::event::KeyEvent;
+use std::collections::HashMap;
+use toml::{Table, Value};
+
+/// Default single-key bindings (prefix bindings come from the prefix types)
+pub const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    // Movement
+    ("C-f", "forward-char"),
+    ("C-b", "backward-char"),
+    ("C-n", "next-line"),
+    ("C-p", "previous-line"),
+    ("C-a", "beginning-of-line"),
+    ("C-e", "end-of-line"),
+    ("M-f", "forward-word"),
+    ("M-b", "backward-word"),
+    ("C-u", "page-up"),
+    ("M-u", "page-down"),
+    ("C-l", "recenter"),
+    ("M-j", "jump-to-char"),
+    ("Left", "backward-char"),
+    ("Right", "forward-char"),
+    ("Up", "previous-line"),
+    ("Down", "next-line"),
+    ("Home", "beginning-of-line"),
+    ("End", "end-of-line"),
+    ("PgUp", "page-up"),
+    ("PgDn", "page-down"),
+    // Structural movement
+    ("C-M-f", "forward-sibling-node"),
+    ("C-M-b", "backward-sibling-node"),
+    ("C-M-u", "up-node"),
+    ("C-M-a", "beginning-of-defun"),
+    ("C-M-e", "end-of-defun"),
+    ("C-M-n", "next-function"),
+    ("C-M-p", "previous-function"),
+    // Search and replace
+    ("C-s", "isearch-forward"),
+    ("C-r", "isearch-backward"),
+    ("M-%", "query-replace"),
+    ("C-M-%", "query-replace-regexp"),
+    // Shell commands
+    ("M-!", "shell-command"),
+    ("M-|", "shell-command-on-region"),
+    // Language server
+    ("M-.", "lsp-goto-definition"),
+    ("M-/", "lsp-complete"),
+    // Mark and selection
+    ("C-SPC", "set-mark"),
+    ("M-o", "expand-selection"),
+    ("M-i", "shrink-selection"),
+    // Editing
+    ("C-w", "kill-region"),
+    ("M-w", "copy-region"),
+    ("C-y", "yank"),
+    ("M-y", "yank-pop"),
+    ("C-k", "kill-line"),
+    ("C-d", "delete-char"),
+    ("C-h", "delete-char-backward"),
+    ("M-d", "kill-word"),
+    ("M-DEL", "kill-word-backward"),
+    ("C-z", "undo"),
+    ("M-z", "redo"),
+    // System
+    ("M-q", "operate"),
+    ("M-?", "settings"),
+    ("M-x", "execute-command"),
+];
+
+/// Built-in prefix keys
+fn default_prefixes() -> [&'static dyn KeyPrefix; 5] {
+    [&CtrlXPrefix, &CtrlCPrefix, &CtrlTPrefix, &SelectInsidePrefix, &SelectAroundPrefix]
+}
+
+/// A prefix key and its table of follow-up bindings
+#[derive(Clone)]
+pub struct PrefixMap {
+    trigger: KeyCombo,
+    name: String,
+    bindings: Vec<PrefixBinding>,
+}
+
+impl PrefixMap {
+    /// Copy the bindings of a prefix definition into an editable table
+    pub fn from_prefix(prefix: &dyn KeyPrefix) -> Self {
+        Self {
+            trigger: prefix.trigger(),
+            name: prefix.display_name().to_string(),
+            bindings: prefix.bindings(),
+        }
+    }
+
+    /// Bind (or with `None`, unbind) a follow-up key
+    fn set(&mut self, key: KeyCombo, command: Option<&'static str>) {
+        self.bindings.retain(|b| b.key != key);
+        match command {
+            Some(command) => self.bindings.push(PrefixBinding { key, command }),
+            None => {}
+        }
+    }
+}
+
+impl KeyPrefix for PrefixMap {
+    fn trigger(&self) -> KeyCombo {
+        self.trigger
+    }
+
+    fn display_name(&self) -> &str {
+        &self.name
+    }
+
+    fn bindings(&self) -> Vec<PrefixBinding> {
+        self.bindings.clone()
+    }
+}
+
+/// What a key resolves to in the keymap
+pub enum KeyLookup<'a> {
+    Command(&'static str),
+    Prefix(&'a PrefixMap),
+}
+
+/// The active set of key bindings
+pub struct Keymap {
+    global: HashMap<KeyCombo, &'static str>,
+    prefixes: Vec<PrefixMap>,
+}
+
+impl Keymap {
+    /// Build the default keymap
+    pub fn new() -> Self {
+        let global = DEFAULT_BINDINGS
+            .iter()
+            .map(|(key, command)| match KeyCombo::parse(key) {
+                Ok(combo) => (combo, *command),
+                Err(e) => panic!("bad default binding: {}", e),
+            })
+            .collect();
+
+        Self {
+            global,
+            prefixes: default_prefixes().into_iter().map(PrefixMap::from_prefix).collect(),
+        }
+    }
+
+    /// Resolve a top-level key press
+    pub fn lookup(&self, key: &KeyEvent) -> Option<KeyLookup<'_>> {
+        let combo = KeyCombo::from_event(key);
+        match self.prefixes.iter().find(|p| p.trigger == combo) {
+            Some(prefix) => return Some(KeyLookup::Prefix(prefix)),
+            None => {}
+        }
+        self.global.get(&combo).map(|command| KeyLookup::Command(command))
+    }
+
+    /// Register a prefix key (or rename an existing one). Any command bound
+    /// to the trigger key itself is unbound.
+    pub fn add_prefix(&mut self, trigger: KeyCombo, name: &str) {
+        match self.prefixes.iter_mut().find(|p| p.trigger == trigger) {
+            Some(prefix) => prefix.name = name.to_string(),
+            None => {
+                self.global.remove(&trigger);
+                self.prefixes.push(PrefixMap {
+                    trigger,
+                    name: name.to_string(),
+                    bindings: Vec::new(),
+                });
+            }
+        }
+    }
+
+    /// Bind a key sequence to a command, or unbind it with `None`
+    pub fn bind(&mut self, keys: &[KeyCombo], command: Option<&'static str>) -> Result<(), String> {
+        match keys {
+            [key] => {
+                match self.prefixes.iter().any(|p| p.trigger == *key) {
+                    true => return Err(format!("{} is a prefix key", key.display())),
+                    false => {}
+                }
+                match command {
+                    Some(command) => self.global.insert(*key, command),
+                    None => self.global.remove(key),
+                };
+                Ok(())
+            }
+            [prefix, key] => match self.prefixes.iter_mut().find(|p| p.trigger == *prefix) {
+                Some(map) => {
+                    map.set(*key, command);
+                    Ok(())
+                }
+                None => Err(format!("{} is not a prefix key", prefix.display())),
+            },
+            _ => Err("only single keys and two-key sequences are supported".to_string()),
+        }
+    }
+
+    /// The key sequence currently bound to a command, if any
+    pub fn binding_for(&self, command: &str) -> Option<Keybinding> {
+        let mut global: Vec<&KeyCombo> = self
+            .global
+            .iter()
+            .filter(|(_, c)| **c == command)
+            .map(|(k, _)| k)
+            .collect();
+        // Deterministic choice when several keys run the same command
+        global.sort_by_key(|k| k.display());
+        match global.first() {
+            Some(key) => return Some(Keybinding::Single(**key)),
+            None => {}
+        }
+
+        self.prefixes.iter().find_map(|p| {
+            p.bindings
+                .iter()
+                .find(|b| b.command == command)
+                .map(|b| Keybinding::Sequence(vec![p.trigger, b.key]))
+        })
+    }
+
+    /// Whether a command's default binding comes from the keymap (as opposed
+    /// to keys the input loop handles itself, like C-g or RET)
+    pub fn is_default_binding(command: &str) -> bool {
+        DEFAULT_BINDINGS.iter().any(|(_, c)| *c == command)
+            || default_prefixes()
+                .iter()
+                .any(|p| p.bindings().iter().any(|b| b.command == command))
+    }
+
+    /// Apply the `[prefixes]` config section (trigger key = which-key name).
+    /// Must run before `apply_config` so `[keys]` can bind into new prefixes.
+    pub fn apply_prefixes(&mut self, prefixes: &Table) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        for (key, value) in prefixes {
+            let result = match value {
+                Value::String(name) => KeyCombo::parse(key).map(|trigger| {
+                    let name = match name.is_empty() {
+                        true => trigger.display(),
+                        false => name.clone(),
+                    };
+                    self.add_prefix(trigger, &name)
+                }),
+                _ => Err("expected a display name".to_string()),
+            };
+
+            match result {
+                Ok(()) => {}
+                Err(e) => warnings.push(format!("config: prefixes: {}: {}", key, e)),
+            }
+        }
+
+        warnings
+    }
+
+    /// Apply the `[keys]` config section. Returns a warning for every entry
+    /// that could not be applied.
+    pub fn apply_config(&mut self, keys: &Table, registry: &CommandRegistry) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        for (key, value) in keys {
+            let result = match value {
+                Value::String(name) => {
+                    let command = match name.is_empty() {
+                        true => Ok(None),
+                        false => registry
+                            .get(name)
+                            .map(|c| Some(c.name))
+                            .ok_or_else(|| format!("unknown command '{}'", name)),
+                    };
+                    command.and_then(|command| {
+                        let keys = match Keybinding::parse(key)? {
+                            Keybinding::Single(k) => vec![k],
+                            Keybinding::Sequence(ks) => ks,
+                        };
+                        self.bind(&keys, command)
+                    })
+                }
+                _ => Err("expected a command name".to_string()),
+            };
+
+            match result {
+                Ok(()) => {}
+                Err(e) => warnings.push(format!("config: keys: {}: {}", key, e)),
+            }
+        }
+
+        warnings
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+    fn lookup_command(keymap: &Keymap, code: KeyCode, modifiers: KeyModifiers) -> Option<&'static str> {
+        match keymap.lookup(&KeyEvent::new(code, modifiers)) {
+            Some(KeyLookup::Command(c)) => Some(c),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_default_bindings_resolve() {
+        let keymap = Keymap::new();
+        let registry = CommandRegistry::new();
+
+        // Every default binding names a registered command
+        for (_, command) in DEFAULT_BINDINGS {
+            assert!(registry.get(command).is_some(), "unregistered: {}", command);
+        }
+
+        assert_eq!(lookup_command(&keymap, KeyCode::Char('f'), KeyModifiers::CONTROL), Some("forward-char"));
+        assert_eq!(lookup_command(&keymap, KeyCode::Char('y'), KeyModifiers::ALT), Some("yank-pop"));
+        assert!(matches!(
+            keymap.lookup(&KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL)),
+            Some(KeyLookup::Prefix(_))
+        ));
+    }
+
+    #[test]
+    fn test_registry_bindings_match_keymap() {
+        let keymap = Keymap::new();
+        let registry = CommandRegistry::new();
+        let event = |combo: &KeyCombo| KeyEvent::new(combo.key, combo.modifiers);
+        // Handled by the input loop before the keymap
+        let hard_wired = [KeyCombo::ctrl('g'), KeyCombo::special(KeyCode::Enter), KeyCombo::special(KeyCode::Tab)];
+
+        let mut unbound: Vec<&str> = Vec::new();
+        for command in registry.all_commands() {
+            let bound = match &command.keybinding {
+                Some(Keybinding::Single(key)) if hard_wired.contains(key) => continue,
+                Some(Keybinding::Single(key)) => lookup_command(&keymap, key.key, key.modifiers),
+                Some(Keybinding::Sequence(keys)) => match (keys.as_slice(), keymap.lookup(&event(&keys[0]))) {
+                    ([_, key], Some(KeyLookup::Prefix(prefix))) => prefix.get_command(&event(key)),
+                    _ => None,
+                },
+                None => continue,
+            };
+            match bound == Some(command.name) {
+                true => {}
+                false => unbound.push(command.name),
+            }
+        }
+        unbound.sort();
+        assert!(unbound.is_empty(), "not bound to their registry keys: {:?}", unbound);
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let mut keymap = Keymap::new();
+        let registry = CommandRegistry::new();
+        let keys: Table = r#"
+            "C-o" = "undo"
+            "C-z" = ""
+            "C-x C-o" = "open-file"
+            "C-x C-f" = ""
+            "C-q" = "no-such-command"
+            "C-v C-c" = "undo"
+            "C-x" = "undo"
+        "#
+        .parse()
+        .unwrap();
+
+        let warnings = keymap.apply_config(&keys, &registry);
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+
+        assert_eq!(lookup_command(&keymap, KeyCode::Char('o'), KeyModifiers::CONTROL), Some("undo"));
+        assert_eq!(lookup_command(&keymap, KeyCode::Char('z'), KeyModifiers::CONTROL), None);
+
+        // The which-key table reflects the remap
+        let prefix = match keymap.lookup(&KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL)) {
+            Some(KeyLookup::Prefix(p)) => p.clone(),
+            _ => panic!("C-x should be a prefix"),
+        };
+        let open_key = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL);
+        assert_eq!(prefix.get_command(&open_key), Some("open-file"));
+        assert!(prefix.bindings().iter().all(|b| b.key != KeyCombo::ctrl('f')));
+
+        assert_eq!(keymap.binding_for("open-file").map(|k| k.display()), Some("C-x C-o".to_string()));
+        assert_eq!(keymap.binding_for("undo").map(|k| k.display()), Some("C-o".to_string()));
+    }
+
+    #[test]
+    fn test_user_defined_prefix() {
+        let mut keymap = Keymap::new();
+        let registry = CommandRegistry::new();
+        let config: Table = r#"
+            [prefixes]
+            "C-o" = "C-o (open)"
+            "C-q" = 3
+
+            [keys]
+            "C-o f" = "open-file"
+            "C-t z" = "undo"
+        "#
+        .parse()
+        .unwrap();
+
+        let prefixes = config["prefixes"].as_table().unwrap();
+        let keys = config["keys"].as_table().unwrap();
+        let mut warnings = keymap.apply_prefixes(prefixes);
+        warnings.extend(keymap.apply_config(keys, &registry));
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+
+        let prefix = match keymap.lookup(&KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL)) {
+            Some(KeyLookup::Prefix(p)) => p.clone(),
+            _ => panic!("C-o should be a prefix"),
+        };
+        assert_eq!(prefix.display_name(), "C-o (open)");
+        assert_eq!(prefix.get_command(&KeyEvent::new(KeyCode::Char('f'), KeyModifiers::NONE)), Some("open-file"));
+
+        // Built-in prefixes can be extended too
+        assert_eq!(keymap.binding_for("undo").map(|k| k.display()), Some("C-z".to_string()));
+        assert!(keymap.prefixes.iter().any(|p| p.trigger == KeyCombo::ctrl('t')
+            && p.bindings.iter().any(|b| b.command == "undo")));
+    }
+}
@@ -1,6 +1,14 @@
 pub mod clipboard;
 pub mod commands;
+pub mod config;
 pub mod editor;
+pub mod fileio;
+pub mod git;
 pub mod keybindings;
+pub mod keymap;
 pub mod logging;
-pub mod ui;
\ No newline at end of file
+pub mod lsp;
+pub mod ui;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,295 @@
+//! A single language server process speaking JSON-RPC over stdio.
+//!
+//! A reader thread parses messages from the server's stdout and hands them to
+//! the editor through a channel, and a writer thread takes the editor's
+//! messages to the server's stdin, so the UI thread never blocks on the
+//! server. Messages sent before the `initialize` handshake completes are
+//! queued.
+
+use super::protocol::PositionEncoding;
+use super::transport::{read_message, write_message};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// What a pending request was for, so its response can be routed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    Initialize,
+    Hover,
+    Definition,
+    References,
+    Completion,
+    Shutdown,
+}
+
+/// Something the server sent that the editor needs to act on
+#[derive(Debug)]
+pub enum ClientEvent {
+    /// A server notification (e.g. `textDocument/publishDiagnostics`)
+    Notification { method: String, params: Value },
+    /// The result of one of our requests
+    Response { kind: RequestKind, result: Value },
+    /// One of our requests failed
+    Error { kind: RequestKind, message: String },
+    /// The server process went away
+    Exited,
+}
+
+/// A running language server
+pub struct LspClient {
+    child: Child,
+    /// Messages for the writer thread
+    outgoing: Sender<Value>,
+    receiver: Receiver<Value>,
+    next_id: i64,
+    pending: HashMap<i64, RequestKind>,
+    /// Whether the initialize handshake has completed
+    initialized: bool,
+    /// Messages waiting for the handshake to complete
+    queued: Vec<Value>,
+    /// Column encoding negotiated with the server
+    pub encoding: PositionEncoding,
+    exited: bool,
+}
+
+impl LspClient {
+    /// Spawn `command` in `root` and start the initialize handshake
+    pub fn start(command: &[String], root: &Path) -> Result<Self, String> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| "empty server command".to_string())?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("{}: {}", program, e))?;
+
+        let stdin = child.stdin.take().ok_or("no stdin")?;
+        let stdout = child.stdout.take().ok_or("no stdout")?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                match sender.send(message) {
+                    Ok(()) => {}
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let (outgoing, to_write) = mpsc::channel::<Value>();
+        thread::spawn(move || {
+            let mut stdin = stdin;
+            for message in to_write {
+                match write_message(&mut stdin, &message) {
+                    Ok(()) => {}
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut client = Self {
+            child,
+            outgoing,
+            receiver,
+            next_id: 1,
+            pending: HashMap::new(),
+            initialized: false,
+            queued: Vec::new(),
+            encoding: PositionEncoding::default(),
+            exited: false,
+        };
+
+        let root_uri = super::protocol::path_to_uri(root);
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "workspaceFolders": [{"uri": root_uri, "name": root.display().to_string()}],
+            "capabilities": {
+                "general": {"positionEncodings": ["utf-8", "utf-16"]},
+                "textDocument": {
+                    "synchronization": {"didSave": true},
+                    "publishDiagnostics": {},
+                    "hover": {"contentFormat": ["plaintext", "markdown"]},
+                    "definition": {"linkSupport": true},
+                    "references": {},
+                    "completion": {"completionItem": {"snippetSupport": false}}
+                }
+            }
+        });
+        client.send_now(RequestKind::Initialize, "initialize", params)?;
+        Ok(client)
+    }
+
+    /// Whether the server has exited
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Send a request, returning its id
+    pub fn request(&mut self, kind: RequestKind, method: &str, params: Value) -> Result<i64, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, kind);
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        self.send(message)?;
+        Ok(id)
+    }
+
+    /// Send a notification
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}))
+    }
+
+    /// Send immediately, bypassing the handshake queue
+    fn send_now(&mut self, kind: RequestKind, method: &str, params: Value) -> Result<(), String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, kind);
+        self.write(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), String> {
+        match self.initialized {
+            true => self.write(&message),
+            false => {
+                self.queued.push(message);
+                Ok(())
+            }
+        }
+    }
+
+    /// Hand a message to the writer thread, which is gone once the server
+    /// stops reading
+    fn write(&mut self, message: &Value) -> Result<(), String> {
+        self.outgoing.send(message.clone()).map_err(|_| {
+            self.exited = true;
+            "language server: not accepting messages".to_string()
+        })
+    }
+
+    /// Collect everything the server has sent since the last poll
+    pub fn poll(&mut self) -> Vec<ClientEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => match self.handle_message(message) {
+                    Some(event) => events.push(event),
+                    None => {}
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    match self.exited {
+                        true => {}
+                        false => events.push(ClientEvent::Exited),
+                    }
+                    self.exited = true;
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    fn handle_message(&mut self, message: Value) -> Option<ClientEvent> {
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(|m| m.as_str()).map(str::to_string);
+
+        match (id, method) {
+            // Request from the server: answer so it doesn't wait on us
+            (Some(id), Some(method)) => {
+                let result = match method.as_str() {
+                    "workspace/configuration" => {
+                        let count = message
+                            .pointer("/params/items")
+                            .and_then(|i| i.as_array())
+                            .map_or(0, |i| i.len());
+                        Value::Array(vec![Value::Null; count])
+                    }
+                    _ => Value::Null,
+                };
+                let _ = self.write(&json!({"jsonrpc": "2.0", "id": id, "result": result}));
+                None
+            }
+            (None, Some(method)) => Some(ClientEvent::Notification {
+                method,
+                params: message.get("params").cloned().unwrap_or(Value::Null),
+            }),
+            (Some(id), None) => {
+                let kind = self.pending.remove(&id.as_i64()?)?;
+                match message.get("error") {
+                    Some(error) => Some(ClientEvent::Error {
+                        kind,
+                        message: error
+                            .get("message")
+                            .and_then(|m| m.as_str())
+                            .unwrap_or("request failed")
+                            .to_string(),
+                    }),
+                    None => {
+                        let result = message.get("result").cloned().unwrap_or(Value::Null);
+                        match kind {
+                            RequestKind::Initialize => {
+                                self.finish_handshake(&result);
+                                None
+                            }
+                            _ => Some(ClientEvent::Response { kind, result }),
+                        }
+                    }
+                }
+            }
+            (None, None) => None,
+        }
+    }
+
+    fn finish_handshake(&mut self, result: &Value) {
+        self.encoding = PositionEncoding::from_capability(
+            result
+                .pointer("/capabilities/positionEncoding")
+                .and_then(|e| e.as_str()),
+        );
+        self.initialized = true;
+        let _ = self.write(&json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}));
+        for message in std::mem::take(&mut self.queued) {
+            let _ = self.write(&message);
+        }
+    }
+
+    /// Ask the server to shut down, then make sure the process is gone
+    pub fn shutdown(&mut self) {
+        match self.exited {
+            true => {}
+            false => {
+                let _ = self.send_now(RequestKind::Shutdown, "shutdown", Value::Null);
+                let _ = self.write(&json!({"jsonrpc": "2.0", "method": "exit"}));
+                // Give the server a moment to exit on its own
+                for _ in 0..10 {
+                    match self.child.try_wait() {
+                        Ok(Some(_)) => break,
+                        _ => thread::sleep(Duration::from_millis(20)),
+                    }
+                }
+                self.exited = true;
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,376 @@
+//! Language Server Protocol support.
+//!
+//! One server process is started per `Language` on first use, with the
+//! command taken from the `[lsp]` config section (or a built-in default):
+//!
+//! ```toml
+//! [lsp]
+//! rust = "rust-analyzer"
+//! typescript = ["typescript-language-server", "--stdio"]
+//! python = ""          # disable
+//! ```
+//!
+//! Documents are synced with full-text `didChange` notifications whenever the
+//! buffer's version (`TextArea::version`) differs from the one the server
+//! last saw, so an unchanged buffer costs nothing per tick.
+
+pub mod client;
+pub mod protocol;
+pub mod transport;
+
+pub use client::{ClientEvent, LspClient, RequestKind};
+pub use protocol::{CompletionItem, Diagnostic, Location, Position, PositionEncoding, Severity};
+
+use crate::editor::syntax::Language;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use toml::Table;
+
+/// Files or directories that mark a project root
+const ROOT_MARKERS: &[&str] = &[".git", "Cargo.toml", "package.json", "go.mod", "pyproject.toml"];
+
+/// Built-in server commands
+fn default_server(language: Language) -> Option<&'static [&'static str]> {
+    match language {
+        Language::Rust => Some(&["rust-analyzer"]),
+        Language::TypeScript | Language::Tsx | Language::JavaScript => {
+            Some(&["typescript-language-server", "--stdio"])
+        }
+        Language::Go => Some(&["gopls"]),
+        Language::Python => Some(&["pylsp"]),
+        Language::C | Language::Cpp => Some(&["clangd"]),
+        _ => None,
+    }
+}
+
+/// Find the project root for a file: the nearest ancestor with a root marker,
+/// or the file's directory
+pub fn find_root(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    dir.ancestors()
+        .find(|d| ROOT_MARKERS.iter().any(|m| d.join(m).exists()))
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Absolute form of a path, as used in document URIs
+pub fn absolute_path(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A document the server has been told about
+struct Document {
+    language: Language,
+    version: i64,
+    /// `TextArea::version` of the text the server has
+    buffer_version: u64,
+}
+
+/// All language servers and the documents synced to them
+#[derive(Default)]
+pub struct LspManager {
+    /// Server command per language (overrides the built-in defaults)
+    servers: HashMap<Language, Vec<String>>,
+    clients: HashMap<Language, LspClient>,
+    /// Languages whose server failed to start (not retried until restart)
+    failed: HashSet<Language>,
+    documents: HashMap<String, Document>,
+    /// Latest diagnostics per document URI
+    pub diagnostics: HashMap<String, Vec<Diagnostic>>,
+}
+
+impl LspManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the server command for a language (an empty command disables it)
+    pub fn set_server(&mut self, language: Language, command: Vec<String>) {
+        self.servers.insert(language, command);
+        self.failed.remove(&language);
+    }
+
+    /// The server command for a language, if one is configured
+    pub fn server_command(&self, language: Language) -> Option<Vec<String>> {
+        let command = match self.servers.get(&language) {
+            Some(command) => command.clone(),
+            None => default_server(language)?.iter().map(|s| s.to_string()).collect(),
+        };
+        match command.is_empty() {
+            true => None,
+            false => Some(command),
+        }
+    }
+
+    /// Apply the `[lsp]` config section (language id = command)
+    pub fn apply_config(&mut self, table: &Table) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        for (key, value) in table {
+            let command = match value {
+                toml::Value::String(s) => Ok(s.split_whitespace().map(str::to_string).collect()),
+                toml::Value::Array(parts) => parts
+                    .iter()
+                    .map(|p| p.as_str().map(str::to_string))
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or_else(|| "expected an array of strings".to_string()),
+                _ => Err("expected a command string or array".to_string()),
+            };
+            let result = match Language::from_id(key) {
+                Some(language) => command.map(|c| self.set_server(language, c)),
+                None => Err("unknown language".to_string()),
+            };
+
+            match result {
+                Ok(()) => {}
+                Err(e) => warnings.push(format!("config: lsp: {}: {}", key, e)),
+            }
+        }
+
+        warnings
+    }
+
+    /// Whether any server is running
+    pub fn is_active(&self) -> bool {
+        !self.clients.is_empty()
+    }
+
+    /// The running client for a language, starting it if needed.
+    /// Returns `Ok(None)` when no server is configured for the language.
+    pub fn client_for(&mut self, language: Language, path: &Path) -> Result<Option<&mut LspClient>, String> {
+        let command = match self.failed.contains(&language) {
+            true => return Ok(None),
+            false => match self.server_command(language) {
+                Some(command) => command,
+                None => return Ok(None),
+            },
+        };
+
+        match self.clients.contains_key(&language) {
+            true => {}
+            false => match LspClient::start(&command, &find_root(path)) {
+                Ok(client) => {
+                    log::info!("Started language server for {}: {}", language.id(), command.join(" "));
+                    self.clients.insert(language, client);
+                }
+                Err(e) => {
+                    self.failed.insert(language);
+                    return Err(e);
+                }
+            },
+        }
+        Ok(self.clients.get_mut(&language))
+    }
+
+    /// Make sure the server has the current text of a document, given the
+    /// buffer's version; `text` is only called when that version is new.
+    /// Returns the client's position encoding, or None without a server.
+    pub fn sync_document(
+        &mut self,
+        path: &Path,
+        language: Language,
+        buffer_version: u64,
+        text: impl FnOnce() -> String,
+    ) -> Result<Option<PositionEncoding>, String> {
+        let uri = protocol::path_to_uri(path);
+        let (version, opened) = match self.documents.get(&uri) {
+            Some(doc) if doc.language == language && doc.buffer_version == buffer_version => {
+                return Ok(self.clients.get(&language).map(|c| c.encoding))
+            }
+            Some(doc) if doc.language == language => (doc.version + 1, true),
+            _ => (1, false),
+        };
+
+        let client = match self.client_for(language, path)? {
+            Some(client) => client,
+            None => return Ok(None),
+        };
+        let text = text();
+        match opened {
+            true => client.notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": {"uri": uri, "version": version},
+                    "contentChanges": [{"text": text}]
+                }),
+            )?,
+            false => client.notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": language.id(),
+                        "version": version,
+                        "text": text
+                    }
+                }),
+            )?,
+        }
+        let encoding = client.encoding;
+        self.documents.insert(
+            uri,
+            Document {
+                language,
+                version,
+                buffer_version,
+            },
+        );
+        Ok(Some(encoding))
+    }
+
+    /// Tell the server a document was saved
+    pub fn did_save(&mut self, path: &Path) {
+        let uri = protocol::path_to_uri(path);
+        let language = match self.documents.get(&uri) {
+            Some(doc) => doc.language,
+            None => return,
+        };
+        match self.clients.get_mut(&language) {
+            Some(client) => {
+                let _ = client.notify("textDocument/didSave", json!({"textDocument": {"uri": uri}}));
+            }
+            None => {}
+        }
+    }
+
+    /// Tell the server a document is no longer open
+    pub fn close_document(&mut self, path: &Path) {
+        let uri = protocol::path_to_uri(path);
+        let doc = match self.documents.remove(&uri) {
+            Some(doc) => doc,
+            None => return,
+        };
+        self.diagnostics.remove(&uri);
+        match self.clients.get_mut(&doc.language) {
+            Some(client) => {
+                let _ = client.notify("textDocument/didClose", json!({"textDocument": {"uri": uri}}));
+            }
+            None => {}
+        }
+    }
+
+    /// Send a position request (hover, definition, ...) for a synced document
+    pub fn request_at(
+        &mut self,
+        language: Language,
+        kind: RequestKind,
+        method: &str,
+        path: &Path,
+        position: Position,
+    ) -> Result<(), String> {
+        let mut params = json!({
+            "textDocument": {"uri": protocol::path_to_uri(path)},
+            "position": {"line": position.line, "character": position.character}
+        });
+        match kind {
+            RequestKind::References => params["context"] = json!({"includeDeclaration": true}),
+            _ => {}
+        }
+        match self.clients.get_mut(&language) {
+            Some(client) => client.request(kind, method, params).map(|_| ()),
+            None => Err(format!("no language server for {}", language.id())),
+        }
+    }
+
+    /// Collect events from all servers. Diagnostics are stored here;
+    /// everything else is returned for the editor to handle.
+    pub fn poll(&mut self) -> Vec<(Language, ClientEvent)> {
+        let mut events = Vec::new();
+        for (language, client) in self.clients.iter_mut() {
+            for event in client.poll() {
+                events.push((*language, event));
+            }
+        }
+
+        let mut exited = Vec::new();
+        events.retain(|(language, event)| match event {
+            ClientEvent::Notification { method, params } if method == "textDocument/publishDiagnostics" => {
+                match protocol::parse_diagnostics(params) {
+                    Some((uri, diagnostics)) => {
+                        self.diagnostics.insert(uri, diagnostics);
+                    }
+                    None => {}
+                }
+                false
+            }
+            ClientEvent::Notification { .. } => false,
+            ClientEvent::Exited => {
+                exited.push(*language);
+                true
+            }
+            _ => true,
+        });
+
+        // Forget servers that died so the next use restarts them
+        for language in exited {
+            self.clients.remove(&language);
+            self.documents.retain(|_, doc| doc.language != language);
+        }
+        events
+    }
+
+    /// Diagnostics for a file, most severe first
+    pub fn diagnostics_for(&self, path: &Path) -> &[Diagnostic] {
+        self.diagnostics
+            .get(&protocol::path_to_uri(path))
+            .map_or(&[], |d| d.as_slice())
+    }
+
+    /// Position encoding of the server for a language
+    pub fn encoding(&self, language: Language) -> PositionEncoding {
+        self.clients.get(&language).map(|c| c.encoding).unwrap_or_default()
+    }
+
+    /// Stop the server for a language (it restarts on next use)
+    pub fn restart(&mut self, language: Language) {
+        match self.clients.remove(&language) {
+            Some(mut client) => client.shutdown(),
+            None => {}
+        }
+        self.failed.remove(&language);
+        self.documents.retain(|_, doc| doc.language != language);
+    }
+
+    /// Stop all servers
+    pub fn shutdown_all(&mut self) {
+        for (_, mut client) in self.clients.drain() {
+            client.shutdown();
+        }
+        self.documents.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config() {
+        let mut manager = LspManager::new();
+        let table: Table = r#"
+            rust = ["my-analyzer", "--stdio"]
+            go = "gopls -remote=auto"
+            python = ""
+            cobol = "cobol-ls"
+            c = 3
+        "#
+        .parse()
+        .unwrap();
+
+        let warnings = manager.apply_config(&table);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert_eq!(
+            manager.server_command(Language::Rust),
+            Some(vec!["my-analyzer".to_string(), "--stdio".to_string()])
+        );
+        assert_eq!(
+            manager.server_command(Language::Go),
+            Some(vec!["gopls".to_string(), "-remote=auto".to_string()])
+        );
+        assert_eq!(manager.server_command(Language::Python), None);
+        // Untouched languages keep their defaults
+        assert_eq!(manager.server_command(Language::C), Some(vec!["clangd".to_string()]));
+        assert_eq!(manager.server_command(Language::PlainText), None);
+    }
+}
@@ -0,0 +1,308 @@
+//! LSP data types and conversions between protocol JSON and editor types.
+//!
+//! Only the handful of structures the editor uses are modelled. Positions
+//! are kept in protocol units (line, character in the negotiated encoding)
+//! and converted to char columns against the buffer text when used.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// How the `character` field of an LSP position counts columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    Utf8,
+    /// The protocol default
+    #[default]
+    Utf16,
+}
+
+impl PositionEncoding {
+    /// Parse the `positionEncoding` server capability
+    pub fn from_capability(value: Option<&str>) -> Self {
+        match value {
+            Some("utf-8") => PositionEncoding::Utf8,
+            _ => PositionEncoding::Utf16,
+        }
+    }
+}
+
+/// Convert a char column in `line` to a protocol column
+pub fn char_to_lsp_col(line: &str, col: usize, encoding: PositionEncoding) -> usize {
+    line.chars()
+        .take(col)
+        .map(|c| match encoding {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+        })
+        .sum()
+}
+
+/// Convert a protocol column in `line` to a char column (clamped to the line)
+pub fn lsp_col_to_char(line: &str, col: usize, encoding: PositionEncoding) -> usize {
+    let mut units = 0;
+    for (idx, c) in line.chars().enumerate() {
+        match units >= col {
+            true => return idx,
+            false => {}
+        }
+        units += match encoding {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+        };
+    }
+    line.chars().count()
+}
+
+/// A protocol position (zero-based line and column in protocol units)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            line: value.get("line")?.as_u64()? as usize,
+            character: value.get("character")?.as_u64()? as usize,
+        })
+    }
+
+    /// Convert to a (row, char col) position in the given lines
+    pub fn to_char_pos(self, lines: &[String], encoding: PositionEncoding) -> (usize, usize) {
+        match lines.get(self.line) {
+            Some(line) => (self.line, lsp_col_to_char(line, self.character, encoding)),
+            None => (lines.len().saturating_sub(1), lines.last().map_or(0, |l| l.chars().count())),
+        }
+    }
+}
+
+fn range_from_json(value: &Value) -> Option<(Position, Position)> {
+    Some((
+        Position::from_json(value.get("start")?)?,
+        Position::from_json(value.get("end")?)?,
+    ))
+}
+
+/// Diagnostic severity, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    /// Short label for the status bar and lists
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+/// A diagnostic published by the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub start: Position,
+    pub end: Position,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A location in a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub position: Position,
+}
+
+/// One completion candidate
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub insert_text: String,
+    pub detail: Option<String>,
+}
+
+/// Characters that are kept verbatim in file URIs
+fn is_uri_safe(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"/-_.~".contains(&b)
+}
+
+/// Build a `file://` URI for an absolute path
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match is_uri_safe(b) {
+            true => uri.push(b as char),
+            false => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+/// Extract the path from a `file://` URI
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    urlencoding::decode(encoded)
+        .ok()
+        .map(|decoded| PathBuf::from(decoded.into_owned()))
+}
+
+/// Parse `textDocument/publishDiagnostics` params into (uri, diagnostics)
+pub fn parse_diagnostics(params: &Value) -> Option<(String, Vec<Diagnostic>)> {
+    let uri = params.get("uri")?.as_str()?.to_string();
+    let diagnostics = params
+        .get("diagnostics")?
+        .as_array()?
+        .iter()
+        .filter_map(|d| {
+            let (start, end) = range_from_json(d.get("range")?)?;
+            let severity = match d.get("severity").and_then(|s| s.as_u64()) {
+                Some(2) => Severity::Warning,
+                Some(3) => Severity::Information,
+                Some(4) => Severity::Hint,
+                _ => Severity::Error,
+            };
+            Some(Diagnostic {
+                start,
+                end,
+                severity,
+                message: d.get("message")?.as_str()?.to_string(),
+            })
+        })
+        .collect();
+    Some((uri, diagnostics))
+}
+
+/// Parse a definition/references result: `Location`, `Location[]`,
+/// `LocationLink[]` or null
+pub fn parse_locations(result: &Value) -> Vec<Location> {
+    let parse_one = |value: &Value| -> Option<Location> {
+        // LocationLink uses targetUri/targetSelectionRange
+        let uri = value.get("uri").or_else(|| value.get("targetUri"))?.as_str()?;
+        let range = value
+            .get("range")
+            .or_else(|| value.get("targetSelectionRange"))?;
+        Some(Location {
+            path: uri_to_path(uri)?,
+            position: range_from_json(range)?.0,
+        })
+    };
+
+    match result {
+        Value::Array(items) => items.iter().filter_map(parse_one).collect(),
+        Value::Object(_) => parse_one(result).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse a hover result into plain text (markdown is shown as-is)
+pub fn parse_hover(result: &Value) -> Option<String> {
+    fn marked_string(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            // MarkupContent and { language, value } both carry `value`
+            Value::Object(_) => value.get("value")?.as_str().map(str::to_string),
+            _ => None,
+        }
+    }
+
+    let text = match result.get("contents")? {
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(marked_string)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        contents => marked_string(contents)?,
+    };
+    match text.trim().is_empty() {
+        true => None,
+        false => Some(text.trim().to_string()),
+    }
+}
+
+/// Parse a completion result: `CompletionItem[]`, `CompletionList` or null
+pub fn parse_completions(result: &Value) -> Vec<CompletionItem> {
+    let items = match result {
+        Value::Array(items) => items,
+        Value::Object(_) => match result.get("items").and_then(|i| i.as_array()) {
+            Some(items) => items,
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let label = item.get("label")?.as_str()?.to_string();
+            let insert_text = item
+                .get("textEdit")
+                .and_then(|e| e.get("newText"))
+                .or_else(|| item.get("insertText"))
+                .and_then(|t| t.as_str())
+                .unwrap_or(&label)
+                .to_string();
+            Some(CompletionItem {
+                detail: item.get("detail").and_then(|d| d.as_str()).map(str::to_string),
+                label,
+                insert_text,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_column_conversion() {
+        // '😀' is 2 UTF-16 units and 4 UTF-8 bytes
+        let line = "a😀b";
+        assert_eq!(char_to_lsp_col(line, 2, PositionEncoding::Utf16), 3);
+        assert_eq!(char_to_lsp_col(line, 2, PositionEncoding::Utf8), 5);
+        assert_eq!(lsp_col_to_char(line, 3, PositionEncoding::Utf16), 2);
+        assert_eq!(lsp_col_to_char(line, 5, PositionEncoding::Utf8), 2);
+        assert_eq!(lsp_col_to_char(line, 99, PositionEncoding::Utf16), 3);
+    }
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let path = Path::new("/tmp/my project/main.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/my%20project/main.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let locations = parse_locations(&json!([{
+            "targetUri": "file:///a.rs",
+            "targetRange": {"start": {"line": 0, "character": 0}, "end": {"line": 9, "character": 0}},
+            "targetSelectionRange": {"start": {"line": 3, "character": 4}, "end": {"line": 3, "character": 8}}
+        }]));
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].path, PathBuf::from("/a.rs"));
+        assert_eq!(locations[0].position, Position { line: 3, character: 4 });
+        assert!(parse_locations(&Value::Null).is_empty());
+
+        let hover = json!({"contents": {"kind": "markdown", "value": "```rust\nfn main()\n```"}});
+        assert_eq!(parse_hover(&hover).as_deref(), Some("```rust\nfn main()\n```"));
+        assert_eq!(parse_hover(&json!({"contents": []})), None);
+
+        let completions = parse_completions(&json!({"isIncomplete": false, "items": [
+            {"label": "println!", "insertText": "println", "detail": "macro"},
+            {"label": "print"}
+        ]}));
+        assert_eq!(completions[0].insert_text, "println");
+        assert_eq!(completions[1].insert_text, "print");
+    }
+}
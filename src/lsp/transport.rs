@@ -0,0 +1,88 @@
+//! JSON-RPC message framing over a byte stream.
+//!
+//! Every message is a `Content-Length` header block followed by a JSON body:
+//!
+//! ```text
+//! Content-Length: 52\r\n
+//! \r\n
+//! {"jsonrpc":"2.0","id":1,"method":"shutdown"}
+//! ```
+
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// Write one framed message and flush it
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Read one framed message. Returns `Ok(None)` at end of stream.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length: Option<usize> = None;
+
+    // Header block, terminated by an empty line
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line)? {
+            0 => return Ok(None),
+            _ => {}
+        }
+        let line = line.trim_end();
+        match line.is_empty() {
+            true => break,
+            false => {}
+        }
+        match line.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("Content-Length") => {
+                content_length = value.trim().parse().ok();
+            }
+            // Content-Type and unknown headers are ignored
+            _ => {}
+        }
+    }
+
+    let length = match content_length {
+        Some(length) => length,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing Content-Length header",
+            ))
+        }
+    };
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_roundtrip_multiple_messages() {
+        let mut buf = Vec::new();
+        let first = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}});
+        let second = json!({"jsonrpc": "2.0", "method": "note", "params": {"text": "héllo"}});
+        write_message(&mut buf, &first).unwrap();
+        write_message(&mut buf, &second).unwrap();
+
+        let mut reader = Cursor::new(buf);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(first));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(second));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_missing_length_is_an_error() {
+        let mut reader = Cursor::new(b"Content-Type: json\r\n\r\n{}".to_vec());
+        assert!(read_message(&mut reader).is_err());
+    }
+}
@@ -54,6 +54,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut terminal = ratatui::init();
     let mut editor = Editor::new();
+    editor.load_config();
 
     // Handle path argument
     match args.path {
@@ -64,7 +65,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     log::info!("Opening file: {}", path.display());
                     match editor.open_file(path) {
                         Ok(_) => {}
-                        Err(e) => log::error!("Failed to open file: {}", e),
+                        Err(e) => editor.error(format!("Failed to open {}: {}", path.display(), e)),
                     }
                 }
                 (_, true) => {
@@ -75,7 +76,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     // Path doesn't exist - could be a new file
                     // Just set current_file so save will work
                     log::info!("New file: {}", path.display());
-                    editor.current_file = Some(path.to_path_buf());
+                    editor.current_file = Some(jae::fileio::normalize_path(path));
                 }
             }
         }
@@ -83,6 +84,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let result = run_app(&mut terminal, &mut editor);
+    editor.lsp.shutdown_all();
 
     ratatui::restore();
     result
@@ -93,13 +95,25 @@ fn run_app(
     editor: &mut Editor,
 ) -> Result<(), Box<dyn std::error::Error>> {
     loop {
+        // Sync buffers to language servers and handle their replies
+        editor.lsp_tick();
+        // Re-diff the buffer against HEAD once edits settle
+        editor.git_tick();
+        // Copy edited buffers to their recovery files every few seconds
+        editor.autosave_tick();
+        // Notice when another program rewrites the open file
+        editor.external_change_tick();
+
+        // Open any fold an edit or search left the cursor inside
+        editor.reveal_cursor();
+
         // Lazy parse/highlight before render (only when cache is invalid)
         editor.ensure_highlights_current();
         terminal.draw(|frame| ui::draw(frame, editor))?;
 
         // Use poll with timeout to support jump mode timeout detection
-        // When jump mode is active, use short timeout; otherwise use longer timeout
-        let poll_timeout = match editor.jump_mode.is_some() {
+        // When jump mode, a language server or a pending diff is active, use short timeout; otherwise use longer timeout
+        let poll_timeout = match editor.jump_mode.is_some() || editor.lsp.is_active() || editor.git_gutter.is_pending() {
             true => Duration::from_millis(50),
             false => Duration::from_millis(500),
         };
@@ -1,5 +1,6 @@
-use crate::editor::text_widget::EditorWidget;
-use crate::editor::{Editor, FloatingMode};
+use crate::editor::shell::status_text;
+use crate::editor::text_widget::{severity_color, EditorWidget};
+use crate::editor::{Editor, FloatingMode, MessageLevel};
 use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -27,11 +28,17 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
     // Status bar with optional which-key line above
     render_status_bar(frame, editor, chunks[1]);
 
+    // Incremental search prompt (shown in place of the status line)
+    match &editor.isearch {
+        Some(isearch) => render_isearch_prompt(frame, isearch),
+        None => {}
+    }
+
     // Floating window
     match &editor.floating_window {
-        Some(ref fw) => {
-            if fw.visible {
-            let area = Rect::new(fw.x, fw.y, fw.width, fw.height);
+        Some(ref fw) if fw.visible => {
+            // Popups placed near the cursor may not fit on screen
+            let area = Rect::new(fw.x, fw.y, fw.width, fw.height).intersection(frame.area());
 
             // Clear background first
             frame.render_widget(Clear, area);
@@ -46,10 +53,26 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
                         format!("Menu [{}] - C-l:enter C-h:back", state.path.join(" > "))
                     }
                 },
-                FloatingMode::Settings { .. } => "Settings - ↑↓:nav Space:toggle C-h/C-l:adjust ESC:close".to_string(),
+                FloatingMode::Settings { .. } => "Settings - ↑↓:nav Space:toggle C-h/C-l:adjust C-s:save ESC:close".to_string(),
                 FloatingMode::Minibuffer { .. } => "".to_string(),  // Minibuffer renders its own prompt
                 FloatingMode::Confirm { .. } => "".to_string(),  // Confirm renders its own prompt
                 FloatingMode::CommandPalette { .. } => "".to_string(),  // CommandPalette renders its own prompt
+                FloatingMode::Outline { .. } => "".to_string(),  // Outline renders its own prompt
+                FloatingMode::BufferList { .. } => "Buffers - ↑↓:nav Enter:switch k:kill q:close".to_string(),
+                FloatingMode::Hover { .. } => "Hover - ↑↓:scroll".to_string(),
+                FloatingMode::Locations { title, entries, .. } => {
+                    format!("{} ({}) - ↑↓:nav Enter:jump q:close", title, entries.len())
+                }
+                FloatingMode::Completion { .. } => "".to_string(),
+                FloatingMode::ShellOutput { command, status, .. } => {
+                    format!("$ {} [{}] - ↑↓:scroll q:close", command, status_text(*status))
+                }
+                FloatingMode::Diff { title, .. } => format!("{} - ↑↓:scroll q:back", title),
+                FloatingMode::Messages { .. } => "*Messages* - ↑↓:scroll q:close".to_string(),
+                FloatingMode::UndoTree { .. } => "Undo tree - ↑↓:nav Enter:go to state q:close".to_string(),
+                FloatingMode::KillRing { entries, .. } => {
+                    format!("Kill ring ({}) - ↑↓:nav Enter:yank q:close", entries.len())
+                }
             };
 
             let block = Block::default()
@@ -135,17 +158,15 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
 
                     // Render preview if available
                     match preview {
-                        Some(preview_text) => {
-                            if has_preview {
-                                let preview_widget = Paragraph::new(preview_text.clone())
-                                    .block(Block::default()
-                                        .borders(Borders::TOP)
-                                        .title("Preview")
-                                        .border_style(Style::default().fg(Color::DarkGray)));
-                                frame.render_widget(preview_widget, chunks[1]);
-                            }
+                        Some(preview_text) if has_preview => {
+                            let preview_widget = Paragraph::new(preview_text.clone())
+                                .block(Block::default()
+                                    .borders(Borders::TOP)
+                                    .title("Preview")
+                                    .border_style(Style::default().fg(Color::DarkGray)));
+                            frame.render_widget(preview_widget, chunks[1]);
                         }
-                        None => {}
+                        _ => {}
                     }
 
                     // Render metadata if available
@@ -180,6 +201,7 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
                                     let opt = options.get(*current).map(|s| s.as_str()).unwrap_or("?");
                                     format!("[{}]", opt)
                                 }
+                                SettingValue::Info(text) => format!("({})", text),
                             };
 
                             let content = if i == *selected {
@@ -217,7 +239,7 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
                     cursor_pos,
                     completions,
                     selected_completion,
-                    ..
+                    callback,
                 } => {
                     // For minibuffer, render at the bottom of the screen
                     let minibuffer_area = Rect::new(
@@ -233,11 +255,14 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
                     // Find the last directory separator to show |/| marker
                     let chars: Vec<char> = input.chars().collect();
 
-                    // Find last '/' position for directory boundary indicator
-                    let last_slash_pos = input.rfind('/').map(|i| {
-                        // Convert byte index to char index
-                        input[..i].chars().count()
-                    });
+                    // Find last '/' position for directory boundary indicator (paths only)
+                    let last_slash_pos = match callback.is_path_input() {
+                        true => input.rfind('/').map(|i| {
+                            // Convert byte index to char index
+                            input[..i].chars().count()
+                        }),
+                        false => None,
+                    };
 
                     let mut spans = vec![
                         Span::styled(prompt.clone(), Style::default().fg(Color::Cyan)),
@@ -317,11 +342,12 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
                         false => String::new(),
                     };
 
+                    let help = match callback.is_path_input() {
+                        true => "Tab:enter dir ↑↓:navigate Enter:open/enter C-g:cancel",
+                        false => "Tab:complete ↑↓:navigate Enter:select C-g:cancel",
+                    };
                     let status_line = Line::from(vec![
-                        Span::styled(
-                            "Tab:enter dir ↑↓:navigate Enter:open/enter C-g:cancel",
-                            Style::default().fg(Color::DarkGray),
-                        ),
+                        Span::styled(help, Style::default().fg(Color::DarkGray)),
                         Span::styled(completion_hint, Style::default().fg(Color::Yellow)),
                     ]);
 
@@ -510,13 +536,338 @@ pub fn draw(frame: &mut Frame, editor: &mut Editor) {
 
                     frame.render_widget(widget, palette_area);
                 }
-            }
+
+                FloatingMode::Outline {
+                    input,
+                    cursor_pos,
+                    entries,
+                    filtered,
+                    selected,
+                } => {
+                    // Laid out like the command palette
+                    let max_visible = 10;
+                    let visible_count = filtered.len().clamp(1, max_visible);
+                    let outline_height = (visible_count + 3) as u16; // +3 for input line, help line, border
+
+                    let outline_area = Rect::new(
+                        0,
+                        frame.area().height.saturating_sub(outline_height),
+                        frame.area().width,
+                        outline_height,
+                    );
+
+                    frame.render_widget(Clear, outline_area);
+
+                    let chars: Vec<char> = input.chars().collect();
+                    let before_cursor: String = chars[..*cursor_pos].iter().collect();
+                    let cursor_char = chars.get(*cursor_pos).unwrap_or(&' ');
+                    let after_cursor: String = chars[*cursor_pos..].iter().skip(1).collect();
+
+                    let mut lines = vec![Line::from(vec![
+                        Span::styled("Symbol: ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                        Span::raw(before_cursor),
+                        Span::styled(
+                            cursor_char.to_string(),
+                            Style::default().bg(Color::White).fg(Color::Black),
+                        ),
+                        Span::raw(after_cursor),
+                    ])];
+
+                    // Keep the selection in view, with the symbols around it
+                    let scroll_offset = selected
+                        .saturating_sub(max_visible / 2)
+                        .min(filtered.len().saturating_sub(max_visible));
+                    for (i, index) in filtered.iter().enumerate().skip(scroll_offset).take(max_visible) {
+                        let entry = &entries[*index];
+                        let is_selected = i == *selected;
+                        let style = if is_selected {
+                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(Color::White)
+                        };
+                        let prefix = if is_selected { "→ " } else { "  " };
+                        lines.push(Line::from(vec![
+                            Span::styled(format!("{}{}", prefix, "  ".repeat(entry.depth)), style),
+                            Span::styled(entry.name.clone(), style),
+                            Span::styled(format!(" {}", entry.kind.label()), Style::default().fg(Color::Cyan)),
+                            Span::styled(format!(" :{}", entry.row + 1), Style::default().fg(Color::DarkGray)),
+                        ]));
+                    }
+                    match filtered.is_empty() {
+                        true => lines.push(Line::styled("  No matching symbols", Style::default().fg(Color::DarkGray))),
+                        false => {}
+                    }
+
+                    let position_info = if filtered.len() > max_visible {
+                        let end_idx = (scroll_offset + max_visible).min(filtered.len());
+                        format!(" [{}-{}/{}]", scroll_offset + 1, end_idx, filtered.len())
+                    } else {
+                        String::new()
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled("↑↓:navigate Enter:jump C-g:cancel", Style::default().fg(Color::DarkGray)),
+                        Span::styled(position_info, Style::default().fg(Color::Yellow)),
+                    ]));
+
+                    let widget = Paragraph::new(lines)
+                        .block(Block::default().borders(Borders::TOP).title("Outline"));
+
+                    frame.render_widget(widget, outline_area);
+                }
+
+                FloatingMode::BufferList { entries, selected } => {
+                    let items: Vec<ListItem> = entries
+                        .iter()
+                        .enumerate()
+                        .map(|(i, entry)| {
+                            let marker = match (entry.is_current, entry.modified) {
+                                (true, true) => ".*",
+                                (true, false) => ". ",
+                                (false, true) => " *",
+                                (false, false) => "  ",
+                            };
+                            let path = entry
+                                .path
+                                .as_ref()
+                                .map(|p| p.display().to_string())
+                                .unwrap_or_default();
+
+                            let prefix = if i == *selected { "→ " } else { "  " };
+                            let style = if i == *selected {
+                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                            } else if entry.modified {
+                                Style::default().fg(Color::Red)
+                            } else {
+                                Style::default()
+                            };
+
+                            ListItem::new(Line::from(vec![
+                                Span::styled(format!("{}{} {}", prefix, marker, entry.name), style),
+                                Span::styled(format!("  {}", path), Style::default().fg(Color::DarkGray)),
+                            ]))
+                        })
+                        .collect();
+
+                    frame.render_widget(List::new(items), inner_area);
+                }
+
+                FloatingMode::Hover { lines, scroll } => {
+                    let text: Vec<Line> = lines
+                        .iter()
+                        .skip(*scroll)
+                        .map(|l| Line::from(l.as_str()))
+                        .collect();
+                    frame.render_widget(Paragraph::new(text), inner_area);
+                }
+
+                FloatingMode::ShellOutput { stdout, stderr, scroll, .. } => {
+                    let error_style = Style::default().fg(Color::Red);
+                    let text: Vec<Line> = stdout
+                        .iter()
+                        .map(|l| Line::from(l.as_str()))
+                        .chain(stderr.iter().map(|l| Line::styled(l.as_str(), error_style)))
+                        .skip(*scroll)
+                        .collect();
+                    frame.render_widget(Paragraph::new(text), inner_area);
+                }
+
+                FloatingMode::Messages { scroll } => {
+                    let text: Vec<Line> = editor
+                        .messages
+                        .history()
+                        .skip(*scroll)
+                        .map(|m| {
+                            let style = match m.level {
+                                MessageLevel::Info => Style::default(),
+                                level => Style::default().fg(level.color()),
+                            };
+                            Line::styled(m.history_line(), style)
+                        })
+                        .collect();
+                    frame.render_widget(Paragraph::new(text), inner_area);
+                }
+
+                FloatingMode::Diff { lines, scroll, .. } => {
+                    let text: Vec<Line> = lines
+                        .iter()
+                        .skip(*scroll)
+                        .map(|l| {
+                            let style = match l.chars().next() {
+                                _ if l.starts_with("+++") || l.starts_with("---") => {
+                                    Style::default().add_modifier(Modifier::BOLD)
+                                }
+                                Some('+') => Style::default().fg(Color::Green),
+                                Some('-') => Style::default().fg(Color::Red),
+                                Some('@') => Style::default().fg(Color::Cyan),
+                                _ => Style::default(),
+                            };
+                            Line::styled(l.as_str(), style)
+                        })
+                        .collect();
+                    frame.render_widget(Paragraph::new(text), inner_area);
+                }
+
+                FloatingMode::Locations { entries, selected, .. } => {
+                    // Keep the selection visible in long lists
+                    let visible = inner_area.height.max(1) as usize;
+                    let first = selected.saturating_sub(visible - 1);
+                    let items: Vec<ListItem> = entries
+                        .iter()
+                        .enumerate()
+                        .skip(first)
+                        .map(|(i, entry)| {
+                            let prefix = if i == *selected { "→ " } else { "  " };
+                            let style = if i == *selected {
+                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                            } else {
+                                Style::default()
+                            };
+                            let file = entry
+                                .path
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_default();
+
+                            ListItem::new(Line::from(vec![
+                                Span::styled(format!("{}{}:{}:{}", prefix, file, entry.row + 1, entry.col + 1), style),
+                                Span::styled(format!("  {}", entry.text), Style::default().fg(Color::DarkGray)),
+                            ]))
+                        })
+                        .collect();
+
+                    frame.render_widget(List::new(items), inner_area);
+                }
+
+                FloatingMode::UndoTree { entries, selected } => {
+                    // Keep the selection visible in long histories
+                    let visible = inner_area.height.max(1) as usize;
+                    let first = selected.saturating_sub(visible - 1);
+                    let items: Vec<ListItem> = entries
+                        .iter()
+                        .enumerate()
+                        .skip(first)
+                        .map(|(i, entry)| {
+                            let prefix = if i == *selected { "→ " } else { "  " };
+                            let marker = match (entry.current, entry.branch) {
+                                (true, _) => "● ",
+                                (false, true) => "└ ",
+                                (false, false) => "○ ",
+                            };
+                            let style = if i == *selected {
+                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                            } else if entry.current {
+                                Style::default().fg(Color::Green)
+                            } else {
+                                Style::default()
+                            };
+                            ListItem::new(Line::styled(
+                                format!("{}{}{}{}", prefix, "  ".repeat(entry.depth), marker, entry.label),
+                                style,
+                            ))
+                        })
+                        .collect();
+
+                    frame.render_widget(List::new(items), inner_area);
+                }
+
+                FloatingMode::KillRing { entries, selected } => {
+                    let visible = inner_area.height.max(1) as usize;
+                    let first = selected.saturating_sub(visible - 1);
+                    let width = inner_area.width.saturating_sub(2) as usize;
+                    let items: Vec<ListItem> = entries
+                        .iter()
+                        .enumerate()
+                        .skip(first)
+                        .map(|(i, entry)| {
+                            let prefix = if i == *selected { "→ " } else { "  " };
+                            let style = if i == *selected {
+                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                            } else {
+                                Style::default()
+                            };
+                            // One line per entry, line breaks shown as ⏎
+                            let text: String =
+                                entry.chars().map(|c| if c == '\n' { '⏎' } else { c }).take(width).collect();
+                            ListItem::new(Line::styled(format!("{}{}", prefix, text), style))
+                        })
+                        .collect();
+
+                    frame.render_widget(List::new(items), inner_area);
+                }
+
+                FloatingMode::Completion { items, selected, .. } => {
+                    let visible = inner_area.height.max(1) as usize;
+                    let first = selected.saturating_sub(visible - 1);
+                    let rows: Vec<ListItem> = items
+                        .iter()
+                        .enumerate()
+                        .skip(first)
+                        .map(|(i, item)| {
+                            let style = if i == *selected {
+                                Style::default().bg(Color::Blue).fg(Color::White).add_modifier(Modifier::BOLD)
+                            } else {
+                                Style::default()
+                            };
+                            let mut spans = vec![Span::styled(item.label.clone(), style)];
+                            match &item.detail {
+                                Some(detail) => spans.push(Span::styled(
+                                    format!("  {}", detail),
+                                    Style::default().fg(Color::DarkGray),
+                                )),
+                                None => {}
+                            }
+                            ListItem::new(Line::from(spans))
+                        })
+                        .collect();
+
+                    frame.render_widget(List::new(rows), inner_area);
+                }
             }
         }
-        None => {}
+        _ => {}
     }
 }
 
+/// Render the incremental search prompt at the bottom of the screen
+fn render_isearch_prompt(frame: &mut Frame, isearch: &crate::editor::ISearch) {
+    let area = Rect::new(
+        0,
+        frame.area().height.saturating_sub(2),
+        frame.area().width,
+        2,
+    );
+    frame.render_widget(Clear, area);
+
+    let prompt_color = match isearch.is_failing() {
+        true => Color::Red,
+        false => Color::Cyan,
+    };
+    // A full list may be missing matches
+    let more = match isearch.matches.len() >= crate::editor::search::MAX_SEARCH_MATCHES {
+        true => "+",
+        false => "",
+    };
+    let position = match (isearch.current, isearch.matches.len()) {
+        (_, 0) => String::new(),
+        (Some(idx), total) => format!("  ({}/{}{})", idx + 1, total, more),
+        (None, total) => format!("  ({}{})", total, more),
+    };
+
+    let line = Line::from(vec![
+        Span::styled(isearch.prompt(), Style::default().fg(prompt_color)),
+        Span::raw(isearch.pattern.clone()),
+        Span::styled(" ", Style::default().bg(Color::White).fg(Color::Black)),
+        Span::styled(position, Style::default().fg(Color::Yellow)),
+        Span::styled(
+            "  C-s/C-r:next/prev Enter:done C-g:cancel",
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]);
+
+    let widget = Paragraph::new(vec![line]).block(Block::default().borders(Borders::TOP));
+    frame.render_widget(widget, area);
+}
+
 /// Render the status bar (2 lines: which-key above, status below)
 fn render_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
     let mut lines: Vec<Line> = Vec::new();
@@ -573,8 +924,18 @@ fn render_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
 
         lines.push(Line::from(spans));
     } else {
-        // No which-key - empty line
-        lines.push(Line::from(""));
+        // No which-key - show the echo area message, or the diagnostic on the cursor line
+        match (editor.messages.echo(), editor.diagnostic_at_cursor()) {
+            (Some((level, message)), _) => lines.push(Line::from(Span::styled(
+                message.to_string(),
+                Style::default().fg(level.color()),
+            ))),
+            (None, Some((severity, message))) => lines.push(Line::from(Span::styled(
+                format!("{}: {}", severity.label(), message),
+                Style::default().fg(severity_color(severity)),
+            ))),
+            (None, None) => lines.push(Line::from("")),
+        }
     }
 
     // Line 2: Status info
@@ -582,12 +943,7 @@ fn render_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
 
     // File indicator
     let modified_marker = if editor.modified { "*" } else { "" };
-    let filename = editor.current_file
-        .as_ref()
-        .and_then(|p| p.file_name())
-        .and_then(|n| n.to_str())
-        .map(|s| format!("{}{}", modified_marker, s))
-        .unwrap_or_else(|| format!("{}[No File]", modified_marker));
+    let filename = format!("{}{}", modified_marker, editor.current_buffer_name());
 
     status_spans.push(Span::styled(
         filename,
@@ -596,6 +952,20 @@ fn render_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
             .add_modifier(Modifier::BOLD),
     ));
 
+    // Other open buffers
+    if !editor.buffers.is_empty() {
+        status_spans.push(Span::styled(
+            format!(" (+{} buf)", editor.buffers.len()),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    // File format (encoding, line endings, final newline)
+    status_spans.push(Span::styled(
+        format!(" [{}]", editor.file_format),
+        Style::default().fg(Color::DarkGray),
+    ));
+
     // Mark indicator
     if editor.mark.is_active() {
         status_spans.push(Span::styled(
@@ -612,6 +982,22 @@ fn render_status_bar(frame: &mut Frame, editor: &Editor, area: Rect) {
         ));
     }
 
+    // Language server diagnostics
+    let (errors, warnings) = editor.diagnostic_counts();
+    match (errors, warnings) {
+        (0, 0) => {}
+        _ => {
+            status_spans.push(Span::styled(
+                format!(" E{}", errors),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+            status_spans.push(Span::styled(
+                format!(" W{}", warnings),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+
     // Syntax error indicator
     match &editor.syntax_error {
         Some(_) => {
@@ -0,0 +1,109 @@
+//! End-to-end tests of the LSP client against the bundled fake server.
+
+use jae::editor::syntax::Language;
+use jae::editor::{Editor, FloatingMode};
+use jae::lsp::Severity;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use jae::editor::CursorMove;
+
+const SOURCE: &str = "fn helper() {}\n\nfn main() {\n    // TODO: tidy\n    helper();\n    hel\n}\n";
+
+fn setup(name: &str) -> (Editor, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("jae-lsp-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.rs");
+    fs::write(&path, SOURCE).unwrap();
+
+    let mut editor = Editor::new();
+    editor
+        .lsp
+        .set_server(Language::Rust, vec![env!("CARGO_BIN_EXE_jae-fake-lsp").to_string()]);
+    editor.open_file(&path).unwrap();
+    (editor, dir)
+}
+
+/// Run the event loop until `done` holds (or fail after a timeout)
+fn tick_until(editor: &mut Editor, what: &str, done: impl Fn(&Editor) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !done(editor) {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        editor.lsp_tick();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_diagnostics_follow_edits() {
+    let (mut editor, dir) = setup("diagnostics");
+
+    tick_until(&mut editor, "diagnostics", |e| e.diagnostic_counts() == (0, 1));
+    let ranges = editor.diagnostic_ranges();
+    assert_eq!(ranges, vec![((3, 7), (3, 11), Severity::Warning)]);
+
+    // Editing the buffer resyncs it
+    editor.textarea.move_cursor(CursorMove::Bottom);
+    editor.textarea.insert_str("// FIXME");
+    tick_until(&mut editor, "updated diagnostics", |e| e.diagnostic_counts() == (1, 1));
+
+    editor.lsp.shutdown_all();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_definition_hover_and_references() {
+    let (mut editor, dir) = setup("navigation");
+
+    // Cursor on the `helper` call
+    editor.textarea.move_cursor(CursorMove::Jump(4, 6));
+    editor.lsp_goto_definition();
+    tick_until(&mut editor, "definition", |e| e.textarea.cursor() == (0, 3));
+
+    editor.lsp_hover();
+    tick_until(&mut editor, "hover", |e| e.floating_window.is_some());
+    match &editor.floating_window.as_ref().unwrap().mode {
+        FloatingMode::Hover { lines, .. } => assert_eq!(lines, &["word `helper`"]),
+        _ => panic!("expected a hover window"),
+    }
+    editor.floating_window = None;
+
+    editor.lsp_find_references();
+    tick_until(&mut editor, "references", |e| e.floating_window.is_some());
+    match &editor.floating_window.as_ref().unwrap().mode {
+        FloatingMode::Locations { entries, .. } => {
+            let rows: Vec<usize> = entries.iter().map(|e| e.row).collect();
+            assert_eq!(rows, vec![0, 4]);
+            assert_eq!(entries[1].text, "helper();");
+        }
+        _ => panic!("expected a location list"),
+    }
+
+    editor.lsp.shutdown_all();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_completion_replaces_prefix() {
+    let (mut editor, dir) = setup("completion");
+
+    // End of the `hel` line
+    editor.textarea.move_cursor(CursorMove::Jump(5, 7));
+    editor.lsp_complete();
+    tick_until(&mut editor, "completions", |e| e.floating_window.is_some());
+
+    let (item, row, start_col) = match &editor.floating_window.as_ref().unwrap().mode {
+        FloatingMode::Completion { items, row, start_col, .. } => (items[0].clone(), *row, *start_col),
+        _ => panic!("expected a completion popup"),
+    };
+    assert_eq!(item.label, "helper");
+    assert_eq!((row, start_col), (5, 4));
+
+    editor.floating_window = None;
+    editor.accept_completion(&item, row, start_col);
+    assert_eq!(editor.textarea.line(5), "    helper");
+    assert!(editor.modified);
+
+    editor.lsp.shutdown_all();
+    fs::remove_dir_all(dir).unwrap();
+}